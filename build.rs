@@ -2,6 +2,8 @@ fn main() {
     prost_build::compile_protos(
         &[
             "src/schema/keys.proto",
+            "src/schema/envelope.proto",
+            "src/schema/peer_record.proto",
             "src/schema/noise.proto",
             "src/schema/webrtc.proto",
             "src/protocol/libp2p/schema/identify.proto",
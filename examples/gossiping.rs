@@ -121,7 +121,7 @@ impl TransactionProtocol {
 
                         self.peers.insert(peer, HashSet::new());
                     }
-                    NotificationEvent::NotificationStreamClosed { peer } => {
+                    NotificationEvent::NotificationStreamClosed { peer, .. } => {
                         tracing::info!("substream closed for {peer}");
 
                         self.peers.remove(&peer);
@@ -129,21 +129,21 @@ impl TransactionProtocol {
                     NotificationEvent::NotificationReceived { peer, notification } => {
                         tracing::info!("transaction received from {peer}: {notification:?}");
 
-                        // send transaction to all peers who don't have it yet
-                        let notification = notification.freeze();
-
-                        for (connected, txs) in &mut self.peers {
-                            let not_seen = txs.insert(Transaction { tx: notification.clone().into() });
-                            if connected != &peer && not_seen {
-                                self.tx_handle.send_sync_notification(
-                                    *connected,
-                                    notification.clone().into(),
-                                ).unwrap();
-                            }
+                        let transaction = Transaction { tx: notification.to_vec() };
+
+                        // the sender already has the transaction
+                        if let Some(txs) = self.peers.get_mut(&peer) {
+                            txs.insert(transaction.clone());
                         }
 
-                        if self.seen.insert(Transaction { tx: notification.clone().into() }) {
-                            return Some((peer, Transaction { tx: notification.clone().into() }))
+                        // send transaction to all peers who don't have it yet
+                        let peers = &mut self.peers;
+                        self.tx_handle.broadcast(notification.freeze(), |connected| {
+                            peers.get_mut(connected).is_some_and(|txs| txs.insert(transaction.clone()))
+                        });
+
+                        if self.seen.insert(transaction.clone()) {
+                            return Some((peer, transaction))
                         }
                     }
                     _ => {}
@@ -154,14 +154,10 @@ impl TransactionProtocol {
                         // send transaction to all peers who don't have it yet
                         self.seen.insert(transaction.clone());
 
-                        for (peer, txs) in &mut self.peers {
-                            if txs.insert(transaction.clone()) {
-                                self.tx_handle.send_sync_notification(
-                                    *peer,
-                                    transaction.tx.clone(),
-                                ).unwrap();
-                            }
-                        }
+                        let peers = &mut self.peers;
+                        self.tx_handle.broadcast(transaction.tx.clone(), |peer| {
+                            peers.get_mut(peer).is_some_and(|txs| txs.insert(transaction.clone()))
+                        });
                     }
                 }
             }
@@ -20,11 +20,31 @@
 
 //! Protocol codecs.
 
+use bytes::{Bytes, BytesMut};
+
+use std::{fmt::Debug, sync::Arc};
+
 pub mod identity;
 pub mod unsigned_varint;
 
+/// User-defined codec.
+///
+/// The codec is shared by all substreams of the protocol so it must not keep per-substream state.
+/// Partially received frames are kept by the substream and given back to the codec once more
+/// data has been read.
+pub trait Codec: Debug + Send + Sync {
+    /// Encode `item` into a frame and append it to `dst`.
+    fn encode(&self, item: Bytes, dst: &mut BytesMut) -> crate::Result<()>;
+
+    /// Attempt to decode a frame from `src`.
+    ///
+    /// If `src` contains a complete frame, the frame must be removed from `src` and its payload
+    /// returned. If more data is needed, `Ok(None)` must be returned and `src` left untouched.
+    fn decode(&self, src: &mut BytesMut) -> crate::Result<Option<BytesMut>>;
+}
+
 /// Supported protocol codecs.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum ProtocolCodec {
     /// Identity codec where the argument denotes the payload size.
     Identity(usize),
@@ -32,6 +52,9 @@ pub enum ProtocolCodec {
     /// Unsigned varint where the argument denotes the maximum message size, if specified.
     UnsignedVarint(Option<usize>),
 
-    /// Protocol doens't need framing for its messages or is using a custom codec.
+    /// User-defined codec.
+    Custom(Arc<dyn Codec>),
+
+    /// Protocol doens't need framing for its messages or frames them itself.
     Unspecified,
 }
@@ -22,24 +22,36 @@
 
 use crate::{
     crypto::ed25519::Keypair,
+    error::{ConfigError, ConfigErrorKind},
     executor::{DefaultExecutor, Executor},
     protocol::{
         libp2p::{bitswap, identify, kademlia, ping},
         mdns::Config as MdnsConfig,
-        notification, request_response, UserProtocol,
+        notification, request_response, ReputationConfig, UserProtocol,
     },
     transport::{
+        manager::CustomTransport, memory::config::Config as MemoryConfig,
         quic::config::Config as QuicConfig, tcp::config::Config as TcpConfig,
         webrtc::config::Config as WebRtcConfig, websocket::config::Config as WebSocketConfig,
-        MAX_PARALLEL_DIALS,
+        Transport, TransportBuilder, TransportEvent, TransportHandle, MAX_PARALLEL_DIALS,
     },
     types::protocol::ProtocolName,
     PeerId,
 };
 
-use multiaddr::Multiaddr;
+use multiaddr::{Multiaddr, Protocol};
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Type-erased transport.
+type BoxedTransport = Box<dyn Transport<Item = TransportEvent>>;
+
+/// Constructor of a custom transport.
+pub(crate) type TransportFactory =
+    Box<dyn FnOnce(TransportHandle) -> crate::Result<(BoxedTransport, Vec<Multiaddr>)> + Send>;
 
 /// Connection role.
 #[derive(Debug, Copy, Clone)]
@@ -74,6 +86,12 @@ pub struct ConfigBuilder {
     /// WebSocket transport config.
     websocket: Option<WebSocketConfig>,
 
+    /// Memory transport config.
+    memory: Option<MemoryConfig>,
+
+    /// Custom transports.
+    custom_transports: Vec<(CustomTransport, TransportFactory)>,
+
     /// Keypair.
     keypair: Option<Keypair>,
 
@@ -109,6 +127,12 @@ pub struct ConfigBuilder {
 
     /// Maximum number of parallel dial attempts.
     max_parallel_dials: usize,
+
+    /// Protocols that were registered more than once.
+    duplicate_protocols: Vec<ProtocolName>,
+
+    /// Reputation configuration.
+    reputation: ReputationConfig,
 }
 
 impl ConfigBuilder {
@@ -119,6 +143,8 @@ impl ConfigBuilder {
             quic: None,
             webrtc: None,
             websocket: None,
+            memory: None,
+            custom_transports: Vec::new(),
             keypair: None,
             ping: None,
             identify: None,
@@ -131,6 +157,8 @@ impl ConfigBuilder {
             notification_protocols: HashMap::new(),
             request_response_protocols: HashMap::new(),
             known_addresses: Vec::new(),
+            duplicate_protocols: Vec::new(),
+            reputation: ReputationConfig::default(),
         }
     }
 
@@ -158,6 +186,40 @@ impl ConfigBuilder {
         self
     }
 
+    /// Add memory transport configuration, enabling the transport.
+    ///
+    /// The memory transport connects nodes running in the same process and is intended for
+    /// tests and simulations.
+    pub fn with_memory_transport(mut self, config: MemoryConfig) -> Self {
+        self.memory = Some(config);
+        self
+    }
+
+    /// Add custom transport, enabling the transport.
+    ///
+    /// `name` identifies the transport and must be unique. Addresses for which `matcher` returns
+    /// `true` are dialed using the custom transport, even if a built-in transport could also dial
+    /// them. The address given to `matcher` may or may not end in `/p2p/<peer id>`.
+    pub fn with_transport<T>(
+        mut self,
+        name: &'static str,
+        config: T::Config,
+        matcher: fn(&Multiaddr) -> bool,
+    ) -> Self
+    where
+        T: TransportBuilder + Transport<Item = TransportEvent> + 'static,
+        T::Config: Send + 'static,
+    {
+        let factory: TransportFactory = Box::new(move |handle| {
+            let (transport, listen_addresses) = T::new(handle, config)?;
+
+            Ok((Box::new(transport) as BoxedTransport, listen_addresses))
+        });
+
+        self.custom_transports.push((CustomTransport::new(name, matcher), factory));
+        self
+    }
+
     /// Add keypair.
     ///
     /// If no keypair is specified, litep2p creates a new keypair.
@@ -168,7 +230,11 @@ impl ConfigBuilder {
 
     /// Enable notification protocol.
     pub fn with_notification_protocol(mut self, config: notification::Config) -> Self {
-        self.notification_protocols.insert(config.protocol_name().clone(), config);
+        let protocol = config.protocol_name().clone();
+
+        if self.notification_protocols.insert(protocol.clone(), config).is_some() {
+            self.duplicate_protocols.push(protocol);
+        }
         self
     }
 
@@ -198,13 +264,21 @@ impl ConfigBuilder {
 
     /// Enable request-response protocol.
     pub fn with_request_response_protocol(mut self, config: request_response::Config) -> Self {
-        self.request_response_protocols.insert(config.protocol_name().clone(), config);
+        let protocol = config.protocol_name().clone();
+
+        if self.request_response_protocols.insert(protocol.clone(), config).is_some() {
+            self.duplicate_protocols.push(protocol);
+        }
         self
     }
 
     /// Enable user protocol.
     pub fn with_user_protocol(mut self, protocol: Box<dyn UserProtocol>) -> Self {
-        self.user_protocols.insert(protocol.protocol(), protocol);
+        let protocol_name = protocol.protocol();
+
+        if self.user_protocols.insert(protocol_name.clone(), protocol).is_some() {
+            self.duplicate_protocols.push(protocol_name);
+        }
         self
     }
 
@@ -237,6 +311,177 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set how peer reputations decay and when peers are banned.
+    pub fn with_reputation(mut self, config: ReputationConfig) -> Self {
+        self.reputation = config;
+        self
+    }
+
+    /// Validate the configuration and build [`Litep2pConfig`].
+    ///
+    /// Unlike [`ConfigBuilder::build()`], the configuration is checked for problems that would
+    /// otherwise surface only when [`Litep2p`](`crate::Litep2p`) is initialized, such as
+    /// duplicate protocol names, listen addresses that don't match their transport or zero-sized
+    /// channels. All problems found are reported in the returned [`ConfigError`].
+    pub fn try_build(self) -> Result<Litep2pConfig, ConfigError> {
+        let mut errors = Vec::new();
+
+        self.validate_transports(&mut errors);
+        self.validate_protocols(&mut errors);
+
+        if self.max_parallel_dials == 0 {
+            errors.push(ConfigErrorKind::ZeroSized("max_parallel_dials".to_string()));
+        }
+
+        match errors.is_empty() {
+            true => Ok(self.build()),
+            false => Err(ConfigError { errors }),
+        }
+    }
+
+    /// Verify that at least one transport is enabled and that the listen addresses of each
+    /// enabled transport can be used by that transport.
+    fn validate_transports(&self, errors: &mut Vec<ConfigErrorKind>) {
+        if self.tcp.is_none()
+            && self.quic.is_none()
+            && self.webrtc.is_none()
+            && self.websocket.is_none()
+            && self.memory.is_none()
+            && self.custom_transports.is_empty()
+        {
+            errors.push(ConfigErrorKind::NoTransport);
+        }
+
+        let mut check = |transport: &'static str,
+                         addresses: &Vec<Multiaddr>,
+                         is_valid: fn(&Multiaddr) -> bool| {
+            for address in addresses.iter().filter(|address| !is_valid(address)) {
+                errors.push(ConfigErrorKind::InvalidListenAddress {
+                    transport,
+                    address: address.clone(),
+                });
+            }
+        };
+
+        if let Some(config) = &self.tcp {
+            check("tcp", &config.listen_addresses, is_tcp_address);
+        }
+
+        if let Some(config) = &self.quic {
+            check("quic", &config.listen_addresses, is_quic_address);
+        }
+
+        if let Some(config) = &self.webrtc {
+            check("webrtc", &config.listen_addresses, is_webrtc_address);
+        }
+
+        if let Some(config) = &self.websocket {
+            check("websocket", &config.listen_addresses, is_websocket_address);
+        }
+
+        if let Some(config) = &self.memory {
+            check("memory", &config.listen_addresses, is_memory_address);
+        }
+
+        let mut names = HashSet::new();
+        for (transport, _) in &self.custom_transports {
+            if !names.insert(transport.name()) {
+                errors.push(ConfigErrorKind::DuplicateTransport(transport.name()));
+            }
+        }
+    }
+
+    /// Verify that main and fallback names of all enabled protocols are unique and that
+    /// protocol channels are not zero-sized.
+    fn validate_protocols(&self, errors: &mut Vec<ConfigErrorKind>) {
+        let mut names = HashSet::new();
+        let mut duplicates = HashSet::new();
+
+        // protocols that were overwritten in the builder are already known to be duplicates
+        for protocol in &self.duplicate_protocols {
+            duplicates.insert(protocol.clone());
+        }
+
+        let mut insert = |protocol: &ProtocolName| {
+            if !names.insert(protocol.clone()) {
+                duplicates.insert(protocol.clone());
+            }
+        };
+
+        for (protocol, config) in &self.notification_protocols {
+            std::iter::once(protocol)
+                .chain(config.fallback_names.iter())
+                .for_each(&mut insert);
+        }
+
+        for (protocol, config) in &self.request_response_protocols {
+            std::iter::once(protocol)
+                .chain(config.fallback_names.iter())
+                .for_each(&mut insert);
+        }
+
+        self.user_protocols.keys().for_each(&mut insert);
+
+        if let Some(config) = &self.ping {
+            insert(&config.protocol);
+        }
+
+        if let Some(config) = &self.identify {
+            insert(&config.protocol);
+        }
+
+        if let Some(config) = &self.kademlia {
+            config.protocol_names.iter().for_each(&mut insert);
+        }
+
+        if let Some(config) = &self.bitswap {
+            insert(&config.protocol);
+        }
+
+        errors.extend(duplicates.into_iter().map(ConfigErrorKind::DuplicateProtocol));
+
+        for (protocol, config) in &self.notification_protocols {
+            if config.sync_channel_size == 0 {
+                errors.push(ConfigErrorKind::ZeroSized(format!(
+                    "{protocol}: sync_channel_size"
+                )));
+            }
+
+            if config.async_channel_size == 0 {
+                errors.push(ConfigErrorKind::ZeroSized(format!(
+                    "{protocol}: async_channel_size"
+                )));
+            }
+
+            if config.high_priority_channel_size == 0 {
+                errors.push(ConfigErrorKind::ZeroSized(format!(
+                    "{protocol}: high_priority_channel_size"
+                )));
+            }
+
+            if config.low_priority_channel_size == 0 {
+                errors.push(ConfigErrorKind::ZeroSized(format!(
+                    "{protocol}: low_priority_channel_size"
+                )));
+            }
+
+            // main protocol of a group must be a registered notification protocol which is not
+            // a secondary protocol of another group
+            if let Some(main) = &config.main_protocol {
+                if !self
+                    .notification_protocols
+                    .get(main)
+                    .is_some_and(|config| config.main_protocol.is_none())
+                {
+                    errors.push(ConfigErrorKind::InvalidProtocolGroup {
+                        protocol: protocol.clone(),
+                        main: main.clone(),
+                    });
+                }
+            }
+        }
+    }
+
     /// Build [`Litep2pConfig`].
     pub fn build(mut self) -> Litep2pConfig {
         let keypair = match self.keypair {
@@ -251,11 +496,14 @@ impl ConfigBuilder {
             quic: self.quic.take(),
             webrtc: self.webrtc.take(),
             websocket: self.websocket.take(),
+            memory: self.memory.take(),
+            custom_transports: self.custom_transports,
             ping: self.ping.take(),
             identify: self.identify.take(),
             kademlia: self.kademlia.take(),
             bitswap: self.bitswap.take(),
             max_parallel_dials: self.max_parallel_dials,
+            reputation: self.reputation,
             executor: self.executor.map_or(Arc::new(DefaultExecutor {}), |executor| executor),
             user_protocols: self.user_protocols,
             notification_protocols: self.notification_protocols,
@@ -265,6 +513,64 @@ impl ConfigBuilder {
     }
 }
 
+/// Check if `protocol` is an IP or DNS protocol.
+fn is_ip_or_dns(protocol: Option<Protocol<'_>>) -> bool {
+    std::matches!(
+        protocol,
+        Some(Protocol::Ip4(_))
+            | Some(Protocol::Ip6(_))
+            | Some(Protocol::Dns(_))
+            | Some(Protocol::Dns4(_))
+            | Some(Protocol::Dns6(_))
+    )
+}
+
+/// Check if `address` is a valid TCP listen address.
+fn is_tcp_address(address: &Multiaddr) -> bool {
+    let mut iter = address.iter();
+
+    is_ip_or_dns(iter.next())
+        && std::matches!(iter.next(), Some(Protocol::Tcp(_)))
+        && iter.next().is_none()
+}
+
+/// Check if `address` is a valid WebSocket listen address.
+fn is_websocket_address(address: &Multiaddr) -> bool {
+    let mut iter = address.iter();
+
+    is_ip_or_dns(iter.next())
+        && std::matches!(iter.next(), Some(Protocol::Tcp(_)))
+        && std::matches!(iter.next(), Some(Protocol::Ws(_)) | Some(Protocol::Wss(_)))
+        && iter.next().is_none()
+}
+
+/// Check if `address` is a valid QUIC listen address.
+fn is_quic_address(address: &Multiaddr) -> bool {
+    let mut iter = address.iter();
+
+    std::matches!(iter.next(), Some(Protocol::Ip4(_)) | Some(Protocol::Ip6(_)))
+        && std::matches!(iter.next(), Some(Protocol::Udp(_)))
+        && std::matches!(iter.next(), Some(Protocol::QuicV1))
+        && iter.next().is_none()
+}
+
+/// Check if `address` is a valid WebRTC listen address.
+fn is_webrtc_address(address: &Multiaddr) -> bool {
+    let mut iter = address.iter();
+
+    std::matches!(iter.next(), Some(Protocol::Ip4(_)) | Some(Protocol::Ip6(_)))
+        && std::matches!(iter.next(), Some(Protocol::Udp(_)))
+        && std::matches!(iter.next(), Some(Protocol::WebRTC))
+        && iter.next().is_none()
+}
+
+/// Check if `address` is a valid memory listen address.
+fn is_memory_address(address: &Multiaddr) -> bool {
+    let mut iter = address.iter();
+
+    std::matches!(iter.next(), Some(Protocol::Memory(_))) && iter.next().is_none()
+}
+
 /// Configuration for [`Litep2p`](`crate::Litep2p`).
 pub struct Litep2pConfig {
     // TCP transport configuration.
@@ -279,6 +585,12 @@ pub struct Litep2pConfig {
     /// WebSocket transport config.
     pub(crate) websocket: Option<WebSocketConfig>,
 
+    /// Memory transport config.
+    pub(crate) memory: Option<MemoryConfig>,
+
+    /// Custom transports.
+    pub(crate) custom_transports: Vec<(CustomTransport, TransportFactory)>,
+
     /// Keypair.
     pub(crate) keypair: Keypair,
 
@@ -314,4 +626,207 @@ pub struct Litep2pConfig {
 
     /// Known addresses.
     pub(crate) known_addresses: Vec<(PeerId, Vec<Multiaddr>)>,
+
+    /// Reputation configuration.
+    pub(crate) reputation: ReputationConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::libp2p::ping;
+
+    fn config_errors(builder: ConfigBuilder) -> Vec<ConfigErrorKind> {
+        match builder.try_build() {
+            Err(error) => error.errors,
+            Ok(_) => panic!("configuration to be invalid"),
+        }
+    }
+
+    #[test]
+    fn valid_config() {
+        let (ping_config, _ping_event_stream) = ping::Config::default();
+
+        assert!(ConfigBuilder::new()
+            .with_tcp(Default::default())
+            .with_quic(Default::default())
+            .with_libp2p_ping(ping_config)
+            .try_build()
+            .is_ok());
+    }
+
+    #[test]
+    fn no_transport() {
+        let errors = config_errors(ConfigBuilder::new());
+
+        assert_eq!(errors, vec![ConfigErrorKind::NoTransport]);
+    }
+
+    #[test]
+    fn duplicate_protocol_names() {
+        let (notif_config1, _handle1) = notification::Config::new(
+            ProtocolName::from("/notif/1"),
+            1024usize,
+            vec![1, 2, 3, 4],
+            Vec::new(),
+            false,
+            64,
+            64,
+            true,
+        );
+        let (notif_config2, _handle2) = notification::Config::new(
+            ProtocolName::from("/notif/1"),
+            1024usize,
+            vec![1, 2, 3, 4],
+            Vec::new(),
+            false,
+            64,
+            64,
+            true,
+        );
+        let (req_resp_config, _handle3) = request_response::Config::new(
+            ProtocolName::from("/req/1"),
+            vec![
+                ProtocolName::from("/notif/1"),
+                ProtocolName::from("/ipfs/ping/1.0.0"),
+            ],
+            1024,
+            std::time::Duration::from_secs(5),
+            None,
+        );
+        let (ping_config, _ping_event_stream) = ping::Config::default();
+
+        let mut errors = config_errors(
+            ConfigBuilder::new()
+                .with_tcp(Default::default())
+                .with_notification_protocol(notif_config1)
+                .with_notification_protocol(notif_config2)
+                .with_request_response_protocol(req_resp_config)
+                .with_libp2p_ping(ping_config),
+        );
+        errors.sort_by_key(|error| error.to_string());
+
+        assert_eq!(
+            errors,
+            vec![
+                ConfigErrorKind::DuplicateProtocol(ProtocolName::from("/ipfs/ping/1.0.0")),
+                ConfigErrorKind::DuplicateProtocol(ProtocolName::from("/notif/1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn listen_address_transport_mismatch() {
+        let address: Multiaddr = "/ip4/127.0.0.1/udp/8888/quic-v1".parse().unwrap();
+
+        let errors = config_errors(ConfigBuilder::new().with_tcp(TcpConfig {
+            listen_addresses: vec![address.clone(), "/ip6/::/tcp/0".parse().unwrap()],
+            ..Default::default()
+        }));
+
+        assert_eq!(
+            errors,
+            vec![ConfigErrorKind::InvalidListenAddress {
+                transport: "tcp",
+                address,
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_memory_listen_address() {
+        let address: Multiaddr = "/ip4/127.0.0.1/tcp/8888".parse().unwrap();
+
+        let errors = config_errors(ConfigBuilder::new().with_memory_transport(MemoryConfig {
+            listen_addresses: vec![address.clone(), "/memory/0".parse().unwrap()],
+            ..Default::default()
+        }));
+
+        assert_eq!(
+            errors,
+            vec![ConfigErrorKind::InvalidListenAddress {
+                transport: "memory",
+                address,
+            }]
+        );
+    }
+
+    #[test]
+    fn duplicate_custom_transport() {
+        use crate::transport::memory::MemoryTransport;
+
+        let errors = config_errors(
+            ConfigBuilder::new()
+                .with_transport::<MemoryTransport>("memory", Default::default(), is_memory_address)
+                .with_transport::<MemoryTransport>("memory", Default::default(), is_memory_address),
+        );
+
+        assert_eq!(errors, vec![ConfigErrorKind::DuplicateTransport("memory")]);
+    }
+
+    #[test]
+    fn zero_sized_channels() {
+        let (notif_config, _handle) = notification::Config::new(
+            ProtocolName::from("/notif/1"),
+            1024usize,
+            vec![1, 2, 3, 4],
+            Vec::new(),
+            false,
+            0,
+            64,
+            true,
+        );
+
+        let errors = config_errors(
+            ConfigBuilder::new()
+                .with_tcp(Default::default())
+                .with_notification_protocol(notif_config)
+                .with_max_parallel_dials(0),
+        );
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.contains(&ConfigErrorKind::ZeroSized(
+            "max_parallel_dials".to_string()
+        )));
+        assert!(errors.contains(&ConfigErrorKind::ZeroSized(
+            "/notif/1: sync_channel_size".to_string()
+        )));
+    }
+
+    #[test]
+    fn invalid_protocol_group() {
+        let (main_config, _handle1) =
+            notification::ConfigBuilder::new(ProtocolName::from("/main/1"))
+                .with_max_size(1024)
+                .with_handshake(vec![1, 2, 3, 4])
+                .build();
+        let (secondary_config, _handle2) =
+            notification::ConfigBuilder::new(ProtocolName::from("/secondary/1"))
+                .with_max_size(1024)
+                .with_handshake(vec![1, 2, 3, 4])
+                .with_main_protocol(ProtocolName::from("/main/1"))
+                .build();
+        let (unknown_config, _handle3) =
+            notification::ConfigBuilder::new(ProtocolName::from("/unknown/1"))
+                .with_max_size(1024)
+                .with_handshake(vec![1, 2, 3, 4])
+                .with_main_protocol(ProtocolName::from("/main/2"))
+                .build();
+
+        let errors = config_errors(
+            ConfigBuilder::new()
+                .with_tcp(Default::default())
+                .with_notification_protocol(main_config)
+                .with_notification_protocol(secondary_config)
+                .with_notification_protocol(unknown_config),
+        );
+
+        assert_eq!(
+            errors,
+            vec![ConfigErrorKind::InvalidProtocolGroup {
+                protocol: ProtocolName::from("/unknown/1"),
+                main: ProtocolName::from("/main/2"),
+            }]
+        );
+    }
 }
@@ -0,0 +1,194 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! ECDSA keys using the NIST P-256 curve.
+
+use crate::{error::Error, PeerId};
+
+use ring::{
+    rand::SystemRandom,
+    signature::{self, EcdsaKeyPair, KeyPair as _},
+};
+use x509_parser::{
+    oid_registry::OID_KEY_TYPE_EC_PUBLIC_KEY, prelude::FromDer, x509::SubjectPublicKeyInfo,
+};
+
+use std::{fmt, sync::Arc};
+
+/// DER prefix of a `SubjectPublicKeyInfo` holding an uncompressed P-256 point.
+///
+/// ```text
+/// SEQUENCE {
+///   SEQUENCE {
+///     OBJECT IDENTIFIER 1.2.840.10045.2.1 (ecPublicKey)
+///     OBJECT IDENTIFIER 1.2.840.10045.3.1.7 (prime256v1)
+///   }
+///   BIT STRING (65 bytes)
+/// }
+/// ```
+const SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// Length of an uncompressed P-256 point.
+const UNCOMPRESSED_POINT_LENGTH: usize = 65;
+
+/// An ECDSA keypair.
+#[derive(Clone)]
+pub struct Keypair(Arc<EcdsaKeyPair>);
+
+impl Keypair {
+    /// Generate a new random ECDSA keypair.
+    pub fn generate() -> Keypair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+            .expect("system random number generator to work");
+
+        Self::from_pkcs8(pkcs8.as_ref()).expect("generated PKCS #8 document to be valid")
+    }
+
+    /// Create keypair from a PKCS #8 v1 document containing a P-256 private key.
+    pub fn from_pkcs8(pkcs8: &[u8]) -> crate::Result<Keypair> {
+        EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8)
+            .map(|keypair| Keypair(Arc::new(keypair)))
+            .map_err(|error| Error::Other(format!("Failed to parse keypair: {error:?}")))
+    }
+
+    /// Sign a message using the private key of this keypair.
+    ///
+    /// The message is hashed with SHA-256 and the signature is DER-encoded.
+    pub fn sign(&self, msg: &[u8]) -> crate::Result<Vec<u8>> {
+        self.0
+            .sign(&SystemRandom::new(), msg)
+            .map(|signature| signature.as_ref().to_vec())
+            .map_err(|error| Error::Other(format!("Failed to sign message: {error:?}")))
+    }
+
+    /// Get the public key of this keypair.
+    pub fn public(&self) -> PublicKey {
+        PublicKey(self.0.public_key().as_ref().to_vec())
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair").field("public", &self.public()).finish()
+    }
+}
+
+/// An ECDSA public key, stored as an uncompressed P-256 point.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey(Vec<u8>);
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicKey(uncompressed): ")?;
+        for byte in &self.0 {
+            write!(f, "{byte:x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl PublicKey {
+    /// Verify an ECDSA signature on a message using the public key.
+    ///
+    /// The signature must be DER-encoded and the message is hashed with SHA-256.
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> bool {
+        signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, &self.0)
+            .verify(msg, sig)
+            .is_ok()
+    }
+
+    /// Encode the public key as a DER-encoded X.509 `SubjectPublicKeyInfo` structure.
+    pub fn encode_der(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(SPKI_PREFIX.len() + self.0.len());
+        encoded.extend_from_slice(&SPKI_PREFIX);
+        encoded.extend_from_slice(&self.0);
+
+        encoded
+    }
+
+    /// Decode a public key from a DER-encoded X.509 `SubjectPublicKeyInfo` structure.
+    pub fn decode_der(k: &[u8]) -> crate::Result<PublicKey> {
+        let (_, info) = SubjectPublicKeyInfo::from_der(k)
+            .map_err(|error| Error::Other(format!("Failed to parse public key: {error:?}")))?;
+
+        if info.algorithm.algorithm != OID_KEY_TYPE_EC_PUBLIC_KEY {
+            return Err(Error::Other("Public key is not an EC key".to_string()));
+        }
+
+        Self::from_uncompressed_point(&info.subject_public_key.data)
+    }
+
+    /// Create public key from an uncompressed P-256 point.
+    pub fn from_uncompressed_point(point: &[u8]) -> crate::Result<PublicKey> {
+        if point.len() != UNCOMPRESSED_POINT_LENGTH || point[0] != 0x04 {
+            return Err(Error::Other(
+                "Public key is not an uncompressed P-256 point".to_string(),
+            ));
+        }
+
+        Ok(PublicKey(point.to_vec()))
+    }
+
+    /// Get the public key as an uncompressed P-256 point.
+    pub fn to_uncompressed_point(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Convert public key to `PeerId`.
+    pub fn to_peer_id(&self) -> PeerId {
+        crate::crypto::PublicKey::Ecdsa(self.clone()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ecdsa_public_key_encode_decode() {
+        let public = Keypair::generate().public();
+        let encoded = public.encode_der();
+
+        assert_eq!(PublicKey::decode_der(&encoded).unwrap(), public);
+    }
+
+    #[test]
+    fn ecdsa_signature() {
+        let keypair = Keypair::generate();
+        let public = keypair.public();
+
+        let msg = "hello world".as_bytes();
+        let sig = keypair.sign(msg).unwrap();
+        assert!(public.verify(msg, &sig));
+
+        let invalid_msg = "h3ll0 w0rld".as_bytes();
+        assert!(!public.verify(invalid_msg, &sig));
+    }
+
+    #[test]
+    fn ecdsa_invalid_public_key() {
+        assert!(PublicKey::decode_der(&[0u8; 91]).is_err());
+        assert!(PublicKey::from_uncompressed_point(&[0u8; 33]).is_err());
+    }
+}
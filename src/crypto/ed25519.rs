@@ -21,13 +21,20 @@
 
 //! Ed25519 keys.
 
-use crate::{error::Error, PeerId};
+use crate::{crypto::keys_proto, error::Error, PeerId};
 
 use ed25519_dalek::{self as ed25519, Signer as _, Verifier as _};
 use rand::RngCore;
 use zeroize::Zeroize;
 
-use std::{cmp, convert::TryFrom, fmt};
+use std::{
+    cmp,
+    convert::TryFrom,
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
 
 /// An Ed25519 keypair.
 pub struct Keypair(ed25519::Keypair);
@@ -73,6 +80,117 @@ impl Keypair {
         SecretKey::from_bytes(&mut self.0.secret.to_bytes())
             .expect("ed25519::SecretKey::from_bytes(to_bytes(k)) != k")
     }
+
+    /// Encode the keypair into a libp2p `PrivateKey` protobuf structure.
+    ///
+    /// The encoding is compatible with the private key files of `go-libp2p` and `rust-libp2p`.
+    pub fn to_protobuf_encoding(&self) -> Vec<u8> {
+        use prost::Message;
+
+        let mut private_key = keys_proto::PrivateKey {
+            r#type: keys_proto::KeyType::Ed25519 as i32,
+            data: self.encode().to_vec(),
+        };
+
+        let mut buf = Vec::with_capacity(private_key.encoded_len());
+        private_key.encode(&mut buf).expect("Vec<u8> provides capacity as needed");
+        private_key.data.zeroize();
+
+        buf
+    }
+
+    /// Decode a keypair from a libp2p `PrivateKey` protobuf structure.
+    ///
+    /// The decoded secret is zeroed after the keypair has been constructed.
+    pub fn from_protobuf_encoding(bytes: &[u8]) -> crate::Result<Keypair> {
+        use prost::Message;
+
+        let mut private_key = keys_proto::PrivateKey::decode(bytes)
+            .map_err(|error| Error::Other(format!("Invalid Protobuf: {error:?}")))?;
+
+        let keypair = match keys_proto::KeyType::from_i32(private_key.r#type) {
+            Some(keys_proto::KeyType::Ed25519) => Self::from_keypair_bytes(&mut private_key.data),
+            _ => Err(Error::Other(format!(
+                "Unsupported key type: {}",
+                private_key.r#type
+            ))),
+        };
+        private_key.data.zeroize();
+
+        keypair
+    }
+
+    /// Create keypair from the 64-byte secret and public key concatenation, verifying that the
+    /// public key matches the secret key.
+    fn from_keypair_bytes(bytes: &mut [u8]) -> crate::Result<Keypair> {
+        if bytes.len() != 64 {
+            return Err(Error::Other(format!(
+                "Invalid keypair length: {}",
+                bytes.len()
+            )));
+        }
+
+        let (secret, public) = bytes.split_at_mut(32);
+        let keypair = Keypair::from(SecretKey::from_bytes(secret)?);
+
+        if keypair.public().encode() != *public {
+            return Err(Error::Other(
+                "Public key doesn't match secret key".to_string(),
+            ));
+        }
+
+        Ok(keypair)
+    }
+
+    /// Load keypair from a private key file at `path`.
+    ///
+    /// The file must contain a protobuf-encoded private key, as produced by
+    /// [`Keypair::to_protobuf_encoding()`].
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Keypair> {
+        let mut bytes = std::fs::read(path)?;
+        let keypair = Self::from_protobuf_encoding(&bytes);
+        bytes.zeroize();
+
+        keypair
+    }
+
+    /// Save keypair into a new private key file at `path`.
+    ///
+    /// The file is created readable and writable only by its owner and existing files are never
+    /// overwritten.
+    pub fn save(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path)?;
+        let mut bytes = self.to_protobuf_encoding();
+        let result = file.write_all(&bytes).and_then(|_| file.sync_all());
+        bytes.zeroize();
+
+        result.map_err(From::from)
+    }
+
+    /// Load keypair from `path` or, if the file doesn't exist, generate a new keypair and save it
+    /// to `path`.
+    pub fn load_or_generate(path: impl AsRef<Path>) -> crate::Result<Keypair> {
+        let path = path.as_ref();
+
+        match Self::load(path) {
+            Err(Error::IoError(io::ErrorKind::NotFound)) => {
+                let keypair = Self::generate();
+                keypair.save(path)?;
+
+                Ok(keypair)
+            }
+            result => result,
+        }
+    }
 }
 
 impl fmt::Debug for Keypair {
@@ -252,6 +370,59 @@ mod tests {
         assert!(!pk.verify(invalid_msg, &sig));
     }
 
+    #[test]
+    fn ed25519_keypair_protobuf_encode_decode() {
+        let keypair = Keypair::generate();
+        let decoded = Keypair::from_protobuf_encoding(&keypair.to_protobuf_encoding()).unwrap();
+
+        assert!(eq_keypairs(&keypair, &decoded));
+    }
+
+    #[test]
+    fn ed25519_keypair_protobuf_libp2p_compatible() {
+        let keypair = Keypair::from_protobuf_encoding(&hex_literal::hex!(
+            "080112407e0830617c4a7de83925dfb2694556b12936c477a0e1feb2e148ec9da60fee7d1ed1e8fae2c4a144b8be8fd4b47bf3d3b34b871c3cacf6010f0e42d474fce27e"
+        ))
+        .unwrap();
+        let public = crate::crypto::PublicKey::from_protobuf_encoding(&hex_literal::hex!(
+            "080112201ed1e8fae2c4a144b8be8fd4b47bf3d3b34b871c3cacf6010f0e42d474fce27e"
+        ))
+        .unwrap();
+
+        assert_eq!(crate::crypto::PublicKey::Ed25519(keypair.public()), public);
+    }
+
+    #[test]
+    fn ed25519_keypair_protobuf_public_key_mismatch() {
+        let mut bytes = Keypair::generate().to_protobuf_encoding();
+        let len = bytes.len();
+        bytes[len - 1] ^= 0xff;
+
+        assert!(Keypair::from_protobuf_encoding(&bytes).is_err());
+    }
+
+    #[test]
+    fn ed25519_keypair_load_or_generate() {
+        let path = std::env::temp_dir().join(format!("litep2p-key-{}", rand::random::<u64>()));
+
+        let keypair = Keypair::load_or_generate(&path).unwrap();
+        let loaded = Keypair::load_or_generate(&path).unwrap();
+        assert!(eq_keypairs(&keypair, &loaded));
+
+        // saving must not overwrite an existing key file
+        assert!(Keypair::generate().save(&path).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn secret_key() {
         let _ = tracing_subscriber::fmt()
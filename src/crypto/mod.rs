@@ -23,7 +23,12 @@
 
 use crate::{error::*, peer_id::*};
 
+pub mod ecdsa;
 pub mod ed25519;
+pub mod rsa;
+pub mod secp256k1;
+pub mod signed_envelope;
+
 pub(crate) mod noise;
 pub(crate) mod tls;
 pub(crate) mod keys_proto {
@@ -35,6 +40,15 @@ pub(crate) mod keys_proto {
 pub enum PublicKey {
     /// A public Ed25519 key.
     Ed25519(ed25519::PublicKey),
+
+    /// A public Secp256k1 key.
+    Secp256k1(secp256k1::PublicKey),
+
+    /// A public ECDSA key.
+    Ecdsa(ecdsa::PublicKey),
+
+    /// A public RSA key.
+    Rsa(rsa::PublicKey),
 }
 
 impl PublicKey {
@@ -47,6 +61,9 @@ impl PublicKey {
         use PublicKey::*;
         match self {
             Ed25519(pk) => pk.verify(msg, sig),
+            Secp256k1(pk) => pk.verify(msg, sig),
+            Ecdsa(pk) => pk.verify(msg, sig),
+            Rsa(pk) => pk.verify(msg, sig),
         }
     }
 
@@ -86,6 +103,18 @@ impl From<&PublicKey> for keys_proto::PublicKey {
                 r#type: keys_proto::KeyType::Ed25519 as i32,
                 data: key.encode().to_vec(),
             },
+            PublicKey::Secp256k1(key) => keys_proto::PublicKey {
+                r#type: keys_proto::KeyType::Secp256k1 as i32,
+                data: key.encode().to_vec(),
+            },
+            PublicKey::Ecdsa(key) => keys_proto::PublicKey {
+                r#type: keys_proto::KeyType::Ecdsa as i32,
+                data: key.encode_der(),
+            },
+            PublicKey::Rsa(key) => keys_proto::PublicKey {
+                r#type: keys_proto::KeyType::Rsa as i32,
+                data: key.encode_x509(),
+            },
         }
     }
 }
@@ -99,8 +128,13 @@ impl TryFrom<keys_proto::PublicKey> for PublicKey {
 
         match key_type {
             keys_proto::KeyType::Ed25519 =>
-                Ok(ed25519::PublicKey::decode(&pubkey.data).map(PublicKey::Ed25519)?),
-            _ => unimplemented!("unsupported key type"),
+                ed25519::PublicKey::decode(&pubkey.data).map(PublicKey::Ed25519),
+            keys_proto::KeyType::Secp256k1 =>
+                secp256k1::PublicKey::decode(&pubkey.data).map(PublicKey::Secp256k1),
+            keys_proto::KeyType::Ecdsa =>
+                ecdsa::PublicKey::decode_der(&pubkey.data).map(PublicKey::Ecdsa),
+            keys_proto::KeyType::Rsa =>
+                rsa::PublicKey::decode_x509(&pubkey.data).map(PublicKey::Rsa),
         }
     }
 }
@@ -110,3 +144,70 @@ impl From<ed25519::PublicKey> for PublicKey {
         PublicKey::Ed25519(public_key)
     }
 }
+
+impl From<secp256k1::PublicKey> for PublicKey {
+    fn from(public_key: secp256k1::PublicKey) -> Self {
+        PublicKey::Secp256k1(public_key)
+    }
+}
+
+impl From<ecdsa::PublicKey> for PublicKey {
+    fn from(public_key: ecdsa::PublicKey) -> Self {
+        PublicKey::Ecdsa(public_key)
+    }
+}
+
+impl From<rsa::PublicKey> for PublicKey {
+    fn from(public_key: rsa::PublicKey) -> Self {
+        PublicKey::Rsa(public_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protobuf_roundtrip(public: PublicKey) {
+        let decoded = PublicKey::from_protobuf_encoding(&public.to_protobuf_encoding()).unwrap();
+
+        assert_eq!(decoded, public);
+        assert_eq!(decoded.to_peer_id(), public.to_peer_id());
+    }
+
+    #[test]
+    fn public_key_protobuf_roundtrip() {
+        protobuf_roundtrip(ed25519::Keypair::generate().public().into());
+        protobuf_roundtrip(secp256k1::Keypair::generate().public().into());
+        protobuf_roundtrip(ecdsa::Keypair::generate().public().into());
+        protobuf_roundtrip(
+            rsa::PublicKey::decode_x509(include_bytes!("test_assets/rsa_2048.spki.der"))
+                .unwrap()
+                .into(),
+        );
+    }
+
+    #[test]
+    fn secp256k1_libp2p_compatible() {
+        // public key and peer ID taken from the `rust-libp2p` test vectors
+        let public = PublicKey::from_protobuf_encoding(&hex_literal::hex!(
+            "08021221037777e994e452c21604f91de093ce415f5432f701dd8cd1a7a6fea0e630bfca99"
+        ))
+        .unwrap();
+
+        assert!(std::matches!(public, PublicKey::Secp256k1(_)));
+        assert_eq!(
+            public.to_peer_id().to_base58(),
+            "16Uiu2HAmLhLvBoYaoZfaMUKuibM6ac163GwKY74c5kiSLg5KvLpY"
+        );
+    }
+
+    #[test]
+    fn unknown_key_type() {
+        let encoded = keys_proto::PublicKey {
+            r#type: 5,
+            data: vec![1, 2, 3, 4],
+        };
+
+        assert!(PublicKey::try_from(encoded).is_err());
+    }
+}
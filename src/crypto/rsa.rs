@@ -0,0 +1,121 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! RSA keys.
+//!
+//! Only verification of signatures produced by remote peers is supported.
+
+use crate::{error::Error, PeerId};
+
+use ring::signature;
+use x509_parser::{
+    oid_registry::OID_PKCS1_RSAENCRYPTION, prelude::FromDer, x509::SubjectPublicKeyInfo,
+};
+
+use std::fmt;
+
+/// An RSA public key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    /// DER-encoded X.509 `SubjectPublicKeyInfo` structure.
+    spki: Vec<u8>,
+
+    /// DER-encoded PKCS #1 `RSAPublicKey` structure.
+    pkcs1: Vec<u8>,
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicKey(PKCS1): ")?;
+        for byte in &self.pkcs1 {
+            write!(f, "{byte:x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl PublicKey {
+    /// Verify an RSA signature on a message using the public key.
+    ///
+    /// The signature must use the RSASSA-PKCS1-v1_5 scheme with SHA-256.
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> bool {
+        signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, &self.pkcs1)
+            .verify(msg, sig)
+            .is_ok()
+    }
+
+    /// Encode the public key as a DER-encoded X.509 `SubjectPublicKeyInfo` structure.
+    pub fn encode_x509(&self) -> Vec<u8> {
+        self.spki.clone()
+    }
+
+    /// Encode the public key as a DER-encoded PKCS #1 `RSAPublicKey` structure.
+    pub fn encode_pkcs1(&self) -> Vec<u8> {
+        self.pkcs1.clone()
+    }
+
+    /// Decode a public key from a DER-encoded X.509 `SubjectPublicKeyInfo` structure.
+    pub fn decode_x509(k: &[u8]) -> crate::Result<PublicKey> {
+        let (_, info) = SubjectPublicKeyInfo::from_der(k)
+            .map_err(|error| Error::Other(format!("Failed to parse public key: {error:?}")))?;
+
+        if info.algorithm.algorithm != OID_PKCS1_RSAENCRYPTION {
+            return Err(Error::Other("Public key is not an RSA key".to_string()));
+        }
+
+        Ok(PublicKey {
+            spki: k.to_vec(),
+            pkcs1: info.subject_public_key.data.to_vec(),
+        })
+    }
+
+    /// Convert public key to `PeerId`.
+    pub fn to_peer_id(&self) -> PeerId {
+        crate::crypto::PublicKey::Rsa(self.clone()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rsa_signature() {
+        let public =
+            PublicKey::decode_x509(include_bytes!("test_assets/rsa_2048.spki.der")).unwrap();
+        let sig = include_bytes!("test_assets/rsa_2048_hello_world.sig");
+
+        assert!(public.verify("hello world".as_bytes(), sig));
+        assert!(!public.verify("h3ll0 w0rld".as_bytes(), sig));
+        assert_eq!(
+            public.encode_x509(),
+            include_bytes!("test_assets/rsa_2048.spki.der").to_vec()
+        );
+    }
+
+    #[test]
+    fn rsa_invalid_public_key() {
+        assert!(PublicKey::decode_x509(&[0u8; 64]).is_err());
+
+        // valid `SubjectPublicKeyInfo` of a P-256 key
+        let ecdsa = crate::crypto::ecdsa::Keypair::generate().public().encode_der();
+        assert!(PublicKey::decode_x509(&ecdsa).is_err());
+    }
+}
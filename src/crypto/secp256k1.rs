@@ -0,0 +1,222 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Secp256k1 keys.
+
+use crate::{error::Error, PeerId};
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use std::{cmp, fmt};
+
+/// A Secp256k1 keypair.
+#[derive(Clone)]
+pub struct Keypair {
+    /// Secret key.
+    secret: SecretKey,
+
+    /// Public key.
+    public: PublicKey,
+}
+
+impl Keypair {
+    /// Generate a new random Secp256k1 keypair.
+    pub fn generate() -> Keypair {
+        Keypair::from(SecretKey::generate())
+    }
+
+    /// Sign a message using the private key of this keypair.
+    ///
+    /// The message is hashed with SHA-256 and the signature is DER-encoded.
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        self.secret.sign(msg)
+    }
+
+    /// Get the public key of this keypair.
+    pub fn public(&self) -> PublicKey {
+        self.public.clone()
+    }
+
+    /// Get the secret key of this keypair.
+    pub fn secret(&self) -> SecretKey {
+        self.secret.clone()
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair").field("public", &self.public).finish()
+    }
+}
+
+/// Promote a Secp256k1 secret key into a keypair.
+impl From<SecretKey> for Keypair {
+    fn from(secret: SecretKey) -> Keypair {
+        let public = PublicKey(libsecp256k1::PublicKey::from_secret_key(&secret.0));
+
+        Keypair { secret, public }
+    }
+}
+
+/// Demote a Secp256k1 keypair to a secret key.
+impl From<Keypair> for SecretKey {
+    fn from(keypair: Keypair) -> SecretKey {
+        keypair.secret
+    }
+}
+
+/// A Secp256k1 secret key.
+#[derive(Clone)]
+pub struct SecretKey(libsecp256k1::SecretKey);
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey")
+    }
+}
+
+impl SecretKey {
+    /// Generate a new random Secp256k1 secret key.
+    pub fn generate() -> SecretKey {
+        SecretKey(libsecp256k1::SecretKey::random(&mut rand::thread_rng()))
+    }
+
+    /// Create a Secp256k1 secret key from a byte slice, zeroing the input on success.
+    /// If the bytes do not constitute a valid Secp256k1 secret key, an error is
+    /// returned.
+    pub fn from_bytes(mut sk_bytes: impl AsMut<[u8]>) -> crate::Result<SecretKey> {
+        let sk_bytes = sk_bytes.as_mut();
+        let secret = libsecp256k1::SecretKey::parse_slice(&*sk_bytes)
+            .map_err(|error| Error::Other(format!("Failed to parse secret key: {error:?}")))?;
+        sk_bytes.zeroize();
+
+        Ok(SecretKey(secret))
+    }
+
+    /// Convert the secret key to a byte array.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.serialize()
+    }
+
+    /// Sign a message with this secret key, producing a DER-encoded ECDSA signature.
+    ///
+    /// The message is hashed with SHA-256 before signing.
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(Sha256::digest(msg).as_ref());
+
+        let message = libsecp256k1::Message::parse(&digest);
+        libsecp256k1::sign(&message, &self.0).0.serialize_der().as_ref().to_vec()
+    }
+}
+
+/// A Secp256k1 public key.
+#[derive(Eq, Clone)]
+pub struct PublicKey(libsecp256k1::PublicKey);
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicKey(compressed): ")?;
+        for byte in &self.encode() {
+            write!(f, "{byte:x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl cmp::PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.encode().eq(&other.encode())
+    }
+}
+
+impl PublicKey {
+    /// Verify the Secp256k1 signature on a message using the public key.
+    ///
+    /// The signature must be DER-encoded and the message is hashed with SHA-256.
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> bool {
+        self.verify_hash(Sha256::digest(msg).as_ref(), sig)
+    }
+
+    /// Verify the Secp256k1 DER-encoded signature on a raw 256-bit message using the public key.
+    pub fn verify_hash(&self, msg: &[u8], sig: &[u8]) -> bool {
+        libsecp256k1::Message::parse_slice(msg)
+            .and_then(|message| {
+                libsecp256k1::Signature::parse_der(sig)
+                    .map(|signature| libsecp256k1::verify(&message, &signature, &self.0))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Encode the public key in compressed form, i.e. with one coordinate represented by a
+    /// single bit.
+    pub fn encode(&self) -> [u8; 33] {
+        self.0.serialize_compressed()
+    }
+
+    /// Decode a public key from a byte slice in the format produced by `encode`.
+    pub fn decode(k: &[u8]) -> crate::Result<PublicKey> {
+        libsecp256k1::PublicKey::parse_slice(k, Some(libsecp256k1::PublicKeyFormat::Compressed))
+            .map_err(|error| Error::Other(format!("Failed to parse public key: {error:?}")))
+            .map(PublicKey)
+    }
+
+    /// Convert public key to `PeerId`.
+    pub fn to_peer_id(&self) -> PeerId {
+        crate::crypto::PublicKey::Secp256k1(self.clone()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secp256k1_secret_from_bytes() {
+        let sk1 = SecretKey::generate();
+        let mut sk_bytes = sk1.to_bytes();
+        let sk2 = SecretKey::from_bytes(&mut sk_bytes).unwrap();
+
+        assert_eq!(sk1.to_bytes(), sk2.to_bytes());
+        assert_eq!(sk_bytes, [0; 32]);
+    }
+
+    #[test]
+    fn secp256k1_public_key_encode_decode() {
+        let public = Keypair::generate().public();
+
+        assert_eq!(PublicKey::decode(&public.encode()).unwrap(), public);
+    }
+
+    #[test]
+    fn secp256k1_signature() {
+        let keypair = Keypair::generate();
+        let public = keypair.public();
+
+        let msg = "hello world".as_bytes();
+        let sig = keypair.sign(msg);
+        assert!(public.verify(msg, &sig));
+
+        let invalid_msg = "h3ll0 w0rld".as_bytes();
+        assert!(!public.verify(invalid_msg, &sig));
+        assert!(!public.verify(msg, &sig[1..]));
+    }
+}
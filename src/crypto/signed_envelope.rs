@@ -0,0 +1,203 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Signed envelopes, as specified in [RFC 0002](https://github.com/libp2p/specs/blob/master/RFC/0002-signed-envelopes.md).
+
+use crate::{
+    crypto::{ed25519::Keypair, PublicKey},
+    error::{EnvelopeError, Error},
+};
+
+use prost::Message;
+
+mod envelope_proto {
+    include!(concat!(env!("OUT_DIR"), "/envelope_proto.rs"));
+}
+
+/// Signed envelope.
+///
+/// Wraps an arbitrary payload together with the public key of the signer and a signature
+/// over the payload, its type and a domain string that limits where the signature is valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedEnvelope {
+    /// Public key of the signer.
+    key: PublicKey,
+
+    /// Payload type.
+    payload_type: Vec<u8>,
+
+    /// Payload.
+    payload: Vec<u8>,
+
+    /// Signature.
+    signature: Vec<u8>,
+}
+
+impl SignedEnvelope {
+    /// Create new [`SignedEnvelope`] by signing `payload` with `keypair`.
+    pub fn new(keypair: &Keypair, domain: &str, payload_type: Vec<u8>, payload: Vec<u8>) -> Self {
+        let signature = keypair.sign(&signature_payload(domain, &payload_type, &payload));
+
+        Self {
+            key: keypair.public().into(),
+            payload_type,
+            payload,
+            signature,
+        }
+    }
+
+    /// Get the public key of the signer.
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// Get payload type.
+    pub fn payload_type(&self) -> &[u8] {
+        &self.payload_type
+    }
+
+    /// Verify the signature of the envelope for `domain` and return the payload.
+    ///
+    /// Fails if the signature is invalid or the payload type is not `payload_type`.
+    pub fn payload(&self, domain: &str, payload_type: &[u8]) -> crate::Result<&[u8]> {
+        if self.payload_type != payload_type {
+            return Err(Error::EnvelopeError(EnvelopeError::UnexpectedPayloadType(
+                self.payload_type.clone(),
+            )));
+        }
+
+        let message = signature_payload(domain, &self.payload_type, &self.payload);
+        if !self.key.verify(&message, &self.signature) {
+            return Err(Error::EnvelopeError(EnvelopeError::InvalidSignature));
+        }
+
+        Ok(&self.payload)
+    }
+
+    /// Encode [`SignedEnvelope`] into bytes.
+    pub fn to_protobuf_encoding(&self) -> Vec<u8> {
+        let envelope = envelope_proto::Envelope {
+            public_key: self.key.to_protobuf_encoding(),
+            payload_type: self.payload_type.clone(),
+            payload: self.payload.clone(),
+            signature: self.signature.clone(),
+        };
+
+        let mut buf = Vec::with_capacity(envelope.encoded_len());
+        envelope.encode(&mut buf).expect("Vec<u8> provides capacity as needed");
+        buf
+    }
+
+    /// Decode [`SignedEnvelope`] from bytes.
+    ///
+    /// The signature is not verified until [`SignedEnvelope::payload()`] is called.
+    pub fn from_protobuf_encoding(bytes: &[u8]) -> crate::Result<Self> {
+        let envelope = envelope_proto::Envelope::decode(bytes)?;
+
+        Ok(Self {
+            key: PublicKey::from_protobuf_encoding(&envelope.public_key)?,
+            payload_type: envelope.payload_type,
+            payload: envelope.payload,
+            signature: envelope.signature,
+        })
+    }
+}
+
+/// Create the buffer that is signed: each field is prefixed with its varint-encoded length.
+fn signature_payload(domain: &str, payload_type: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(domain.len() + payload_type.len() + payload.len() + 30);
+
+    for field in [domain.as_bytes(), payload_type, payload] {
+        let mut length = unsigned_varint::encode::usize_buffer();
+        buffer.extend_from_slice(unsigned_varint::encode::usize(field.len(), &mut length));
+        buffer.extend_from_slice(field);
+    }
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOMAIN: &str = "libp2p-testing";
+    const PAYLOAD_TYPE: &[u8] = &[0x00, 0x01];
+
+    #[test]
+    fn seal_and_open() {
+        let keypair = Keypair::generate();
+        let envelope =
+            SignedEnvelope::new(&keypair, DOMAIN, PAYLOAD_TYPE.to_vec(), b"hello".to_vec());
+
+        assert_eq!(envelope.key(), &PublicKey::from(keypair.public()));
+        assert_eq!(envelope.payload(DOMAIN, PAYLOAD_TYPE).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn protobuf_roundtrip() {
+        let keypair = Keypair::generate();
+        let envelope =
+            SignedEnvelope::new(&keypair, DOMAIN, PAYLOAD_TYPE.to_vec(), b"hello".to_vec());
+        let decoded =
+            SignedEnvelope::from_protobuf_encoding(&envelope.to_protobuf_encoding()).unwrap();
+
+        assert_eq!(envelope, decoded);
+        assert_eq!(decoded.payload(DOMAIN, PAYLOAD_TYPE).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn wrong_domain() {
+        let keypair = Keypair::generate();
+        let envelope =
+            SignedEnvelope::new(&keypair, DOMAIN, PAYLOAD_TYPE.to_vec(), b"hello".to_vec());
+
+        assert!(std::matches!(
+            envelope.payload("libp2p-other", PAYLOAD_TYPE),
+            Err(Error::EnvelopeError(EnvelopeError::InvalidSignature))
+        ));
+    }
+
+    #[test]
+    fn wrong_payload_type() {
+        let keypair = Keypair::generate();
+        let envelope =
+            SignedEnvelope::new(&keypair, DOMAIN, PAYLOAD_TYPE.to_vec(), b"hello".to_vec());
+
+        assert!(std::matches!(
+            envelope.payload(DOMAIN, &[0x03, 0x01]),
+            Err(Error::EnvelopeError(EnvelopeError::UnexpectedPayloadType(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn tampered_payload() {
+        let keypair = Keypair::generate();
+        let mut envelope =
+            SignedEnvelope::new(&keypair, DOMAIN, PAYLOAD_TYPE.to_vec(), b"hello".to_vec());
+        envelope.payload = b"world".to_vec();
+
+        assert!(std::matches!(
+            envelope.payload(DOMAIN, PAYLOAD_TYPE),
+            Err(Error::EnvelopeError(EnvelopeError::InvalidSignature))
+        ));
+    }
+}
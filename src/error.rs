@@ -43,6 +43,8 @@ pub enum Error {
     PeerDoesntExist(PeerId),
     #[error("Peer `{0}` already exists")]
     PeerAlreadyExists(PeerId),
+    #[error("Peer `{0}` is banned")]
+    PeerBanned(PeerId),
     #[error("Protocol `{0}` not supported")]
     ProtocolNotSupported(String),
     #[error("Address error: `{0}`")]
@@ -117,6 +119,10 @@ pub enum Error {
     ChannelClogged,
     #[error("Connection doesn't exist: `{0:?}`")]
     ConnectionDoesntExist(ConnectionId),
+    #[error("Signed envelope error: `{0}`")]
+    EnvelopeError(EnvelopeError),
+    #[error("Retry policy must allow at least one attempt")]
+    InvalidRetryPolicy,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -135,6 +141,22 @@ pub enum ParseError {
     InvalidMultihash(Multihash),
     #[error("Failed to decode protobuf message: `{0:?}`")]
     ProstDecodeError(prost::DecodeError),
+    #[cfg(feature = "json-codec")]
+    #[error("Failed to encode/decode JSON message: `{0}`")]
+    SerdeJsonError(serde_json::Error),
+    #[cfg(feature = "scale-codec")]
+    #[error("Failed to decode SCALE message: `{0}`")]
+    ScaleDecodeError(parity_scale_codec::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnvelopeError {
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Unexpected payload type: `{0:?}`")]
+    UnexpectedPayloadType(Vec<u8>),
+    #[error("Record `PeerId` doesn't match the signing key")]
+    PeerIdMismatch,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -171,6 +193,47 @@ pub enum NotificationError {
     NotificationsClogged,
     #[error("Notification stream closed")]
     NotificationStreamClosed(PeerId),
+    #[error("Handshake update larger than the maximum notification size")]
+    HandshakeTooLarge,
+}
+
+/// Problem found while validating [`ConfigBuilder`](crate::config::ConfigBuilder).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigErrorKind {
+    #[error("No transport specified")]
+    NoTransport,
+    #[error("Protocol `{0}` registered more than once")]
+    DuplicateProtocol(ProtocolName),
+    #[error("Listen address `{address}` cannot be used with `{transport}` transport")]
+    InvalidListenAddress {
+        /// Name of the transport.
+        transport: &'static str,
+
+        /// Listen address.
+        address: Multiaddr,
+    },
+    #[error("`{0}` must be greater than zero")]
+    ZeroSized(String),
+    #[error("Transport `{0}` registered more than once")]
+    DuplicateTransport(&'static str),
+    #[error("Notification protocol `{protocol}` depends on `{main}` which is not a main notification protocol")]
+    InvalidProtocolGroup {
+        /// Secondary protocol of the group.
+        protocol: ProtocolName,
+
+        /// Main protocol of the group.
+        main: ProtocolName,
+    },
+}
+
+/// Error returned by [`ConfigBuilder::try_build()`](crate::config::ConfigBuilder::try_build).
+///
+/// Contains all problems found in the configuration.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid configuration: `{errors:?}`")]
+pub struct ConfigError {
+    /// Configuration errors.
+    pub errors: Vec<ConfigErrorKind>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -225,6 +288,20 @@ impl From<prost::DecodeError> for Error {
     }
 }
 
+#[cfg(feature = "json-codec")]
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::ParseError(ParseError::SerdeJsonError(error))
+    }
+}
+
+#[cfg(feature = "scale-codec")]
+impl From<parity_scale_codec::Error> for Error {
+    fn from(error: parity_scale_codec::Error) -> Self {
+        Error::ParseError(ParseError::ScaleDecodeError(error))
+    }
+}
+
 impl From<quinn::ConnectionError> for Error {
     fn from(error: quinn::ConnectionError) -> Self {
         match error {
@@ -23,11 +23,12 @@ use crate::{
     protocol::{
         libp2p::{bitswap::Bitswap, identify::Identify, kademlia::Kademlia, ping::Ping},
         mdns::Mdns,
-        notification::NotificationProtocol,
+        notification::{self, NotificationProtocol},
         request_response::RequestResponseProtocol,
     },
     transport::{
         manager::{SupportedTransport, TransportManager},
+        memory::MemoryTransport,
         quic::QuicTransport,
         tcp::TcpTransport,
         webrtc::WebRtcTransport,
@@ -57,6 +58,7 @@ pub mod config;
 pub mod crypto;
 pub mod error;
 pub mod executor;
+pub mod peer_record;
 pub mod protocol;
 pub mod substream;
 pub mod transport;
@@ -136,6 +138,7 @@ impl Litep2p {
             bandwidth_sink.clone(),
             litep2p_config.max_parallel_dials,
         );
+        transport_manager.set_reputation_config(litep2p_config.reputation);
 
         // add known addresses to `TransportManager`, if any exist
         if !litep2p_config.known_addresses.is_empty() {
@@ -144,6 +147,9 @@ impl Litep2p {
             }
         }
 
+        // connect secondary notification protocols to the main protocols of their groups
+        notification::connect_groups(&mut litep2p_config.notification_protocols)?;
+
         // start notification protocol event loops
         for (protocol, config) in litep2p_config.notification_protocols.into_iter() {
             tracing::debug!(
@@ -155,7 +161,7 @@ impl Litep2p {
             let service = transport_manager.register_protocol(
                 protocol,
                 config.fallback_names.clone(),
-                config.codec,
+                config.codec.clone(),
             );
             let executor = Arc::clone(&litep2p_config.executor);
             litep2p_config.executor.run(Box::pin(async move {
@@ -174,7 +180,7 @@ impl Litep2p {
             let service = transport_manager.register_protocol(
                 protocol,
                 config.fallback_names.clone(),
-                config.codec,
+                config.codec.clone(),
             );
             litep2p_config.executor.run(Box::pin(async move {
                 RequestResponseProtocol::new(service, config).run().await
@@ -203,7 +209,7 @@ impl Litep2p {
             let service = transport_manager.register_protocol(
                 ping_config.protocol.clone(),
                 Vec::new(),
-                ping_config.codec,
+                ping_config.codec.clone(),
             );
             litep2p_config.executor.run(Box::pin(async move {
                 Ping::new(service, ping_config).run().await
@@ -211,26 +217,29 @@ impl Litep2p {
         }
 
         // start kademlia protocol event loop if enabled
-        if let Some(kademlia_config) = litep2p_config.kademlia.take() {
-            tracing::debug!(
-                target: LOG_TARGET,
-                protocol_names = ?kademlia_config.protocol_names,
-                "enable ipfs kademlia protocol",
-            );
+        let mut kademlia_info = match litep2p_config.kademlia.take() {
+            None => None,
+            Some(kademlia_config) => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    protocol_names = ?kademlia_config.protocol_names,
+                    "enable ipfs kademlia protocol",
+                );
 
-            let main_protocol =
-                kademlia_config.protocol_names.get(0).expect("protocol name to exist");
-            let fallback_names = kademlia_config.protocol_names.iter().skip(1).cloned().collect();
+                let main_protocol =
+                    kademlia_config.protocol_names.get(0).expect("protocol name to exist");
+                let fallback_names =
+                    kademlia_config.protocol_names.iter().skip(1).cloned().collect();
 
-            let service = transport_manager.register_protocol(
-                main_protocol.clone(),
-                fallback_names,
-                kademlia_config.codec,
-            );
-            litep2p_config.executor.run(Box::pin(async move {
-                let _ = Kademlia::new(service, kademlia_config).run().await;
-            }));
-        }
+                let service = transport_manager.register_protocol(
+                    main_protocol.clone(),
+                    fallback_names,
+                    kademlia_config.codec.clone(),
+                );
+
+                Some((service, kademlia_config))
+            }
+        };
 
         // start identify protocol event loop if enabled
         let mut identify_info = match litep2p_config.identify.take() {
@@ -247,7 +256,7 @@ impl Litep2p {
                     Vec::new(),
                     identify_config.codec.clone(),
                 );
-                identify_config.public = Some(litep2p_config.keypair.public().into());
+                identify_config.keypair = Some(litep2p_config.keypair.clone());
 
                 Some((service, identify_config))
             }
@@ -264,7 +273,7 @@ impl Litep2p {
             let service = transport_manager.register_protocol(
                 bitswap_config.protocol.clone(),
                 Vec::new(),
-                bitswap_config.codec,
+                bitswap_config.codec.clone(),
             );
             litep2p_config.executor.run(Box::pin(async move {
                 Bitswap::new(service, bitswap_config).run().await
@@ -336,6 +345,38 @@ impl Litep2p {
                 .register_transport(SupportedTransport::WebSocket, Box::new(transport));
         }
 
+        // enable memory transport if the config exists
+        if let Some(config) = litep2p_config.memory.take() {
+            let handle = transport_manager.transport_handle(Arc::clone(&litep2p_config.executor));
+            let (transport, transport_listen_addresses) =
+                <MemoryTransport as TransportBuilder>::new(handle, config)?;
+
+            for address in transport_listen_addresses {
+                transport_manager.register_listen_address(address.clone());
+                listen_addresses.push(address.with(Protocol::P2p(
+                    Multihash::from_bytes(&local_peer_id.to_bytes()).unwrap(),
+                )));
+            }
+
+            transport_manager.register_transport(SupportedTransport::Memory, Box::new(transport));
+        }
+
+        // enable custom transports
+        for (transport, factory) in std::mem::take(&mut litep2p_config.custom_transports) {
+            let handle = transport_manager.transport_handle(Arc::clone(&litep2p_config.executor));
+            let (transport_object, transport_listen_addresses) = factory(handle)?;
+
+            for address in transport_listen_addresses {
+                transport_manager.register_listen_address(address.clone());
+                listen_addresses.push(address.with(Protocol::P2p(
+                    Multihash::from_bytes(&local_peer_id.to_bytes()).unwrap(),
+                )));
+            }
+
+            transport_manager
+                .register_transport(SupportedTransport::Custom(transport), transport_object);
+        }
+
         // enable mdns if the config exists
         if let Some(config) = litep2p_config.mdns.take() {
             let mdns = Mdns::new(transport_handle, config, listen_addresses.clone())?;
@@ -345,6 +386,16 @@ impl Litep2p {
             }));
         }
 
+        // if kademlia was enabled, give it the listen addresses advertised in provider records and
+        // start it
+        if let Some((service, kademlia_config)) = kademlia_info.take() {
+            let kademlia = Kademlia::new(service, kademlia_config, listen_addresses.clone());
+
+            litep2p_config.executor.run(Box::pin(async move {
+                let _ = kademlia.run().await;
+            }));
+        }
+
         // if identify was enabled, give it the enabled protocols and listen addresses and start it
         if let Some((service, mut identify_config)) = identify_info.take() {
             identify_config.protocols = transport_manager.protocols().cloned().collect();
@@ -399,6 +450,14 @@ impl Litep2p {
             .webrtc
             .is_some()
             .then(|| supported_transports.insert(SupportedTransport::WebRtc));
+        config
+            .memory
+            .is_some()
+            .then(|| supported_transports.insert(SupportedTransport::Memory));
+
+        for (transport, _) in &config.custom_transports {
+            supported_transports.insert(SupportedTransport::Custom(*transport));
+        }
 
         supported_transports
     }
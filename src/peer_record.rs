@@ -0,0 +1,240 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Signed peer records, as specified in [RFC 0003](https://github.com/libp2p/specs/blob/master/RFC/0003-routing-records.md).
+
+use crate::{
+    crypto::{ed25519::Keypair, signed_envelope::SignedEnvelope, PublicKey},
+    error::{EnvelopeError, Error},
+    PeerId,
+};
+
+use multiaddr::Multiaddr;
+use prost::Message;
+
+mod peer_record_proto {
+    include!(concat!(env!("OUT_DIR"), "/peer_record_proto.rs"));
+}
+
+/// Domain string used when signing peer records.
+const PEER_RECORD_DOMAIN: &str = "libp2p-routing-state";
+
+/// Multicodec of the peer record payload type (`libp2p-peer-record`).
+const PEER_RECORD_PAYLOAD_TYPE: &[u8] = &[0x03, 0x01];
+
+/// Peer record.
+///
+/// Contains the addresses of a peer and a sequence number which allows selecting
+/// the most recent record if multiple records have been received for the same peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerRecord {
+    /// Peer ID.
+    peer: PeerId,
+
+    /// Sequence number.
+    seq: u64,
+
+    /// Addresses of the peer.
+    addresses: Vec<Multiaddr>,
+}
+
+impl PeerRecord {
+    /// Create new [`PeerRecord`].
+    pub fn new(peer: PeerId, seq: u64, addresses: Vec<Multiaddr>) -> Self {
+        Self {
+            peer,
+            seq,
+            addresses,
+        }
+    }
+
+    /// Get peer ID.
+    pub fn peer(&self) -> &PeerId {
+        &self.peer
+    }
+
+    /// Get sequence number.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Get addresses.
+    pub fn addresses(&self) -> &[Multiaddr] {
+        &self.addresses
+    }
+
+    /// Encode [`PeerRecord`] into bytes.
+    fn encode(&self) -> Vec<u8> {
+        let record = peer_record_proto::PeerRecord {
+            peer_id: self.peer.to_bytes(),
+            seq: self.seq,
+            addresses: self
+                .addresses
+                .iter()
+                .map(|address| peer_record_proto::peer_record::AddressInfo {
+                    multiaddr: address.to_vec(),
+                })
+                .collect(),
+        };
+
+        let mut buf = Vec::with_capacity(record.encoded_len());
+        record.encode(&mut buf).expect("Vec<u8> provides capacity as needed");
+        buf
+    }
+
+    /// Decode [`PeerRecord`] from bytes.
+    ///
+    /// Addresses which cannot be parsed are ignored.
+    fn decode(bytes: &[u8]) -> crate::Result<Self> {
+        let record = peer_record_proto::PeerRecord::decode(bytes)?;
+
+        Ok(Self {
+            peer: PeerId::from_bytes(&record.peer_id).map_err(|_| Error::InvalidData)?,
+            seq: record.seq,
+            addresses: record
+                .addresses
+                .into_iter()
+                .filter_map(|info| Multiaddr::try_from(info.multiaddr).ok())
+                .collect(),
+        })
+    }
+}
+
+/// Peer record signed by the peer it describes.
+///
+/// Addresses of a [`SignedPeerRecord`] are certified: unlike addresses learned from third
+/// parties, they are known to originate from the peer itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedPeerRecord {
+    /// Signed envelope containing the encoded record.
+    envelope: SignedEnvelope,
+
+    /// Decoded peer record.
+    record: PeerRecord,
+}
+
+impl SignedPeerRecord {
+    /// Create new [`SignedPeerRecord`] for the local node and sign it with `keypair`.
+    pub fn new(keypair: &Keypair, seq: u64, addresses: Vec<Multiaddr>) -> Self {
+        let peer = PeerId::from_public_key(&PublicKey::from(keypair.public()));
+        let record = PeerRecord::new(peer, seq, addresses);
+        let envelope = SignedEnvelope::new(
+            keypair,
+            PEER_RECORD_DOMAIN,
+            PEER_RECORD_PAYLOAD_TYPE.to_vec(),
+            record.encode(),
+        );
+
+        Self { envelope, record }
+    }
+
+    /// Get the peer record.
+    pub fn record(&self) -> &PeerRecord {
+        &self.record
+    }
+
+    /// Get the signed envelope.
+    pub fn envelope(&self) -> &SignedEnvelope {
+        &self.envelope
+    }
+
+    /// Verify `envelope` and extract the peer record from it.
+    ///
+    /// Fails if the signature is invalid or the record was not signed by the peer it describes.
+    pub fn from_envelope(envelope: SignedEnvelope) -> crate::Result<Self> {
+        let record =
+            PeerRecord::decode(envelope.payload(PEER_RECORD_DOMAIN, PEER_RECORD_PAYLOAD_TYPE)?)?;
+
+        if record.peer != envelope.key().to_peer_id() {
+            return Err(Error::EnvelopeError(EnvelopeError::PeerIdMismatch));
+        }
+
+        Ok(Self { envelope, record })
+    }
+
+    /// Encode [`SignedPeerRecord`] into bytes.
+    pub fn to_protobuf_encoding(&self) -> Vec<u8> {
+        self.envelope.to_protobuf_encoding()
+    }
+
+    /// Decode and verify [`SignedPeerRecord`] from bytes.
+    pub fn from_protobuf_encoding(bytes: &[u8]) -> crate::Result<Self> {
+        Self::from_envelope(SignedEnvelope::from_protobuf_encoding(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let keypair = Keypair::generate();
+        let addresses = vec![
+            "/ip4/127.0.0.1/tcp/8888".parse().unwrap(),
+            "/ip6/::1/udp/8888/quic-v1".parse().unwrap(),
+        ];
+        let record = SignedPeerRecord::new(&keypair, 1337, addresses.clone());
+        let decoded =
+            SignedPeerRecord::from_protobuf_encoding(&record.to_protobuf_encoding()).unwrap();
+
+        assert_eq!(record, decoded);
+        assert_eq!(
+            decoded.record().peer(),
+            &PeerId::from_public_key(&PublicKey::from(keypair.public()))
+        );
+        assert_eq!(decoded.record().seq(), 1337);
+        assert_eq!(decoded.record().addresses(), &addresses);
+    }
+
+    #[test]
+    fn record_for_other_peer_rejected() {
+        let keypair = Keypair::generate();
+        let record = PeerRecord::new(PeerId::random(), 1, vec![]);
+        let envelope = SignedEnvelope::new(
+            &keypair,
+            PEER_RECORD_DOMAIN,
+            PEER_RECORD_PAYLOAD_TYPE.to_vec(),
+            record.encode(),
+        );
+
+        assert!(std::matches!(
+            SignedPeerRecord::from_envelope(envelope),
+            Err(Error::EnvelopeError(EnvelopeError::PeerIdMismatch))
+        ));
+    }
+
+    #[test]
+    fn wrong_domain_rejected() {
+        let keypair = Keypair::generate();
+        let peer = PeerId::from_public_key(&PublicKey::from(keypair.public()));
+        let envelope = SignedEnvelope::new(
+            &keypair,
+            "libp2p-other",
+            PEER_RECORD_PAYLOAD_TYPE.to_vec(),
+            PeerRecord::new(peer, 1, vec![]).encode(),
+        );
+
+        assert!(std::matches!(
+            SignedPeerRecord::from_envelope(envelope),
+            Err(Error::EnvelopeError(EnvelopeError::InvalidSignature))
+        ));
+    }
+}
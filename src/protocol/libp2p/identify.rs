@@ -22,8 +22,9 @@
 
 use crate::{
     codec::ProtocolCodec,
-    crypto::PublicKey,
+    crypto::{ed25519::Keypair, PublicKey},
     error::{Error, SubstreamError},
+    peer_record::SignedPeerRecord,
     protocol::{Direction, TransportEvent, TransportService},
     substream::Substream,
     transport::Endpoint,
@@ -39,7 +40,7 @@ use tokio_stream::wrappers::ReceiverStream;
 
 use std::{
     collections::{HashMap, HashSet},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 /// Log target for the file.
@@ -73,8 +74,8 @@ pub struct Config {
     /// TX channel for sending events to the user protocol.
     tx_event: Sender<IdentifyEvent>,
 
-    // Public key of the local node, filled by `Litep2p`.
-    pub(crate) public: Option<PublicKey>,
+    /// Keypair of the local node, filled by `Litep2p`.
+    pub(crate) keypair: Option<Keypair>,
 
     /// Protocols supported by the local node, filled by `Litep2p`.
     pub(crate) protocols: Vec<ProtocolName>,
@@ -104,7 +105,7 @@ impl Config {
         (
             Self {
                 tx_event,
-                public: None,
+                keypair: None,
                 public_addresses,
                 protocol_version,
                 user_agent,
@@ -139,6 +140,12 @@ pub enum IdentifyEvent {
 
         /// Listen addresses.
         listen_addresses: Vec<Multiaddr>,
+
+        /// Signed peer record, if remote sent a valid one.
+        ///
+        /// Addresses of the record have been added to known addresses of the peer as certified
+        /// addresses.
+        signed_peer_record: Option<SignedPeerRecord>,
     },
 }
 
@@ -161,6 +168,9 @@ struct IdentifyResponse {
 
     /// Observed address.
     observed_address: Option<Multiaddr>,
+
+    /// Verified signed peer record.
+    signed_peer_record: Option<SignedPeerRecord>,
 }
 
 pub(crate) struct Identify {
@@ -176,6 +186,9 @@ pub(crate) struct Identify {
     // Public key of the local node, filled by `Litep2p`.
     public: PublicKey,
 
+    /// Encoded signed peer record of the local node.
+    signed_peer_record: Vec<u8>,
+
     /// Protocol version.
     protocol_version: String,
 
@@ -205,16 +218,26 @@ impl Identify {
         config: Config,
         listen_addresses: Vec<Multiaddr>,
     ) -> Self {
+        let keypair = config.keypair.expect("keypair to be supplied");
+        let listen_addresses: HashSet<Multiaddr> =
+            config.public_addresses.into_iter().chain(listen_addresses).collect();
+
+        // use the current time as sequence number so records created after a restart
+        // supersede the old ones
+        let seq = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0u64, |duration| duration.as_millis() as u64);
+        let signed_peer_record =
+            SignedPeerRecord::new(&keypair, seq, listen_addresses.iter().cloned().collect())
+                .to_protobuf_encoding();
+
         Self {
             service,
             tx: config.tx_event,
             peers: HashMap::new(),
-            listen_addresses: config
-                .public_addresses
-                .into_iter()
-                .chain(listen_addresses.into_iter())
-                .collect(),
-            public: config.public.expect("public key to be supplied"),
+            listen_addresses,
+            public: keypair.public().into(),
+            signed_peer_record,
             protocol_version: config.protocol_version,
             user_agent: config.user_agent.unwrap_or(DEFAULT_AGENT.to_string()),
             pending_opens: HashMap::new(),
@@ -280,6 +303,7 @@ impl Identify {
                 .collect::<Vec<_>>(),
             observed_addr,
             protocols: self.protocols.clone(),
+            signed_peer_record: Some(self.signed_peer_record.clone()),
         };
 
         tracing::trace!(
@@ -358,6 +382,29 @@ impl Identify {
                 info.observed_addr.map(|address| Multiaddr::try_from(address).ok()).flatten();
             let protocol_version = info.protocol_version;
             let user_agent = info.agent_version;
+            let signed_peer_record = info.signed_peer_record.and_then(|record| {
+                match SignedPeerRecord::from_protobuf_encoding(&record) {
+                    Ok(record) if record.record().peer() == &peer => Some(record),
+                    Ok(record) => {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            record_peer = ?record.record().peer(),
+                            "signed peer record is for a different peer",
+                        );
+                        None
+                    }
+                    Err(error) => {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            ?error,
+                            "invalid signed peer record",
+                        );
+                        None
+                    }
+                }
+            });
 
             Ok(IdentifyResponse {
                 peer,
@@ -366,6 +413,7 @@ impl Identify {
                 supported_protocols: HashSet::from_iter(info.protocols),
                 observed_address,
                 listen_addresses,
+                signed_peer_record,
             })
         }));
     }
@@ -399,6 +447,13 @@ impl Identify {
                 _ = self.pending_inbound.next(), if !self.pending_inbound.is_empty() => {}
                 event = self.pending_outbound.next(), if !self.pending_outbound.is_empty() => match event {
                     Some(Ok(response)) => {
+                        if let Some(record) = &response.signed_peer_record {
+                            self.service.add_certified_address(
+                                &response.peer,
+                                record.record().addresses().iter().cloned(),
+                            );
+                        }
+
                         let _ = self.tx
                             .send(IdentifyEvent::PeerIdentified {
                                 peer: response.peer,
@@ -407,6 +462,7 @@ impl Identify {
                                 supported_protocols: response.supported_protocols.into_iter().map(From::from).collect(),
                                 observed_address: response.observed_address.map_or(Multiaddr::empty(), |address| address),
                                 listen_addresses: response.listen_addresses,
+                                signed_peer_record: response.signed_peer_record,
                             })
                             .await;
                     }
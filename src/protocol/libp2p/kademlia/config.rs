@@ -30,7 +30,11 @@ use crate::{
 use multiaddr::Multiaddr;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
 
 /// Protocol name.
 const PROTOCOL_NAME: &str = "/ipfs/kad/1.0.0";
@@ -38,6 +42,21 @@ const PROTOCOL_NAME: &str = "/ipfs/kad/1.0.0";
 /// Kademlia replication factor.
 const REPLICATION_FACTOR: usize = 20usize;
 
+/// Default TTL for records.
+const RECORD_TTL: Duration = Duration::from_secs(36 * 60 * 60);
+
+/// Default republish interval for locally published records.
+const RECORD_REPUBLISH_INTERVAL: Duration = Duration::from_secs(22 * 60 * 60);
+
+/// Default replication interval for stored records.
+const RECORD_REPLICATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default TTL for provider records.
+const PROVIDER_TTL: Duration = Duration::from_secs(48 * 60 * 60);
+
+/// Default republish interval for local provider records.
+const PROVIDER_REPUBLISH_INTERVAL: Duration = Duration::from_secs(22 * 60 * 60);
+
 /// Kademlia configuration.
 #[derive(Debug)]
 pub struct Config {
@@ -59,6 +78,24 @@ pub struct Config {
     /// Routing table update mode.
     pub(super) update_mode: RoutingTableUpdateMode,
 
+    /// TTL for records.
+    pub(super) record_ttl: Duration,
+
+    /// Republish interval for locally published records.
+    pub(super) record_republish_interval: Duration,
+
+    /// Replication interval for stored records.
+    pub(super) record_replication_interval: Duration,
+
+    /// TTL for provider records.
+    pub(super) provider_ttl: Duration,
+
+    /// Republish interval for local provider records.
+    pub(super) provider_republish_interval: Duration,
+
+    /// Next query ID, shared with `KademliaHandle`.
+    pub(super) next_query_id: Arc<AtomicUsize>,
+
     /// TX channel for sending events to `KademliaHandle`.
     pub(super) event_tx: Sender<KademliaEvent>,
 
@@ -72,9 +109,15 @@ impl Config {
         known_peers: HashMap<PeerId, Vec<Multiaddr>>,
         mut protocol_names: Vec<ProtocolName>,
         update_mode: RoutingTableUpdateMode,
+        record_ttl: Duration,
+        record_republish_interval: Duration,
+        record_replication_interval: Duration,
+        provider_ttl: Duration,
+        provider_republish_interval: Duration,
     ) -> (Self, KademliaHandle) {
         let (cmd_tx, cmd_rx) = channel(DEFAULT_CHANNEL_SIZE);
         let (event_tx, event_rx) = channel(DEFAULT_CHANNEL_SIZE);
+        let next_query_id = Arc::new(AtomicUsize::new(0usize));
 
         // if no protocol names were provided, use the default protocol
         if protocol_names.is_empty() {
@@ -88,10 +131,16 @@ impl Config {
                 codec: ProtocolCodec::UnsignedVarint(None),
                 replication_factor,
                 known_peers,
+                record_ttl,
+                record_republish_interval,
+                record_replication_interval,
+                provider_ttl,
+                provider_republish_interval,
+                next_query_id: next_query_id.clone(),
                 cmd_rx,
                 event_tx,
             },
-            KademliaHandle::new(cmd_tx, event_rx),
+            KademliaHandle::new(cmd_tx, event_rx, next_query_id),
         )
     }
 
@@ -102,6 +151,11 @@ impl Config {
             HashMap::new(),
             Vec::new(),
             RoutingTableUpdateMode::Automatic,
+            RECORD_TTL,
+            RECORD_REPUBLISH_INTERVAL,
+            RECORD_REPLICATION_INTERVAL,
+            PROVIDER_TTL,
+            PROVIDER_REPUBLISH_INTERVAL,
         )
     }
 }
@@ -120,6 +174,21 @@ pub struct ConfigBuilder {
 
     /// Protocol names.
     pub(super) protocol_names: Vec<ProtocolName>,
+
+    /// TTL for records.
+    pub(super) record_ttl: Duration,
+
+    /// Republish interval for locally published records.
+    pub(super) record_republish_interval: Duration,
+
+    /// Replication interval for stored records.
+    pub(super) record_replication_interval: Duration,
+
+    /// TTL for provider records.
+    pub(super) provider_ttl: Duration,
+
+    /// Republish interval for local provider records.
+    pub(super) provider_republish_interval: Duration,
 }
 
 impl ConfigBuilder {
@@ -130,6 +199,11 @@ impl ConfigBuilder {
             known_peers: HashMap::new(),
             protocol_names: Vec::new(),
             update_mode: RoutingTableUpdateMode::Automatic,
+            record_ttl: RECORD_TTL,
+            record_republish_interval: RECORD_REPUBLISH_INTERVAL,
+            record_replication_interval: RECORD_REPLICATION_INTERVAL,
+            provider_ttl: PROVIDER_TTL,
+            provider_republish_interval: PROVIDER_REPUBLISH_INTERVAL,
         }
     }
 
@@ -165,6 +239,50 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set default TTL for records.
+    ///
+    /// Records published without an expiration time expire after `ttl` and records received
+    /// from remote peers are stored for at most `ttl`. Defaults to 36 hours.
+    pub fn with_record_ttl(mut self, ttl: Duration) -> Self {
+        self.record_ttl = ttl;
+        self
+    }
+
+    /// Set the interval at which the local node republishes the records it has published.
+    ///
+    /// The interval should be shorter than the record TTL. Defaults to 22 hours.
+    pub fn with_record_republish_interval(mut self, interval: Duration) -> Self {
+        self.record_republish_interval = interval;
+        self
+    }
+
+    /// Set the interval at which records stored by the local node are replicated to the peers
+    /// closest to them.
+    ///
+    /// Replication keeps the records available as peers join and leave the network.
+    /// Defaults to 1 hour.
+    pub fn with_record_replication_interval(mut self, interval: Duration) -> Self {
+        self.record_replication_interval = interval;
+        self
+    }
+
+    /// Set TTL for provider records.
+    ///
+    /// Provider records stored by the local node expire after `ttl` unless they're republished
+    /// by the provider. Defaults to 48 hours.
+    pub fn with_provider_record_ttl(mut self, ttl: Duration) -> Self {
+        self.provider_ttl = ttl;
+        self
+    }
+
+    /// Set the interval at which the local node republishes its provider records.
+    ///
+    /// The interval should be shorter than the provider record TTL. Defaults to 22 hours.
+    pub fn with_provider_republish_interval(mut self, interval: Duration) -> Self {
+        self.provider_republish_interval = interval;
+        self
+    }
+
     /// Build Kademlia [`Config`].
     pub fn build(self) -> (Config, KademliaHandle) {
         Config::new(
@@ -172,6 +290,11 @@ impl ConfigBuilder {
             self.known_peers,
             self.protocol_names,
             self.update_mode,
+            self.record_ttl,
+            self.record_republish_interval,
+            self.record_replication_interval,
+            self.provider_ttl,
+            self.provider_republish_interval,
         )
     }
 }
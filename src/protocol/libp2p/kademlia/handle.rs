@@ -30,6 +30,10 @@ use tokio::sync::mpsc::{Receiver, Sender};
 use std::{
     num::NonZeroUsize,
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
@@ -101,6 +105,30 @@ pub(crate) enum KademliaCommand {
         /// Query ID for the query.
         query_id: QueryId,
     },
+
+    /// Start providing the content identified by `key`.
+    StartProviding {
+        /// Provider record key.
+        key: RecordKey,
+
+        /// Query ID for the query.
+        query_id: QueryId,
+    },
+
+    /// Stop providing the content identified by `key`.
+    StopProviding {
+        /// Provider record key.
+        key: RecordKey,
+    },
+
+    /// Get providers of the content identified by `key`.
+    GetProviders {
+        /// Provider record key.
+        key: RecordKey,
+
+        /// Query ID for the query.
+        query_id: QueryId,
+    },
 }
 
 /// Kademlia events.
@@ -149,6 +177,18 @@ pub enum KademliaEvent {
         key: RecordKey,
     },
 
+    /// `GET_PROVIDERS` query succeeded.
+    GetProvidersSuccess {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Provider record key.
+        key: RecordKey,
+
+        /// Found providers and their addresses.
+        providers: Vec<(PeerId, Vec<Multiaddr>)>,
+    },
+
     /// Query failed.
     QueryFailed {
         /// Query ID.
@@ -165,25 +205,26 @@ pub struct KademliaHandle {
     event_rx: Receiver<KademliaEvent>,
 
     /// Next query ID.
-    next_query_id: usize,
+    next_query_id: Arc<AtomicUsize>,
 }
 
 impl KademliaHandle {
     /// Create new [`KademliaHandle`].
-    pub(super) fn new(cmd_tx: Sender<KademliaCommand>, event_rx: Receiver<KademliaEvent>) -> Self {
+    pub(super) fn new(
+        cmd_tx: Sender<KademliaCommand>,
+        event_rx: Receiver<KademliaEvent>,
+        next_query_id: Arc<AtomicUsize>,
+    ) -> Self {
         Self {
             cmd_tx,
             event_rx,
-            next_query_id: 0usize,
+            next_query_id,
         }
     }
 
     /// Allocate next query ID.
     fn next_query_id(&mut self) -> QueryId {
-        let query_id = self.next_query_id;
-        self.next_query_id += 1;
-
-        QueryId(query_id)
+        QueryId(self.next_query_id.fetch_add(1usize, Ordering::Relaxed))
     }
 
     /// Add known peer.
@@ -222,6 +263,38 @@ impl KademliaHandle {
         query_id
     }
 
+    /// Start providing the content identified by `key`.
+    ///
+    /// The local node is advertised as a provider to the peers closest to `key` and the
+    /// advertisement is republished periodically until [`KademliaHandle::stop_providing()`]
+    /// is called.
+    ///
+    /// [`KademliaEvent::QueryFailed`] is emitted if no peer could be reached, e.g., because the
+    /// routing table is empty. The local provider record is stored regardless and advertised
+    /// again when provider records are republished.
+    pub async fn start_providing(&mut self, key: RecordKey) -> QueryId {
+        let query_id = self.next_query_id();
+        let _ = self.cmd_tx.send(KademliaCommand::StartProviding { key, query_id }).await;
+
+        query_id
+    }
+
+    /// Stop providing the content identified by `key`.
+    ///
+    /// The local provider record is removed and the advertisement is no longer republished.
+    /// Provider records stored by remote peers expire on their own.
+    pub async fn stop_providing(&mut self, key: RecordKey) {
+        let _ = self.cmd_tx.send(KademliaCommand::StopProviding { key }).await;
+    }
+
+    /// Get providers of the content identified by `key` from DHT.
+    pub async fn get_providers(&mut self, key: RecordKey) -> QueryId {
+        let query_id = self.next_query_id();
+        let _ = self.cmd_tx.send(KademliaCommand::GetProviders { key, query_id }).await;
+
+        query_id
+    }
+
     /// Try to add known peer and if the channel is clogged, return an error.
     pub fn try_add_known_peer(&self, peer: PeerId, addresses: Vec<Multiaddr>) -> Result<(), ()> {
         self.cmd_tx
@@ -259,6 +332,29 @@ impl KademliaHandle {
             .map(|_| query_id)
             .map_err(|_| ())
     }
+
+    /// Try to start providing `key` and if the channel is clogged, return an error.
+    pub fn try_start_providing(&mut self, key: RecordKey) -> Result<QueryId, ()> {
+        let query_id = self.next_query_id();
+        self.cmd_tx
+            .try_send(KademliaCommand::StartProviding { key, query_id })
+            .map(|_| query_id)
+            .map_err(|_| ())
+    }
+
+    /// Try to stop providing `key` and if the channel is clogged, return an error.
+    pub fn try_stop_providing(&mut self, key: RecordKey) -> Result<(), ()> {
+        self.cmd_tx.try_send(KademliaCommand::StopProviding { key }).map_err(|_| ())
+    }
+
+    /// Try to initiate `GET_PROVIDERS` query and if the channel is clogged, return an error.
+    pub fn try_get_providers(&mut self, key: RecordKey) -> Result<QueryId, ()> {
+        let query_id = self.next_query_id();
+        self.cmd_tx
+            .try_send(KademliaCommand::GetProviders { key, query_id })
+            .map(|_| query_id)
+            .map_err(|_| ())
+    }
 }
 
 impl Stream for KademliaHandle {
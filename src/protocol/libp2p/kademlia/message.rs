@@ -18,18 +18,57 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::protocol::libp2p::kademlia::{
-    record::{Key as RecordKey, Record},
-    schema,
-    types::KademliaPeer,
+use crate::{
+    protocol::libp2p::kademlia::{
+        record::{Key as RecordKey, Record},
+        schema,
+        types::KademliaPeer,
+    },
+    PeerId,
 };
 
 use bytes::{Bytes, BytesMut};
 use prost::Message;
 
+use std::time::{Duration, Instant};
+
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::ipfs::kademlia::message";
 
+/// Convert [`Record`] into its protobuf representation.
+///
+/// The remaining time-to-live of the record is sent in the `ttl` field, rounded up to seconds.
+/// Zero `ttl` means that the record doesn't expire, so the `ttl` of a record with an expiration
+/// time is at least one second.
+fn record_to_schema(record: Record) -> schema::kademlia::Record {
+    let ttl = record.expires.map_or(0u32, |expires| {
+        let ttl = expires.saturating_duration_since(Instant::now());
+        let ttl = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+        u32::try_from(ttl.max(1)).unwrap_or(u32::MAX)
+    });
+
+    schema::kademlia::Record {
+        key: record.key.into(),
+        value: record.value,
+        publisher: record.publisher.map_or(Vec::new(), |publisher| publisher.to_bytes()),
+        ttl,
+        ..Default::default()
+    }
+}
+
+/// Convert protobuf representation of a record into [`Record`].
+///
+/// Zero `ttl` means that the remote didn't specify a time-to-live for the record.
+fn record_from_schema(record: schema::kademlia::Record) -> Record {
+    Record {
+        key: RecordKey::from(record.key),
+        value: record.value,
+        publisher: PeerId::from_bytes(&record.publisher).ok(),
+        expires: (record.ttl > 0)
+            .then(|| Instant::now() + Duration::from_secs(u64::from(record.ttl))),
+    }
+}
+
 /// Kademlia message.
 #[derive(Debug, Clone)]
 pub enum KademliaMessage {
@@ -59,6 +98,27 @@ pub enum KademliaMessage {
         /// Peers closest to key.
         peers: Vec<KademliaPeer>,
     },
+
+    /// `ADD_PROVIDER` message.
+    AddProvider {
+        /// Key of the provided content.
+        key: RecordKey,
+
+        /// Providers of the content.
+        providers: Vec<KademliaPeer>,
+    },
+
+    /// `GET_PROVIDERS` message.
+    GetProviders {
+        /// Key.
+        key: Option<RecordKey>,
+
+        /// Providers of the content.
+        providers: Vec<KademliaPeer>,
+
+        /// Peers closest to key.
+        peers: Vec<KademliaPeer>,
+    },
 }
 
 impl KademliaMessage {
@@ -78,16 +138,11 @@ impl KademliaMessage {
     }
 
     /// Create `PUT_VALUE` message for `record`.
-    // TODO: set ttl
     pub fn put_value(record: Record) -> Bytes {
         let message = schema::kademlia::Message {
             key: record.key.clone().into(),
             r#type: schema::kademlia::MessageType::PutValue.into(),
-            record: Some(schema::kademlia::Record {
-                key: record.key.into(),
-                value: record.value,
-                ..Default::default()
-            }),
+            record: Some(record_to_schema(record)),
             cluster_level_raw: 10,
             ..Default::default()
         };
@@ -113,6 +168,37 @@ impl KademliaMessage {
         buf.freeze()
     }
 
+    /// Create `ADD_PROVIDER` message for `key`, advertising `provider` as a provider for it.
+    pub fn add_provider(key: RecordKey, provider: KademliaPeer) -> Bytes {
+        let message = schema::kademlia::Message {
+            key: key.into(),
+            r#type: schema::kademlia::MessageType::AddProvider.into(),
+            provider_peers: vec![(&provider).into()],
+            cluster_level_raw: 10,
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::with_capacity(message.encoded_len());
+        message.encode(&mut buf).expect("BytesMut to provide needed capacity");
+
+        buf.freeze()
+    }
+
+    /// Create `GET_PROVIDERS` message for `key`.
+    pub fn get_providers_request(key: RecordKey) -> Bytes {
+        let message = schema::kademlia::Message {
+            key: key.into(),
+            r#type: schema::kademlia::MessageType::GetProviders.into(),
+            cluster_level_raw: 10,
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::with_capacity(message.encoded_len());
+        message.encode(&mut buf).expect("BytesMut to provide needed capacity");
+
+        buf.freeze()
+    }
+
     /// Create `FIND_NODE` response.
     pub fn find_node_response<K: AsRef<[u8]>>(key: K, peers: Vec<KademliaPeer>) -> Vec<u8> {
         let message = schema::kademlia::Message {
@@ -140,11 +226,28 @@ impl KademliaMessage {
             cluster_level_raw: 10,
             r#type: schema::kademlia::MessageType::GetValue.into(),
             closer_peers: peers.iter().map(|peer| peer.into()).collect(),
-            record: record.map(|record| schema::kademlia::Record {
-                key: record.key.to_vec(),
-                value: record.value,
-                ..Default::default()
-            }),
+            record: record.map(record_to_schema),
+            ..Default::default()
+        };
+
+        let mut buf = Vec::with_capacity(message.encoded_len());
+        message.encode(&mut buf).expect("Vec<u8> to provide needed capacity");
+
+        buf
+    }
+
+    /// Create `GET_PROVIDERS` response.
+    pub fn get_providers_response(
+        key: RecordKey,
+        providers: Vec<KademliaPeer>,
+        peers: Vec<KademliaPeer>,
+    ) -> Vec<u8> {
+        let message = schema::kademlia::Message {
+            key: key.to_vec(),
+            cluster_level_raw: 10,
+            r#type: schema::kademlia::MessageType::GetProviders.into(),
+            closer_peers: peers.iter().map(|peer| peer.into()).collect(),
+            provider_peers: providers.iter().map(|peer| peer.into()).collect(),
             ..Default::default()
         };
 
@@ -174,7 +277,7 @@ impl KademliaMessage {
                     let record = message.record?;
 
                     Some(Self::PutValue {
-                        record: Record::new(record.key, record.value),
+                        record: record_from_schema(record),
                     })
                 }
                 1 => {
@@ -192,7 +295,7 @@ impl KademliaMessage {
 
                     Some(Self::GetRecord {
                         key,
-                        record: message.record.map(|record| Record::new(record.key, record.value)),
+                        record: message.record.map(record_from_schema),
                         peers: message
                             .closer_peers
                             .iter()
@@ -200,6 +303,34 @@ impl KademliaMessage {
                             .collect(),
                     })
                 }
+                2 => {
+                    if message.key.is_empty() {
+                        tracing::debug!(target: LOG_TARGET, "`ADD_PROVIDER` message without key");
+                        return None;
+                    }
+
+                    Some(Self::AddProvider {
+                        key: RecordKey::from(message.key),
+                        providers: message
+                            .provider_peers
+                            .iter()
+                            .filter_map(|peer| KademliaPeer::try_from(peer).ok())
+                            .collect(),
+                    })
+                }
+                3 => Some(Self::GetProviders {
+                    key: (!message.key.is_empty()).then(|| RecordKey::from(message.key)),
+                    providers: message
+                        .provider_peers
+                        .iter()
+                        .filter_map(|peer| KademliaPeer::try_from(peer).ok())
+                        .collect(),
+                    peers: message
+                        .closer_peers
+                        .iter()
+                        .filter_map(|peer| KademliaPeer::try_from(peer).ok())
+                        .collect(),
+                }),
                 message => {
                     tracing::warn!(target: LOG_TARGET, ?message, "unhandled message");
                     None
@@ -212,3 +343,64 @@ impl KademliaMessage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_value_ttl_and_publisher() {
+        let publisher = PeerId::random();
+        let mut record = Record::new(RecordKey::from(vec![1, 2, 3, 4]), vec![1, 3, 3, 7])
+            .with_ttl(Duration::from_secs(60 * 60));
+        record.publisher = Some(publisher);
+
+        let bytes = KademliaMessage::put_value(record.clone());
+
+        match KademliaMessage::from_bytes(BytesMut::from(&bytes[..])) {
+            Some(KademliaMessage::PutValue { record: decoded }) => {
+                assert_eq!(decoded.key, record.key);
+                assert_eq!(decoded.value, record.value);
+                assert_eq!(decoded.publisher, Some(publisher));
+
+                let ttl = decoded.expires.unwrap().saturating_duration_since(Instant::now());
+                assert!(ttl > Duration::from_secs(59 * 60) && ttl <= Duration::from_secs(60 * 60));
+            }
+            message => panic!("invalid message decoded: {message:?}"),
+        }
+    }
+
+    #[test]
+    fn put_value_without_ttl() {
+        let record = Record::new(RecordKey::from(vec![1, 2, 3, 4]), vec![1, 3, 3, 7]);
+        let bytes = KademliaMessage::put_value(record);
+
+        match KademliaMessage::from_bytes(BytesMut::from(&bytes[..])) {
+            Some(KademliaMessage::PutValue { record }) => {
+                assert!(record.expires.is_none());
+                assert!(record.publisher.is_none());
+            }
+            message => panic!("invalid message decoded: {message:?}"),
+        }
+    }
+
+    #[test]
+    fn put_value_expiring_record_keeps_ttl() {
+        let mut record = Record::new(RecordKey::from(vec![1, 2, 3, 4]), vec![1, 3, 3, 7]);
+        record.expires = Some(Instant::now() + Duration::from_millis(500));
+
+        assert_eq!(record_to_schema(record.clone()).ttl, 1);
+
+        // expired record isn't sent without a ttl either
+        record.expires = Some(Instant::now());
+        let bytes = KademliaMessage::put_value(record);
+
+        match KademliaMessage::from_bytes(BytesMut::from(&bytes[..])) {
+            Some(KademliaMessage::PutValue { record }) => {
+                let ttl = record.expires.unwrap().saturating_duration_since(Instant::now());
+                assert!(ttl <= Duration::from_secs(1));
+            }
+            message => panic!("invalid message decoded: {message:?}"),
+        }
+    }
+}
@@ -43,14 +43,24 @@ use crate::{
 use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use multiaddr::Multiaddr;
-use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::{
+    sync::mpsc::{Receiver, Sender},
+    time::{Interval, MissedTickBehavior},
+};
 
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 pub use config::{Config, ConfigBuilder};
 pub use handle::{KademliaEvent, KademliaHandle, Quorum, RoutingTableUpdateMode};
 pub use query::QueryId;
-pub use record::{Key as RecordKey, Record};
+pub use record::{Key as RecordKey, ProviderRecord, Record};
 
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::ipfs::kademlia";
@@ -75,6 +85,14 @@ mod schema {
     }
 }
 
+/// Create a timer which ticks every `period`, starting after the first `period` has elapsed.
+fn periodic_timer(period: Duration) -> Interval {
+    let mut timer = tokio::time::interval_at((Instant::now() + period).into(), period);
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    timer
+}
+
 /// Peer action.
 #[derive(Debug)]
 enum PeerAction {
@@ -83,6 +101,9 @@ enum PeerAction {
 
     /// Send `PUT_VALUE` message to peer.
     SendPutValue(Bytes),
+
+    /// Send `ADD_PROVIDER` message to peer.
+    SendAddProvider(Bytes),
 }
 
 /// Peer context.
@@ -146,11 +167,53 @@ pub(crate) struct Kademlia {
 
     /// Query executor.
     executor: QueryExecutor,
+
+    /// Default TTL for records.
+    record_ttl: Duration,
+
+    /// Republish interval for locally published records.
+    record_republish_interval: Duration,
+
+    /// Replication interval for stored records.
+    record_replication_interval: Duration,
+
+    /// Records published by the local node with their requested expiration times.
+    ///
+    /// Records without an expiration time are kept alive by republication. The local copy is
+    /// republished even if the stored record was overwritten by a remote peer.
+    local_records: HashMap<RecordKey, Record>,
+
+    /// Keys of records received from remote peers since the last replication.
+    ///
+    /// These records were just replicated by another peer so they're not replicated again.
+    received_records: HashSet<RecordKey>,
+
+    /// Local listen addresses, advertised in provider records.
+    listen_addresses: Vec<Multiaddr>,
+
+    /// TTL for provider records.
+    provider_ttl: Duration,
+
+    /// Keys the local node is providing.
+    local_providers: HashSet<RecordKey>,
+
+    /// Republish interval for local provider records.
+    provider_republish_interval: Duration,
+
+    /// Next query ID, shared with `KademliaHandle`.
+    next_query_id: Arc<AtomicUsize>,
+
+    /// Queries started by [`Kademlia`] itself whose results are not reported to the user.
+    internal_queries: HashSet<QueryId>,
 }
 
 impl Kademlia {
     /// Create new [`Kademlia`].
-    pub(crate) fn new(mut service: TransportService, config: Config) -> Self {
+    pub(crate) fn new(
+        mut service: TransportService,
+        config: Config,
+        listen_addresses: Vec<Multiaddr>,
+    ) -> Self {
         let local_peer_id = service.local_peer_id;
         let local_key = Key::from(service.local_peer_id);
         let mut routing_table = RoutingTable::new(local_key.clone());
@@ -176,6 +239,17 @@ impl Kademlia {
             update_mode: config.update_mode,
             replication_factor: config.replication_factor,
             engine: QueryEngine::new(local_peer_id, config.replication_factor, PARALLELISM_FACTOR),
+            listen_addresses,
+            record_ttl: config.record_ttl,
+            record_republish_interval: config.record_republish_interval,
+            record_replication_interval: config.record_replication_interval,
+            local_records: HashMap::new(),
+            received_records: HashSet::new(),
+            provider_ttl: config.provider_ttl,
+            local_providers: HashSet::new(),
+            provider_republish_interval: config.provider_republish_interval,
+            next_query_id: config.next_query_id,
+            internal_queries: HashSet::new(),
         }
     }
 
@@ -318,6 +392,11 @@ impl Kademlia {
             Some(PeerAction::SendPutValue(message)) => {
                 tracing::trace!(target: LOG_TARGET, ?peer, "send `PUT_VALUE` response");
 
+                self.executor.send_message(peer, message, substream);
+            }
+            Some(PeerAction::SendAddProvider(message)) => {
+                tracing::trace!(target: LOG_TARGET, ?peer, "send `ADD_PROVIDER` message");
+
                 self.executor.send_message(peer, message, substream);
             }
         }
@@ -419,7 +498,7 @@ impl Kademlia {
                     "handle `PUT_VALUE` message",
                 );
 
-                self.store.put(record);
+                self.store_remote_record(record);
             }
             ref message @ KademliaMessage::GetRecord {
                 ref key,
@@ -469,11 +548,251 @@ impl Kademlia {
                     ),
                 }
             }
+            KademliaMessage::AddProvider { key, providers } => {
+                tracing::trace!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    ?key,
+                    "handle `ADD_PROVIDER` message",
+                );
+
+                // only accept provider records advertised by the providers themselves
+                let expires = Instant::now() + self.provider_ttl;
+
+                for provider in providers {
+                    if provider.peer != peer {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            provider = ?provider.peer,
+                            "ignore provider record advertised on behalf of another peer",
+                        );
+                        continue;
+                    }
+
+                    self.store.put_provider(ProviderRecord {
+                        key: key.clone(),
+                        provider: provider.peer,
+                        addresses: provider.addresses,
+                        expires,
+                    });
+                }
+            }
+            ref message @ KademliaMessage::GetProviders {
+                ref key,
+                ref providers,
+                ref peers,
+            } => match (query_id, key) {
+                (Some(query_id), _) => {
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?query_id,
+                        ?providers,
+                        ?peers,
+                        "handle `GET_PROVIDERS` response",
+                    );
+
+                    // update routing table and inform user about the update
+                    self.update_routing_table(peers).await;
+                    self.engine.register_response(query_id, peer, message.clone());
+                }
+                (None, Some(key)) => {
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?key,
+                        "handle `GET_PROVIDERS` request",
+                    );
+
+                    let providers = self.providers_from_store(key);
+                    let closest_peers = self
+                        .routing_table
+                        .closest(Key::from(key.to_vec()), self.replication_factor);
+
+                    let message = KademliaMessage::get_providers_response(
+                        key.clone(),
+                        providers,
+                        closest_peers,
+                    );
+                    self.executor.send_message(peer, message.into(), substream);
+                }
+                (None, None) => tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    ?message,
+                    "both query and provider key missing, unable to handle message",
+                ),
+            },
         }
 
         Ok(())
     }
 
+    /// Store record received from a remote peer.
+    ///
+    /// The record is stored for at most the configured record TTL, regardless of the TTL
+    /// specified by the remote peer.
+    fn store_remote_record(&mut self, mut record: Record) {
+        let max_expires = Instant::now() + self.record_ttl;
+        record.expires =
+            Some(record.expires.map_or(max_expires, |expires| expires.min(max_expires)));
+
+        self.received_records.insert(record.key.clone());
+        self.store.put(record);
+    }
+
+    /// Publish `record` as the local node and start storing it to the peers closest to its key.
+    fn put_local_record(&mut self, query_id: QueryId, mut record: Record) {
+        record.publisher.get_or_insert(self.service.local_peer_id);
+        self.local_records.insert(record.key.clone(), record.clone());
+
+        record.expires.get_or_insert(Instant::now() + self.record_ttl);
+        self.start_put_record(query_id, record);
+    }
+
+    /// Store `record` locally and start storing it to the peers closest to its key.
+    fn start_put_record(&mut self, query_id: QueryId, record: Record) {
+        self.store.put(record.clone());
+        let key = Key::new(record.key.clone());
+
+        self.engine.start_put_record(
+            query_id,
+            record,
+            self.routing_table.closest(key, self.replication_factor).into(),
+        );
+    }
+
+    /// Allocate query ID for a query started by [`Kademlia`] itself.
+    fn next_internal_query_id(&mut self) -> QueryId {
+        let query_id = QueryId(self.next_query_id.fetch_add(1usize, Ordering::Relaxed));
+        self.internal_queries.insert(query_id);
+
+        query_id
+    }
+
+    /// Republish records published by the local node.
+    ///
+    /// Expiration time of records published without one is refreshed before republishing
+    /// them and records which have expired are no longer republished.
+    fn on_record_republish(&mut self) {
+        tracing::trace!(
+            target: LOG_TARGET,
+            num_records = ?self.local_records.len(),
+            "republish records",
+        );
+
+        let now = Instant::now();
+        self.store.remove_expired_records(now);
+        self.local_records.retain(|key, record| {
+            if record.is_expired(now) {
+                tracing::trace!(target: LOG_TARGET, ?key, "record expired, stop republishing");
+                return false;
+            }
+
+            true
+        });
+
+        for mut record in self.local_records.values().cloned().collect::<Vec<_>>() {
+            record.expires.get_or_insert(now + self.record_ttl);
+
+            let query_id = self.next_internal_query_id();
+            self.start_put_record(query_id, record);
+        }
+    }
+
+    /// Replicate records stored by the local node to the peers currently closest to them.
+    ///
+    /// Records published by the local node are skipped as they're republished separately and so
+    /// are records received since the last replication as they were just replicated by a remote
+    /// peer.
+    fn on_record_replication(&mut self) {
+        self.store.remove_expired_records(Instant::now());
+
+        let records = self
+            .store
+            .records()
+            .filter(|record| {
+                !self.local_records.contains_key(&record.key)
+                    && !self.received_records.contains(&record.key)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.received_records.clear();
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            num_records = ?records.len(),
+            "replicate records",
+        );
+
+        for record in records {
+            let query_id = self.next_internal_query_id();
+            self.start_put_record(query_id, record);
+        }
+    }
+
+    /// Get providers for `key` from the local store.
+    fn providers_from_store(&self, key: &RecordKey) -> Vec<KademliaPeer> {
+        self.store
+            .get_providers(key)
+            .into_iter()
+            .map(|record| {
+                let connection = match record.provider == self.service.local_peer_id
+                    || self.peers.contains_key(&record.provider)
+                {
+                    true => ConnectionType::Connected,
+                    false => ConnectionType::NotConnected,
+                };
+
+                KademliaPeer::new(record.provider, record.addresses, connection)
+            })
+            .collect()
+    }
+
+    /// Store local provider record for `key` and start advertising it to the peers closest to
+    /// `key`.
+    fn start_providing(&mut self, key: RecordKey, query_id: QueryId) {
+        let local_peer_id = self.service.local_peer_id;
+
+        self.store.put_provider(ProviderRecord {
+            key: key.clone(),
+            provider: local_peer_id,
+            addresses: self.listen_addresses.clone(),
+            expires: Instant::now() + self.provider_ttl,
+        });
+        self.local_providers.insert(key.clone());
+
+        let candidates = self.routing_table.closest(Key::new(key.clone()), self.replication_factor);
+
+        self.engine.start_add_provider(
+            query_id,
+            key,
+            KademliaPeer::new(
+                local_peer_id,
+                self.listen_addresses.clone(),
+                ConnectionType::Connected,
+            ),
+            candidates.into(),
+        );
+    }
+
+    /// Republish local provider records and purge expired provider records from the store.
+    fn on_provider_republish(&mut self) {
+        tracing::trace!(
+            target: LOG_TARGET,
+            num_keys = ?self.local_providers.len(),
+            "republish provider records",
+        );
+
+        self.store.remove_expired_providers(Instant::now());
+
+        for key in self.local_providers.clone() {
+            let query_id = self.next_internal_query_id();
+            self.start_providing(key, query_id);
+        }
+    }
+
     /// Failed to open substream to remote peer.
     async fn on_substream_open_failure(&mut self, substream_id: SubstreamId, error: Error) {
         tracing::trace!(
@@ -592,13 +911,19 @@ impl Kademlia {
                     .await;
                 Ok(())
             }
-            QueryAction::PutRecordToFoundNodes { record, peers } => {
+            QueryAction::PutRecordToFoundNodes {
+                query_id,
+                record,
+                peers,
+            } => {
                 tracing::trace!(
                     target: LOG_TARGET,
+                    ?query_id,
                     record_key = ?record.key,
                     num_peers = ?peers.len(),
                     "store record to found peers",
                 );
+                self.internal_queries.remove(&query_id);
                 let key = record.key.clone();
                 let message = KademliaMessage::put_value(record);
 
@@ -638,8 +963,81 @@ impl Kademlia {
 
                 Ok(())
             }
+            QueryAction::AddProviderToFoundNodes {
+                query_id,
+                key,
+                provider,
+                peers,
+            } => {
+                tracing::trace!(
+                    target: LOG_TARGET,
+                    ?query_id,
+                    ?key,
+                    num_peers = ?peers.len(),
+                    "advertise provider record to found peers",
+                );
+                self.internal_queries.remove(&query_id);
+                let message = KademliaMessage::add_provider(key.clone(), provider);
+
+                for peer in peers {
+                    match self.service.open_substream(peer.peer) {
+                        Ok(substream_id) => {
+                            self.pending_substreams.insert(substream_id, peer.peer);
+                            self.peers
+                                .entry(peer.peer)
+                                .or_default()
+                                .pending_actions
+                                .insert(substream_id, PeerAction::SendAddProvider(message.clone()));
+                        }
+                        Err(_) => match self.service.dial(&peer.peer) {
+                            Ok(_) => self
+                                .pending_dials
+                                .entry(peer.peer)
+                                .or_default()
+                                .push(PeerAction::SendAddProvider(message.clone())),
+                            Err(error) => {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?peer,
+                                    ?key,
+                                    ?error,
+                                    "failed to dial peer",
+                                );
+                            }
+                        },
+                    }
+                }
+
+                Ok(())
+            }
+            QueryAction::GetProvidersQueryDone {
+                query_id,
+                key,
+                providers,
+            } => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?query_id,
+                    ?key,
+                    num_providers = ?providers.len(),
+                    "`GET_PROVIDERS` succeeded",
+                );
+
+                let _ = self
+                    .event_tx
+                    .send(KademliaEvent::GetProvidersSuccess {
+                        query_id,
+                        key,
+                        providers: providers
+                            .into_iter()
+                            .map(|provider| (provider.peer, provider.addresses))
+                            .collect(),
+                    })
+                    .await;
+                Ok(())
+            }
             QueryAction::GetRecordQueryDone { query_id, record } => {
-                self.store.put(record.clone());
+                self.store_remote_record(record.clone());
 
                 let _ =
                     self.event_tx.send(KademliaEvent::GetRecordSuccess { query_id, record }).await;
@@ -648,6 +1046,11 @@ impl Kademlia {
             QueryAction::QueryFailed { query } => {
                 tracing::debug!(target: LOG_TARGET, ?query, "query failed");
 
+                // failures of internal queries are not reported to the user
+                if self.internal_queries.remove(&query) {
+                    return Ok(());
+                }
+
                 let _ = self.event_tx.send(KademliaEvent::QueryFailed { query_id: query }).await;
                 Ok(())
             }
@@ -659,6 +1062,10 @@ impl Kademlia {
     pub async fn run(mut self) -> crate::Result<()> {
         tracing::debug!(target: LOG_TARGET, "starting kademlia event loop");
 
+        let mut record_republish_timer = periodic_timer(self.record_republish_interval);
+        let mut record_replication_timer = periodic_timer(self.record_replication_interval);
+        let mut provider_republish_timer = periodic_timer(self.provider_republish_interval);
+
         loop {
             // poll `QueryEngine` for next actions.
             while let Some(action) = self.engine.next_action() {
@@ -741,14 +1148,7 @@ impl Kademlia {
                         Some(KademliaCommand::PutRecord { record, query_id }) => {
                             tracing::debug!(target: LOG_TARGET, ?query_id, key = ?record.key, "store record to DHT");
 
-                            self.store.put(record.clone());
-                            let key = Key::new(record.key.clone());
-
-                            self.engine.start_put_record(
-                                query_id,
-                                record,
-                                self.routing_table.closest(key, self.replication_factor).into(),
-                            );
+                            self.put_local_record(query_id, record);
                         }
                         Some(KademliaCommand::GetRecord { key, quorum, query_id }) => {
                             tracing::debug!(target: LOG_TARGET, ?key, "get record from DHT");
@@ -772,6 +1172,29 @@ impl Kademlia {
                             }
 
                         }
+                        Some(KademliaCommand::StartProviding { key, query_id }) => {
+                            tracing::debug!(target: LOG_TARGET, ?query_id, ?key, "start providing");
+
+                            self.start_providing(key, query_id);
+                        }
+                        Some(KademliaCommand::StopProviding { key }) => {
+                            tracing::debug!(target: LOG_TARGET, ?key, "stop providing");
+
+                            self.local_providers.remove(&key);
+                            self.store.remove_provider(&key, &self.service.local_peer_id);
+                        }
+                        Some(KademliaCommand::GetProviders { key, query_id }) => {
+                            tracing::debug!(target: LOG_TARGET, ?query_id, ?key, "get providers from DHT");
+
+                            let known_providers = self.providers_from_store(&key);
+
+                            self.engine.start_get_providers(
+                                query_id,
+                                key.clone(),
+                                self.routing_table.closest(Key::new(key), self.replication_factor).into(),
+                                known_providers,
+                            );
+                        }
                         Some(KademliaCommand::AddKnownPeer { peer, addresses }) => {
                             tracing::trace!(
                                 target: LOG_TARGET,
@@ -793,6 +1216,9 @@ impl Kademlia {
                         None => return Err(Error::EssentialTaskClosed),
                     }
                 },
+                _ = record_republish_timer.tick() => self.on_record_republish(),
+                _ = record_replication_timer.tick() => self.on_record_replication(),
+                _ = provider_republish_timer.tick() => self.on_provider_republish(),
             }
         }
     }
@@ -815,7 +1241,7 @@ mod tests {
         event_rx: Receiver<KademliaEvent>,
     }
 
-    fn _make_kademlia() -> (Kademlia, Context, TransportManager) {
+    fn make_kademlia() -> (Kademlia, Context, TransportManager) {
         let (manager, handle) = TransportManager::new(
             Keypair::generate(),
             HashSet::new(),
@@ -840,14 +1266,126 @@ mod tests {
             codec: ProtocolCodec::UnsignedVarint(None),
             replication_factor: 20usize,
             update_mode: RoutingTableUpdateMode::Automatic,
+            record_ttl: Duration::from_secs(36 * 60 * 60),
+            record_republish_interval: Duration::from_secs(22 * 60 * 60),
+            record_replication_interval: Duration::from_secs(60 * 60),
+            provider_ttl: Duration::from_secs(48 * 60 * 60),
+            provider_republish_interval: Duration::from_secs(22 * 60 * 60),
+            next_query_id: Arc::new(AtomicUsize::new(0usize)),
             event_tx,
             cmd_rx,
         };
 
         (
-            Kademlia::new(transport_service, config),
+            Kademlia::new(transport_service, config, Vec::new()),
             Context { _cmd_tx, event_rx },
             manager,
         )
     }
+
+    // answer the `FIND_NODE` lookups of the queries started by `kademlia` and return the records
+    // which are then sent to the found peers with `PUT_VALUE`
+    fn put_records(kademlia: &mut Kademlia) -> Vec<Record> {
+        let mut records = Vec::new();
+
+        while let Some(action) = kademlia.engine.next_action() {
+            match action {
+                QueryAction::SendMessage { query, peer, .. } => kademlia.engine.register_response(
+                    query,
+                    peer,
+                    KademliaMessage::FindNode {
+                        target: Vec::new(),
+                        peers: vec![],
+                    },
+                ),
+                QueryAction::PutRecordToFoundNodes { record, .. } => records.push(record),
+                action => panic!("invalid action: {action:?}"),
+            }
+        }
+
+        records.sort_by(|a, b| a.key.to_vec().cmp(&b.key.to_vec()));
+        records
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recently_received_records_not_replicated() {
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        let mut timer = periodic_timer(kademlia.record_replication_interval);
+        kademlia.routing_table.add_known_peer(
+            PeerId::random(),
+            vec![],
+            ConnectionType::NotConnected,
+        );
+
+        let stored = Record::new(vec![1], vec![1, 3, 3, 7]).with_ttl(Duration::from_secs(60));
+        let received = Record::new(vec![2], vec![1, 3, 3, 8]);
+        kademlia.store.put(stored.clone());
+        kademlia.store_remote_record(received.clone());
+
+        // record received within the replication interval is not replicated
+        timer.tick().await;
+        kademlia.on_record_replication();
+        assert_eq!(put_records(&mut kademlia), vec![stored.clone()]);
+
+        // but it's replicated after the next interval with its expiration time capped
+        timer.tick().await;
+        kademlia.on_record_replication();
+
+        let records = put_records(&mut kademlia);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], stored);
+        assert_eq!(records[1].value, received.value);
+        assert!(records[1].expires.unwrap() <= Instant::now() + kademlia.record_ttl);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn remote_record_expiration_capped() {
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        let record_ttl = kademlia.record_ttl;
+
+        kademlia
+            .store_remote_record(Record::new(vec![1], vec![1, 3, 3, 7]).with_ttl(record_ttl * 2));
+        kademlia.store_remote_record(Record::new(vec![2], vec![1, 3, 3, 8]));
+
+        for key in [RecordKey::from(vec![1]), RecordKey::from(vec![2])] {
+            let expires = kademlia.store.get(&key).unwrap().expires.unwrap();
+            assert!(expires <= Instant::now() + record_ttl);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn local_copy_of_record_republished() {
+        let (mut kademlia, _context, _manager) = make_kademlia();
+        let mut timer = periodic_timer(kademlia.record_republish_interval);
+        let local_peer_id = kademlia.service.local_peer_id;
+        kademlia.routing_table.add_known_peer(
+            PeerId::random(),
+            vec![],
+            ConnectionType::NotConnected,
+        );
+
+        kademlia.put_local_record(QueryId(0), Record::new(vec![1], vec![1, 3, 3, 7]));
+
+        let records = put_records(&mut kademlia);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].publisher, Some(local_peer_id));
+
+        // remote peer overwrites the stored record
+        kademlia.store_remote_record(Record::new(vec![1], vec![1, 3, 3, 8]));
+
+        // the local copy is republished with a refreshed expiration time
+        timer.tick().await;
+        kademlia.on_record_republish();
+
+        let records = put_records(&mut kademlia);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].value, vec![1, 3, 3, 7]);
+        assert_eq!(records[0].publisher, Some(local_peer_id));
+        assert!(records[0].expires.is_some());
+
+        // and local records are not replicated
+        kademlia.received_records.clear();
+        kademlia.on_record_replication();
+        assert!(put_records(&mut kademlia).is_empty());
+    }
 }
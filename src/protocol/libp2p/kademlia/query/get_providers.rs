@@ -0,0 +1,208 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{
+    protocol::libp2p::kademlia::{
+        message::KademliaMessage,
+        query::{QueryAction, QueryId},
+        record::Key as RecordKey,
+        types::{Distance, KademliaPeer, Key},
+    },
+    PeerId,
+};
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::ipfs::kademlia::query::get_providers";
+
+/// Context for `GET_PROVIDERS` queries.
+#[derive(Debug)]
+pub struct GetProvidersContext {
+    /// Local peer ID.
+    local_peer_id: PeerId,
+
+    /// Query ID.
+    pub query: QueryId,
+
+    /// Target key.
+    pub target: Key<RecordKey>,
+
+    /// Peers from whom the `QueryEngine` is waiting to hear a response.
+    pub pending: HashMap<PeerId, KademliaPeer>,
+
+    /// Queried candidates.
+    ///
+    /// These are the peers for whom the query has already been sent
+    /// and who have either returned their closest peers or failed to answer.
+    pub queried: HashSet<PeerId>,
+
+    /// Candidates.
+    pub candidates: BTreeMap<Distance, KademliaPeer>,
+
+    /// Number of peers who responded to the query.
+    pub num_responses: usize,
+
+    /// Found providers.
+    pub found_providers: HashMap<PeerId, KademliaPeer>,
+
+    /// Replication factor.
+    pub replication_factor: usize,
+
+    /// Parallelism factor.
+    pub parallelism_factor: usize,
+}
+
+impl GetProvidersContext {
+    /// Create new [`GetProvidersContext`].
+    ///
+    /// `known_providers` are the providers found from the local store.
+    pub fn new(
+        local_peer_id: PeerId,
+        query: QueryId,
+        target: Key<RecordKey>,
+        in_peers: VecDeque<KademliaPeer>,
+        known_providers: Vec<KademliaPeer>,
+        replication_factor: usize,
+        parallelism_factor: usize,
+    ) -> Self {
+        let mut candidates = BTreeMap::new();
+
+        for candidate in &in_peers {
+            let distance = target.distance(&candidate.key);
+            candidates.insert(distance, candidate.clone());
+        }
+
+        Self {
+            query,
+            target,
+            candidates,
+            local_peer_id,
+            replication_factor,
+            parallelism_factor,
+            num_responses: 0usize,
+            pending: HashMap::new(),
+            queried: HashSet::new(),
+            found_providers: known_providers
+                .into_iter()
+                .map(|provider| (provider.peer, provider))
+                .collect(),
+        }
+    }
+
+    /// Get the found providers.
+    pub fn found_providers(self) -> Vec<KademliaPeer> {
+        self.found_providers.into_values().collect()
+    }
+
+    /// Register response failure for `peer`.
+    pub fn register_response_failure(&mut self, peer: PeerId) {
+        let Some(peer) = self.pending.remove(&peer) else {
+            tracing::trace!(target: LOG_TARGET, ?peer, "pending peer doesn't exist");
+            return;
+        };
+
+        self.queried.insert(peer.peer);
+    }
+
+    /// Register `GET_PROVIDERS` response from `peer`.
+    pub fn register_response(
+        &mut self,
+        peer: PeerId,
+        providers: Vec<KademliaPeer>,
+        peers: Vec<KademliaPeer>,
+    ) {
+        let Some(peer) = self.pending.remove(&peer) else {
+            tracing::trace!(target: LOG_TARGET, ?peer, "received response from peer but didn't expect it");
+            return;
+        };
+
+        self.num_responses += 1;
+        self.queried.insert(peer.peer);
+
+        // the query is done once enough providers have been found so the rest are not stored
+        for provider in providers {
+            if self.found_providers.len() >= self.replication_factor {
+                break;
+            }
+
+            self.found_providers.insert(provider.peer, provider);
+        }
+
+        for candidate in peers {
+            if !self.queried.contains(&candidate.peer)
+                && !self.pending.contains_key(&candidate.peer)
+            {
+                if self.local_peer_id == candidate.peer {
+                    continue;
+                }
+
+                let distance = self.target.distance(&candidate.key);
+                self.candidates.insert(distance, candidate);
+            }
+        }
+    }
+
+    /// Get next action for `peer`.
+    pub fn next_peer_action(&mut self, peer: &PeerId) -> Option<QueryAction> {
+        self.pending.contains_key(peer).then_some(QueryAction::SendMessage {
+            query: self.query,
+            peer: *peer,
+            message: KademliaMessage::get_providers_request(self.target.clone().into_preimage()),
+        })
+    }
+
+    /// Schedule next peer for outbound `GET_PROVIDERS` query.
+    pub fn schedule_next_peer(&mut self) -> QueryAction {
+        tracing::trace!(target: LOG_TARGET, query = ?self.query, "get next peer");
+
+        let (_, candidate) = self.candidates.pop_first().expect("entry to exist");
+        let peer = candidate.peer;
+
+        self.pending.insert(candidate.peer, candidate);
+
+        QueryAction::SendMessage {
+            query: self.query,
+            peer,
+            message: KademliaMessage::get_providers_request(self.target.clone().into_preimage()),
+        }
+    }
+
+    /// Get next action for a `GET_PROVIDERS` query.
+    pub fn next_action(&mut self) -> Option<QueryAction> {
+        // enough providers found or enough peers have responded to the query
+        let done = self.found_providers.len() >= self.replication_factor
+            || self.num_responses >= self.replication_factor;
+
+        // the query succeeds if at least one provider was found or one peer responded
+        if done || (self.pending.is_empty() && self.candidates.is_empty()) {
+            return match self.num_responses + self.found_providers.len() {
+                0 => Some(QueryAction::QueryFailed { query: self.query }),
+                _ => Some(QueryAction::QuerySucceeded { query: self.query }),
+            };
+        }
+
+        if self.pending.len() == self.parallelism_factor || self.candidates.is_empty() {
+            return None;
+        }
+
+        Some(self.schedule_next_peer())
+    }
+}
@@ -21,7 +21,10 @@
 use crate::{
     protocol::libp2p::kademlia::{
         message::KademliaMessage,
-        query::{find_node::FindNodeContext, get_record::GetRecordContext},
+        query::{
+            find_node::FindNodeContext, get_providers::GetProvidersContext,
+            get_record::GetRecordContext,
+        },
         record::{Key as RecordKey, Record},
         types::{KademliaPeer, Key},
         Quorum,
@@ -34,6 +37,7 @@ use bytes::Bytes;
 use std::collections::{HashMap, VecDeque};
 
 mod find_node;
+mod get_providers;
 mod get_record;
 
 /// Logging target for the file.
@@ -68,6 +72,24 @@ enum QueryType {
         /// Context for the `GET_VALUE` query.
         context: GetRecordContext,
     },
+
+    /// `ADD_PROVIDER` query.
+    AddProvider {
+        /// Provider record key.
+        key: RecordKey,
+
+        /// Local node as a provider.
+        provider: KademliaPeer,
+
+        /// Context for the `FIND_NODE` query.
+        context: FindNodeContext<RecordKey>,
+    },
+
+    /// `GET_PROVIDERS` query.
+    GetProviders {
+        /// Context for the `GET_PROVIDERS` query.
+        context: GetProvidersContext,
+    },
 }
 
 /// Query action.
@@ -100,6 +122,9 @@ pub enum QueryAction {
     /// Store the record to nodest closest to target key.
     // TODO: horrible name
     PutRecordToFoundNodes {
+        /// Query ID.
+        query_id: QueryId,
+
         /// Target peer.
         record: Record,
 
@@ -116,6 +141,33 @@ pub enum QueryAction {
         record: Record,
     },
 
+    /// Advertise the local node as a provider to nodes closest to target key.
+    AddProviderToFoundNodes {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Provider record key.
+        key: RecordKey,
+
+        /// Local node as a provider.
+        provider: KademliaPeer,
+
+        /// Peers for whom the `ADD_PROVIDER` must be sent to.
+        peers: Vec<KademliaPeer>,
+    },
+
+    /// `GET_PROVIDERS` query succeeded.
+    GetProvidersQueryDone {
+        /// Query ID.
+        query_id: QueryId,
+
+        /// Provider record key.
+        key: RecordKey,
+
+        /// Found providers.
+        providers: Vec<KademliaPeer>,
+    },
+
     // TODO: remove
     /// Query succeeded.
     QuerySucceeded {
@@ -265,6 +317,78 @@ impl QueryEngine {
         query_id
     }
 
+    /// Start `ADD_PROVIDER` query.
+    pub fn start_add_provider(
+        &mut self,
+        query_id: QueryId,
+        key: RecordKey,
+        provider: KademliaPeer,
+        candidates: VecDeque<KademliaPeer>,
+    ) -> QueryId {
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?query_id,
+            target = ?key,
+            num_peers = ?candidates.len(),
+            "start `ADD_PROVIDER` query"
+        );
+
+        let target = Key::new(key.clone());
+
+        self.queries.insert(
+            query_id,
+            QueryType::AddProvider {
+                key,
+                provider,
+                context: FindNodeContext::new(
+                    self.local_peer_id,
+                    query_id,
+                    target,
+                    candidates,
+                    self.replication_factor,
+                    self.parallelism_factor,
+                ),
+            },
+        );
+
+        query_id
+    }
+
+    /// Start `GET_PROVIDERS` query.
+    pub fn start_get_providers(
+        &mut self,
+        query_id: QueryId,
+        key: RecordKey,
+        candidates: VecDeque<KademliaPeer>,
+        known_providers: Vec<KademliaPeer>,
+    ) -> QueryId {
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?query_id,
+            target = ?key,
+            num_peers = ?candidates.len(),
+            num_known_providers = ?known_providers.len(),
+            "start `GET_PROVIDERS` query"
+        );
+
+        self.queries.insert(
+            query_id,
+            QueryType::GetProviders {
+                context: GetProvidersContext::new(
+                    self.local_peer_id,
+                    query_id,
+                    Key::new(key),
+                    candidates,
+                    known_providers,
+                    self.replication_factor,
+                    self.parallelism_factor,
+                ),
+            },
+        );
+
+        query_id
+    }
+
     /// Register response failure from a queried peer.
     pub fn register_response_failure(&mut self, query: QueryId, peer: PeerId) {
         tracing::trace!(target: LOG_TARGET, ?query, ?peer, "register response failure");
@@ -283,6 +407,12 @@ impl QueryEngine {
             Some(QueryType::GetRecord { context }) => {
                 context.register_response_failure(peer);
             }
+            Some(QueryType::AddProvider { context, .. }) => {
+                context.register_response_failure(peer);
+            }
+            Some(QueryType::GetProviders { context }) => {
+                context.register_response_failure(peer);
+            }
         }
     }
 
@@ -299,19 +429,78 @@ impl QueryEngine {
                 KademliaMessage::FindNode { peers, .. } => {
                     context.register_response(peer, peers);
                 }
-                _ => unreachable!(),
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response to `FIND_NODE` query",
+                    );
+                    context.register_response_failure(peer);
+                }
             },
             Some(QueryType::PutRecord { context, .. }) => match message {
                 KademliaMessage::FindNode { peers, .. } => {
                     context.register_response(peer, peers);
                 }
-                _ => unreachable!(),
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response to `PUT_VALUE` query",
+                    );
+                    context.register_response_failure(peer);
+                }
             },
             Some(QueryType::GetRecord { context }) => match message {
                 KademliaMessage::GetRecord { record, peers, .. } => {
                     context.register_response(peer, record, peers);
                 }
-                _ => unreachable!(),
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response to `GET_VALUE` query",
+                    );
+                    context.register_response_failure(peer);
+                }
+            },
+            Some(QueryType::AddProvider { context, .. }) => match message {
+                KademliaMessage::FindNode { peers, .. } => {
+                    context.register_response(peer, peers);
+                }
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response to `ADD_PROVIDER` query",
+                    );
+                    context.register_response_failure(peer);
+                }
+            },
+            Some(QueryType::GetProviders { context }) => match message {
+                KademliaMessage::GetProviders {
+                    providers, peers, ..
+                } => {
+                    context.register_response(peer, providers, peers);
+                }
+                message => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?query,
+                        ?peer,
+                        ?message,
+                        "unexpected response to `GET_PROVIDERS` query",
+                    );
+                    context.register_response_failure(peer);
+                }
             },
         }
     }
@@ -328,6 +517,8 @@ impl QueryEngine {
             Some(QueryType::FindNode { context }) => return context.next_peer_action(peer),
             Some(QueryType::PutRecord { context, .. }) => return context.next_peer_action(peer),
             Some(QueryType::GetRecord { context }) => return context.next_peer_action(peer),
+            Some(QueryType::AddProvider { context, .. }) => return context.next_peer_action(peer),
+            Some(QueryType::GetProviders { context }) => return context.next_peer_action(peer),
         }
     }
 
@@ -341,6 +532,7 @@ impl QueryEngine {
                 peers: context.responses.into_iter().map(|(_, peer)| peer).collect::<Vec<_>>(),
             },
             QueryType::PutRecord { record, context } => QueryAction::PutRecordToFoundNodes {
+                query_id: context.query,
                 record,
                 peers: context.responses.into_iter().map(|(_, peer)| peer).collect::<Vec<_>>(),
             },
@@ -348,6 +540,21 @@ impl QueryEngine {
                 query_id: context.query,
                 record: context.found_record(),
             },
+            QueryType::AddProvider {
+                key,
+                provider,
+                context,
+            } => QueryAction::AddProviderToFoundNodes {
+                query_id: context.query,
+                key,
+                provider,
+                peers: context.responses.into_iter().map(|(_, peer)| peer).collect::<Vec<_>>(),
+            },
+            QueryType::GetProviders { context } => QueryAction::GetProvidersQueryDone {
+                query_id: context.query,
+                key: context.target.clone().into_preimage(),
+                providers: context.found_providers(),
+            },
         }
     }
 
@@ -366,6 +573,8 @@ impl QueryEngine {
                 QueryType::FindNode { context } => context.next_action(),
                 QueryType::PutRecord { context, .. } => context.next_action(),
                 QueryType::GetRecord { context } => context.next_action(),
+                QueryType::AddProvider { context, .. } => context.next_action(),
+                QueryType::GetProviders { context } => context.next_action(),
             };
 
             match action {
@@ -652,7 +861,7 @@ mod tests {
         }
 
         match engine.next_action() {
-            Some(QueryAction::PutRecordToFoundNodes { peers, record }) => {
+            Some(QueryAction::PutRecordToFoundNodes { peers, record, .. }) => {
                 assert_eq!(peers.len(), 4);
                 assert_eq!(record.key, original_record.key);
                 assert_eq!(record.value, original_record.value);
@@ -662,4 +871,213 @@ mod tests {
 
         assert!(engine.next_action().is_none());
     }
+
+    #[test]
+    fn get_providers_succeeds() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize);
+        let record_key = RecordKey::new(&vec![1, 2, 3, 4]);
+        let local_provider =
+            KademliaPeer::new(PeerId::random(), vec![], ConnectionType::NotConnected);
+        let remote_provider =
+            KademliaPeer::new(PeerId::random(), vec![], ConnectionType::NotConnected);
+
+        let query = engine.start_get_providers(
+            QueryId(1341),
+            record_key.clone(),
+            vec![
+                KademliaPeer::new(make_peer_id(1, 0), vec![], ConnectionType::NotConnected),
+                KademliaPeer::new(make_peer_id(2, 0), vec![], ConnectionType::NotConnected),
+            ]
+            .into(),
+            vec![local_provider.clone()],
+        );
+
+        // first peer knows a provider, second peer fails to answer
+        match engine.next_action() {
+            Some(QueryAction::SendMessage { query, peer, .. }) => {
+                engine.register_response(
+                    query,
+                    peer,
+                    KademliaMessage::GetProviders {
+                        key: None,
+                        providers: vec![remote_provider.clone()],
+                        peers: vec![],
+                    },
+                );
+            }
+            _ => panic!("invalid event received"),
+        }
+
+        match engine.next_action() {
+            Some(QueryAction::SendMessage { query, peer, .. }) => {
+                engine.register_response_failure(query, peer);
+            }
+            _ => panic!("invalid event received"),
+        }
+
+        match engine.next_action() {
+            Some(QueryAction::GetProvidersQueryDone {
+                query_id,
+                key,
+                providers,
+            }) => {
+                assert_eq!(query_id, query);
+                assert_eq!(key, record_key);
+                assert_eq!(providers.len(), 2);
+                assert!(providers.contains(&local_provider));
+                assert!(providers.contains(&remote_provider));
+            }
+            _ => panic!("invalid event received"),
+        }
+
+        assert!(engine.next_action().is_none());
+    }
+
+    #[test]
+    fn get_providers_capped_at_replication_factor() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let mut engine = QueryEngine::new(PeerId::random(), 2usize, 3usize);
+        let query = engine.start_get_providers(
+            QueryId(1341),
+            RecordKey::new(&vec![1, 2, 3, 4]),
+            vec![KademliaPeer::new(
+                make_peer_id(1, 0),
+                vec![],
+                ConnectionType::NotConnected,
+            )]
+            .into(),
+            vec![],
+        );
+
+        // peer answers with more providers than the replication factor
+        match engine.next_action() {
+            Some(QueryAction::SendMessage { query, peer, .. }) => {
+                engine.register_response(
+                    query,
+                    peer,
+                    KademliaMessage::GetProviders {
+                        key: None,
+                        providers: (0..10)
+                            .map(|_| {
+                                KademliaPeer::new(
+                                    PeerId::random(),
+                                    vec![],
+                                    ConnectionType::NotConnected,
+                                )
+                            })
+                            .collect(),
+                        peers: vec![],
+                    },
+                );
+            }
+            _ => panic!("invalid event received"),
+        }
+
+        match engine.next_action() {
+            Some(QueryAction::GetProvidersQueryDone {
+                query_id,
+                providers,
+                ..
+            }) => {
+                assert_eq!(query_id, query);
+                assert_eq!(providers.len(), 2);
+            }
+            _ => panic!("invalid event received"),
+        }
+    }
+
+    #[test]
+    fn mismatched_response_fails_peer() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let mut engine = QueryEngine::new(PeerId::random(), 20usize, 3usize);
+        let record_key = RecordKey::new(&vec![1, 2, 3, 4]);
+        let candidates = || {
+            VecDeque::from([KademliaPeer::new(
+                make_peer_id(1, 0),
+                vec![],
+                ConnectionType::NotConnected,
+            )])
+        };
+        let find_node = KademliaMessage::FindNode {
+            target: Vec::new(),
+            peers: vec![],
+        };
+        let get_providers = KademliaMessage::GetProviders {
+            key: None,
+            providers: vec![],
+            peers: vec![],
+        };
+
+        // each query is answered with a message of the wrong type
+        for index in 0..5 {
+            let query_id = QueryId(index);
+            let (query, response) = match index {
+                0 => (
+                    engine.start_find_node(query_id, PeerId::random(), candidates()),
+                    get_providers.clone(),
+                ),
+                1 => (
+                    engine.start_put_record(
+                        query_id,
+                        Record::new(record_key.clone(), vec![1, 3, 3, 7]),
+                        candidates(),
+                    ),
+                    get_providers.clone(),
+                ),
+                2 => (
+                    engine.start_get_record(
+                        query_id,
+                        record_key.clone(),
+                        candidates(),
+                        Quorum::One,
+                        0,
+                    ),
+                    get_providers.clone(),
+                ),
+                3 => (
+                    engine.start_add_provider(
+                        query_id,
+                        record_key.clone(),
+                        KademliaPeer::new(PeerId::random(), vec![], ConnectionType::NotConnected),
+                        candidates(),
+                    ),
+                    get_providers.clone(),
+                ),
+                _ => (
+                    engine.start_get_providers(query_id, record_key.clone(), candidates(), vec![]),
+                    find_node.clone(),
+                ),
+            };
+
+            match engine.next_action() {
+                Some(QueryAction::SendMessage {
+                    query: sent_query,
+                    peer,
+                    ..
+                }) => {
+                    assert_eq!(sent_query, query);
+                    engine.register_response(query, peer, response);
+                }
+                _ => panic!("invalid event received"),
+            }
+
+            // the only candidate failed to answer so the query fails
+            match engine.next_action() {
+                Some(QueryAction::QueryFailed { query: failed }) => assert_eq!(failed, query),
+                _ => panic!("invalid event received"),
+            }
+        }
+
+        assert!(engine.next_action().is_none());
+    }
 }
@@ -22,9 +22,13 @@
 use crate::PeerId;
 
 use bytes::Bytes;
+use multiaddr::Multiaddr;
 use multihash::Multihash;
 
-use std::{borrow::Borrow, time::Instant};
+use std::{
+    borrow::Borrow,
+    time::{Duration, Instant},
+};
 
 /// The (opaque) key of a record.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -103,8 +107,37 @@ impl Record {
         }
     }
 
+    /// Set the time-to-live of the record, measured from `now`.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires = Some(Instant::now() + ttl);
+        self
+    }
+
     /// Checks whether the record is expired w.r.t. the given `Instant`.
-    pub fn _is_expired(&self, now: Instant) -> bool {
+    pub fn is_expired(&self, now: Instant) -> bool {
         self.expires.map_or(false, |t| now >= t)
     }
 }
+
+/// A record advertising that a peer provides the content identified by `key`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProviderRecord {
+    /// Key of the provided content.
+    pub key: Key,
+
+    /// Peer providing the content.
+    pub provider: PeerId,
+
+    /// Known addresses of the provider.
+    pub addresses: Vec<Multiaddr>,
+
+    /// The expiration time as measured by a local, monotonic clock.
+    pub expires: Instant,
+}
+
+impl ProviderRecord {
+    /// Checks whether the provider record is expired w.r.t. the given `Instant`.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires
+    }
+}
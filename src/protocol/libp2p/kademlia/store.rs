@@ -21,9 +21,27 @@
 //! Memory store implementation for Kademlia.
 
 #![allow(unused)]
-use crate::protocol::libp2p::kademlia::record::{Key, Record};
+use crate::{
+    protocol::libp2p::kademlia::{
+        record::{Key, ProviderRecord, Record},
+        types::Key as KademliaKey,
+    },
+    PeerId,
+};
 
-use std::collections::HashMap;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    time::Instant,
+};
+
+/// Maximum number of providers stored for a single key.
+const MAX_PROVIDERS_PER_KEY: usize = 20usize;
+
+/// Maximum number of keys with stored provider records.
+const MAX_PROVIDER_KEYS: usize = 1024usize;
+
+/// Maximum number of provider records stored for all keys.
+const MAX_PROVIDER_RECORDS: usize = 10240usize;
 
 /// Memory store events.
 pub enum MemoryStoreEvent {}
@@ -32,6 +50,12 @@ pub enum MemoryStoreEvent {}
 pub struct MemoryStore {
     /// Records.
     records: HashMap<Key, Record>,
+
+    /// Provider records.
+    providers: HashMap<Key, Vec<ProviderRecord>>,
+
+    /// Number of stored provider records.
+    num_providers: usize,
 }
 
 impl MemoryStore {
@@ -39,12 +63,28 @@ impl MemoryStore {
     pub fn new() -> Self {
         Self {
             records: HashMap::new(),
+            providers: HashMap::new(),
+            num_providers: 0usize,
         }
     }
 
-    /// Try to get record from local store for `key`.
+    /// Try to get record from local store for `key`, ignoring expired records.
     pub fn get(&self, key: &Key) -> Option<&Record> {
-        self.records.get(key)
+        let now = Instant::now();
+
+        self.records.get(key).filter(|record| !record.is_expired(now))
+    }
+
+    /// Get all records which have not expired.
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        let now = Instant::now();
+
+        self.records.values().filter(move |record| !record.is_expired(now))
+    }
+
+    /// Remove expired records from the store.
+    pub fn remove_expired_records(&mut self, now: Instant) {
+        self.records.retain(|_, record| !record.is_expired(now));
     }
 
     /// Store record.
@@ -52,8 +92,233 @@ impl MemoryStore {
         self.records.insert(record.key.clone(), record);
     }
 
+    /// Store provider record.
+    ///
+    /// If the provider already exists for the key, its addresses and expiration time are
+    /// updated. Records for new keys are ignored if the store is full and if the key already has
+    /// the maximum number of providers or the store is full, the new provider replaces the
+    /// provider farthest from the key if it's closer to the key.
+    pub fn put_provider(&mut self, record: ProviderRecord) {
+        let num_keys = self.providers.len();
+        let providers = match self.providers.entry(record.key.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(_)
+                if num_keys >= MAX_PROVIDER_KEYS || self.num_providers >= MAX_PROVIDER_RECORDS =>
+                return,
+            Entry::Vacant(entry) => entry.insert(Vec::new()),
+        };
+
+        if let Some(provider) =
+            providers.iter_mut().find(|provider| provider.provider == record.provider)
+        {
+            *provider = record;
+            return;
+        }
+
+        if providers.len() < MAX_PROVIDERS_PER_KEY && self.num_providers < MAX_PROVIDER_RECORDS {
+            providers.push(record);
+            self.num_providers += 1;
+            return;
+        }
+
+        let key = KademliaKey::new(record.key.clone());
+        let distance = |provider: &PeerId| key.distance(&KademliaKey::from(*provider));
+
+        if let Some(farthest) =
+            providers.iter_mut().max_by_key(|provider| distance(&provider.provider))
+        {
+            if distance(&record.provider) < distance(&farthest.provider) {
+                *farthest = record;
+            }
+        }
+    }
+
+    /// Get providers for `key`, ignoring expired provider records.
+    pub fn get_providers(&self, key: &Key) -> Vec<ProviderRecord> {
+        let now = Instant::now();
+
+        self.providers.get(key).map_or(Vec::new(), |providers| {
+            providers.iter().filter(|provider| !provider.is_expired(now)).cloned().collect()
+        })
+    }
+
+    /// Remove provider record of `provider` for `key`.
+    pub fn remove_provider(&mut self, key: &Key, provider: &PeerId) {
+        if let Entry::Occupied(mut entry) = self.providers.entry(key.clone()) {
+            let num_providers = entry.get().len();
+            entry.get_mut().retain(|record| &record.provider != provider);
+            self.num_providers -= num_providers - entry.get().len();
+
+            if entry.get().is_empty() {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Remove expired provider records from the store.
+    pub fn remove_expired_providers(&mut self, now: Instant) {
+        self.providers.retain(|_, providers| {
+            providers.retain(|provider| !provider.is_expired(now));
+            !providers.is_empty()
+        });
+        self.num_providers = self.providers.values().map(Vec::len).sum();
+    }
+
     /// Poll next event from the store.
     async fn next_event() -> Option<MemoryStoreEvent> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn make_provider(key: &Key, expires: Instant) -> ProviderRecord {
+        ProviderRecord {
+            key: key.clone(),
+            provider: PeerId::random(),
+            addresses: Vec::new(),
+            expires,
+        }
+    }
+
+    #[test]
+    fn expired_records_ignored_and_removed() {
+        let mut store = MemoryStore::new();
+        let now = Instant::now();
+        let mut expired = Record::new(vec![1, 2, 3, 4], vec![1, 3, 3, 7]);
+        expired.expires = Some(now);
+        let valid =
+            Record::new(vec![5, 6, 7, 8], vec![1, 3, 3, 8]).with_ttl(Duration::from_secs(60));
+
+        store.put(expired.clone());
+        store.put(valid.clone());
+
+        assert!(store.get(&expired.key).is_none());
+        assert_eq!(store.get(&valid.key), Some(&valid));
+        assert_eq!(store.records().collect::<Vec<_>>(), vec![&valid]);
+
+        store.remove_expired_records(now);
+        assert_eq!(store.records.len(), 1);
+    }
+
+    #[test]
+    fn provider_updated() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3, 4]);
+        let mut record = make_provider(&key, Instant::now() + Duration::from_secs(60));
+
+        store.put_provider(record.clone());
+        record.addresses = vec!["/ip6/::1/tcp/8888".parse().unwrap()];
+        store.put_provider(record.clone());
+
+        assert_eq!(store.get_providers(&key), vec![record.clone()]);
+
+        store.remove_provider(&key, &record.provider);
+        assert!(store.get_providers(&key).is_empty());
+        assert!(store.providers.is_empty());
+    }
+
+    #[test]
+    fn provider_limit_enforced() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3, 4]);
+
+        for _ in 0..MAX_PROVIDERS_PER_KEY + 5 {
+            store.put_provider(make_provider(
+                &key,
+                Instant::now() + Duration::from_secs(60),
+            ));
+        }
+
+        assert_eq!(store.get_providers(&key).len(), MAX_PROVIDERS_PER_KEY);
+    }
+
+    #[test]
+    fn farthest_provider_replaced() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3, 4]);
+        let target = KademliaKey::new(key.clone());
+        let distance = |provider: &PeerId| target.distance(&KademliaKey::from(*provider));
+        let expires = Instant::now() + Duration::from_secs(60);
+
+        for _ in 0..MAX_PROVIDERS_PER_KEY {
+            store.put_provider(make_provider(&key, expires));
+        }
+
+        let farthest = store
+            .get_providers(&key)
+            .into_iter()
+            .max_by_key(|record| distance(&record.provider))
+            .unwrap();
+
+        // provider farther than all stored providers is ignored
+        let farther = loop {
+            let record = make_provider(&key, expires);
+
+            if distance(&record.provider) > distance(&farthest.provider) {
+                break record;
+            }
+        };
+        store.put_provider(farther.clone());
+        assert!(!store.get_providers(&key).contains(&farther));
+
+        // closer provider replaces the farthest provider
+        let closer = loop {
+            let record = make_provider(&key, expires);
+
+            if distance(&record.provider) < distance(&farthest.provider) {
+                break record;
+            }
+        };
+        store.put_provider(closer.clone());
+
+        let providers = store.get_providers(&key);
+        assert_eq!(providers.len(), MAX_PROVIDERS_PER_KEY);
+        assert!(providers.contains(&closer));
+        assert!(!providers.contains(&farthest));
+    }
+
+    #[test]
+    fn provider_key_limit_enforced() {
+        let mut store = MemoryStore::new();
+        let expires = Instant::now() + Duration::from_secs(60);
+
+        for i in 0..MAX_PROVIDER_KEYS as u32 {
+            store.put_provider(make_provider(&Key::from(i.to_le_bytes().to_vec()), expires));
+        }
+
+        // providers for new keys are ignored once the store is full
+        let key = Key::from(vec![0xff; 8]);
+        store.put_provider(make_provider(&key, expires));
+        assert!(store.get_providers(&key).is_empty());
+
+        // but existing keys accept new providers
+        let key = Key::from(0u32.to_le_bytes().to_vec());
+        store.put_provider(make_provider(&key, expires));
+        assert_eq!(store.get_providers(&key).len(), 2);
+        assert_eq!(store.num_providers, MAX_PROVIDER_KEYS + 1);
+
+        store.remove_expired_providers(expires);
+        assert_eq!(store.num_providers, 0);
+    }
+
+    #[test]
+    fn expired_providers_ignored_and_removed() {
+        let mut store = MemoryStore::new();
+        let key = Key::from(vec![1, 2, 3, 4]);
+        let now = Instant::now();
+        let valid = make_provider(&key, now + Duration::from_secs(60));
+
+        store.put_provider(make_provider(&key, now));
+        store.put_provider(valid.clone());
+
+        assert_eq!(store.get_providers(&key), vec![valid]);
+        assert_eq!(store.providers[&key].len(), 2);
+
+        store.remove_expired_providers(now + Duration::from_secs(60));
+        assert!(store.providers.is_empty());
+    }
+}
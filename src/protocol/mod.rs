@@ -33,9 +33,12 @@ use multiaddr::Multiaddr;
 
 use std::fmt::Debug;
 
-pub(crate) use connection::Permit;
-pub(crate) use protocol_set::{InnerTransportEvent, ProtocolCommand, ProtocolSet};
+pub use connection::Permit;
+pub use protocol_set::{ProtocolCommand, ProtocolSet};
 
+pub(crate) use protocol_set::InnerTransportEvent;
+
+pub use crate::transport::manager::{ReputationChange, ReputationConfig};
 pub use transport_service::TransportService;
 
 pub mod libp2p;
@@ -43,7 +46,7 @@ pub mod mdns;
 pub mod notification;
 pub mod request_response;
 
-mod connection;
+pub(crate) mod connection;
 mod protocol_set;
 mod transport_service;
 
@@ -21,9 +21,13 @@
 use crate::{
     codec::ProtocolCodec,
     protocol::notification::{
+        group::GroupEvent,
         handle::NotificationHandle,
+        rate_limit::RateLimitConfig,
+        slots::SlotsConfig,
         types::{
-            InnerNotificationEvent, NotificationCommand, ASYNC_CHANNEL_SIZE, SYNC_CHANNEL_SIZE,
+            HandshakeValidator, InnerNotificationEvent, NotificationCommand, NotificationPriority,
+            OverflowPolicy, ASYNC_CHANNEL_SIZE, SYNC_CHANNEL_SIZE,
         },
     },
     types::protocol::ProtocolName,
@@ -32,9 +36,9 @@ use crate::{
 
 use bytes::BytesMut;
 use parking_lot::RwLock;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::mpsc::{channel, Receiver, Sender, UnboundedReceiver, UnboundedSender};
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 /// Notification configuration.
 #[derive(Debug)]
@@ -75,6 +79,39 @@ pub struct Config {
     /// Should `NotificationProtocol` dial the peer if there is no connection to them
     /// when an outbound substream is requested.
     pub(crate) should_dial: bool,
+
+    /// Peer slots, if the number of notification streams is limited.
+    pub(crate) slots: Option<SlotsConfig>,
+
+    /// Policy applied when the synchronous channel of a peer is full.
+    pub(crate) overflow_policy: OverflowPolicy,
+
+    /// High-priority channel size.
+    pub(crate) high_priority_channel_size: usize,
+
+    /// Low-priority channel size.
+    pub(crate) low_priority_channel_size: usize,
+
+    /// How long to wait for an inbound substream to be validated before rejecting it, if limited.
+    pub(crate) validation_timeout: Option<Duration>,
+
+    /// Validator for inbound substreams, if installed.
+    pub(crate) handshake_validator: Option<Arc<dyn HandshakeValidator>>,
+
+    /// Should handshake changes be sent to peers with an open substream.
+    pub(crate) handshake_updates: bool,
+
+    /// Inbound rate limit applied to each peer, if configured.
+    pub(crate) rate_limit: Option<RateLimitConfig>,
+
+    /// Main protocol of the group, if this is a secondary protocol of a protocol group.
+    pub(crate) main_protocol: Option<ProtocolName>,
+
+    /// TX channels for sending events to the secondary protocols of the group.
+    pub(crate) group_tx: Vec<UnboundedSender<GroupEvent>>,
+
+    /// RX channel for receiving events from the main protocol of the group.
+    pub(crate) group_rx: Option<UnboundedReceiver<GroupEvent>>,
 }
 
 impl Config {
@@ -108,6 +145,17 @@ impl Config {
                 notif_tx,
                 command_rx,
                 should_dial,
+                slots: None,
+                overflow_policy: OverflowPolicy::default(),
+                high_priority_channel_size: SYNC_CHANNEL_SIZE,
+                low_priority_channel_size: SYNC_CHANNEL_SIZE,
+                validation_timeout: None,
+                handshake_validator: None,
+                handshake_updates: false,
+                rate_limit: None,
+                main_protocol: None,
+                group_tx: Vec::new(),
+                group_rx: None,
                 sync_channel_size,
                 async_channel_size,
             },
@@ -156,6 +204,33 @@ pub struct ConfigBuilder {
 
     /// Asynchronous channel size.
     async_channel_size: usize,
+
+    /// Peer slots.
+    slots: Option<SlotsConfig>,
+
+    /// Policy applied when the synchronous channel of a peer is full.
+    overflow_policy: OverflowPolicy,
+
+    /// High-priority channel size.
+    high_priority_channel_size: usize,
+
+    /// Low-priority channel size.
+    low_priority_channel_size: usize,
+
+    /// How long to wait for an inbound substream to be validated before rejecting it, if limited.
+    validation_timeout: Option<Duration>,
+
+    /// Validator for inbound substreams.
+    handshake_validator: Option<Arc<dyn HandshakeValidator>>,
+
+    /// Should handshake changes be sent to peers with an open substream.
+    handshake_updates: bool,
+
+    /// Inbound rate limit applied to each peer.
+    rate_limit: Option<RateLimitConfig>,
+
+    /// Main protocol of the group.
+    main_protocol: Option<ProtocolName>,
 }
 
 impl ConfigBuilder {
@@ -170,6 +245,15 @@ impl ConfigBuilder {
             sync_channel_size: SYNC_CHANNEL_SIZE,
             async_channel_size: ASYNC_CHANNEL_SIZE,
             should_dial: true,
+            slots: None,
+            overflow_policy: OverflowPolicy::default(),
+            high_priority_channel_size: SYNC_CHANNEL_SIZE,
+            low_priority_channel_size: SYNC_CHANNEL_SIZE,
+            validation_timeout: None,
+            handshake_validator: None,
+            handshake_updates: false,
+            rate_limit: None,
+            main_protocol: None,
         }
     }
 
@@ -232,6 +316,101 @@ impl ConfigBuilder {
         self
     }
 
+    /// Configure size of the channel for sending notifications of `priority`.
+    ///
+    /// The channel of [`NotificationPriority::Normal`] is the synchronous channel, so for it this
+    /// is equivalent to [`ConfigBuilder::with_sync_channel_size()`].
+    ///
+    /// Default value is `2048` for all priority classes.
+    pub fn with_priority_channel_size(
+        mut self,
+        priority: NotificationPriority,
+        size: usize,
+    ) -> Self {
+        match priority {
+            NotificationPriority::High => self.high_priority_channel_size = size,
+            NotificationPriority::Normal => self.sync_channel_size = size,
+            NotificationPriority::Low => self.low_priority_channel_size = size,
+        }
+        self
+    }
+
+    /// Configure what happens when a synchronous notification is sent to a peer whose channel is
+    /// full.
+    ///
+    /// Default value is [`OverflowPolicy::ForceClose`].
+    pub fn with_sync_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Configure how long an inbound substream can wait for validation before it's rejected.
+    ///
+    /// Applies both to substreams validated by the user protocol with
+    /// [`NotificationHandle::send_validation_result()`] and those validated by a
+    /// [`HandshakeValidator`].
+    ///
+    /// By default inbound substreams wait for validation indefinitely.
+    pub fn with_validation_timeout(mut self, timeout: Duration) -> Self {
+        self.validation_timeout = Some(timeout);
+        self
+    }
+
+    /// Validate inbound substreams using `validator`.
+    ///
+    /// By default, inbound substreams are validated by the user protocol which receives a
+    /// [`NotificationEvent::ValidateSubstream`](super::NotificationEvent::ValidateSubstream)
+    /// event and must respond with [`NotificationHandle::send_validation_result()`]. If a
+    /// validator is installed, it's called directly and the event is not emitted.
+    pub fn with_handshake_validator(mut self, validator: Arc<dyn HandshakeValidator>) -> Self {
+        self.handshake_validator = Some(validator);
+        self
+    }
+
+    /// Send handshake changes to peers with an open substream.
+    ///
+    /// When enabled, [`NotificationHandle::set_handshake()`] sends the new handshake to all peers
+    /// with an open substream who receive it as
+    /// [`NotificationEvent::HandshakeUpdated`](super::NotificationEvent::HandshakeUpdated).
+    ///
+    /// The handshake is sent in-band and each message sent over the substream is prefixed with a
+    /// one-byte message type. This changes the wire format of the protocol, so all peers of the
+    /// protocol must enable handshake updates, e.g., by using a dedicated protocol name.
+    ///
+    /// Handshake updates are disabled by default.
+    pub fn with_handshake_updates(mut self, enabled: bool) -> Self {
+        self.handshake_updates = enabled;
+        self
+    }
+
+    /// Limit the rate of notifications received from each peer.
+    ///
+    /// Notifications received from a peer are counted regardless of the limit and the counters
+    /// can be queried with [`NotificationHandle::inbound_stats()`].
+    ///
+    /// By default the inbound rate is not limited.
+    pub fn with_inbound_rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Make the protocol a secondary protocol of the group whose main protocol is `protocol`.
+    ///
+    /// Notification streams of a secondary protocol are opened automatically once the
+    /// notification stream of the main protocol to the peer is open and closed when it closes.
+    /// Outbound substreams requested before that are opened only once the main notification
+    /// stream is open and inbound substreams are validated by the main protocol, i.e., they're
+    /// accepted once the main notification stream is open and
+    /// [`NotificationEvent::ValidateSubstream`](super::NotificationEvent::ValidateSubstream) is
+    /// not emitted for them.
+    ///
+    /// `protocol` must be a notification protocol registered to the same
+    /// [`Litep2p`](crate::Litep2p) instance and it can't be a secondary protocol itself.
+    pub fn with_main_protocol(mut self, protocol: ProtocolName) -> Self {
+        self.main_protocol = Some(protocol);
+        self
+    }
+
     /// Should `NotificationProtocol` attempt to dial the peer if an outbound substream is opened
     /// but no connection to the peer exist.
     ///
@@ -241,9 +420,22 @@ impl ConfigBuilder {
         self
     }
 
+    /// Manage notification streams using peer slots.
+    ///
+    /// By default the number of notification streams is not limited and outbound substreams are
+    /// only opened when requested by the user. With slots enabled, inbound substreams are rejected
+    /// if there are no free inbound slots, opening an outbound substream fails with
+    /// [`NotificationError::NoSlotsAvailable`](super::NotificationError::NoSlotsAvailable) if
+    /// there are no free outbound slots and outbound substreams are opened automatically to
+    /// connected peers while there are free outbound slots. See [`SlotsConfig`] for more details.
+    pub fn with_peer_slots(mut self, slots: SlotsConfig) -> Self {
+        self.slots = Some(slots);
+        self
+    }
+
     /// Build notification configuration.
     pub fn build(mut self) -> (Config, NotificationHandle) {
-        Config::new(
+        let (mut config, mut handle) = Config::new(
             self.protocol_name,
             self.max_notification_size.take().expect("notification size to be specified"),
             self.handshake.take().expect("handshake to be specified"),
@@ -252,6 +444,23 @@ impl ConfigBuilder {
             self.sync_channel_size,
             self.async_channel_size,
             self.should_dial,
-        )
+        );
+        config.slots = self.slots;
+        config.overflow_policy = self.overflow_policy;
+        config.high_priority_channel_size = self.high_priority_channel_size;
+        config.low_priority_channel_size = self.low_priority_channel_size;
+        config.validation_timeout = self.validation_timeout;
+        config.handshake_validator = self.handshake_validator;
+        config.handshake_updates = self.handshake_updates;
+        config.rate_limit = self.rate_limit;
+        config.main_protocol = self.main_protocol;
+
+        // account for the message type prefixed to each message
+        if self.handshake_updates {
+            config.codec = ProtocolCodec::UnsignedVarint(Some(config._max_notification_size + 1));
+            handle.max_handshake_update_size = Some(config._max_notification_size);
+        }
+
+        (config, handle)
     }
 }
@@ -19,11 +19,19 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
-    protocol::notification::handle::NotificationEventHandle, substream::Substream, PeerId,
+    protocol::notification::{
+        handle::NotificationEventHandle,
+        rate_limit::{InboundLimiter, RateLimitAction},
+        types::CloseReason,
+    },
+    substream::Substream,
+    PeerId,
 };
 
-use bytes::BytesMut;
+use bytes::{Buf, Bytes, BytesMut};
 use futures::{FutureExt, SinkExt, Stream, StreamExt};
+use futures_timer::Delay;
+use parking_lot::Mutex;
 use tokio::sync::{
     mpsc::{Receiver, Sender},
     oneshot,
@@ -32,12 +40,23 @@ use tokio_util::sync::PollSender;
 
 use std::{
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 /// Logging target for the file.
 const LOG_TARGET: &str = "litep2p::notification::connection";
 
+/// Message type of a notification if handshake updates are enabled.
+const MESSAGE_NOTIFICATION: u8 = 0;
+
+/// Message type of a handshake update if handshake updates are enabled.
+const MESSAGE_HANDSHAKE: u8 = 1;
+
+/// How many notifications over the inbound rate limit are dropped before yielding to the runtime.
+const DROP_BUDGET: usize = 64;
+
 /// Bidirectional substream pair representing a connection to a remote peer.
 pub(crate) struct Connection {
     /// Remote peer ID.
@@ -60,17 +79,70 @@ pub(crate) struct Connection {
     notif_tx: PollSender<(PeerId, BytesMut)>,
 
     /// Receiver for asynchronously sent notifications.
-    async_rx: Receiver<Vec<u8>>,
+    async_rx: Receiver<Bytes>,
 
     /// Receiver for synchronously sent notifications.
-    sync_rx: Receiver<Vec<u8>>,
+    ///
+    /// The receiver is shared with [`NotificationSink`](super::NotificationSink)s which may drop
+    /// the oldest notification if the channel is full.
+    sync_rx: Arc<Mutex<Receiver<Bytes>>>,
+
+    /// Should the synchronous channel be polled first.
+    ///
+    /// Channels of normal priority are polled in alternating order so neither of them is starved.
+    prefer_sync: bool,
+
+    /// Receiver for notifications of high priority.
+    ///
+    /// Drained before notifications of any other priority.
+    high_rx: Arc<Mutex<Receiver<Bytes>>>,
+
+    /// Receiver for notifications of low priority.
+    ///
+    /// Drained only when there are no notifications of any other priority.
+    low_rx: Arc<Mutex<Receiver<Bytes>>>,
+
+    /// Receiver for local handshake updates, if handshake updates are enabled.
+    ///
+    /// If enabled, each message sent or received is prefixed with its type.
+    handshake_rx: Option<Arc<Mutex<Receiver<Bytes>>>>,
+
+    /// Rate limiter and counters for received notifications.
+    limiter: InboundLimiter,
 
     /// Oneshot receiver used by [`NotificationProtocol`](super::NotificationProtocol)
     /// to signal that local node wishes the close the connection.
-    rx: oneshot::Receiver<()>,
+    rx: oneshot::Receiver<CloseRequest>,
+
+    /// Reason for closing the connection and the timer for flushing the queued notifications,
+    /// if the connection is being closed gracefully.
+    closing: Option<(CloseReason, Delay)>,
 
-    /// Next notification to send, if any.
-    next_notification: Option<Vec<u8>>,
+    /// Next message to send and its type, if any.
+    ///
+    /// The message type is set only if handshake updates are enabled.
+    next_notification: Option<(Option<u8>, Bytes)>,
+}
+
+/// Request to close the connection.
+#[derive(Debug)]
+pub(crate) struct CloseRequest {
+    /// Why the connection is closed.
+    pub(crate) reason: CloseReason,
+
+    /// How long to wait for queued notifications to be sent before closing the connection,
+    /// if at all.
+    pub(crate) flush: Option<Duration>,
+}
+
+impl CloseRequest {
+    /// Create new [`CloseRequest`] which closes the connection immediately.
+    pub(crate) fn new(reason: CloseReason) -> Self {
+        Self {
+            reason,
+            flush: None,
+        }
+    }
 }
 
 /// Notify [`NotificationProtocol`](super::NotificationProtocol) that the connection was closed.
@@ -92,9 +164,13 @@ impl Connection {
         event_handle: NotificationEventHandle,
         conn_closed_tx: Sender<PeerId>,
         notif_tx: Sender<(PeerId, BytesMut)>,
-        async_rx: Receiver<Vec<u8>>,
-        sync_rx: Receiver<Vec<u8>>,
-    ) -> (Self, oneshot::Sender<()>) {
+        async_rx: Receiver<Bytes>,
+        sync_rx: Arc<Mutex<Receiver<Bytes>>>,
+        high_rx: Arc<Mutex<Receiver<Bytes>>>,
+        low_rx: Arc<Mutex<Receiver<Bytes>>>,
+        handshake_rx: Option<Arc<Mutex<Receiver<Bytes>>>>,
+        limiter: InboundLimiter,
+    ) -> (Self, oneshot::Sender<CloseRequest>) {
         let (tx, rx) = oneshot::channel();
 
         (
@@ -103,11 +179,17 @@ impl Connection {
                 peer,
                 sync_rx,
                 async_rx,
+                high_rx,
+                low_rx,
+                handshake_rx,
+                limiter,
                 inbound,
                 outbound,
                 event_handle,
                 conn_closed_tx,
                 next_notification: None,
+                closing: None,
+                prefer_sync: false,
                 notif_tx: PollSender::new(notif_tx),
             },
             tx,
@@ -118,11 +200,15 @@ impl Connection {
     ///
     /// If [`NotificationProtocol`](super::NotificationProtocol) was the one that initiated
     /// shut down, it's not notified of connection getting closed.
-    async fn close_connection(self, notify_protocol: NotifyProtocol) {
+    async fn close_connection(mut self, notify_protocol: NotifyProtocol, reason: CloseReason) {
+        let dropped = self.drop_queued_notifications();
+
         tracing::trace!(
             target: LOG_TARGET,
             peer = ?self.peer,
             ?notify_protocol,
+            ?reason,
+            ?dropped,
             "close notification protocol",
         );
 
@@ -133,7 +219,125 @@ impl Connection {
             let _ = self.conn_closed_tx.send(self.peer).await;
         }
 
-        self.event_handle.report_notification_stream_closed(self.peer).await;
+        self.event_handle
+            .report_notification_stream_closed(self.peer, reason, dropped)
+            .await;
+    }
+
+    /// Close the notification channels and drop all queued notifications.
+    ///
+    /// Returns the number of dropped notifications.
+    fn drop_queued_notifications(&mut self) -> usize {
+        let mut dropped = usize::from(
+            self.next_notification
+                .take()
+                .is_some_and(|(message_type, _)| message_type != Some(MESSAGE_HANDSHAKE)),
+        );
+
+        for rx in [&self.high_rx, &self.sync_rx, &self.low_rx] {
+            let mut rx = rx.lock();
+            rx.close();
+
+            while rx.try_recv().is_ok() {
+                dropped += 1;
+            }
+        }
+
+        self.async_rx.close();
+        while self.async_rx.try_recv().is_ok() {
+            dropped += 1;
+        }
+
+        dropped
+    }
+
+    /// Poll the channels of normal priority for the next notification.
+    fn poll_normal(&mut self, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        self.prefer_sync = !self.prefer_sync;
+
+        match self.prefer_sync {
+            true => match self.sync_rx.lock().poll_recv(cx) {
+                Poll::Pending => self.async_rx.poll_recv(cx),
+                ready => ready,
+            },
+            false => match self.async_rx.poll_recv(cx) {
+                Poll::Pending => self.sync_rx.lock().poll_recv(cx),
+                ready => ready,
+            },
+        }
+    }
+
+    /// Poll the channels for the next message to send to remote peer.
+    ///
+    /// Pending handshake update is sent before any notification and notifications are sent in
+    /// the order of their priority.
+    ///
+    /// If handshake updates are enabled, the message is returned with its type.
+    fn poll_next_message(&mut self, cx: &mut Context<'_>) -> Poll<Option<(Option<u8>, Bytes)>> {
+        if let Some(handshake_rx) = &self.handshake_rx {
+            let handshake = handshake_rx.lock().poll_recv(cx);
+
+            if let Poll::Ready(handshake) = handshake {
+                return Poll::Ready(
+                    handshake.map(|handshake| (Some(MESSAGE_HANDSHAKE), handshake)),
+                );
+            }
+        }
+
+        let high = self.high_rx.lock().poll_recv(cx);
+        let notification = match high {
+            Poll::Pending => self.poll_normal(cx),
+            ready => ready,
+        };
+        let notification = match notification {
+            Poll::Pending => self.low_rx.lock().poll_recv(cx),
+            ready => ready,
+        };
+
+        let message_type = self.handshake_rx.as_ref().map(|_| MESSAGE_NOTIFICATION);
+
+        notification
+            .map(|notification| notification.map(|notification| (message_type, notification)))
+    }
+
+    /// Decode message received from remote peer.
+    fn decode_message(&self, mut message: BytesMut) -> ConnectionEvent {
+        if self.handshake_rx.is_none() {
+            return ConnectionEvent::NotificationReceived {
+                notification: message,
+            };
+        }
+
+        if message.is_empty() {
+            tracing::debug!(target: LOG_TARGET, peer = ?self.peer, "empty message received");
+
+            return ConnectionEvent::CloseConnection {
+                notify: NotifyProtocol::Yes,
+                reason: CloseReason::ConnectionLost,
+            };
+        }
+
+        match message.get_u8() {
+            MESSAGE_NOTIFICATION => ConnectionEvent::NotificationReceived {
+                notification: message,
+            },
+            MESSAGE_HANDSHAKE => ConnectionEvent::HandshakeUpdated {
+                handshake: message.to_vec(),
+            },
+            message_type => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    peer = ?self.peer,
+                    ?message_type,
+                    "invalid message type received",
+                );
+
+                ConnectionEvent::CloseConnection {
+                    notify: NotifyProtocol::Yes,
+                    reason: CloseReason::ConnectionLost,
+                }
+            }
+        }
     }
 
     pub async fn start(mut self) {
@@ -145,18 +349,22 @@ impl Connection {
 
         loop {
             match self.next().await {
-                None
-                | Some(ConnectionEvent::CloseConnection {
-                    notify: NotifyProtocol::Yes,
-                }) => return self.close_connection(NotifyProtocol::Yes).await,
-                Some(ConnectionEvent::CloseConnection {
-                    notify: NotifyProtocol::No,
-                }) => return self.close_connection(NotifyProtocol::No).await,
+                None =>
+                    return self
+                        .close_connection(NotifyProtocol::Yes, CloseReason::ConnectionLost)
+                        .await,
+                Some(ConnectionEvent::CloseConnection { notify, reason }) =>
+                    return self.close_connection(notify, reason).await,
                 Some(ConnectionEvent::NotificationReceived { notification }) => {
                     if let Err(_) = self.notif_tx.send_item((self.peer, notification)) {
-                        return self.close_connection(NotifyProtocol::Yes).await;
+                        return self
+                            .close_connection(NotifyProtocol::Yes, CloseReason::LocalClosed)
+                            .await;
                     }
                 }
+                Some(ConnectionEvent::HandshakeUpdated { handshake }) => {
+                    self.event_handle.report_handshake_updated(self.peer, handshake).await;
+                }
             }
         }
     }
@@ -173,6 +381,9 @@ pub enum ConnectionEvent {
     CloseConnection {
         /// Whether to notify `NotificationProtocol` or not.
         notify: NotifyProtocol,
+
+        /// Why the connection is closed.
+        reason: CloseReason,
     },
 
     /// Notification read from the inbound substream.
@@ -186,6 +397,12 @@ pub enum ConnectionEvent {
         /// Notification.
         notification: BytesMut,
     },
+
+    /// Remote peer updated its handshake.
+    HandshakeUpdated {
+        /// New handshake.
+        handshake: Vec<u8>,
+    },
 }
 
 impl Stream for Connection {
@@ -194,79 +411,175 @@ impl Stream for Connection {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = Pin::into_inner(self);
 
-        if let Poll::Ready(_) = this.rx.poll_unpin(cx) {
-            return Poll::Ready(Some(ConnectionEvent::CloseConnection {
-                notify: NotifyProtocol::No,
-            }));
+        // the close request is polled only until it's received
+        if this.closing.is_none() {
+            if let Poll::Ready(request) = this.rx.poll_unpin(cx) {
+                let request =
+                    request.unwrap_or_else(|_| CloseRequest::new(CloseReason::LocalClosed));
+
+                match request.flush {
+                    None =>
+                        return Poll::Ready(Some(ConnectionEvent::CloseConnection {
+                            notify: NotifyProtocol::No,
+                            reason: request.reason,
+                        })),
+                    Some(timeout) => this.closing = Some((request.reason, Delay::new(timeout))),
+                }
+            }
+        }
+
+        // queued notifications were not sent in time
+        if let Some((reason, timer)) = &mut this.closing {
+            if timer.poll_unpin(cx).is_ready() {
+                return Poll::Ready(Some(ConnectionEvent::CloseConnection {
+                    notify: NotifyProtocol::No,
+                    reason: *reason,
+                }));
+            }
         }
 
         loop {
             let notification = match this.next_notification.take() {
                 Some(notification) => Some(notification),
                 None => {
-                    let future = async {
-                        tokio::select! {
-                            notification = this.async_rx.recv() => notification,
-                            notification = this.sync_rx.recv() => notification,
-                        }
-                    };
-                    futures::pin_mut!(future);
+                    let notification = this.poll_next_message(cx);
 
-                    match future.poll_unpin(cx) {
+                    match notification {
                         Poll::Pending => None,
-                        Poll::Ready(None) =>
+                        Poll::Ready(None) => {
+                            let (notify, reason) = match &this.closing {
+                                Some((reason, _)) => (NotifyProtocol::No, *reason),
+                                None => (NotifyProtocol::Yes, CloseReason::LocalClosed),
+                            };
+
                             return Poll::Ready(Some(ConnectionEvent::CloseConnection {
-                                notify: NotifyProtocol::Yes,
-                            })),
+                                notify,
+                                reason,
+                            }));
+                        }
                         Poll::Ready(Some(notification)) => Some(notification),
                     }
                 }
             };
 
-            let Some(notification) = notification else {
+            let Some((message_type, notification)) = notification else {
                 break;
             };
 
             match this.outbound.poll_ready_unpin(cx) {
                 Poll::Ready(Ok(())) => {}
                 Poll::Pending => {
-                    this.next_notification = Some(notification);
+                    this.next_notification = Some((message_type, notification));
                     break;
                 }
                 Poll::Ready(Err(_)) =>
                     return Poll::Ready(Some(ConnectionEvent::CloseConnection {
                         notify: NotifyProtocol::Yes,
+                        reason: CloseReason::ConnectionLost,
                     })),
             }
 
-            if let Err(_) = this.outbound.start_send_unpin(notification.into()) {
+            // the type prefix is sent as a separate frame to avoid copying the notification
+            let result = match message_type {
+                Some(message_type) => this.outbound.start_send_prefixed(message_type, notification),
+                None => this.outbound.start_send_unpin(notification),
+            };
+
+            if let Err(_) = result {
                 return Poll::Ready(Some(ConnectionEvent::CloseConnection {
                     notify: NotifyProtocol::Yes,
+                    reason: CloseReason::ConnectionLost,
                 }));
             }
         }
 
-        match this.outbound.poll_flush_unpin(cx) {
+        let flushed = match this.outbound.poll_flush_unpin(cx) {
             Poll::Ready(Err(_)) =>
                 return Poll::Ready(Some(ConnectionEvent::CloseConnection {
                     notify: NotifyProtocol::Yes,
+                    reason: CloseReason::ConnectionLost,
                 })),
-            Poll::Ready(Ok(())) | Poll::Pending => {}
+            Poll::Ready(Ok(())) => true,
+            Poll::Pending => false,
+        };
+
+        // while the connection is being closed gracefully, inbound notifications are not read
+        // and the connection is closed once all queued notifications have been sent
+        if let Some((reason, _)) = &this.closing {
+            if flushed && this.next_notification.is_none() {
+                return Poll::Ready(Some(ConnectionEvent::CloseConnection {
+                    notify: NotifyProtocol::No,
+                    reason: *reason,
+                }));
+            }
+
+            return Poll::Pending;
         }
 
         if let Err(_) = futures::ready!(this.notif_tx.poll_reserve(cx)) {
             return Poll::Ready(Some(ConnectionEvent::CloseConnection {
                 notify: NotifyProtocol::Yes,
+                reason: CloseReason::LocalClosed,
             }));
         }
 
-        match futures::ready!(this.inbound.poll_next_unpin(cx)) {
-            None | Some(Err(_)) =>
-                return Poll::Ready(Some(ConnectionEvent::CloseConnection {
-                    notify: NotifyProtocol::Yes,
-                })),
-            Some(Ok(notification)) =>
-                return Poll::Ready(Some(ConnectionEvent::NotificationReceived { notification })),
+        let mut budget = DROP_BUDGET;
+
+        loop {
+            // stop reading notifications if the peer is backpressured
+            futures::ready!(this.limiter.poll_ready(cx));
+
+            let message = match futures::ready!(this.inbound.poll_next_unpin(cx)) {
+                None =>
+                    return Poll::Ready(Some(ConnectionEvent::CloseConnection {
+                        notify: NotifyProtocol::Yes,
+                        reason: CloseReason::RemoteClosed,
+                    })),
+                Some(Err(_)) =>
+                    return Poll::Ready(Some(ConnectionEvent::CloseConnection {
+                        notify: NotifyProtocol::Yes,
+                        reason: CloseReason::ConnectionLost,
+                    })),
+                Some(Ok(message)) => message,
+            };
+
+            let notification = match this.decode_message(message) {
+                ConnectionEvent::NotificationReceived { notification } => notification,
+                event => return Poll::Ready(Some(event)),
+            };
+
+            match this.limiter.on_notification(notification.len()) {
+                None | Some(RateLimitAction::Backpressure) =>
+                    return Poll::Ready(Some(ConnectionEvent::NotificationReceived {
+                        notification,
+                    })),
+                Some(RateLimitAction::Drop) => {
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        peer = ?this.peer,
+                        "inbound rate limit exceeded, drop notification",
+                    );
+
+                    // don't let a flooding peer starve other tasks
+                    budget -= 1;
+                    if budget == 0 {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                }
+                Some(RateLimitAction::Close) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        peer = ?this.peer,
+                        "inbound rate limit exceeded, close connection",
+                    );
+
+                    return Poll::Ready(Some(ConnectionEvent::CloseConnection {
+                        notify: NotifyProtocol::Yes,
+                        reason: CloseReason::RateLimited,
+                    }));
+                }
+            }
         }
     }
 }
@@ -0,0 +1,333 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Protocol groups.
+//!
+//! Secondary protocols of a group depend on the main protocol of the group: their notification
+//! streams are opened only after the notification stream of the main protocol is open and they
+//! are closed when it closes.
+
+use crate::{error::Error, protocol::notification::Config, types::protocol::ProtocolName, PeerId};
+
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::notification::group";
+
+/// Event sent by the main protocol of a group to its secondary protocols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroupEvent {
+    /// Notification stream of the main protocol was opened to `peer`.
+    Opened {
+        /// Peer ID.
+        peer: PeerId,
+    },
+
+    /// Notification stream of the main protocol to `peer` was closed.
+    Closed {
+        /// Peer ID.
+        peer: PeerId,
+    },
+}
+
+/// Connect secondary notification protocols to the main protocols of their groups.
+///
+/// Returns [`Error::ProtocolNotSupported`] if the main protocol of a group is not a registered
+/// notification protocol or is itself a secondary protocol of another group.
+pub(crate) fn connect_groups(protocols: &mut HashMap<ProtocolName, Config>) -> crate::Result<()> {
+    let secondaries = protocols
+        .iter()
+        .filter_map(|(protocol, config)| {
+            config.main_protocol.as_ref().map(|main| (protocol.clone(), main.clone()))
+        })
+        .collect::<Vec<_>>();
+
+    for (protocol, main) in secondaries {
+        let config = match protocols.get_mut(&main) {
+            Some(config) if config.main_protocol.is_none() => config,
+            _ => {
+                tracing::error!(
+                    target: LOG_TARGET,
+                    %protocol,
+                    %main,
+                    "main protocol of the group is not a main notification protocol",
+                );
+                return Err(Error::ProtocolNotSupported(main.to_string()));
+            }
+        };
+
+        tracing::debug!(target: LOG_TARGET, %protocol, %main, "add protocol to group");
+
+        // events are sent without blocking so a slow secondary protocol can't stall the main
+        // protocol and the number of queued events is bounded by the number of peers
+        let (tx, rx) = unbounded_channel();
+        config.group_tx.push(tx);
+        protocols.get_mut(&protocol).expect("protocol to exist").group_rx = Some(rx);
+    }
+
+    Ok(())
+}
+
+/// State of the main protocol, tracked by a secondary protocol of the group.
+#[derive(Debug)]
+pub(super) struct MainProtocol {
+    /// RX channel for receiving events from the main protocol.
+    rx: UnboundedReceiver<GroupEvent>,
+
+    /// Peers with an open notification stream of the main protocol.
+    open: HashSet<PeerId>,
+
+    /// Inbound substreams waiting for the notification stream of the main protocol to open.
+    waiting: HashMap<PeerId, Vec<oneshot::Sender<()>>>,
+}
+
+/// Membership of a notification protocol in a protocol group.
+#[derive(Debug)]
+pub(super) struct ProtocolGroup {
+    /// TX channels for sending events to secondary protocols if this is the main protocol.
+    secondaries: Vec<UnboundedSender<GroupEvent>>,
+
+    /// Peers whose notification streams have been reported open to secondary protocols.
+    opened: HashSet<PeerId>,
+
+    /// State of the main protocol if this is a secondary protocol.
+    main: Option<MainProtocol>,
+}
+
+impl ProtocolGroup {
+    /// Create new [`ProtocolGroup`].
+    pub(super) fn new(
+        secondaries: Vec<UnboundedSender<GroupEvent>>,
+        rx: Option<UnboundedReceiver<GroupEvent>>,
+    ) -> Self {
+        Self {
+            secondaries,
+            opened: HashSet::new(),
+            main: rx.map(|rx| MainProtocol {
+                rx,
+                open: HashSet::new(),
+                waiting: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Is this a secondary protocol of a group.
+    pub(super) fn is_secondary(&self) -> bool {
+        self.main.is_some()
+    }
+
+    /// Is the notification stream of the main protocol open to `peer`.
+    ///
+    /// Always `true` if this is not a secondary protocol.
+    pub(super) fn is_main_open(&self, peer: &PeerId) -> bool {
+        self.main.as_ref().map_or(true, |main| main.open.contains(peer))
+    }
+
+    /// Wait until the notification stream of the main protocol to `peer` is open.
+    ///
+    /// Returns a receiver which resolves once the stream is open or fails if it's closed first.
+    /// Must only be called for secondary protocols.
+    pub(super) fn wait_main_open(&mut self, peer: PeerId) -> oneshot::Receiver<()> {
+        let main = self.main.as_mut().expect("secondary protocol to have a main protocol");
+        let (tx, rx) = oneshot::channel();
+
+        match main.open.contains(&peer) {
+            true => {
+                let _ = tx.send(());
+            }
+            false => {
+                main.waiting.entry(peer).or_default().push(tx);
+            }
+        }
+
+        rx
+    }
+
+    /// Stop waiting for the notification stream of the main protocol to `peer` to open.
+    ///
+    /// Receivers returned by [`ProtocolGroup::wait_main_open()`] for `peer` fail.
+    pub(super) fn cancel_waiting(&mut self, peer: &PeerId) {
+        if let Some(main) = self.main.as_mut() {
+            main.waiting.remove(peer);
+        }
+    }
+
+    /// Report to secondary protocols that a notification stream was opened to `peer`.
+    pub(super) fn report_opened(&mut self, peer: PeerId) {
+        if self.secondaries.is_empty() || !self.opened.insert(peer) {
+            return;
+        }
+
+        for tx in &self.secondaries {
+            let _ = tx.send(GroupEvent::Opened { peer });
+        }
+    }
+
+    /// Report to secondary protocols that the notification stream to `peer` was closed.
+    ///
+    /// Ignored if the notification stream wasn't reported open.
+    pub(super) fn report_closed(&mut self, peer: PeerId) {
+        if !self.opened.remove(&peer) {
+            return;
+        }
+
+        for tx in &self.secondaries {
+            let _ = tx.send(GroupEvent::Closed { peer });
+        }
+    }
+
+    /// Receive next event from the main protocol.
+    ///
+    /// Never resolves if this is not a secondary protocol or if the main protocol has exited.
+    pub(super) async fn next_event(&mut self) -> GroupEvent {
+        let Some(main) = self.main.as_mut() else {
+            return futures::future::pending().await;
+        };
+
+        let Some(event) = main.rx.recv().await else {
+            return futures::future::pending().await;
+        };
+
+        match event {
+            GroupEvent::Opened { peer } => {
+                main.open.insert(peer);
+
+                for tx in main.waiting.remove(&peer).unwrap_or_default() {
+                    let _ = tx.send(());
+                }
+            }
+            GroupEvent::Closed { peer } => {
+                main.open.remove(&peer);
+                main.waiting.remove(&peer);
+            }
+        }
+
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::notification::ConfigBuilder;
+
+    fn make_config(protocol: &'static str, main: Option<&'static str>) -> Config {
+        let builder = ConfigBuilder::new(ProtocolName::from(protocol))
+            .with_max_size(1024)
+            .with_handshake(vec![1, 2, 3, 4]);
+
+        match main {
+            Some(main) => builder.with_main_protocol(ProtocolName::from(main)).build().0,
+            None => builder.build().0,
+        }
+    }
+
+    #[test]
+    fn secondary_protocols_connected_to_main() {
+        let mut protocols = HashMap::from_iter([
+            (ProtocolName::from("/main/1"), make_config("/main/1", None)),
+            (
+                ProtocolName::from("/tx/1"),
+                make_config("/tx/1", Some("/main/1")),
+            ),
+            (
+                ProtocolName::from("/grandpa/1"),
+                make_config("/grandpa/1", Some("/main/1")),
+            ),
+        ]);
+
+        connect_groups(&mut protocols).unwrap();
+
+        assert_eq!(protocols[&ProtocolName::from("/main/1")].group_tx.len(), 2);
+        assert!(protocols[&ProtocolName::from("/main/1")].group_rx.is_none());
+        assert!(protocols[&ProtocolName::from("/tx/1")].group_rx.is_some());
+        assert!(protocols[&ProtocolName::from("/grandpa/1")].group_rx.is_some());
+    }
+
+    #[test]
+    fn nested_groups_are_rejected() {
+        let mut protocols = HashMap::from_iter([
+            (ProtocolName::from("/main/1"), make_config("/main/1", None)),
+            (
+                ProtocolName::from("/tx/1"),
+                make_config("/tx/1", Some("/main/1")),
+            ),
+            (
+                ProtocolName::from("/grandpa/1"),
+                make_config("/grandpa/1", Some("/tx/1")),
+            ),
+        ]);
+
+        assert!(std::matches!(
+            connect_groups(&mut protocols),
+            Err(Error::ProtocolNotSupported(protocol)) if protocol == "/tx/1"
+        ));
+    }
+
+    #[test]
+    fn unknown_main_protocol_is_rejected() {
+        let mut protocols = HashMap::from_iter([(
+            ProtocolName::from("/tx/1"),
+            make_config("/tx/1", Some("/main/1")),
+        )]);
+
+        assert!(connect_groups(&mut protocols).is_err());
+    }
+
+    #[tokio::test]
+    async fn secondary_tracks_main_notification_streams() {
+        let (tx, rx) = unbounded_channel();
+        let mut main = ProtocolGroup::new(vec![tx], None);
+        let mut secondary = ProtocolGroup::new(Vec::new(), Some(rx));
+        let peer = PeerId::random();
+
+        assert!(secondary.is_secondary());
+        assert!(!secondary.is_main_open(&peer));
+
+        // inbound substreams wait until the main notification stream is open
+        let mut waiting1 = secondary.wait_main_open(peer);
+        let waiting2 = secondary.wait_main_open(peer);
+        assert!(waiting1.try_recv().is_err());
+
+        main.report_opened(peer);
+        assert_eq!(secondary.next_event().await, GroupEvent::Opened { peer });
+        assert!(secondary.is_main_open(&peer));
+        assert!(waiting1.await.is_ok());
+        assert!(waiting2.await.is_ok());
+
+        main.report_closed(peer);
+        assert_eq!(secondary.next_event().await, GroupEvent::Closed { peer });
+        assert!(!secondary.is_main_open(&peer));
+
+        // closing a notification stream that was never reported open is not reported
+        main.report_closed(peer);
+        assert!(secondary.main.as_mut().unwrap().rx.try_recv().is_err());
+
+        // inbound substream is rejected if waiting is canceled before the main stream opens
+        let waiting = secondary.wait_main_open(peer);
+        secondary.cancel_waiting(&peer);
+        assert!(waiting.await.is_err());
+    }
+}
@@ -19,28 +19,37 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
-    error::Error,
-    protocol::notification::types::{
-        Direction, InnerNotificationEvent, NotificationCommand, NotificationError,
-        NotificationEvent, ValidationResult,
+    error::{self, Error},
+    protocol::{
+        notification::{
+            rate_limit::{InboundCounters, InboundStats},
+            types::{
+                BroadcastOutcome, CloseReason, Direction, InnerNotificationEvent,
+                NotificationCommand, NotificationError, NotificationEvent, NotificationPriority,
+                OverflowPolicy, ValidationResult,
+            },
+        },
+        ReputationChange,
     },
     types::protocol::ProtocolName,
     PeerId,
 };
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use futures::Stream;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use tokio::sync::{
-    mpsc::{error::TrySendError, Receiver, Sender},
+    mpsc::{channel, error::TrySendError, Receiver, Sender},
     oneshot,
 };
+use tokio_util::sync::PollSender;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Weak},
     task::{Context, Poll},
+    time::Duration,
 };
 
 /// Logging target for the file.
@@ -102,8 +111,20 @@ impl NotificationEventHandle {
     }
 
     /// Notification stream closed.
-    pub(crate) async fn report_notification_stream_closed(&self, peer: PeerId) {
-        let _ = self.tx.send(InnerNotificationEvent::NotificationStreamClosed { peer }).await;
+    pub(crate) async fn report_notification_stream_closed(
+        &self,
+        peer: PeerId,
+        reason: CloseReason,
+        dropped: usize,
+    ) {
+        let _ = self
+            .tx
+            .send(InnerNotificationEvent::NotificationStreamClosed {
+                peer,
+                reason,
+                dropped,
+            })
+            .await;
     }
 
     /// Failed to open notification stream.
@@ -117,51 +138,323 @@ impl NotificationEventHandle {
             .send(InnerNotificationEvent::NotificationStreamOpenFailure { peer, error })
             .await;
     }
+
+    /// Remote peer updated its handshake.
+    pub(crate) async fn report_handshake_updated(&self, peer: PeerId, handshake: Vec<u8>) {
+        let _ = self.tx.send(InnerNotificationEvent::HandshakeUpdated { peer, handshake }).await;
+    }
+}
+
+/// Queue for synchronously sent notifications of a single priority class.
+#[derive(Debug, Clone)]
+pub(crate) struct PriorityQueue {
+    /// TX channel for sending notifications.
+    tx: Sender<Bytes>,
+
+    /// RX channel of the queue, used for dropping the oldest notification.
+    ///
+    /// The reference is weak so the sink doesn't keep the channel open after the connection has
+    /// been closed.
+    rx: Weak<Mutex<Receiver<Bytes>>>,
+}
+
+impl PriorityQueue {
+    /// Create new [`PriorityQueue`] with `capacity`.
+    ///
+    /// Returns the queue and the receiver that must be kept alive by the connection.
+    pub(crate) fn new(capacity: usize) -> (Self, Arc<Mutex<Receiver<Bytes>>>) {
+        let (tx, rx) = channel(capacity);
+        let rx = Arc::new(Mutex::new(rx));
+
+        (
+            Self {
+                tx,
+                rx: Arc::downgrade(&rx),
+            },
+            rx,
+        )
+    }
 }
 
 /// Notification sink.
 ///
 /// Allows the user to send notifications both synchronously and asynchronously.
-#[derive(Debug, Clone)]
+///
+/// In addition to the fail-fast [`NotificationSink::send_sync_notification()`], the synchronous
+/// channel can be used in a `poll_ready()`/`start_send()` fashion, allowing the producer to wait
+/// until the channel has capacity and adapt its sending rate instead of dropping notifications.
+///
+/// Notifications can be assigned a [`NotificationPriority`]. Each priority class has its own
+/// queue and queued notifications of a higher priority are sent before those of a lower priority.
+/// Notifications sent without an explicit priority use [`NotificationPriority::Normal`].
+#[derive(Debug)]
 pub struct NotificationSink {
     /// Peer ID.
     peer: PeerId,
 
-    /// TX channel for sending notifications synchronously.
-    sync_tx: Sender<Vec<u8>>,
+    /// Queue for synchronously sent notifications of normal priority.
+    sync: PriorityQueue,
+
+    /// Sender for reserving capacity in the synchronous channel.
+    sync_sink: PollSender<Bytes>,
+
+    /// Has capacity been reserved in the synchronous channel.
+    reserved: bool,
 
     /// TX channel for sending notifications asynchronously.
-    async_tx: Sender<Vec<u8>>,
+    async_tx: Sender<Bytes>,
+
+    /// Queue for notifications of high priority.
+    high: PriorityQueue,
+
+    /// Queue for notifications of low priority.
+    low: PriorityQueue,
+
+    /// Queue for handshake updates, if they're enabled.
+    handshake: Option<PriorityQueue>,
+
+    /// What to do when the synchronous channel is full.
+    overflow_policy: OverflowPolicy,
+
+    /// Counters of notifications received from the peer.
+    inbound_counters: Arc<InboundCounters>,
+}
+
+impl Clone for NotificationSink {
+    fn clone(&self) -> Self {
+        // reserved capacity is not shared with the clone
+        Self {
+            peer: self.peer,
+            sync: self.sync.clone(),
+            sync_sink: PollSender::new(self.sync.tx.clone()),
+            reserved: false,
+            async_tx: self.async_tx.clone(),
+            high: self.high.clone(),
+            low: self.low.clone(),
+            handshake: self.handshake.clone(),
+            overflow_policy: self.overflow_policy,
+            inbound_counters: Arc::clone(&self.inbound_counters),
+        }
+    }
 }
 
 impl NotificationSink {
     /// Create new [`NotificationSink`].
-    pub(crate) fn new(peer: PeerId, sync_tx: Sender<Vec<u8>>, async_tx: Sender<Vec<u8>>) -> Self {
+    pub(crate) fn new(
+        peer: PeerId,
+        sync: PriorityQueue,
+        async_tx: Sender<Bytes>,
+        high: PriorityQueue,
+        low: PriorityQueue,
+        handshake: Option<PriorityQueue>,
+        overflow_policy: OverflowPolicy,
+        inbound_counters: Arc<InboundCounters>,
+    ) -> Self {
         Self {
             peer,
             async_tx,
-            sync_tx,
+            high,
+            low,
+            handshake,
+            overflow_policy,
+            inbound_counters,
+            reserved: false,
+            sync_sink: PollSender::new(sync.tx.clone()),
+            sync,
         }
     }
 
+    /// Get the policy applied when the synchronous channel is full.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// Get statistics of notifications received from the peer.
+    pub fn inbound_stats(&self) -> InboundStats {
+        self.inbound_counters.stats()
+    }
+
+    /// Get the queue of `priority`.
+    fn queue(&self, priority: NotificationPriority) -> &PriorityQueue {
+        match priority {
+            NotificationPriority::High => &self.high,
+            NotificationPriority::Normal => &self.sync,
+            NotificationPriority::Low => &self.low,
+        }
+    }
+
+    /// Get the number of notifications queued in the synchronous channel.
+    ///
+    /// Capacity reserved with [`NotificationSink::poll_ready()`] is counted as queued.
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth_for(NotificationPriority::Normal)
+    }
+
+    /// Get the number of notifications queued in the queue of `priority`.
+    pub fn queue_depth_for(&self, priority: NotificationPriority) -> usize {
+        let tx = &self.queue(priority).tx;
+        tx.max_capacity() - tx.capacity()
+    }
+
+    /// Get the capacity of the synchronous channel.
+    pub fn capacity(&self) -> usize {
+        self.capacity_for(NotificationPriority::Normal)
+    }
+
+    /// Get the capacity of the queue of `priority`.
+    pub fn capacity_for(&self, priority: NotificationPriority) -> usize {
+        self.queue(priority).tx.max_capacity()
+    }
+
     /// Send notification to `peer` synchronously.
     ///
-    /// If the channel is clogged, [`NotificationError::ChannelClogged`] is returned.
+    /// If the channel is clogged, the notification is handled according to the configured
+    /// [`OverflowPolicy`]: with [`OverflowPolicy::DropOldest`] the oldest queued notification is
+    /// dropped to make room for `notification`, otherwise [`NotificationError::ChannelClogged`]
+    /// is returned.
     pub fn send_sync_notification(&self, notification: Vec<u8>) -> Result<(), NotificationError> {
-        self.sync_tx.try_send(notification).map_err(|error| match error {
+        self.try_send(NotificationPriority::Normal, notification.into())
+    }
+
+    /// Send notification of `priority` to `peer` synchronously.
+    ///
+    /// See [`NotificationSink::send_sync_notification()`] for more details.
+    pub fn send_sync_notification_with_priority(
+        &self,
+        priority: NotificationPriority,
+        notification: Vec<u8>,
+    ) -> Result<(), NotificationError> {
+        self.try_send(priority, notification.into())
+    }
+
+    /// Send notification of `priority` to `peer` synchronously without copying the payload.
+    fn try_send(
+        &self,
+        priority: NotificationPriority,
+        notification: Bytes,
+    ) -> Result<(), NotificationError> {
+        let queue = self.queue(priority);
+
+        match queue.tx.try_send(notification) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(_)) => Err(NotificationError::NoConnection),
+            Err(TrySendError::Full(notification)) => match self.overflow_policy {
+                OverflowPolicy::DropOldest => self.replace_oldest(queue, notification),
+                OverflowPolicy::DropNewest | OverflowPolicy::ForceClose =>
+                    Err(NotificationError::ChannelClogged),
+            },
+        }
+    }
+
+    /// Drop the oldest notification of `queue` and send `notification` in its place.
+    fn replace_oldest(
+        &self,
+        queue: &PriorityQueue,
+        notification: Bytes,
+    ) -> Result<(), NotificationError> {
+        let Some(rx) = queue.rx.upgrade() else {
+            return Err(NotificationError::NoConnection);
+        };
+
+        if rx.lock().try_recv().is_ok() {
+            tracing::trace!(
+                target: LOG_TARGET,
+                peer = ?self.peer,
+                "channel clogged, dropped oldest notification",
+            );
+        }
+
+        queue.tx.try_send(notification).map_err(|error| match error {
             TrySendError::Closed(_) => NotificationError::NoConnection,
             TrySendError::Full(_) => NotificationError::ChannelClogged,
         })
     }
 
+    /// Send updated local handshake to `peer` if handshake updates are enabled.
+    ///
+    /// If a previous update hasn't been sent yet, it's replaced by `handshake`.
+    pub(crate) fn send_handshake_update(&self, handshake: Bytes) {
+        let Some(queue) = &self.handshake else {
+            return;
+        };
+
+        if let Err(TrySendError::Full(handshake)) = queue.tx.try_send(handshake) {
+            let _ = self.replace_oldest(queue, handshake);
+        }
+    }
+
+    /// Poll the synchronous channel for capacity.
+    ///
+    /// Once `Poll::Ready(Ok(()))` is returned, capacity for one notification has been reserved
+    /// and the notification can be sent with [`NotificationSink::start_send()`].
+    ///
+    /// Returns [`NotificationError::NoConnection`] if the connection has been closed.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), NotificationError>> {
+        if self.reserved {
+            return Poll::Ready(Ok(()));
+        }
+
+        match futures::ready!(self.sync_sink.poll_reserve(cx)) {
+            Ok(()) => {
+                self.reserved = true;
+                Poll::Ready(Ok(()))
+            }
+            Err(_) => Poll::Ready(Err(NotificationError::NoConnection)),
+        }
+    }
+
+    /// Wait until the synchronous channel has capacity for a notification.
+    ///
+    /// See [`NotificationSink::poll_ready()`] for more details.
+    pub async fn ready(&mut self) -> Result<(), NotificationError> {
+        futures::future::poll_fn(|cx| self.poll_ready(cx)).await
+    }
+
+    /// Send notification to `peer` using the capacity reserved by
+    /// [`NotificationSink::poll_ready()`].
+    ///
+    /// If no capacity has been reserved, the notification is sent as if by
+    /// [`NotificationSink::send_sync_notification()`].
+    pub fn start_send(&mut self, notification: Vec<u8>) -> Result<(), NotificationError> {
+        if !std::mem::replace(&mut self.reserved, false) {
+            return self.try_send(NotificationPriority::Normal, notification.into());
+        }
+
+        self.sync_sink
+            .send_item(notification.into())
+            .map_err(|_| NotificationError::NoConnection)
+    }
+
     /// Send notification to `peer` asynchronously, waiting for the channel to have capacity
     /// if it's clogged.
     ///
     /// Returns [`Error::PeerDoesntExist(PeerId)`](crate::error::Error::PeerDoesntExist)
     /// if the connection has been closed.
     pub async fn send_async_notification(&self, notification: Vec<u8>) -> crate::Result<()> {
-        self.async_tx
-            .send(notification)
+        self.send_async_notification_with_priority(NotificationPriority::Normal, notification)
+            .await
+    }
+
+    /// Send notification of `priority` to `peer` asynchronously, waiting for the queue to have
+    /// capacity if it's clogged.
+    ///
+    /// Notifications of normal priority use a dedicated asynchronous channel whereas
+    /// notifications of high and low priority share the queue with synchronously sent
+    /// notifications of the same priority.
+    ///
+    /// Returns [`Error::PeerDoesntExist(PeerId)`](crate::error::Error::PeerDoesntExist)
+    /// if the connection has been closed.
+    pub async fn send_async_notification_with_priority(
+        &self,
+        priority: NotificationPriority,
+        notification: Vec<u8>,
+    ) -> crate::Result<()> {
+        let tx = match priority {
+            NotificationPriority::Normal => &self.async_tx,
+            priority => &self.queue(priority).tx,
+        };
+
+        tx.send(notification.into())
             .await
             .map_err(|_| Error::PeerDoesntExist(self.peer))
     }
@@ -190,6 +483,13 @@ pub struct NotificationHandle {
 
     /// Handshake.
     handshake: Arc<RwLock<Vec<u8>>>,
+
+    /// Maximum size of a handshake update, if handshake updates are enabled.
+    pub(super) max_handshake_update_size: Option<usize>,
+
+    /// Notifications received before a notification stream was closed and the close event
+    /// itself, reported in the order they were received.
+    pending_events: VecDeque<NotificationEvent>,
 }
 
 impl NotificationHandle {
@@ -208,6 +508,8 @@ impl NotificationHandle {
             peers: HashMap::new(),
             clogged: HashSet::new(),
             pending_validations: HashMap::new(),
+            pending_events: VecDeque::new(),
+            max_handshake_update_size: None,
         }
     }
 
@@ -234,6 +536,29 @@ impl NotificationHandle {
             .map_or(Ok(()), |_| Ok(()))
     }
 
+    /// Open substream to `peer` using a custom handshake.
+    ///
+    /// Similar to [`NotificationHandle::open_substream()`] but `handshake` is sent to `peer`
+    /// instead of the handshake of the protocol. The custom handshake is used for all substreams
+    /// negotiated with `peer` until the connection is closed or a substream is opened using
+    /// [`NotificationHandle::open_substream()`].
+    pub async fn open_substream_with_handshake(
+        &self,
+        peer: PeerId,
+        handshake: Vec<u8>,
+    ) -> crate::Result<()> {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?handshake, "open substream with handshake");
+
+        if self.peers.contains_key(&peer) {
+            return Err(Error::PeerAlreadyExists(peer));
+        }
+
+        self.command_tx
+            .send(NotificationCommand::OpenSubstreamWithHandshake { peer, handshake })
+            .await
+            .map_or(Ok(()), |_| Ok(()))
+    }
+
     /// Open substreams to multiple peers.
     ///
     /// Similar to [`NotificationHandle::open_substream()`] but multiple substreams are initiated
@@ -305,6 +630,8 @@ impl NotificationHandle {
     }
 
     /// Close substream to `peer`.
+    ///
+    /// Notifications still queued for `peer` are dropped.
     pub async fn close_substream(&self, peer: PeerId) {
         tracing::trace!(target: LOG_TARGET, ?peer, "close substream");
 
@@ -316,6 +643,27 @@ impl NotificationHandle {
             .command_tx
             .send(NotificationCommand::CloseSubstream {
                 peers: HashSet::from_iter([peer]),
+                flush: None,
+            })
+            .await;
+    }
+
+    /// Close substream to `peer` after sending the notifications queued for them.
+    ///
+    /// Notifications which are not sent within `timeout` are dropped and their number is
+    /// reported in [`NotificationEvent::NotificationStreamClosed`].
+    pub async fn close_substream_gracefully(&self, peer: PeerId, timeout: Duration) {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?timeout, "close substream gracefully");
+
+        if !self.peers.contains_key(&peer) {
+            return;
+        }
+
+        let _ = self
+            .command_tx
+            .send(NotificationCommand::CloseSubstream {
+                peers: HashSet::from_iter([peer]),
+                flush: Some(timeout),
             })
             .await;
     }
@@ -339,7 +687,10 @@ impl NotificationHandle {
             "close substreams",
         );
 
-        let _ = self.command_tx.send(NotificationCommand::CloseSubstream { peers }).await;
+        let _ = self
+            .command_tx
+            .send(NotificationCommand::CloseSubstream { peers, flush: None })
+            .await;
     }
 
     /// Try close substream to multiple peers.
@@ -372,15 +723,49 @@ impl NotificationHandle {
         self.command_tx
             .try_send(NotificationCommand::CloseSubstream {
                 peers: peers.clone(),
+                flush: None,
             })
             .map_err(|_| peers)
     }
 
+    /// Report reputation change for `peer`.
+    ///
+    /// The reputation is shared by all installed protocols and if it drops below the ban
+    /// threshold, the peer is disconnected and banned.
+    pub async fn report_peer(&self, peer: PeerId, change: ReputationChange) {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?change, "report peer");
+
+        let _ = self.command_tx.send(NotificationCommand::ReportPeer { peer, change }).await;
+    }
+
     /// Set new handshake.
-    pub fn set_handshake(&mut self, handshake: Vec<u8>) {
+    ///
+    /// If handshake updates are enabled with
+    /// [`ConfigBuilder::with_handshake_updates()`](super::ConfigBuilder::with_handshake_updates),
+    /// the new handshake is also sent to all peers with an open substream. The update is sent
+    /// in-band, so a handshake larger than the maximum notification size of the protocol is
+    /// rejected with [`NotificationError::HandshakeTooLarge`](error::NotificationError) and the
+    /// handshake is left unchanged.
+    pub fn set_handshake(&mut self, handshake: Vec<u8>) -> crate::Result<()> {
         tracing::trace!(target: LOG_TARGET, ?handshake, "set handshake");
 
-        *self.handshake.write() = handshake;
+        if self
+            .max_handshake_update_size
+            .is_some_and(|max_size| handshake.len() > max_size)
+        {
+            return Err(Error::NotificationError(
+                error::NotificationError::HandshakeTooLarge,
+            ));
+        }
+
+        *self.handshake.write() = handshake.clone();
+
+        let handshake = Bytes::from(handshake);
+        for sink in self.peers.values() {
+            sink.send_handshake_update(handshake.clone());
+        }
+
+        Ok(())
     }
 
     /// Send validation result to the notification protocol for an inbound substream received from
@@ -393,22 +778,33 @@ impl NotificationHandle {
 
     /// Send notification to `peer` synchronously.
     ///
-    /// If the channel is clogged, [`NotificationError::ChannelClogged`] is returned.
+    /// If the channel is clogged, [`NotificationError::ChannelClogged`] is returned, unless the
+    /// protocol uses [`OverflowPolicy::DropOldest`]. With [`OverflowPolicy::ForceClose`], the
+    /// connection to `peer` is also closed.
     pub fn send_sync_notification(
         &mut self,
         peer: PeerId,
         notification: Vec<u8>,
+    ) -> Result<(), NotificationError> {
+        self.send_sync_notification_with_priority(peer, NotificationPriority::Normal, notification)
+    }
+
+    /// Send notification of `priority` to `peer` synchronously.
+    ///
+    /// See [`NotificationHandle::send_sync_notification()`] for more details.
+    pub fn send_sync_notification_with_priority(
+        &mut self,
+        peer: PeerId,
+        priority: NotificationPriority,
+        notification: Vec<u8>,
     ) -> Result<(), NotificationError> {
         match self.peers.get_mut(&peer) {
-            Some(sink) => match sink.send_sync_notification(notification) {
+            Some(sink) => match sink.send_sync_notification_with_priority(priority, notification) {
                 Ok(()) => Ok(()),
                 Err(error) => match error {
                     NotificationError::NoConnection => return Err(NotificationError::NoConnection),
                     NotificationError::ChannelClogged => {
-                        let _ = self.clogged.insert(peer).then(|| {
-                            self.command_tx.try_send(NotificationCommand::ForceClose { peer })
-                        });
-
+                        self.on_clogged(peer);
                         Err(NotificationError::ChannelClogged)
                     }
                     // sink doesn't emit any other `NotificationError`s
@@ -419,6 +815,82 @@ impl NotificationHandle {
         }
     }
 
+    /// Close the connection to `peer` whose notification channel is clogged if the sink of the
+    /// peer uses [`OverflowPolicy::ForceClose`].
+    ///
+    /// The connection is closed only once, even if the channel is reported clogged repeatedly.
+    fn on_clogged(&mut self, peer: PeerId) {
+        if self.peers.get(&peer).map(NotificationSink::overflow_policy)
+            != Some(OverflowPolicy::ForceClose)
+        {
+            return;
+        }
+
+        let _ = self
+            .clogged
+            .insert(peer)
+            .then(|| self.command_tx.try_send(NotificationCommand::ForceClose { peer }));
+    }
+
+    /// Broadcast `notification` to all peers with an open substream for which `filter` returns
+    /// `true`.
+    ///
+    /// The payload is shared between peers and isn't copied. Notifications are sent
+    /// synchronously and clogged channels are handled according to the configured
+    /// [`OverflowPolicy`], similar to [`NotificationHandle::send_sync_notification()`].
+    ///
+    /// Returns the outcome for each peer the notification was broadcast to.
+    pub fn broadcast(
+        &mut self,
+        notification: impl Into<Bytes>,
+        filter: impl FnMut(&PeerId) -> bool,
+    ) -> HashMap<PeerId, BroadcastOutcome> {
+        self.broadcast_with_priority(NotificationPriority::Normal, notification, filter)
+    }
+
+    /// Broadcast `notification` of `priority` to all peers with an open substream for which
+    /// `filter` returns `true`.
+    ///
+    /// See [`NotificationHandle::broadcast()`] for more details.
+    pub fn broadcast_with_priority(
+        &mut self,
+        priority: NotificationPriority,
+        notification: impl Into<Bytes>,
+        mut filter: impl FnMut(&PeerId) -> bool,
+    ) -> HashMap<PeerId, BroadcastOutcome> {
+        let notification = notification.into();
+        let outcomes = self
+            .peers
+            .iter()
+            .filter(|(peer, _)| filter(peer))
+            .map(|(peer, sink)| {
+                let outcome = match sink.try_send(priority, notification.clone()) {
+                    Ok(()) => BroadcastOutcome::Sent,
+                    Err(NotificationError::ChannelClogged) => BroadcastOutcome::Clogged,
+                    Err(_) => BroadcastOutcome::Closed,
+                };
+
+                (*peer, outcome)
+            })
+            .collect::<HashMap<_, _>>();
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            num_peers = ?outcomes.len(),
+            ?priority,
+            size = ?notification.len(),
+            "broadcast notification",
+        );
+
+        for (peer, outcome) in &outcomes {
+            if outcome == &BroadcastOutcome::Clogged {
+                self.on_clogged(*peer);
+            }
+        }
+
+        outcomes
+    }
+
     /// Send notification to `peer` asynchronously, waiting for the channel to have capacity
     /// if it's clogged.
     ///
@@ -428,9 +900,23 @@ impl NotificationHandle {
         &mut self,
         peer: PeerId,
         notification: Vec<u8>,
+    ) -> crate::Result<()> {
+        self.send_async_notification_with_priority(peer, NotificationPriority::Normal, notification)
+            .await
+    }
+
+    /// Send notification of `priority` to `peer` asynchronously, waiting for the queue to have
+    /// capacity if it's clogged.
+    ///
+    /// See [`NotificationSink::send_async_notification_with_priority()`] for more details.
+    pub async fn send_async_notification_with_priority(
+        &mut self,
+        peer: PeerId,
+        priority: NotificationPriority,
+        notification: Vec<u8>,
     ) -> crate::Result<()> {
         match self.peers.get_mut(&peer) {
-            Some(sink) => sink.send_async_notification(notification).await,
+            Some(sink) => sink.send_async_notification_with_priority(priority, notification).await,
             None => Err(Error::PeerDoesntExist(peer)),
         }
     }
@@ -441,12 +927,35 @@ impl NotificationHandle {
     pub fn notification_sink(&self, peer: PeerId) -> Option<NotificationSink> {
         self.peers.get(&peer).and_then(|sink| Some(sink.clone()))
     }
+
+    /// Get statistics of notifications received from `peer`.
+    ///
+    /// `None` is returned if `peer` doesn't exist.
+    pub fn inbound_stats(&self, peer: PeerId) -> Option<InboundStats> {
+        self.peers.get(&peer).map(NotificationSink::inbound_stats)
+    }
 }
 
 impl Stream for NotificationHandle {
     type Item = NotificationEvent;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Some(event) = self.pending_events.pop_front() {
+            match event {
+                NotificationEvent::NotificationReceived { peer, .. } =>
+                    if self.peers.contains_key(&peer) {
+                        return Poll::Ready(Some(event));
+                    },
+                NotificationEvent::NotificationStreamClosed { peer, .. } => {
+                    self.peers.remove(&peer);
+                    self.clogged.remove(&peer);
+
+                    return Poll::Ready(Some(event));
+                }
+                event => return Poll::Ready(Some(event)),
+            }
+        }
+
         loop {
             match self.event_rx.poll_recv(cx) {
                 Poll::Pending => {}
@@ -470,13 +979,30 @@ impl Stream for NotificationHandle {
                             handshake,
                         }));
                     }
-                    InnerNotificationEvent::NotificationStreamClosed { peer } => {
-                        self.peers.remove(&peer);
-                        self.clogged.remove(&peer);
+                    InnerNotificationEvent::NotificationStreamClosed {
+                        peer,
+                        reason,
+                        dropped,
+                    } => {
+                        // notifications the connection received before it was closed may still
+                        // be queued and they must be reported before the stream is closed
+                        while let Ok((from, notification)) = self.notif_rx.try_recv() {
+                            self.pending_events.push_back(
+                                NotificationEvent::NotificationReceived {
+                                    peer: from,
+                                    notification,
+                                },
+                            );
+                        }
+                        self.pending_events.push_back(
+                            NotificationEvent::NotificationStreamClosed {
+                                peer,
+                                reason,
+                                dropped,
+                            },
+                        );
 
-                        return Poll::Ready(Some(NotificationEvent::NotificationStreamClosed {
-                            peer,
-                        }));
+                        return self.poll_next(cx);
                     }
                     InnerNotificationEvent::ValidateSubstream {
                         protocol,
@@ -494,10 +1020,23 @@ impl Stream for NotificationHandle {
                             handshake,
                         }));
                     }
-                    InnerNotificationEvent::NotificationStreamOpenFailure { peer, error } =>
+                    InnerNotificationEvent::NotificationStreamOpenFailure { peer, error } => {
+                        // ignore a late validation result for the rejected substream
+                        if error == NotificationError::ValidationTimedOut {
+                            self.pending_validations.remove(&peer);
+                        }
+
                         return Poll::Ready(Some(
                             NotificationEvent::NotificationStreamOpenFailure { peer, error },
-                        )),
+                        ));
+                    }
+                    InnerNotificationEvent::HandshakeUpdated { peer, handshake } =>
+                        if self.peers.contains_key(&peer) {
+                            return Poll::Ready(Some(NotificationEvent::HandshakeUpdated {
+                                peer,
+                                handshake,
+                            }));
+                        },
                 },
             }
 
@@ -26,9 +26,12 @@ use crate::{
     protocol::{
         self,
         notification::{
-            connection::Connection,
-            handle::NotificationEventHandle,
+            connection::{CloseRequest, Connection},
+            group::{GroupEvent, ProtocolGroup},
+            handle::{NotificationEventHandle, PriorityQueue},
             negotiation::{HandshakeEvent, HandshakeService},
+            rate_limit::{InboundCounters, InboundLimiter},
+            slots::PeerSlots,
             types::NotificationCommand,
         },
         TransportEvent, TransportService,
@@ -46,16 +49,31 @@ use tokio::sync::{
     oneshot,
 };
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
 
 pub use config::{Config, ConfigBuilder};
 pub use handle::{NotificationHandle, NotificationSink};
-pub use types::{Direction, NotificationError, NotificationEvent, ValidationResult};
+pub use rate_limit::{InboundStats, RateLimitAction, RateLimitConfig};
+pub use slots::SlotsConfig;
+pub use types::{
+    BroadcastOutcome, CloseReason, Direction, HandshakeValidator, NotificationError,
+    NotificationEvent, NotificationPriority, OverflowPolicy, ValidationResult,
+};
+
+pub(crate) use group::connect_groups;
 
 mod config;
 mod connection;
+mod group;
 mod handle;
 mod negotiation;
+mod rate_limit;
+mod slots;
 mod types;
 
 #[cfg(test)]
@@ -209,7 +227,7 @@ enum PeerState {
     /// Notification stream has been opened.
     Open {
         /// `Oneshot::Sender` for shutting down the connection.
-        shutdown: oneshot::Sender<()>,
+        shutdown: oneshot::Sender<CloseRequest>,
     },
 }
 
@@ -227,6 +245,25 @@ impl PeerContext {
             state: PeerState::Closed { pending_open: None },
         }
     }
+
+    /// Does the peer occupy a slot in its current state.
+    fn occupies_slot(&self) -> bool {
+        std::matches!(
+            self.state,
+            PeerState::Dialing
+                | PeerState::OutboundInitiated { .. }
+                | PeerState::Validating { .. }
+                | PeerState::Open { .. }
+        )
+    }
+}
+
+/// Await `future`, returning `None` if it doesn't complete within `timeout`.
+async fn with_timeout<F: Future>(timeout: Option<Duration>, future: F) -> Option<F::Output> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future).await.ok(),
+        None => Some(future.await),
+    }
 }
 
 pub(crate) struct NotificationProtocol {
@@ -271,17 +308,60 @@ pub(crate) struct NotificationProtocol {
     /// Asynchronous channel size.
     async_channel_size: usize,
 
+    /// Policy applied when the synchronous channel of a peer is full.
+    overflow_policy: OverflowPolicy,
+
+    /// High-priority channel size.
+    high_priority_channel_size: usize,
+
+    /// Low-priority channel size.
+    low_priority_channel_size: usize,
+
+    /// How long to wait for an inbound substream to be validated before rejecting it, if limited.
+    validation_timeout: Option<Duration>,
+
+    /// Validator for inbound substreams, if installed.
+    handshake_validator: Option<Arc<dyn HandshakeValidator>>,
+
+    /// Should handshake changes be sent to peers with an open substream.
+    handshake_updates: bool,
+
+    /// Inbound rate limit applied to each peer, if configured.
+    rate_limit: Option<RateLimitConfig>,
+
+    /// Membership of the protocol in a protocol group.
+    group: ProtocolGroup,
+
     /// Executor for connection handlers.
     executor: Arc<dyn Executor>,
 
     /// Pending substream validations.
-    pending_validations: FuturesUnordered<BoxFuture<'static, (PeerId, ValidationResult)>>,
+    ///
+    /// Each validation is tagged with the ID it was given when it was started and its result is
+    /// `None` if the validation timed out.
+    pending_validations:
+        FuturesUnordered<BoxFuture<'static, (PeerId, usize, Option<ValidationResult>)>>,
+
+    /// ID of the validation of the inbound substream each peer is currently validating.
+    validations: HashMap<PeerId, usize>,
+
+    /// ID of the next substream validation.
+    next_validation_id: usize,
 
     /// Timers for pending outbound substreams.
     timers: FuturesUnordered<BoxFuture<'static, PeerId>>,
 
     /// Should `NotificationProtocol` attempt to dial the peer.
     should_dial: bool,
+
+    /// Peer slots, if the number of notification streams is limited.
+    slots: Option<PeerSlots>,
+
+    /// Connected peers to whom an outbound substream has been opened automatically or whose
+    /// notification stream was closed by the local node.
+    ///
+    /// Outbound substreams are opened automatically at most once per connection.
+    auto_opened: HashSet<PeerId>,
 }
 
 impl NotificationProtocol {
@@ -301,6 +381,8 @@ impl NotificationProtocol {
             protocol: config.protocol_name,
             auto_accept: config.auto_accept,
             pending_validations: FuturesUnordered::new(),
+            validations: HashMap::new(),
+            next_validation_id: 0usize,
             timers: FuturesUnordered::new(),
             event_handle: NotificationEventHandle::new(config.event_tx),
             notif_tx: config.notif_tx,
@@ -309,7 +391,17 @@ impl NotificationProtocol {
             negotiation: HandshakeService::new(config.handshake),
             sync_channel_size: config.sync_channel_size,
             async_channel_size: config.async_channel_size,
+            overflow_policy: config.overflow_policy,
+            high_priority_channel_size: config.high_priority_channel_size,
+            low_priority_channel_size: config.low_priority_channel_size,
+            validation_timeout: config.validation_timeout,
+            handshake_validator: config.handshake_validator,
+            handshake_updates: config.handshake_updates,
+            rate_limit: config.rate_limit,
+            group: ProtocolGroup::new(config.group_tx, config.group_rx),
             should_dial: config.should_dial,
+            slots: config.slots.map(PeerSlots::new),
+            auto_opened: HashSet::new(),
         }
     }
 
@@ -324,6 +416,13 @@ impl NotificationProtocol {
 
         let Some(context) = self.peers.get_mut(&peer) else {
             self.peers.insert(peer, PeerContext::new());
+
+            // notification stream of the main protocol may have been opened before the
+            // connection was reported to the secondary protocol
+            if self.group.is_secondary() && self.group.is_main_open(&peer) {
+                return self.on_open_substream(peer).await;
+            }
+
             return Ok(());
         };
 
@@ -398,6 +497,10 @@ impl NotificationProtocol {
         // clean up all pending state for the peer
         self.negotiation.remove_outbound(&peer);
         self.negotiation.remove_inbound(&peer);
+        self.negotiation.remove_peer_handshake(&peer);
+        self.auto_opened.remove(&peer);
+        self.group.report_closed(peer);
+        self.group.cancel_waiting(&peer);
 
         match context.state {
             // outbound initiated, report open failure to peer
@@ -408,7 +511,7 @@ impl NotificationProtocol {
             }
             // substream fully open, report that the notification stream is closed
             PeerState::Open { shutdown } => {
-                let _ = shutdown.send(());
+                let _ = shutdown.send(CloseRequest::new(CloseReason::ConnectionLost));
             }
             // if the substream was being validated, user must be notified that the substream is
             // now considered rejected if they had been made aware of the existence of the pending
@@ -698,7 +801,25 @@ impl NotificationProtocol {
                     pending_open: Some(substream_id),
                 };
             }
-            // the peer state is closed so this is a fresh inbound substream.
+            // the peer state is closed so this is a fresh inbound substream which is accepted
+            // only if there is a free inbound slot for the peer
+            PeerState::Closed { pending_open: None }
+                if self
+                    .slots
+                    .as_mut()
+                    .is_some_and(|slots| !slots.try_acquire(peer, Direction::Inbound)) =>
+            {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    %protocol,
+                    ?fallback,
+                    "no inbound slot available, rejecting inbound substream",
+                );
+
+                let _ = substream.close().await;
+                context.state = PeerState::Closed { pending_open: None };
+            }
             PeerState::Closed { pending_open: None } => {
                 self.negotiation.read_handshake(peer, substream);
 
@@ -906,6 +1027,44 @@ impl NotificationProtocol {
     async fn on_open_substream(&mut self, peer: PeerId) -> crate::Result<()> {
         tracing::trace!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "open substream");
 
+        // secondary protocol of a group opens the substream once the notification stream of the
+        // main protocol is open
+        if !self.group.is_main_open(&peer) {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                "main notification stream not open, defer opening substream",
+            );
+            return Ok(());
+        }
+
+        // new outbound substream can be opened only if the peer is not yet connected or if their
+        // state is `Closed` so only then is an outbound slot required for the peer
+        let requires_slot = match self.peers.get(&peer) {
+            None => true,
+            Some(context) => std::matches!(context.state, PeerState::Closed { .. }),
+        };
+
+        if requires_slot
+            && self
+                .slots
+                .as_mut()
+                .is_some_and(|slots| !slots.try_acquire(peer, Direction::Outbound))
+        {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                "no outbound slot available, rejecting outbound substream request",
+            );
+
+            self.event_handle
+                .report_notification_stream_open_failure(peer, NotificationError::NoSlotsAvailable)
+                .await;
+            return Ok(());
+        }
+
         let Some(context) = self.peers.get_mut(&peer) else {
             if !self.should_dial {
                 tracing::debug!(
@@ -1039,7 +1198,10 @@ impl NotificationProtocol {
     /// This function can only be called if the substream was actually open, any other state is
     /// unreachable as the user is unable to emit this command to [`NotificationProtocol`] unless
     /// the connection has been fully opened.
-    async fn on_close_substream(&mut self, peer: PeerId) {
+    ///
+    /// If `flush` is set, the connection tries to send all queued notifications before closing
+    /// the substream and closes it forcibly if that doesn't succeed before the timeout expires.
+    async fn on_close_substream(&mut self, peer: PeerId, flush: Option<Duration>) {
         tracing::debug!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "close substream");
 
         let Some(context) = self.peers.get_mut(&peer) else {
@@ -1049,9 +1211,15 @@ impl NotificationProtocol {
 
         match std::mem::replace(&mut context.state, PeerState::Poisoned) {
             PeerState::Open { shutdown } => {
-                let _ = shutdown.send(());
+                let _ = shutdown.send(CloseRequest {
+                    reason: CloseReason::LocalClosed,
+                    flush,
+                });
 
+                // don't reopen the notification stream automatically
+                self.auto_opened.insert(peer);
                 context.state = PeerState::Closed { pending_open: None };
+                self.group.report_closed(peer);
             }
             state => {
                 tracing::debug!(
@@ -1234,6 +1402,68 @@ impl NotificationProtocol {
         }
     }
 
+    /// Handle completed substream validation.
+    ///
+    /// `result` is `None` if the validation timed out, in which case the substream is rejected
+    /// and, if that rejected the substream, the user protocol is told that its validation timed
+    /// out. Results of validations superseded by the validation of a newer substream are ignored.
+    async fn on_validation_completed(
+        &mut self,
+        peer: PeerId,
+        validation_id: usize,
+        result: Option<ValidationResult>,
+    ) {
+        if self.validations.get(&peer) != Some(&validation_id) {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                ?validation_id,
+                "ignore result of stale substream validation",
+            );
+            return;
+        }
+        self.validations.remove(&peer);
+
+        let timed_out = result.is_none();
+        let result = result.unwrap_or(ValidationResult::Reject);
+        let validating = std::matches!(
+            self.peers.get(&peer),
+            Some(PeerContext {
+                state: PeerState::Validating {
+                    inbound: InboundState::Validating { .. },
+                    ..
+                },
+            })
+        );
+
+        match self.on_validation_result(peer, result).await {
+            Err(error) => tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                ?result,
+                ?error,
+                "failed to handle validation result",
+            ),
+            Ok(()) if timed_out && validating => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    protocol = %self.protocol,
+                    "substream validation timed out",
+                );
+
+                self.event_handle
+                    .report_notification_stream_open_failure(
+                        peer,
+                        NotificationError::ValidationTimedOut,
+                    )
+                    .await;
+            }
+            Ok(()) => {}
+        }
+    }
+
     /// Handle handshake event.
     ///
     /// There are three different handshake event types:
@@ -1390,12 +1620,57 @@ impl NotificationProtocol {
                                 direction,
                             };
 
+                            let timeout = self.validation_timeout;
+                            let validation_id = self.next_validation_id;
+                            self.next_validation_id = self.next_validation_id.wrapping_add(1);
+                            self.validations.insert(peer, validation_id);
+
+                            // inbound substreams of a secondary protocol are accepted once the
+                            // notification stream of the main protocol is open
+                            if self.group.is_secondary() {
+                                let rx = self.group.wait_main_open(peer);
+
+                                self.pending_validations.push(Box::pin(async move {
+                                    match with_timeout(timeout, rx).await {
+                                        Some(Ok(())) =>
+                                            (peer, validation_id, Some(ValidationResult::Accept)),
+                                        _ => (peer, validation_id, Some(ValidationResult::Reject)),
+                                    }
+                                }));
+
+                                return;
+                            }
+
+                            // validate the substream using the installed validator or, if there
+                            // is none, let the user protocol validate it
+                            //
+                            // if the substream is not validated in time, it's rejected and if the
+                            // user protocol was asked to validate it, it's told that the
+                            // validation timed out
+                            if let Some(validator) = &self.handshake_validator {
+                                let validator = Arc::clone(validator);
+
+                                self.pending_validations.push(Box::pin(async move {
+                                    let result = validator.validate(peer, handshake.into());
+
+                                    match with_timeout(timeout, result).await {
+                                        Some(ValidationResult::Accept) =>
+                                            (peer, validation_id, Some(ValidationResult::Accept)),
+                                        _ => (peer, validation_id, Some(ValidationResult::Reject)),
+                                    }
+                                }));
+
+                                return;
+                            }
+
                             let (tx, rx) = oneshot::channel();
                             self.pending_validations.push(Box::pin(async move {
-                                match rx.await {
-                                    Ok(ValidationResult::Accept) =>
-                                        (peer, ValidationResult::Accept),
-                                    _ => (peer, ValidationResult::Reject),
+                                match with_timeout(timeout, rx).await {
+                                    Some(Ok(ValidationResult::Accept)) =>
+                                        (peer, validation_id, Some(ValidationResult::Accept)),
+                                    Some(_) =>
+                                        (peer, validation_id, Some(ValidationResult::Reject)),
+                                    None => (peer, validation_id, None),
                                 }
                             }));
 
@@ -1501,8 +1776,22 @@ impl NotificationProtocol {
                 );
 
                 let (async_tx, async_rx) = channel(self.async_channel_size);
-                let (sync_tx, sync_rx) = channel(self.sync_channel_size);
-                let sink = NotificationSink::new(peer, sync_tx, async_tx);
+                let (sync, sync_rx) = PriorityQueue::new(self.sync_channel_size);
+                let (high, high_rx) = PriorityQueue::new(self.high_priority_channel_size);
+                let (low, low_rx) = PriorityQueue::new(self.low_priority_channel_size);
+                let (handshake_tx, handshake_rx) =
+                    self.handshake_updates.then(|| PriorityQueue::new(1)).unzip();
+                let inbound_counters = Arc::new(InboundCounters::default());
+                let sink = NotificationSink::new(
+                    peer,
+                    sync,
+                    async_tx,
+                    high,
+                    low,
+                    handshake_tx,
+                    self.overflow_policy,
+                    Arc::clone(&inbound_counters),
+                );
 
                 // start connection handler for the peer which only deals with sending/receiving
                 // notifications
@@ -1524,6 +1813,10 @@ impl NotificationProtocol {
                     self.notif_tx.clone(),
                     async_rx,
                     sync_rx,
+                    high_rx,
+                    low_rx,
+                    handshake_rx,
+                    InboundLimiter::new(self.rate_limit.clone(), inbound_counters),
                 );
 
                 context.state = PeerState::Open { shutdown };
@@ -1537,6 +1830,7 @@ impl NotificationProtocol {
                         sink,
                     )
                     .await;
+                self.group.report_opened(peer);
 
                 self.executor.run(Box::pin(async move {
                     connection.start().await;
@@ -1601,6 +1895,157 @@ impl NotificationProtocol {
         }
     }
 
+    /// Notification stream of the main protocol of the group was opened to `peer`.
+    ///
+    /// Notification stream of the secondary protocol is opened automatically if the peer is
+    /// connected and the stream is not yet open or being opened.
+    async fn on_main_opened(&mut self, peer: PeerId) {
+        tracing::trace!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "main notification stream opened");
+
+        if !self.peers.get(&peer).is_some_and(|context| {
+            std::matches!(context.state, PeerState::Closed { pending_open: None })
+        }) {
+            return;
+        }
+
+        if let Err(error) = self.on_open_substream(peer).await {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                ?error,
+                "failed to open substream",
+            );
+        }
+    }
+
+    /// Notification stream of the main protocol of the group to `peer` was closed.
+    ///
+    /// Notification stream of the secondary protocol is closed as well or, if it's still being
+    /// opened, the open is aborted and reported to the user as rejected.
+    async fn on_main_closed(&mut self, peer: PeerId) {
+        tracing::trace!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "main notification stream closed");
+
+        let Some(context) = self.peers.get_mut(&peer) else {
+            return;
+        };
+
+        match std::mem::replace(&mut context.state, PeerState::Poisoned) {
+            state @ PeerState::Open { .. } => {
+                context.state = state;
+                self.on_close_substream(peer, None).await;
+            }
+            // the outbound substream is closed if it opens
+            PeerState::OutboundInitiated { substream } => {
+                context.state = PeerState::Closed {
+                    pending_open: Some(substream),
+                };
+                self.event_handle
+                    .report_notification_stream_open_failure(peer, NotificationError::Rejected)
+                    .await;
+            }
+            // inbound substream waiting for the main notification stream to open is rejected once
+            // its validation concludes
+            state @ PeerState::Validating {
+                inbound: InboundState::Validating { .. },
+                ..
+            } => {
+                context.state = state;
+            }
+            PeerState::Validating {
+                inbound, outbound, ..
+            } => {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    protocol = %self.protocol,
+                    "main notification stream closed while substream was opening",
+                );
+
+                self.negotiation.remove_outbound(&peer);
+                self.negotiation.remove_inbound(&peer);
+                context.state = PeerState::Closed {
+                    pending_open: outbound.pending_open(),
+                };
+
+                if let InboundState::Open { inbound } = inbound {
+                    let _ = inbound.close().await;
+                }
+
+                if let OutboundState::Open { outbound, .. } = outbound {
+                    let _ = outbound.close().await;
+                }
+
+                self.event_handle
+                    .report_notification_stream_open_failure(peer, NotificationError::Rejected)
+                    .await;
+            }
+            state => {
+                context.state = state;
+            }
+        }
+    }
+
+    /// Update peer slots.
+    ///
+    /// Release the slots of peers whose notification streams have closed and open outbound
+    /// substreams to connected peers, reserved peers first, while there are free outbound slots.
+    async fn update_slots(&mut self) {
+        let Some(slots) = self.slots.as_mut() else {
+            return;
+        };
+
+        let released = slots
+            .occupied()
+            .filter(|peer| !self.peers.get(peer).is_some_and(PeerContext::occupies_slot))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for peer in released {
+            tracing::trace!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "release slot");
+            slots.release(&peer);
+        }
+
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter_map(|(peer, context)| {
+                (std::matches!(context.state, PeerState::Closed { pending_open: None })
+                    && !self.auto_opened.contains(peer))
+                .then_some(*peer)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|peer| !slots.is_reserved(peer));
+
+        for peer in candidates {
+            let Some(slots) = self.slots.as_ref() else {
+                return;
+            };
+
+            if !slots.is_reserved(&peer) && !slots.has_free_outbound() {
+                return;
+            }
+
+            tracing::trace!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                "open outbound substream to connected peer",
+            );
+
+            self.auto_opened.insert(peer);
+            if let Err(error) = self.on_open_substream(peer).await {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    protocol = %self.protocol,
+                    ?error,
+                    "failed to open substream",
+                );
+            }
+        }
+    }
+
     /// Handle next notification event.
     async fn next_event(&mut self) {
         // biased select is used because the substream events must be prioritized above other events
@@ -1626,9 +2071,14 @@ impl NotificationProtocol {
                             "notification stream to peer closed",
                         );
                         context.state = PeerState::Closed { pending_open: None };
+                        self.group.report_closed(peer);
                     }
                 }
             },
+            event = self.group.next_event() => match event {
+                GroupEvent::Opened { peer } => self.on_main_opened(peer).await,
+                GroupEvent::Closed { peer } => self.on_main_closed(peer).await,
+            },
             // TODO: this could be combined with `Negotiation`
             peer = self.timers.next(), if !self.timers.is_empty() => match peer {
                 Some(peer) => {
@@ -1741,16 +2191,8 @@ impl NotificationProtocol {
                 Some(TransportEvent::DialFailure { peer, address }) => self.on_dial_failure(peer, address).await,
                 None => return,
             },
-            result = self.pending_validations.select_next_some(), if !self.pending_validations.is_empty() => {
-                if let Err(error) = self.on_validation_result(result.0, result.1).await {
-                    tracing::debug!(
-                        target: LOG_TARGET,
-                        peer = ?result.0,
-                        result = ?result.1,
-                        ?error,
-                        "failed to handle validation result",
-                    );
-                }
+            (peer, validation_id, result) = self.pending_validations.select_next_some(), if !self.pending_validations.is_empty() => {
+                self.on_validation_completed(peer, validation_id, result).await;
             }
             command = self.command_rx.recv() => match command {
                 None => {
@@ -1760,6 +2202,8 @@ impl NotificationProtocol {
                 Some(command) => match command {
                     NotificationCommand::OpenSubstream { peers } => {
                         for peer in peers {
+                            self.negotiation.remove_peer_handshake(&peer);
+
                             if let Err(error) = self.on_open_substream(peer).await {
                                 tracing::debug!(
                                     target: LOG_TARGET,
@@ -1770,14 +2214,42 @@ impl NotificationProtocol {
                             }
                         }
                     }
-                    NotificationCommand::CloseSubstream { peers } => {
+                    NotificationCommand::OpenSubstreamWithHandshake { peer, handshake } => {
+                        self.negotiation.set_peer_handshake(peer, handshake);
+
+                        if let Err(error) = self.on_open_substream(peer).await {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                ?error,
+                                "failed to open substream",
+                            );
+                        }
+                    }
+                    NotificationCommand::CloseSubstream { peers, flush } => {
                         for peer in peers {
-                            self.on_close_substream(peer).await;
+                            self.on_close_substream(peer, flush).await;
                         }
                     }
                     NotificationCommand::ForceClose { peer } => {
+                        // the substream is closed before the connection so the user is told
+                        // that it was closed because the peer was clogged
+                        if let Some(context) = self.peers.get_mut(&peer) {
+                            match std::mem::replace(&mut context.state, PeerState::Poisoned) {
+                                PeerState::Open { shutdown } => {
+                                    let _ = shutdown.send(CloseRequest::new(CloseReason::Clogged));
+                                    context.state = PeerState::Closed { pending_open: None };
+                                    self.group.report_closed(peer);
+                                }
+                                state => context.state = state,
+                            }
+                        }
+
                         let _ = self.service.force_close(peer);
                     }
+                    NotificationCommand::ReportPeer { peer, change } => {
+                        self.service.report_peer(peer, change).await;
+                    }
                 }
             },
         }
@@ -1789,6 +2261,7 @@ impl NotificationProtocol {
 
         loop {
             self.next_event().await;
+            self.update_slots().await;
         }
     }
 }
@@ -98,6 +98,9 @@ pub(crate) struct HandshakeService {
     /// Handshake.
     handshake: Arc<RwLock<Vec<u8>>>,
 
+    /// Custom handshakes used instead of `handshake` for specific peers.
+    peer_handshakes: HashMap<PeerId, Vec<u8>>,
+
     /// Pending outbound substreams.
     /// Substreams:
     substreams: HashMap<(PeerId, Direction), (Substream, Delay, HandshakeState)>,
@@ -111,11 +114,22 @@ impl HandshakeService {
     pub fn new(handshake: Arc<RwLock<Vec<u8>>>) -> Self {
         Self {
             handshake,
+            peer_handshakes: HashMap::new(),
             ready: VecDeque::new(),
             substreams: HashMap::new(),
         }
     }
 
+    /// Send `handshake` instead of the protocol handshake to `peer`.
+    pub fn set_peer_handshake(&mut self, peer: PeerId, handshake: Vec<u8>) {
+        self.peer_handshakes.insert(peer, handshake);
+    }
+
+    /// Send the protocol handshake to `peer`.
+    pub fn remove_peer_handshake(&mut self, peer: &PeerId) {
+        self.peer_handshakes.remove(peer);
+    }
+
     /// Remove outbound substream from [`HandshakeService`].
     pub fn remove_outbound(&mut self, peer: &PeerId) -> Option<Substream> {
         self.substreams
@@ -247,7 +261,12 @@ impl Stream for HandshakeService {
                         Poll::Pending => continue 'outer,
                     },
                     HandshakeState::SinkReady => {
-                        match pinned.start_send((*inner.handshake.read()).clone().into()) {
+                        let handshake = match inner.peer_handshakes.get(peer) {
+                            Some(handshake) => handshake.clone(),
+                            None => (*inner.handshake.read()).clone(),
+                        };
+
+                        match pinned.start_send(handshake.into()) {
                             Ok(()) => {
                                 *state = HandshakeState::HandshakeSent;
                                 continue;
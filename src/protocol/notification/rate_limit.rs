@@ -0,0 +1,312 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Inbound rate limiting.
+//!
+//! Notifications received from each peer are counted over a one-second window and once the
+//! configured limit is exceeded, the connection applies the configured [`RateLimitAction`].
+
+use futures::FutureExt;
+use futures_timer::Delay;
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Length of the window over which the inbound rate is measured.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Action taken when a peer exceeds the inbound rate limit.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Drop notifications received over the limit.
+    #[default]
+    Drop,
+
+    /// Stop reading from the substream until the peer is allowed to send more notifications.
+    ///
+    /// The notification which exceeded the limit is still reported.
+    Backpressure,
+
+    /// Close the notification stream.
+    Close,
+}
+
+/// Inbound rate limit configuration.
+///
+/// The limits are applied to each peer separately and measured over one-second windows.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Maximum number of notifications received from a peer per second, if limited.
+    pub messages_per_second: Option<u64>,
+
+    /// Maximum number of notification bytes received from a peer per second, if limited.
+    pub bytes_per_second: Option<u64>,
+
+    /// What to do when a peer exceeds either of the limits.
+    pub action: RateLimitAction,
+}
+
+/// Inbound notification statistics of a peer.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct InboundStats {
+    /// Number of notifications received.
+    pub notifications: u64,
+
+    /// Number of notification bytes received.
+    pub bytes: u64,
+
+    /// Number of received notifications dropped because the peer exceeded the rate limit.
+    pub dropped: u64,
+}
+
+/// Inbound notification counters shared between the connection and notification sinks.
+#[derive(Debug, Default)]
+pub(crate) struct InboundCounters {
+    /// Number of notifications received.
+    notifications: AtomicU64,
+
+    /// Number of notification bytes received.
+    bytes: AtomicU64,
+
+    /// Number of notifications dropped.
+    dropped: AtomicU64,
+}
+
+impl InboundCounters {
+    /// Get current values of the counters.
+    pub(crate) fn stats(&self) -> InboundStats {
+        InboundStats {
+            notifications: self.notifications.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Inbound rate limiter of a peer.
+///
+/// Also keeps track of the [`InboundStats`] of the peer.
+pub(super) struct InboundLimiter {
+    /// Rate limit, if configured.
+    config: Option<RateLimitConfig>,
+
+    /// Inbound counters of the peer.
+    counters: Arc<InboundCounters>,
+
+    /// When did the current window start.
+    window_start: Instant,
+
+    /// Number of notifications received in the current window.
+    messages: u64,
+
+    /// Number of bytes received in the current window.
+    bytes: u64,
+
+    /// Timer for the start of the next window if reading is paused.
+    paused: Option<Delay>,
+}
+
+impl InboundLimiter {
+    /// Create new [`InboundLimiter`].
+    pub(super) fn new(config: Option<RateLimitConfig>, counters: Arc<InboundCounters>) -> Self {
+        Self {
+            config,
+            counters,
+            window_start: Instant::now(),
+            messages: 0,
+            bytes: 0,
+            paused: None,
+        }
+    }
+
+    /// Start a new window if the current window has elapsed.
+    fn update_window(&mut self) {
+        if self.window_start.elapsed() >= RATE_LIMIT_WINDOW {
+            self.window_start = Instant::now();
+            self.messages = 0;
+            self.bytes = 0;
+        }
+    }
+
+    /// Is the limit of the current window reached.
+    fn limit_reached(&self) -> bool {
+        self.config.as_ref().is_some_and(|config| {
+            config.messages_per_second.is_some_and(|limit| self.messages >= limit)
+                || config.bytes_per_second.is_some_and(|limit| self.bytes >= limit)
+        })
+    }
+
+    /// Is the limit of the current window exceeded.
+    fn limit_exceeded(&self) -> bool {
+        self.config.as_ref().is_some_and(|config| {
+            config.messages_per_second.is_some_and(|limit| self.messages > limit)
+                || config.bytes_per_second.is_some_and(|limit| self.bytes > limit)
+        })
+    }
+
+    /// Poll whether the next notification can be read from the substream.
+    ///
+    /// Returns `Poll::Pending` if the peer is backpressured and has reached the limit of the
+    /// current window.
+    pub(super) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &self.config {
+            Some(config) if config.action == RateLimitAction::Backpressure => {}
+            _ => return Poll::Ready(()),
+        }
+
+        if self.paused.is_none() {
+            self.update_window();
+
+            if !self.limit_reached() {
+                return Poll::Ready(());
+            }
+
+            let next_window = RATE_LIMIT_WINDOW.saturating_sub(self.window_start.elapsed());
+            self.paused = Some(Delay::new(next_window));
+        }
+
+        if let Some(timer) = &mut self.paused {
+            futures::ready!(timer.poll_unpin(cx));
+        }
+
+        self.paused = None;
+        self.update_window();
+
+        Poll::Ready(())
+    }
+
+    /// Register notification of `size` bytes received from the peer.
+    ///
+    /// Returns the action to take if the notification exceeded the rate limit.
+    pub(super) fn on_notification(&mut self, size: usize) -> Option<RateLimitAction> {
+        self.counters.notifications.fetch_add(1, Ordering::Relaxed);
+        self.counters.bytes.fetch_add(size as u64, Ordering::Relaxed);
+
+        let action = self.config.as_ref()?.action;
+
+        self.update_window();
+        self.messages += 1;
+        self.bytes += size as u64;
+
+        if !self.limit_exceeded() {
+            return None;
+        }
+
+        if action == RateLimitAction::Drop {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_limiter(config: Option<RateLimitConfig>) -> (InboundLimiter, Arc<InboundCounters>) {
+        let counters = Arc::new(InboundCounters::default());
+
+        (InboundLimiter::new(config, Arc::clone(&counters)), counters)
+    }
+
+    #[test]
+    fn unlimited_peer_is_only_counted() {
+        let (mut limiter, counters) = make_limiter(None);
+
+        for _ in 0..100 {
+            assert_eq!(limiter.on_notification(1024), None);
+        }
+
+        assert_eq!(
+            counters.stats(),
+            InboundStats {
+                notifications: 100,
+                bytes: 100 * 1024,
+                dropped: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn notifications_over_message_limit_are_dropped() {
+        let (mut limiter, counters) = make_limiter(Some(RateLimitConfig {
+            messages_per_second: Some(2),
+            ..Default::default()
+        }));
+
+        assert_eq!(limiter.on_notification(10), None);
+        assert_eq!(limiter.on_notification(10), None);
+        assert_eq!(limiter.on_notification(10), Some(RateLimitAction::Drop));
+        assert_eq!(limiter.on_notification(10), Some(RateLimitAction::Drop));
+
+        assert_eq!(
+            counters.stats(),
+            InboundStats {
+                notifications: 4,
+                bytes: 40,
+                dropped: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn byte_limit_closes_stream() {
+        let (mut limiter, counters) = make_limiter(Some(RateLimitConfig {
+            bytes_per_second: Some(100),
+            action: RateLimitAction::Close,
+            ..Default::default()
+        }));
+
+        assert_eq!(limiter.on_notification(60), None);
+        assert_eq!(limiter.on_notification(60), Some(RateLimitAction::Close));
+        assert_eq!(counters.stats().dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn backpressure_pauses_until_next_window() {
+        let (mut limiter, _counters) = make_limiter(Some(RateLimitConfig {
+            messages_per_second: Some(1),
+            action: RateLimitAction::Backpressure,
+            ..Default::default()
+        }));
+
+        futures::future::poll_fn(|cx| limiter.poll_ready(cx)).await;
+        assert_eq!(limiter.on_notification(10), None);
+
+        // limit of the window is reached so reading is paused until the next window
+        futures::future::poll_fn(|cx| {
+            assert!(limiter.poll_ready(cx).is_pending());
+            Poll::Ready(())
+        })
+        .await;
+
+        let started = Instant::now();
+        futures::future::poll_fn(|cx| limiter.poll_ready(cx)).await;
+        assert!(started.elapsed() >= RATE_LIMIT_WINDOW / 2);
+        assert_eq!(limiter.on_notification(10), None);
+    }
+}
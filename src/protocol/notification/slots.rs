@@ -0,0 +1,181 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Peer slots of a notification protocol.
+
+use crate::{protocol::notification::types::Direction, PeerId};
+
+use std::collections::HashSet;
+
+/// Peer slot configuration.
+///
+/// Limits the number of notification streams the protocol keeps open. Each stream occupies
+/// either an inbound or outbound slot, depending on which node opened it, and the slot is
+/// released once the stream closes. Outbound substreams are opened automatically to connected
+/// peers while there are free outbound slots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlotsConfig {
+    /// Maximum number of inbound notification streams.
+    pub max_inbound: usize,
+
+    /// Maximum number of outbound notification streams.
+    pub max_outbound: usize,
+
+    /// Reserved peers.
+    ///
+    /// Reserved peers don't occupy slots and notification streams to them are opened
+    /// automatically once they're connected.
+    pub reserved_peers: HashSet<PeerId>,
+
+    /// Accept and open notification streams only with reserved peers.
+    pub reserved_only: bool,
+}
+
+/// Peer slots.
+#[derive(Debug)]
+pub(super) struct PeerSlots {
+    /// Slot configuration.
+    config: SlotsConfig,
+
+    /// Peers occupying an inbound slot.
+    inbound: HashSet<PeerId>,
+
+    /// Peers occupying an outbound slot.
+    outbound: HashSet<PeerId>,
+}
+
+impl PeerSlots {
+    /// Create new [`PeerSlots`].
+    pub(super) fn new(config: SlotsConfig) -> Self {
+        Self {
+            config,
+            inbound: HashSet::new(),
+            outbound: HashSet::new(),
+        }
+    }
+
+    /// Is `peer` reserved.
+    pub(super) fn is_reserved(&self, peer: &PeerId) -> bool {
+        self.config.reserved_peers.contains(peer)
+    }
+
+    /// Attempt to acquire a slot for `peer`.
+    ///
+    /// Reserved peers are always allowed without occupying a slot and if `peer` already occupies
+    /// a slot, it's kept. Returns `false` if there are no free slots for `direction` or if only
+    /// reserved peers are allowed.
+    pub(super) fn try_acquire(&mut self, peer: PeerId, direction: Direction) -> bool {
+        if self.is_reserved(&peer) {
+            return true;
+        }
+
+        if self.config.reserved_only {
+            return false;
+        }
+
+        if self.inbound.contains(&peer) || self.outbound.contains(&peer) {
+            return true;
+        }
+
+        match direction {
+            Direction::Inbound if self.inbound.len() < self.config.max_inbound =>
+                self.inbound.insert(peer),
+            Direction::Outbound if self.outbound.len() < self.config.max_outbound =>
+                self.outbound.insert(peer),
+            _ => false,
+        }
+    }
+
+    /// Release the slot occupied by `peer`, if any.
+    pub(super) fn release(&mut self, peer: &PeerId) {
+        self.inbound.remove(peer);
+        self.outbound.remove(peer);
+    }
+
+    /// Get peers occupying a slot.
+    pub(super) fn occupied(&self) -> impl Iterator<Item = &PeerId> {
+        self.inbound.iter().chain(self.outbound.iter())
+    }
+
+    /// Is there a free outbound slot.
+    pub(super) fn has_free_outbound(&self) -> bool {
+        !self.config.reserved_only && self.outbound.len() < self.config.max_outbound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_limited_per_direction() {
+        let mut slots = PeerSlots::new(SlotsConfig {
+            max_inbound: 1,
+            max_outbound: 1,
+            ..Default::default()
+        });
+        let (peer1, peer2, peer3) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+        assert!(slots.try_acquire(peer1, Direction::Inbound));
+        assert!(!slots.try_acquire(peer2, Direction::Inbound));
+        assert!(slots.has_free_outbound());
+        assert!(slots.try_acquire(peer2, Direction::Outbound));
+        assert!(!slots.has_free_outbound());
+        assert!(!slots.try_acquire(peer3, Direction::Outbound));
+
+        // peer keeps its slot when acquiring it again
+        assert!(slots.try_acquire(peer1, Direction::Outbound));
+        assert_eq!(slots.occupied().count(), 2);
+
+        slots.release(&peer2);
+        assert!(slots.try_acquire(peer3, Direction::Outbound));
+    }
+
+    #[test]
+    fn reserved_peers_bypass_slots() {
+        let reserved = PeerId::random();
+        let mut slots = PeerSlots::new(SlotsConfig {
+            max_inbound: 0,
+            max_outbound: 0,
+            reserved_peers: HashSet::from_iter([reserved]),
+            reserved_only: true,
+        });
+
+        assert!(slots.try_acquire(reserved, Direction::Inbound));
+        assert!(slots.try_acquire(reserved, Direction::Outbound));
+        assert!(!slots.try_acquire(PeerId::random(), Direction::Inbound));
+        assert!(!slots.has_free_outbound());
+        assert_eq!(slots.occupied().count(), 0);
+    }
+
+    #[test]
+    fn reserved_only_rejects_other_peers() {
+        let mut slots = PeerSlots::new(SlotsConfig {
+            max_inbound: 8,
+            max_outbound: 8,
+            reserved_peers: HashSet::new(),
+            reserved_only: true,
+        });
+
+        assert!(!slots.try_acquire(PeerId::random(), Direction::Inbound));
+        assert!(!slots.try_acquire(PeerId::random(), Direction::Outbound));
+        assert!(!slots.has_free_outbound());
+    }
+}
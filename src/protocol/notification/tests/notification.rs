@@ -25,11 +25,20 @@ use crate::{
         self,
         connection::ConnectionHandle,
         notification::{
+            connection::{CloseRequest, Connection, ConnectionEvent},
+            handle::{
+                NotificationEventHandle, NotificationHandle, NotificationSink, PriorityQueue,
+            },
             negotiation::HandshakeEvent,
+            rate_limit::{InboundCounters, InboundLimiter},
             tests::make_notification_protocol,
-            types::{Direction, NotificationError, NotificationEvent},
-            ConnectionState, InboundState, NotificationProtocol, OutboundState, PeerContext,
-            PeerState, ValidationResult,
+            types::{
+                BroadcastOutcome, CloseReason, Direction, InnerNotificationEvent,
+                NotificationCommand, NotificationError, NotificationEvent, NotificationPriority,
+                OverflowPolicy,
+            },
+            ConnectionState, InboundState, InboundStats, NotificationProtocol, OutboundState,
+            PeerContext, PeerState, RateLimitAction, RateLimitConfig, ValidationResult,
         },
         InnerTransportEvent, ProtocolCommand,
     },
@@ -39,6 +48,7 @@ use crate::{
     PeerId,
 };
 
+use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use multiaddr::Multiaddr;
 use tokio::sync::{
@@ -46,7 +56,9 @@ use tokio::sync::{
     oneshot,
 };
 
-use std::{task::Poll, time::Duration};
+use parking_lot::{Mutex, RwLock};
+
+use std::{collections::HashMap, sync::Arc, task::Poll, time::Duration};
 
 fn next_inbound_state(state: usize) -> InboundState {
     match state {
@@ -238,7 +250,7 @@ async fn close_substream_to_unknown_peer() {
     let peer = PeerId::random();
 
     assert!(!notif.peers.contains_key(&peer));
-    notif.on_close_substream(peer).await;
+    notif.on_close_substream(peer, None).await;
     assert!(!notif.peers.contains_key(&peer));
 }
 
@@ -1109,3 +1121,591 @@ async fn second_inbound_substream_opened_while_outbound_substream_was_opening()
         state => panic!("invalid state for peer: {state:?}"),
     }
 }
+
+#[tokio::test]
+async fn broadcast_reports_outcome_per_peer() {
+    let (event_tx, event_rx) = channel(64);
+    let (_notif_tx, notif_rx) = channel(64);
+    let (command_tx, mut command_rx) = channel(64);
+    let mut handle = NotificationHandle::new(
+        event_rx,
+        notif_rx,
+        command_tx,
+        Arc::new(RwLock::new(vec![])),
+    );
+
+    let (sent, clogged, closed) = (PeerId::random(), PeerId::random(), PeerId::random());
+    let mut receivers = HashMap::new();
+
+    for peer in [sent, clogged, closed] {
+        let (sink, sync_rx) = make_sink(1, OverflowPolicy::ForceClose);
+
+        event_tx
+            .send(InnerNotificationEvent::NotificationStreamOpened {
+                protocol: ProtocolName::from("/notif/1"),
+                fallback: None,
+                direction: Direction::Inbound,
+                peer,
+                handshake: vec![],
+                sink,
+            })
+            .await
+            .unwrap();
+        assert!(std::matches!(
+            handle.next().await,
+            Some(NotificationEvent::NotificationStreamOpened { .. })
+        ));
+
+        receivers.insert(peer, sync_rx);
+    }
+
+    handle.send_sync_notification(clogged, vec![1, 3, 3, 7]).unwrap();
+    drop(receivers.remove(&closed));
+
+    assert_eq!(
+        handle.broadcast(vec![1, 3, 3, 8], |_| true),
+        HashMap::from_iter([
+            (sent, BroadcastOutcome::Sent),
+            (clogged, BroadcastOutcome::Clogged),
+            (closed, BroadcastOutcome::Closed),
+        ])
+    );
+    assert_eq!(
+        receivers.get(&sent).unwrap().lock().try_recv().unwrap(),
+        vec![1, 3, 3, 8]
+    );
+
+    // connection to the clogged peer is closed
+    match command_rx.try_recv() {
+        Ok(NotificationCommand::ForceClose { peer }) => assert_eq!(peer, clogged),
+        _ => panic!("expected connection to be closed"),
+    }
+}
+
+fn make_sink(
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+) -> (NotificationSink, Arc<Mutex<Receiver<Bytes>>>) {
+    let (sink, mut receivers) = make_priority_sink(capacity, overflow_policy);
+
+    (
+        sink,
+        receivers.remove(&NotificationPriority::Normal).unwrap(),
+    )
+}
+
+fn make_priority_sink(
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+) -> (
+    NotificationSink,
+    HashMap<NotificationPriority, Arc<Mutex<Receiver<Bytes>>>>,
+) {
+    let (sync, sync_rx) = PriorityQueue::new(capacity);
+    let (high, high_rx) = PriorityQueue::new(capacity);
+    let (low, low_rx) = PriorityQueue::new(capacity);
+    let (async_tx, _async_rx) = channel(capacity);
+
+    (
+        NotificationSink::new(
+            PeerId::random(),
+            sync,
+            async_tx,
+            high,
+            low,
+            None,
+            overflow_policy,
+            Default::default(),
+        ),
+        HashMap::from_iter([
+            (NotificationPriority::High, high_rx),
+            (NotificationPriority::Normal, sync_rx),
+            (NotificationPriority::Low, low_rx),
+        ]),
+    )
+}
+
+#[tokio::test]
+async fn sink_drop_oldest() {
+    let (sink, sync_rx) = make_sink(2, OverflowPolicy::DropOldest);
+
+    for notification in [vec![1], vec![2], vec![3]] {
+        sink.send_sync_notification(notification).unwrap();
+    }
+    assert_eq!(sink.queue_depth(), 2);
+    assert_eq!(sink.capacity(), 2);
+
+    assert_eq!(sync_rx.lock().try_recv().unwrap(), vec![2]);
+    assert_eq!(sync_rx.lock().try_recv().unwrap(), vec![3]);
+    assert_eq!(sink.queue_depth(), 0);
+
+    drop(sync_rx);
+    assert_eq!(
+        sink.send_sync_notification(vec![4]),
+        Err(NotificationError::NoConnection)
+    );
+}
+
+#[tokio::test]
+async fn sink_drop_newest() {
+    let (sink, sync_rx) = make_sink(1, OverflowPolicy::DropNewest);
+
+    sink.send_sync_notification(vec![1]).unwrap();
+    assert_eq!(
+        sink.send_sync_notification(vec![2]),
+        Err(NotificationError::ChannelClogged)
+    );
+    assert_eq!(sync_rx.lock().try_recv().unwrap(), vec![1]);
+}
+
+#[tokio::test]
+async fn sink_ready_and_start_send() {
+    let (mut sink, sync_rx) = make_sink(1, OverflowPolicy::ForceClose);
+
+    sink.ready().await.unwrap();
+    sink.start_send(vec![1]).unwrap();
+
+    // channel is full so the sink doesn't become ready until a notification is received
+    assert!(tokio::time::timeout(Duration::from_millis(100), sink.ready()).await.is_err());
+    assert_eq!(sync_rx.lock().try_recv().unwrap(), vec![1]);
+
+    sink.ready().await.unwrap();
+    assert_eq!(sink.queue_depth(), 1);
+
+    // reserved capacity is not shared with clones
+    let clone = sink.clone();
+    assert_eq!(
+        clone.send_sync_notification(vec![2]),
+        Err(NotificationError::ChannelClogged)
+    );
+
+    sink.start_send(vec![3]).unwrap();
+    assert_eq!(sync_rx.lock().try_recv().unwrap(), vec![3]);
+}
+
+#[tokio::test]
+async fn clogged_peer_not_closed_with_drop_newest() {
+    let (event_tx, event_rx) = channel(64);
+    let (_notif_tx, notif_rx) = channel(64);
+    let (command_tx, mut command_rx) = channel(64);
+    let mut handle = NotificationHandle::new(
+        event_rx,
+        notif_rx,
+        command_tx,
+        Arc::new(RwLock::new(vec![])),
+    );
+    let (sink, _sync_rx) = make_sink(1, OverflowPolicy::DropNewest);
+    let peer = PeerId::random();
+
+    event_tx
+        .send(InnerNotificationEvent::NotificationStreamOpened {
+            protocol: ProtocolName::from("/notif/1"),
+            fallback: None,
+            direction: Direction::Inbound,
+            peer,
+            handshake: vec![],
+            sink,
+        })
+        .await
+        .unwrap();
+    assert!(std::matches!(
+        handle.next().await,
+        Some(NotificationEvent::NotificationStreamOpened { .. })
+    ));
+
+    handle.send_sync_notification(peer, vec![1]).unwrap();
+    assert_eq!(
+        handle.send_sync_notification(peer, vec![2]),
+        Err(NotificationError::ChannelClogged)
+    );
+    assert!(command_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn sink_priority_queues() {
+    let (sink, receivers) = make_priority_sink(2, OverflowPolicy::DropOldest);
+
+    sink.send_sync_notification_with_priority(NotificationPriority::High, vec![1])
+        .unwrap();
+    sink.send_sync_notification(vec![2]).unwrap();
+    sink.send_async_notification_with_priority(NotificationPriority::Low, vec![3])
+        .await
+        .unwrap();
+
+    for priority in [
+        NotificationPriority::High,
+        NotificationPriority::Normal,
+        NotificationPriority::Low,
+    ] {
+        assert_eq!(sink.queue_depth_for(priority), 1);
+        assert_eq!(sink.capacity_for(priority), 2);
+    }
+
+    // overflow policy is applied to each queue separately
+    for notification in [vec![4], vec![5]] {
+        sink.send_sync_notification_with_priority(NotificationPriority::Low, notification)
+            .unwrap();
+    }
+
+    let mut recv = |priority| receivers.get(&priority).unwrap().lock().try_recv().ok();
+    assert_eq!(recv(NotificationPriority::High).unwrap(), vec![1]);
+    assert_eq!(recv(NotificationPriority::Normal).unwrap(), vec![2]);
+    assert_eq!(recv(NotificationPriority::Low).unwrap(), vec![4]);
+    assert_eq!(recv(NotificationPriority::Low).unwrap(), vec![5]);
+    assert!(recv(NotificationPriority::Low).is_none());
+}
+
+#[tokio::test]
+async fn connection_drains_higher_priority_first() {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let mut outbound = MockSubstream::new();
+    outbound.expect_poll_ready().returning(|_| Poll::Ready(Ok(())));
+    outbound.expect_poll_flush().returning(|_| Poll::Ready(Ok(())));
+    outbound.expect_start_send().returning({
+        let sent = Arc::clone(&sent);
+        move |notification| {
+            sent.lock().push(notification);
+            Ok(())
+        }
+    });
+
+    let peer = PeerId::random();
+    let (sink, mut receivers) = make_priority_sink(8, OverflowPolicy::ForceClose);
+    let (_async_tx, async_rx) = channel(8);
+    let (event_tx, _event_rx) = channel(8);
+    let (conn_closed_tx, _conn_closed_rx) = channel(8);
+    let (notif_tx, _notif_rx) = channel(8);
+    let (mut connection, _shutdown) = Connection::new(
+        peer,
+        Substream::new_mock(
+            peer,
+            SubstreamId::from(0usize),
+            Box::new(DummySubstream::new()),
+        ),
+        Substream::new_mock(peer, SubstreamId::from(1usize), Box::new(outbound)),
+        NotificationEventHandle::new(event_tx),
+        conn_closed_tx,
+        notif_tx,
+        async_rx,
+        receivers.remove(&NotificationPriority::Normal).unwrap(),
+        receivers.remove(&NotificationPriority::High).unwrap(),
+        receivers.remove(&NotificationPriority::Low).unwrap(),
+        None,
+        InboundLimiter::new(None, Default::default()),
+    );
+
+    for (priority, notification) in [
+        (NotificationPriority::Low, vec![1]),
+        (NotificationPriority::Normal, vec![2]),
+        (NotificationPriority::Low, vec![3]),
+        (NotificationPriority::High, vec![4]),
+        (NotificationPriority::Normal, vec![5]),
+        (NotificationPriority::High, vec![6]),
+    ] {
+        sink.send_sync_notification_with_priority(priority, notification).unwrap();
+    }
+
+    futures::future::poll_fn(|cx| match connection.poll_next_unpin(cx) {
+        Poll::Pending => Poll::Ready(()),
+        Poll::Ready(Some(ConnectionEvent::CloseConnection { .. })) => panic!("connection closed"),
+        Poll::Ready(_) => panic!("invalid event"),
+    })
+    .await;
+
+    assert_eq!(
+        *sent.lock(),
+        vec![vec![4], vec![6], vec![2], vec![5], vec![1], vec![3]]
+    );
+}
+
+#[tokio::test]
+async fn connection_sends_handshake_update_first() {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let mut outbound = MockSubstream::new();
+    outbound.expect_poll_ready().returning(|_| Poll::Ready(Ok(())));
+    outbound.expect_poll_flush().returning(|_| Poll::Ready(Ok(())));
+    outbound.expect_start_send().returning({
+        let sent = Arc::clone(&sent);
+        move |message| {
+            sent.lock().push(message);
+            Ok(())
+        }
+    });
+
+    let peer = PeerId::random();
+    let (sync, sync_rx) = PriorityQueue::new(8);
+    let (high, high_rx) = PriorityQueue::new(8);
+    let (low, low_rx) = PriorityQueue::new(8);
+    let (handshake, handshake_rx) = PriorityQueue::new(1);
+    let (async_tx, async_rx) = channel(8);
+    let sink = NotificationSink::new(
+        peer,
+        sync,
+        async_tx,
+        high,
+        low,
+        Some(handshake),
+        OverflowPolicy::ForceClose,
+        Default::default(),
+    );
+
+    let (event_tx, _event_rx) = channel(8);
+    let (conn_closed_tx, _conn_closed_rx) = channel(8);
+    let (notif_tx, _notif_rx) = channel(8);
+    let (mut connection, _shutdown) = Connection::new(
+        peer,
+        Substream::new_mock(
+            peer,
+            SubstreamId::from(0usize),
+            Box::new(DummySubstream::new()),
+        ),
+        Substream::new_mock(peer, SubstreamId::from(1usize), Box::new(outbound)),
+        NotificationEventHandle::new(event_tx),
+        conn_closed_tx,
+        notif_tx,
+        async_rx,
+        sync_rx,
+        high_rx,
+        low_rx,
+        Some(handshake_rx),
+        InboundLimiter::new(None, Default::default()),
+    );
+
+    sink.send_sync_notification(vec![1, 3, 3, 7]).unwrap();
+
+    // only the latest handshake is sent
+    sink.send_handshake_update(Bytes::from(vec![1, 2, 3]));
+    sink.send_handshake_update(Bytes::from(vec![4, 5, 6]));
+
+    futures::future::poll_fn(|cx| match connection.poll_next_unpin(cx) {
+        Poll::Pending => Poll::Ready(()),
+        Poll::Ready(Some(ConnectionEvent::CloseConnection { .. })) => panic!("connection closed"),
+        Poll::Ready(_) => panic!("invalid event"),
+    })
+    .await;
+
+    assert_eq!(*sent.lock(), vec![vec![1, 4, 5, 6], vec![0, 1, 3, 3, 7]]);
+}
+
+#[tokio::test]
+async fn connection_reports_dropped_notifications_after_flush_timeout() {
+    // outbound substream never becomes writable so queued notifications can't be flushed
+    let mut outbound = MockSubstream::new();
+    outbound.expect_poll_ready().returning(|_| Poll::Pending);
+    outbound.expect_poll_flush().returning(|_| Poll::Ready(Ok(())));
+    outbound.expect_poll_close().returning(|_| Poll::Ready(Ok(())));
+
+    let peer = PeerId::random();
+    let (sync, sync_rx) = PriorityQueue::new(8);
+    let (high, high_rx) = PriorityQueue::new(8);
+    let (low, low_rx) = PriorityQueue::new(8);
+    let (async_tx, async_rx) = channel(8);
+    let sink = NotificationSink::new(
+        peer,
+        sync,
+        async_tx,
+        high,
+        low,
+        None,
+        OverflowPolicy::ForceClose,
+        Default::default(),
+    );
+
+    let (event_tx, mut event_rx) = channel(8);
+    let (conn_closed_tx, _conn_closed_rx) = channel(8);
+    let (notif_tx, _notif_rx) = channel(8);
+    let (connection, shutdown) = Connection::new(
+        peer,
+        Substream::new_mock(
+            peer,
+            SubstreamId::from(0usize),
+            Box::new(DummySubstream::new()),
+        ),
+        Substream::new_mock(peer, SubstreamId::from(1usize), Box::new(outbound)),
+        NotificationEventHandle::new(event_tx),
+        conn_closed_tx,
+        notif_tx,
+        async_rx,
+        sync_rx,
+        high_rx,
+        low_rx,
+        None,
+        InboundLimiter::new(None, Default::default()),
+    );
+
+    sink.send_sync_notification(vec![1]).unwrap();
+    sink.send_sync_notification(vec![2]).unwrap();
+    sink.send_sync_notification_with_priority(NotificationPriority::High, vec![3])
+        .unwrap();
+    sink.send_async_notification(vec![4]).await.unwrap();
+
+    shutdown
+        .send(CloseRequest {
+            reason: CloseReason::LocalClosed,
+            flush: Some(Duration::from_millis(100)),
+        })
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), connection.start())
+        .await
+        .expect("connection to close");
+
+    match event_rx.try_recv() {
+        Ok(InnerNotificationEvent::NotificationStreamClosed {
+            peer: closed,
+            reason,
+            dropped,
+        }) => {
+            assert_eq!(closed, peer);
+            assert_eq!(reason, CloseReason::LocalClosed);
+            assert_eq!(dropped, 4);
+        }
+        _ => panic!("invalid event"),
+    }
+}
+
+/// [`Connection`] which receives notifications from a rate-limited peer.
+///
+/// Holds the channels of the connection so it isn't closed while the test runs.
+struct RateLimitedConnection {
+    connection: Connection,
+    counters: Arc<InboundCounters>,
+    _sink: NotificationSink,
+    _shutdown: oneshot::Sender<CloseRequest>,
+    _notif_rx: Receiver<(PeerId, BytesMut)>,
+}
+
+/// Create [`RateLimitedConnection`] which receives `notifications` from the remote peer and
+/// applies `rate_limit` to them.
+fn make_rate_limited_connection(
+    notifications: usize,
+    rate_limit: RateLimitConfig,
+) -> RateLimitedConnection {
+    let mut inbound = MockSubstream::new();
+    inbound
+        .expect_poll_next()
+        .times(notifications)
+        .returning(|_| Poll::Ready(Some(Ok(BytesMut::from(&[1, 3, 3, 7][..])))));
+    inbound.expect_poll_next().returning(|_| Poll::Pending);
+
+    let mut outbound = MockSubstream::new();
+    outbound.expect_poll_flush().returning(|_| Poll::Ready(Ok(())));
+
+    let peer = PeerId::random();
+    let (sync, sync_rx) = PriorityQueue::new(8);
+    let (high, high_rx) = PriorityQueue::new(8);
+    let (low, low_rx) = PriorityQueue::new(8);
+    let (async_tx, async_rx) = channel(8);
+    let (event_tx, _event_rx) = channel(8);
+    let (conn_closed_tx, _conn_closed_rx) = channel(8);
+    let (notif_tx, notif_rx) = channel(8);
+    let counters = Arc::new(InboundCounters::default());
+    let sink = NotificationSink::new(
+        peer,
+        sync,
+        async_tx,
+        high,
+        low,
+        None,
+        OverflowPolicy::ForceClose,
+        Arc::clone(&counters),
+    );
+    let (connection, shutdown) = Connection::new(
+        peer,
+        Substream::new_mock(peer, SubstreamId::from(0usize), Box::new(inbound)),
+        Substream::new_mock(peer, SubstreamId::from(1usize), Box::new(outbound)),
+        NotificationEventHandle::new(event_tx),
+        conn_closed_tx,
+        notif_tx,
+        async_rx,
+        sync_rx,
+        high_rx,
+        low_rx,
+        None,
+        InboundLimiter::new(Some(rate_limit), Arc::clone(&counters)),
+    );
+
+    RateLimitedConnection {
+        connection,
+        counters,
+        _sink: sink,
+        _shutdown: shutdown,
+        _notif_rx: notif_rx,
+    }
+}
+
+#[tokio::test]
+async fn connection_drops_notifications_over_rate_limit() {
+    let RateLimitedConnection {
+        mut connection,
+        counters,
+        _sink,
+        _shutdown,
+        _notif_rx,
+    } = make_rate_limited_connection(
+        3,
+        RateLimitConfig {
+            messages_per_second: Some(1),
+            ..Default::default()
+        },
+    );
+
+    futures::future::poll_fn(|cx| {
+        match connection.poll_next_unpin(cx) {
+            Poll::Ready(Some(ConnectionEvent::NotificationReceived { notification })) => {
+                assert_eq!(notification, BytesMut::from(&[1, 3, 3, 7][..]));
+            }
+            _ => panic!("invalid event"),
+        }
+
+        // notifications over the limit are dropped
+        assert!(connection.poll_next_unpin(cx).is_pending());
+        Poll::Ready(())
+    })
+    .await;
+
+    assert_eq!(
+        counters.stats(),
+        InboundStats {
+            notifications: 3,
+            bytes: 12,
+            dropped: 2,
+        }
+    );
+}
+
+#[tokio::test]
+async fn connection_closed_when_rate_limit_exceeded() {
+    let RateLimitedConnection {
+        mut connection,
+        counters,
+        _sink,
+        _shutdown,
+        _notif_rx,
+    } = make_rate_limited_connection(
+        2,
+        RateLimitConfig {
+            bytes_per_second: Some(6),
+            action: RateLimitAction::Close,
+            ..Default::default()
+        },
+    );
+
+    futures::future::poll_fn(|cx| {
+        match connection.poll_next_unpin(cx) {
+            Poll::Ready(Some(ConnectionEvent::NotificationReceived { .. })) => {}
+            _ => panic!("invalid event"),
+        }
+
+        match connection.poll_next_unpin(cx) {
+            Poll::Ready(Some(ConnectionEvent::CloseConnection { reason, .. })) => {
+                assert_eq!(reason, CloseReason::RateLimited);
+            }
+            _ => panic!("invalid event"),
+        }
+        Poll::Ready(())
+    })
+    .await;
+
+    assert_eq!(counters.stats().notifications, 2);
+}
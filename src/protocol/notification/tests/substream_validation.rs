@@ -437,7 +437,7 @@ async fn open_substream_accepted() {
     );
 
     // try to accept a closed substream
-    notif.on_close_substream(peer).await;
+    notif.on_close_substream(peer, None).await;
 
     assert!(notif.on_validation_result(peer, ValidationResult::Accept).await.is_err());
 }
@@ -458,7 +458,36 @@ async fn open_substream_rejected() {
     );
 
     // try to reject a closed substream
-    notif.on_close_substream(peer).await;
+    notif.on_close_substream(peer, None).await;
 
     assert!(notif.on_validation_result(peer, ValidationResult::Reject).await.is_err());
 }
+
+#[tokio::test]
+async fn validation_timeout_reported_only_for_rejected_substream() {
+    let (mut notif, mut handle, _sender, _tx) = make_notification_protocol();
+    let (peer, _service, _receiver) = add_peer();
+
+    notif.peers.insert(
+        peer,
+        PeerContext {
+            state: PeerState::Closed { pending_open: None },
+        },
+    );
+    notif.validations.insert(peer, 1usize);
+
+    // result of a validation superseded by a newer one is ignored
+    notif.on_validation_completed(peer, 0usize, None).await;
+    assert_eq!(notif.validations.get(&peer), Some(&1usize));
+
+    // substream is no longer being validated so the timeout didn't reject it
+    notif.on_validation_completed(peer, 1usize, None).await;
+    assert!(notif.validations.is_empty());
+    assert!(std::matches!(
+        notif.peers.get(&peer),
+        Some(PeerContext {
+            state: PeerState::Closed { .. }
+        })
+    ));
+    assert!(futures::poll!(handle.next()).is_pending());
+}
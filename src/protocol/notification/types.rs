@@ -19,13 +19,15 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
-    protocol::notification::handle::NotificationSink, types::protocol::ProtocolName, PeerId,
+    protocol::{notification::handle::NotificationSink, ReputationChange},
+    types::protocol::ProtocolName,
+    PeerId,
 };
 
 use bytes::BytesMut;
 use tokio::sync::oneshot;
 
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt::Debug, time::Duration};
 
 /// Default channel size for synchronous notifications.
 pub(super) const SYNC_CHANNEL_SIZE: usize = 2048;
@@ -53,6 +55,17 @@ pub enum ValidationResult {
     Reject,
 }
 
+/// Validator for inbound substreams.
+///
+/// If a validator is installed, it's called with the handshake of the remote peer when an inbound
+/// substream needs to be validated and the user protocol doesn't receive
+/// [`NotificationEvent::ValidateSubstream`] events.
+#[async_trait::async_trait]
+pub trait HandshakeValidator: Debug + Send + Sync {
+    /// Validate inbound substream from `peer` who sent `handshake`.
+    async fn validate(&self, peer: PeerId, handshake: Vec<u8>) -> ValidationResult;
+}
+
 /// Notification error.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NotificationError {
@@ -73,6 +86,84 @@ pub enum NotificationError {
 
     /// Notification protocol has been closed.
     EssentialTaskClosed,
+
+    /// No free slots for the peer or the peer isn't reserved while only reserved peers are
+    /// allowed.
+    NoSlotsAvailable,
+
+    /// Inbound substream wasn't validated by the user protocol within the validation timeout and
+    /// was rejected.
+    ///
+    /// Validation result sent for the substream after this error has been reported is ignored.
+    ValidationTimedOut,
+}
+
+/// Policy applied when a synchronous notification is sent to a peer whose channel is full.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the new notification and report the channel as clogged.
+    DropNewest,
+
+    /// Drop the oldest queued notification to make room for the new one.
+    DropOldest,
+
+    /// Drop the new notification, report the channel as clogged and forcibly close the
+    /// connection to the peer.
+    ///
+    /// The connection is closed only if the notification was sent using
+    /// [`NotificationHandle`](super::NotificationHandle).
+    #[default]
+    ForceClose,
+}
+
+/// Priority class of a notification.
+///
+/// Each class has its own queue and the connection writer drains higher-priority queues first,
+/// allowing latency-critical notifications to bypass bulky low-priority traffic queued for the
+/// same peer.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NotificationPriority {
+    /// Sent before notifications of any other class.
+    High,
+
+    /// Priority of notifications sent without an explicit priority.
+    #[default]
+    Normal,
+
+    /// Sent only when there are no queued notifications of any other class.
+    Low,
+}
+
+/// Reason why a notification stream was closed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CloseReason {
+    /// Remote peer closed the substream.
+    RemoteClosed,
+
+    /// Local node closed the substream.
+    LocalClosed,
+
+    /// Connection to the peer was closed or the substream failed.
+    ConnectionLost,
+
+    /// Connection to the peer was forcibly closed because its notification channel was clogged.
+    Clogged,
+
+    /// Peer exceeded the inbound rate limit.
+    RateLimited,
+}
+
+/// Outcome of broadcasting a notification to a single peer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BroadcastOutcome {
+    /// Notification was queued for sending.
+    Sent,
+
+    /// Synchronous notification channel of the peer is clogged and the notification was dropped.
+    Clogged,
+
+    /// Connection to peer has been closed.
+    Closed,
 }
 
 /// Notification events.
@@ -120,6 +211,12 @@ pub(crate) enum InnerNotificationEvent {
     NotificationStreamClosed {
         /// Peer ID.
         peer: PeerId,
+
+        /// Why the notification stream was closed.
+        reason: CloseReason,
+
+        /// Number of queued notifications which were not sent before the stream was closed.
+        dropped: usize,
     },
 
     /// Failed to open notification stream.
@@ -130,6 +227,15 @@ pub(crate) enum InnerNotificationEvent {
         /// Error.
         error: NotificationError,
     },
+
+    /// Remote peer updated its handshake.
+    HandshakeUpdated {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// New handshake.
+        handshake: Vec<u8>,
+    },
 }
 
 /// Notification events.
@@ -177,6 +283,12 @@ pub enum NotificationEvent {
     NotificationStreamClosed {
         /// Peer ID.
         peer: PeerId,
+
+        /// Why the notification stream was closed.
+        reason: CloseReason,
+
+        /// Number of queued notifications which were not sent before the stream was closed.
+        dropped: usize,
     },
 
     /// Failed to open notification stream.
@@ -196,6 +308,18 @@ pub enum NotificationEvent {
         /// Notification.
         notification: BytesMut,
     },
+
+    /// Remote peer updated its handshake.
+    ///
+    /// Only emitted if handshake updates are enabled with
+    /// [`ConfigBuilder::with_handshake_updates()`](super::ConfigBuilder::with_handshake_updates).
+    HandshakeUpdated {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// New handshake.
+        handshake: Vec<u8>,
+    },
 }
 
 /// Notification commands sent to the protocol.
@@ -206,10 +330,23 @@ pub(crate) enum NotificationCommand {
         peers: HashSet<PeerId>,
     },
 
+    /// Open substream to peer using a custom handshake.
+    OpenSubstreamWithHandshake {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// Handshake.
+        handshake: Vec<u8>,
+    },
+
     /// Close substreams to one or more peers.
     CloseSubstream {
         /// Peer IDs.
         peers: HashSet<PeerId>,
+
+        /// How long to wait for queued notifications to be sent before closing the substreams,
+        /// if at all.
+        flush: Option<Duration>,
     },
 
     /// Force close the connection because notification channel is clogged.
@@ -217,4 +354,13 @@ pub(crate) enum NotificationCommand {
         /// Peer to disconnect.
         peer: PeerId,
     },
+
+    /// Report reputation change for peer.
+    ReportPeer {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// Reputation change.
+        change: ReputationChange,
+    },
 }
@@ -218,6 +218,7 @@ pub struct ProtocolSet {
 }
 
 impl ProtocolSet {
+    /// Create new [`ProtocolSet`].
     pub fn new(
         connection_id: ConnectionId,
         mgr_tx: Sender<TransportManagerEvent>,
@@ -305,6 +306,7 @@ impl ProtocolSet {
             .get(self.fallback_names.get(&protocol).map_or(protocol, |protocol| protocol))
             .expect("protocol to exist")
             .codec
+            .clone()
     }
 
     /// Report to `protocol` that connection failed to open substream for `peer`.
@@ -332,7 +334,7 @@ impl ProtocolSet {
     }
 
     /// Report to protocols that a connection was established.
-    pub(crate) async fn report_connection_established(
+    pub async fn report_connection_established(
         &mut self,
         peer: PeerId,
         endpoint: Endpoint,
@@ -365,11 +367,21 @@ impl ProtocolSet {
             }
         }
 
+        // give `TransportManager` a handle to the connection so it can disconnect banned peers
+        let _ = self
+            .mgr_tx
+            .send(TransportManagerEvent::ConnectionEstablished {
+                peer,
+                connection: endpoint.connection_id(),
+                handle: connection_handle,
+            })
+            .await;
+
         Ok(())
     }
 
     /// Report to protocols that a connection was closed.
-    pub(crate) async fn report_connection_closed(
+    pub async fn report_connection_closed(
         &mut self,
         peer: PeerId,
         connection_id: ConnectionId,
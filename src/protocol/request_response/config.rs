@@ -35,6 +35,55 @@ use std::{
     time::Duration,
 };
 
+/// Behavior when a peer exceeds its inbound request limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowBehavior {
+    /// Reject the request immediately.
+    Reject,
+
+    /// Queue the request until the peer is within its limits again.
+    ///
+    /// If the queue of the peer is full, the request is rejected. Requests which have been queued
+    /// for longer than `max_wait` are rejected.
+    Queue {
+        /// Maximum number of queued requests per peer.
+        max_queued: usize,
+
+        /// Maximum time a request can spend in the queue.
+        max_wait: Duration,
+    },
+}
+
+/// Per-peer limits for inbound requests.
+///
+/// When a peer exceeds its limits,
+/// [`RequestResponseEvent::InboundQuotaExceeded`](super::RequestResponseEvent) is reported to the
+/// user protocol and the request is handled according to [`OverflowBehavior`].
+#[derive(Debug, Clone)]
+pub struct InboundRequestLimits {
+    /// Maximum number of concurrent inbound requests from a peer.
+    ///
+    /// A request is considered active until the response has been sent or the request has been
+    /// rejected.
+    pub max_concurrent: Option<usize>,
+
+    /// Maximum number of inbound requests accepted from a peer within a time window.
+    pub max_per_window: Option<(usize, Duration)>,
+
+    /// Behavior when a peer exceeds its limits.
+    pub overflow: OverflowBehavior,
+}
+
+impl Default for InboundRequestLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            max_per_window: None,
+            overflow: OverflowBehavior::Reject,
+        }
+    }
+}
+
 /// Request-response protocol configuration.
 pub struct Config {
     /// Protocol name.
@@ -60,6 +109,20 @@ pub struct Config {
 
     /// Maximum number of concurrent inbound requests.
     pub(crate) max_concurrent_inbound_request: Option<usize>,
+
+    /// Are responses streamed in chunks.
+    pub(crate) streaming_responses: bool,
+
+    /// Per-peer limits for inbound requests.
+    pub(crate) inbound_limits: InboundRequestLimits,
+
+    /// Maximum size of a request, sent or received.
+    pub(crate) max_request_size: usize,
+
+    /// Maximum size of a response, sent or received.
+    ///
+    /// If responses are streamed, the limit applies to each chunk.
+    pub(crate) max_response_size: usize,
 }
 
 impl Config {
@@ -85,6 +148,10 @@ impl Config {
                 next_request_id,
                 timeout,
                 max_concurrent_inbound_request,
+                streaming_responses: false,
+                inbound_limits: InboundRequestLimits::default(),
+                max_request_size: max_message_size,
+                max_response_size: max_message_size,
                 codec: ProtocolCodec::UnsignedVarint(Some(max_message_size)),
             },
             handle,
@@ -105,14 +172,23 @@ pub struct ConfigBuilder {
     /// Fallback names for the main protocol name.
     pub(crate) fallback_names: Vec<ProtocolName>,
 
-    /// Maximum message size.
-    max_message_size: Option<usize>,
+    /// Maximum request size.
+    max_request_size: Option<usize>,
+
+    /// Maximum response size.
+    max_response_size: Option<usize>,
 
     /// Timeout for outbound requests.
     timeout: Option<Duration>,
 
     /// Maximum number of concurrent inbound requests.
     max_concurrent_inbound_request: Option<usize>,
+
+    /// Are responses streamed in chunks.
+    streaming_responses: bool,
+
+    /// Per-peer limits for inbound requests.
+    inbound_limits: InboundRequestLimits,
 }
 
 impl ConfigBuilder {
@@ -121,15 +197,43 @@ impl ConfigBuilder {
         Self {
             protocol_name,
             fallback_names: Vec::new(),
-            max_message_size: None,
+            max_request_size: None,
+            max_response_size: None,
             timeout: Some(REQUEST_TIMEOUT),
             max_concurrent_inbound_request: None,
+            streaming_responses: false,
+            inbound_limits: InboundRequestLimits::default(),
         }
     }
 
-    /// Set maximum message size.
+    /// Set maximum message size for both requests and responses.
     pub fn with_max_size(mut self, max_message_size: usize) -> Self {
-        self.max_message_size = Some(max_message_size);
+        self.max_request_size = Some(max_message_size);
+        self.max_response_size = Some(max_message_size);
+        self
+    }
+
+    /// Set maximum request size.
+    ///
+    /// Outbound requests exceeding the size are failed with
+    /// [`RequestResponseError::TooLargePayload`](super::RequestResponseError) and inbound requests
+    /// exceeding it are rejected.
+    ///
+    /// If the maximum response size is not set, it defaults to the maximum request size.
+    pub fn with_max_request_size(mut self, max_request_size: usize) -> Self {
+        self.max_request_size = Some(max_request_size);
+        self
+    }
+
+    /// Set maximum response size.
+    ///
+    /// Received responses exceeding the size are reported with
+    /// [`RequestResponseError::TooLargeResponse`](super::RequestResponseError) and local responses
+    /// exceeding it are not sent, which the remote peer sees as a rejection.
+    ///
+    /// If the maximum request size is not set, it defaults to the maximum response size.
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = Some(max_response_size);
         self
     }
 
@@ -158,14 +262,52 @@ impl ConfigBuilder {
         self
     }
 
+    /// Specify per-peer limits for inbound requests. By default the inbound requests of a peer
+    /// are not limited.
+    ///
+    /// The limits are applied in addition to the maximum number of concurrent inbound requests.
+    pub fn with_inbound_request_limits(mut self, inbound_limits: InboundRequestLimits) -> Self {
+        self.inbound_limits = inbound_limits;
+        self
+    }
+
+    /// Stream responses in chunks.
+    ///
+    /// Instead of a single message, the response is sent as a sequence of chunks, each limited by
+    /// the maximum response size, followed by an end-of-stream marker. Received chunks are reported
+    /// with [`RequestResponseEvent::ResponseChunkReceived`](super::RequestResponseEvent) and the
+    /// end of the stream with [`RequestResponseEvent::ResponseStreamFinished`](
+    /// super::RequestResponseEvent). The timeout for outbound requests applies to each chunk.
+    ///
+    /// Both peers must have streaming responses enabled for the protocol.
+    pub fn with_streaming_responses(mut self) -> Self {
+        self.streaming_responses = true;
+        self
+    }
+
     /// Build [`Config`].
     pub fn build(mut self) -> (Config, RequestResponseHandle) {
-        Config::new(
+        // if only one of the limits is set, it's used for both requests and responses
+        let (max_request_size, max_response_size) =
+            match (self.max_request_size.take(), self.max_response_size.take()) {
+                (Some(max_request_size), Some(max_response_size)) =>
+                    (max_request_size, max_response_size),
+                (Some(max_size), None) | (None, Some(max_size)) => (max_size, max_size),
+                (None, None) => panic!("maximum message size to be set"),
+            };
+
+        let (mut config, handle) = Config::new(
             self.protocol_name,
             self.fallback_names,
-            self.max_message_size.take().expect("maximum message size to be set"),
+            std::cmp::max(max_request_size, max_response_size),
             self.timeout.take().expect("timeout to exist"),
             self.max_concurrent_inbound_request,
-        )
+        );
+        config.streaming_responses = self.streaming_responses;
+        config.inbound_limits = self.inbound_limits;
+        config.max_request_size = max_request_size;
+        config.max_response_size = max_response_size;
+
+        (config, handle)
     }
 }
@@ -19,24 +19,27 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
+    error::SubstreamError,
+    protocol::ReputationChange,
     types::{protocol::ProtocolName, RequestId},
     Error, PeerId,
 };
 
 use futures::channel;
 use tokio::sync::{
-    mpsc::{Receiver, Sender},
+    mpsc::{channel, Receiver, Sender},
     oneshot,
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     pin::Pin,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
     task::{Context, Poll},
+    time::Duration,
 };
 
 /// Logging target for the file.
@@ -58,10 +61,76 @@ pub enum RequestResponseError {
     NotConnected,
 
     /// Too large payload.
+    ///
+    /// The outbound request exceeded the maximum request size.
     TooLargePayload,
 
+    /// Received response exceeded the maximum response size.
+    TooLargeResponse,
+
     /// Protocol not supported.
     UnsupportedProtocol,
+
+    /// Received message could not be decoded.
+    DecodeFailure,
+}
+
+/// Response to an inbound request, given to the request-response protocol.
+pub(super) enum Response {
+    /// Response sent as a single message.
+    Single {
+        /// Response.
+        response: Vec<u8>,
+
+        /// Feedback channel, notified once the response has been sent.
+        feedback: Option<channel::oneshot::Sender<()>>,
+    },
+
+    /// Response streamed in chunks.
+    ///
+    /// `None` marks the end of the stream and if the channel is closed before it's received,
+    /// the response is aborted.
+    Stream(Receiver<Option<Vec<u8>>>),
+}
+
+/// Sender for streaming a response to remote peer in chunks.
+///
+/// At most one chunk is buffered while the previous chunk is being written to the substream,
+/// meaning [`ResponseSender::send_chunk()`] waits until the remote peer is able to receive more
+/// data.
+///
+/// Once all chunks have been sent, [`ResponseSender::finish()`] must be called to signal the end
+/// of the stream to remote peer. If [`ResponseSender`] is dropped before that, the response is
+/// aborted and remote peer sees the request as rejected.
+#[derive(Debug)]
+pub struct ResponseSender {
+    /// TX channel for sending chunks to the request-response protocol.
+    tx: Sender<Option<Vec<u8>>>,
+}
+
+impl ResponseSender {
+    /// Send response chunk to remote peer.
+    ///
+    /// Empty chunks are reserved for marking the end of the stream and are rejected with
+    /// `Error::InvalidData`.
+    pub async fn send_chunk(&mut self, chunk: Vec<u8>) -> crate::Result<()> {
+        if chunk.is_empty() {
+            return Err(Error::InvalidData);
+        }
+
+        self.tx
+            .send(Some(chunk))
+            .await
+            .map_err(|_| Error::SubstreamError(SubstreamError::ConnectionClosed))
+    }
+
+    /// Finish the response stream.
+    pub async fn finish(self) -> crate::Result<()> {
+        self.tx
+            .send(None)
+            .await
+            .map_err(|_| Error::SubstreamError(SubstreamError::ConnectionClosed))
+    }
 }
 
 /// Request-response events.
@@ -81,7 +150,7 @@ pub(super) enum InnerRequestResponseEvent {
         request: Vec<u8>,
 
         /// `oneshot::Sender` for response.
-        response_tx: oneshot::Sender<(Vec<u8>, Option<channel::oneshot::Sender<()>>)>,
+        response_tx: oneshot::Sender<Response>,
     },
 
     /// Response received.
@@ -99,6 +168,36 @@ pub(super) enum InnerRequestResponseEvent {
         response: Vec<u8>,
     },
 
+    /// Response chunk received.
+    ResponseChunkReceived {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Fallback protocol, if the substream was negotiated using a fallback.
+        fallback: Option<ProtocolName>,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Received chunk.
+        chunk: Vec<u8>,
+    },
+
+    /// Response stream finished.
+    ResponseStreamFinished {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+    },
+
+    /// Peer exceeded its inbound request limits.
+    InboundQuotaExceeded {
+        /// Peer Id.
+        peer: PeerId,
+    },
+
     /// Request failed.
     RequestFailed {
         /// Peer Id.
@@ -110,6 +209,14 @@ pub(super) enum InnerRequestResponseEvent {
         /// Request-response error.
         error: RequestResponseError,
     },
+
+    /// Outbound request was canceled by the local node.
+    ///
+    /// No more events are emitted for the request. The event is not reported to the user.
+    RequestCanceled {
+        /// Request ID.
+        request_id: RequestId,
+    },
 }
 
 impl From<InnerRequestResponseEvent> for RequestResponseEvent {
@@ -126,6 +233,21 @@ impl From<InnerRequestResponseEvent> for RequestResponseEvent {
                 response,
                 fallback,
             },
+            InnerRequestResponseEvent::ResponseChunkReceived {
+                peer,
+                fallback,
+                request_id,
+                chunk,
+            } => RequestResponseEvent::ResponseChunkReceived {
+                peer,
+                request_id,
+                fallback,
+                chunk,
+            },
+            InnerRequestResponseEvent::ResponseStreamFinished { peer, request_id } =>
+                RequestResponseEvent::ResponseStreamFinished { peer, request_id },
+            InnerRequestResponseEvent::InboundQuotaExceeded { peer } =>
+                RequestResponseEvent::InboundQuotaExceeded { peer },
             InnerRequestResponseEvent::RequestFailed {
                 peer,
                 request_id,
@@ -177,6 +299,44 @@ pub enum RequestResponseEvent {
         response: Vec<u8>,
     },
 
+    /// Response chunk received.
+    ///
+    /// Only emitted by protocols with streaming responses enabled. The chunks of a response
+    /// are followed by either [`RequestResponseEvent::ResponseStreamFinished`] or
+    /// [`RequestResponseEvent::RequestFailed`].
+    ResponseChunkReceived {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Fallback protocol, if the substream was negotiated using a fallback.
+        fallback: Option<ProtocolName>,
+
+        /// Received chunk.
+        chunk: Vec<u8>,
+    },
+
+    /// All chunks of the response have been received.
+    ResponseStreamFinished {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+    },
+
+    /// Peer sent an inbound request which exceeded its limits.
+    ///
+    /// Reported for each request which was either rejected or queued because of the limits
+    /// configured with [`ConfigBuilder::with_inbound_request_limits()`](
+    /// super::ConfigBuilder::with_inbound_request_limits).
+    InboundQuotaExceeded {
+        /// Peer Id.
+        peer: PeerId,
+    },
+
     /// Request failed.
     RequestFailed {
         /// Peer Id.
@@ -191,7 +351,7 @@ pub enum RequestResponseEvent {
 }
 
 /// Dial behavior when sending requests.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DialOptions {
     /// If the peer is not currently connected, attempt to dial them before sending a request.
     ///
@@ -206,6 +366,55 @@ pub enum DialOptions {
     Reject,
 }
 
+/// Strategy for trying the candidate peers of [`RequestResponseHandle::send_request_to_any()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryStrategy {
+    /// Try the candidates one at a time, moving to the next candidate once the previous attempt
+    /// has failed.
+    Sequential,
+
+    /// Try the candidates in parallel, starting a new attempt if no response has been received
+    /// within `delay` of the previous attempt.
+    ///
+    /// Failed attempts are retried immediately without waiting for the delay to expire.
+    Hedged {
+        /// Delay between attempts.
+        delay: Duration,
+    },
+}
+
+/// Retry policy for [`RequestResponseHandle::send_request_to_any()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How the candidate peers are tried.
+    pub strategy: RetryStrategy,
+
+    /// Maximum number of attempts.
+    ///
+    /// The candidates are tried in a round-robin fashion so if the budget is larger than the
+    /// number of candidates, the request is sent to the same peer more than once.
+    pub max_attempts: usize,
+
+    /// Timeout of each attempt.
+    ///
+    /// If `None`, the timeout configured for the protocol is used.
+    pub timeout: Option<Duration>,
+
+    /// Dial options used for each attempt, see [`DialOptions`] for more details.
+    pub dial_options: DialOptions,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            strategy: RetryStrategy::Sequential,
+            max_attempts: 3usize,
+            timeout: None,
+            dial_options: DialOptions::Reject,
+        }
+    }
+}
+
 /// Request-response commands.
 pub(crate) enum RequestResponseCommand {
     /// Send request to remote peer.
@@ -227,6 +436,11 @@ pub(crate) enum RequestResponseCommand {
 
         /// Dial options, see [`DialOptions`] for more details.
         dial_options: DialOptions,
+
+        /// Request timeout.
+        ///
+        /// If `None`, the timeout configured for the protocol is used.
+        timeout: Option<Duration>,
     },
 
     SendRequestWithFallback {
@@ -244,6 +458,26 @@ pub(crate) enum RequestResponseCommand {
 
         /// Dial options, see [`DialOptions`] for more details.
         dial_options: DialOptions,
+
+        /// Request timeout.
+        ///
+        /// If `None`, the timeout configured for the protocol is used.
+        timeout: Option<Duration>,
+    },
+
+    /// Send request to any of the candidate peers.
+    SendRequestToAny {
+        /// Candidate peers.
+        peers: Vec<PeerId>,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Request.
+        request: Vec<u8>,
+
+        /// Retry policy.
+        policy: RetryPolicy,
     },
 
     /// Cancel outbound request.
@@ -251,6 +485,15 @@ pub(crate) enum RequestResponseCommand {
         /// Request ID.
         request_id: RequestId,
     },
+
+    /// Report reputation change for peer.
+    ReportPeer {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// Reputation change.
+        change: ReputationChange,
+    },
 }
 
 /// Handle given to the user protocol which allows it to interact with the request-response
@@ -263,8 +506,10 @@ pub struct RequestResponseHandle {
     command_tx: Sender<RequestResponseCommand>,
 
     /// Pending responses.
-    pending_responses:
-        HashMap<RequestId, oneshot::Sender<(Vec<u8>, Option<channel::oneshot::Sender<()>>)>>,
+    pending_responses: HashMap<RequestId, oneshot::Sender<Response>>,
+
+    /// Canceled outbound requests whose queued events are discarded.
+    discarded_requests: HashSet<RequestId>,
 
     /// Next ephemeral request ID.
     next_request_id: Arc<AtomicUsize>,
@@ -282,6 +527,7 @@ impl RequestResponseHandle {
             command_tx,
             next_request_id,
             pending_responses: HashMap::new(),
+            discarded_requests: HashSet::new(),
         }
     }
 
@@ -313,6 +559,34 @@ impl RequestResponseHandle {
         let _ = self.command_tx.send(RequestResponseCommand::CancelRequest { request_id }).await;
     }
 
+    /// Cancel an outbound request and discard its events which haven't been reported yet.
+    ///
+    /// If the command channel is clogged, the request is not canceled but its events are still
+    /// discarded until the request finishes.
+    pub(super) fn discard_request(&mut self, request_id: RequestId) {
+        tracing::trace!(target: LOG_TARGET, ?request_id, "discard request");
+
+        self.discarded_requests.insert(request_id);
+
+        if self
+            .command_tx
+            .try_send(RequestResponseCommand::CancelRequest { request_id })
+            .is_err()
+        {
+            tracing::debug!(target: LOG_TARGET, ?request_id, "failed to cancel discarded request");
+        }
+    }
+
+    /// Report reputation change for `peer`.
+    ///
+    /// The reputation is shared by all installed protocols and if it drops below the ban
+    /// threshold, the peer is disconnected and banned.
+    pub async fn report_peer(&self, peer: PeerId, change: ReputationChange) {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?change, "report peer");
+
+        let _ = self.command_tx.send(RequestResponseCommand::ReportPeer { peer, change }).await;
+    }
+
     /// Get next request ID.
     fn next_request_id(&self) -> RequestId {
         let request_id = self.next_request_id.fetch_add(1usize, Ordering::Relaxed);
@@ -340,6 +614,7 @@ impl RequestResponseHandle {
                 request_id,
                 request,
                 dial_options,
+                timeout: None,
             })
             .await
             .map(|_| request_id)
@@ -368,6 +643,7 @@ impl RequestResponseHandle {
                 request_id,
                 request,
                 dial_options,
+                timeout: None,
             })
             .map(|_| request_id)
             .map_err(|_| Error::ChannelClogged)
@@ -397,6 +673,7 @@ impl RequestResponseHandle {
                 fallback,
                 request,
                 dial_options,
+                timeout: None,
             })
             .await
             .map(|_| request_id)
@@ -428,11 +705,174 @@ impl RequestResponseHandle {
                 fallback,
                 request,
                 dial_options,
+                timeout: None,
             })
             .map(|_| request_id)
             .map_err(|_| Error::ChannelClogged)
     }
 
+    /// Send request to remote peer with a custom timeout.
+    ///
+    /// Works like [`RequestResponseHandle::send_request()`] but instead of the timeout configured
+    /// for the protocol, the request is failed with [`RequestResponseError::Timeout`] if no
+    /// response is received within `timeout`.
+    pub async fn send_request_with_timeout(
+        &mut self,
+        peer: PeerId,
+        request: Vec<u8>,
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?timeout, "send request to peer");
+
+        let request_id = self.next_request_id();
+        self.command_tx
+            .send(RequestResponseCommand::SendRequest {
+                peer,
+                request_id,
+                request,
+                dial_options,
+                timeout: Some(timeout),
+            })
+            .await
+            .map(|_| request_id)
+            .map_err(From::from)
+    }
+
+    /// Attempt to send request to peer with a custom timeout and if the channel is clogged,
+    /// return `Error::ChannelClogged`.
+    pub fn try_send_request_with_timeout(
+        &mut self,
+        peer: PeerId,
+        request: Vec<u8>,
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?timeout, "send request to peer");
+
+        let request_id = self.next_request_id();
+        self.command_tx
+            .try_send(RequestResponseCommand::SendRequest {
+                peer,
+                request_id,
+                request,
+                dial_options,
+                timeout: Some(timeout),
+            })
+            .map(|_| request_id)
+            .map_err(|_| Error::ChannelClogged)
+    }
+
+    /// Send request to remote peer with fallback and a custom timeout.
+    pub async fn send_request_with_fallback_and_timeout(
+        &mut self,
+        peer: PeerId,
+        request: Vec<u8>,
+        fallback: (ProtocolName, Vec<u8>),
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            fallback = %fallback.0,
+            ?timeout,
+            ?dial_options,
+            "send request with fallback to peer",
+        );
+
+        let request_id = self.next_request_id();
+        self.command_tx
+            .send(RequestResponseCommand::SendRequestWithFallback {
+                peer,
+                request_id,
+                fallback,
+                request,
+                dial_options,
+                timeout: Some(timeout),
+            })
+            .await
+            .map(|_| request_id)
+            .map_err(From::from)
+    }
+
+    /// Attempt to send request to peer with fallback and a custom timeout and if the channel is
+    /// clogged, return `Error::ChannelClogged`.
+    pub fn try_send_request_with_fallback_and_timeout(
+        &mut self,
+        peer: PeerId,
+        request: Vec<u8>,
+        fallback: (ProtocolName, Vec<u8>),
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            fallback = %fallback.0,
+            ?timeout,
+            ?dial_options,
+            "send request with fallback to peer",
+        );
+
+        let request_id = self.next_request_id();
+        self.command_tx
+            .try_send(RequestResponseCommand::SendRequestWithFallback {
+                peer,
+                request_id,
+                fallback,
+                request,
+                dial_options,
+                timeout: Some(timeout),
+            })
+            .map(|_| request_id)
+            .map_err(|_| Error::ChannelClogged)
+    }
+
+    /// Send request to any of the candidate `peers`.
+    ///
+    /// The request is sent to the candidates in order as specified by `policy` and attempts which
+    /// fail with [`RequestResponseError::Timeout`], [`RequestResponseError::NotConnected`] or
+    /// [`RequestResponseError::Rejected`] are retried with the next candidate until the attempt
+    /// budget is exhausted. Any other error fails the request immediately.
+    ///
+    /// Only a single event is reported for the request: either
+    /// [`RequestResponseEvent::ResponseReceived`] containing the peer that responded or
+    /// [`RequestResponseEvent::RequestFailed`] containing the error of the last failed attempt.
+    /// Once a response has been received, other attempts still in flight are canceled.
+    ///
+    /// Should only be used for idempotent requests and isn't supported for protocols with
+    /// streaming responses. `policy` must allow at least one attempt, otherwise
+    /// `Error::InvalidRetryPolicy` is returned.
+    pub async fn send_request_to_any(
+        &mut self,
+        peers: Vec<PeerId>,
+        request: Vec<u8>,
+        policy: RetryPolicy,
+    ) -> crate::Result<RequestId> {
+        tracing::trace!(target: LOG_TARGET, ?peers, ?policy, "send request to any peer");
+
+        if peers.is_empty() {
+            return Err(Error::InsufficientPeers);
+        }
+
+        if policy.max_attempts == 0 {
+            return Err(Error::InvalidRetryPolicy);
+        }
+
+        let request_id = self.next_request_id();
+        self.command_tx
+            .send(RequestResponseCommand::SendRequestToAny {
+                peers,
+                request_id,
+                request,
+                policy,
+            })
+            .await
+            .map(|_| request_id)
+            .map_err(From::from)
+    }
+
     /// Send response to remote peer.
     pub fn send_response(&mut self, request_id: RequestId, response: Vec<u8>) {
         match self.pending_responses.remove(&request_id) {
@@ -442,7 +882,10 @@ impl RequestResponseHandle {
             Some(response_tx) => {
                 tracing::trace!(target: LOG_TARGET, ?request_id, "send response to peer");
 
-                if let Err(_) = response_tx.send((response, None)) {
+                if let Err(_) = response_tx.send(Response::Single {
+                    response,
+                    feedback: None,
+                }) {
                     tracing::debug!(target: LOG_TARGET, ?request_id, "substream closed");
                 }
             }
@@ -470,38 +913,77 @@ impl RequestResponseHandle {
             Some(response_tx) => {
                 tracing::trace!(target: LOG_TARGET, ?request_id, "send response to peer");
 
-                if let Err(_) = response_tx.send((response, Some(feedback))) {
+                if let Err(_) = response_tx.send(Response::Single {
+                    response,
+                    feedback: Some(feedback),
+                }) {
                     tracing::debug!(target: LOG_TARGET, ?request_id, "substream closed");
                 }
             }
         }
     }
+
+    /// Start streaming response to remote peer.
+    ///
+    /// Returns a [`ResponseSender`] which is used to send the response chunks or `None` if the
+    /// inbound request doesn't exist. The protocol must have streaming responses enabled with
+    /// [`ConfigBuilder::with_streaming_responses()`](super::ConfigBuilder::with_streaming_responses),
+    /// otherwise the request is rejected.
+    pub fn send_response_stream(&mut self, request_id: RequestId) -> Option<ResponseSender> {
+        let Some(response_tx) = self.pending_responses.remove(&request_id) else {
+            tracing::debug!(target: LOG_TARGET, ?request_id, "pending response doens't exist");
+            return None;
+        };
+
+        tracing::trace!(target: LOG_TARGET, ?request_id, "stream response to peer");
+
+        // channel size of one so that at most one chunk is buffered while the previous chunk
+        // is being written to the substream
+        let (tx, rx) = channel(1);
+
+        if response_tx.send(Response::Stream(rx)).is_err() {
+            tracing::debug!(target: LOG_TARGET, ?request_id, "substream closed");
+        }
+
+        Some(ResponseSender { tx })
+    }
 }
 
 impl futures::Stream for RequestResponseHandle {
     type Item = RequestResponseEvent;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match futures::ready!(self.event_rx.poll_recv(cx)) {
-            None => return Poll::Ready(None),
-            Some(event) => match event {
-                InnerRequestResponseEvent::RequestReceived {
-                    peer,
-                    fallback,
-                    request_id,
-                    request,
-                    response_tx,
-                } => {
-                    self.pending_responses.insert(request_id, response_tx);
-                    Poll::Ready(Some(RequestResponseEvent::RequestReceived {
+        loop {
+            match futures::ready!(self.event_rx.poll_recv(cx)) {
+                None => return Poll::Ready(None),
+                Some(event) => match event {
+                    InnerRequestResponseEvent::RequestReceived {
                         peer,
                         fallback,
                         request_id,
                         request,
-                    }))
-                }
-                event => Poll::Ready(Some(event.into())),
-            },
+                        response_tx,
+                    } => {
+                        self.pending_responses.insert(request_id, response_tx);
+                        return Poll::Ready(Some(RequestResponseEvent::RequestReceived {
+                            peer,
+                            fallback,
+                            request_id,
+                            request,
+                        }));
+                    }
+                    InnerRequestResponseEvent::RequestCanceled { request_id } => {
+                        self.discarded_requests.remove(&request_id);
+                    }
+                    InnerRequestResponseEvent::ResponseChunkReceived { request_id, .. }
+                        if self.discarded_requests.contains(&request_id) => {}
+                    InnerRequestResponseEvent::ResponseReceived { request_id, .. }
+                    | InnerRequestResponseEvent::ResponseStreamFinished { request_id, .. }
+                    | InnerRequestResponseEvent::RequestFailed { request_id, .. }
+                        if self.discarded_requests.remove(&request_id) => {}
+                    event => return Poll::Ready(Some(event.into())),
+                },
+            }
         }
     }
 }
@@ -24,7 +24,7 @@ use crate::{
     error::{Error, NegotiationError},
     multistream_select::NegotiationError::Failed as MultistreamFailed,
     protocol::{
-        request_response::handle::{InnerRequestResponseEvent, RequestResponseCommand},
+        request_response::handle::{InnerRequestResponseEvent, RequestResponseCommand, Response},
         Direction, TransportEvent, TransportService,
     },
     substream::{Substream, SubstreamSet},
@@ -32,18 +32,18 @@ use crate::{
     PeerId,
 };
 
-use bytes::BytesMut;
-use futures::{channel, future::BoxFuture, stream::FuturesUnordered, StreamExt};
+use bytes::{Bytes, BytesMut};
+use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
 use tokio::{
     sync::{
         mpsc::{Receiver, Sender},
         oneshot,
     },
-    time::sleep,
+    time::{sleep, Instant},
 };
 
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     io::ErrorKind,
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -52,15 +52,26 @@ use std::{
     time::Duration,
 };
 
-pub use config::{Config, ConfigBuilder};
-pub use handle::{DialOptions, RequestResponseError, RequestResponseEvent, RequestResponseHandle};
+pub use config::{Config, ConfigBuilder, InboundRequestLimits, OverflowBehavior};
+pub use handle::{
+    DialOptions, RequestResponseError, RequestResponseEvent, RequestResponseHandle, ResponseSender,
+    RetryPolicy, RetryStrategy,
+};
+pub use typed::{
+    MessageCodec, ProtobufCodec, TypedRequestResponseEvent, TypedRequestResponseHandle,
+};
+
+#[cfg(feature = "json-codec")]
+pub use typed::JsonCodec;
+#[cfg(feature = "scale-codec")]
+pub use typed::ScaleCodec;
 
 mod config;
 mod handle;
 #[cfg(test)]
 mod tests;
+mod typed;
 
-// TODO: add ability to specify limit for inbound requests?
 // TODO: convert inbound/outbound substreams to use `oneshot:Sender<()>` for sending/rejecting
 // responses.       this way, the code dealing with rejecting/responding doesn't have to block.
 
@@ -71,11 +82,14 @@ const LOG_TARGET: &str = "litep2p::request-response::protocol";
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Pending request.
+///
+/// For streamed responses, the chunks are reported as they're received and `Ok(None)` signals
+/// the end of the stream.
 type PendingRequest = (
     PeerId,
     RequestId,
     Option<ProtocolName>,
-    Result<Vec<u8>, RequestResponseError>,
+    Result<Option<Vec<u8>>, RequestResponseError>,
 );
 
 /// Request context.
@@ -91,6 +105,9 @@ struct RequestContext {
 
     /// Fallback request.
     fallback: Option<(ProtocolName, Vec<u8>)>,
+
+    /// Request timeout.
+    timeout: Duration,
 }
 
 impl RequestContext {
@@ -100,16 +117,36 @@ impl RequestContext {
         request_id: RequestId,
         request: Vec<u8>,
         fallback: Option<(ProtocolName, Vec<u8>)>,
+        timeout: Duration,
     ) -> Self {
         Self {
             peer,
             request_id,
             request,
             fallback,
+            timeout,
         }
     }
 }
 
+/// Context of a request sent to any of the candidate peers.
+struct AnyRequestContext {
+    /// Candidate peers.
+    peers: Vec<PeerId>,
+
+    /// Request.
+    request: Vec<u8>,
+
+    /// Retry policy.
+    policy: RetryPolicy,
+
+    /// Number of attempts started.
+    attempts: usize,
+
+    /// Attempts in flight.
+    active: HashSet<RequestId>,
+}
+
 /// Peer context.
 struct PeerContext {
     /// Active requests.
@@ -117,6 +154,18 @@ struct PeerContext {
 
     /// Active inbound requests and their fallback names.
     active_inbound: HashMap<RequestId, Option<ProtocolName>>,
+
+    /// Inbound requests which have been read and are waiting for the response to be sent.
+    pending_responses: HashSet<RequestId>,
+
+    /// Times when inbound requests were accepted within the current time window.
+    inbound_timestamps: VecDeque<Instant>,
+
+    /// Inbound substreams queued because the peer exceeded its limits, and their deadlines.
+    queued_inbound: VecDeque<(Option<ProtocolName>, Substream, Instant)>,
+
+    /// Is there a pending timer for processing queued inbound substreams.
+    quota_timer_pending: bool,
 }
 
 impl PeerContext {
@@ -125,8 +174,37 @@ impl PeerContext {
         Self {
             active: HashSet::new(),
             active_inbound: HashMap::new(),
+            pending_responses: HashSet::new(),
+            inbound_timestamps: VecDeque::new(),
+            queued_inbound: VecDeque::new(),
+            quota_timer_pending: false,
         }
     }
+
+    /// Check if a new inbound request from the peer is within `limits`.
+    fn is_within_limits(&mut self, limits: &InboundRequestLimits, now: Instant) -> bool {
+        if let Some(max_concurrent) = limits.max_concurrent {
+            if self.active_inbound.len() + self.pending_responses.len() >= max_concurrent {
+                return false;
+            }
+        }
+
+        if let Some((max_requests, window)) = limits.max_per_window {
+            while self
+                .inbound_timestamps
+                .front()
+                .is_some_and(|accepted| now.duration_since(*accepted) >= window)
+            {
+                self.inbound_timestamps.pop_front();
+            }
+
+            if self.inbound_timestamps.len() >= max_requests {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 /// Request-response protocol.
@@ -151,7 +229,7 @@ pub(crate) struct RequestResponseProtocol {
     ///
     /// If the substream is rejected by the local node, the `oneshot::Sender` is dropped which
     /// notifies the future that the request should be rejected by closing the substream.
-    pending_outbound_responses: FuturesUnordered<BoxFuture<'static, ()>>,
+    pending_outbound_responses: FuturesUnordered<BoxFuture<'static, (PeerId, RequestId)>>,
 
     /// Pending inbound responses.
     pending_inbound: FuturesUnordered<BoxFuture<'static, PendingRequest>>,
@@ -163,7 +241,10 @@ pub(crate) struct RequestResponseProtocol {
     pending_inbound_requests: SubstreamSet<(PeerId, RequestId), Substream>,
 
     /// Pending dials for outbound requests.
-    pending_dials: HashMap<PeerId, RequestContext>,
+    pending_dials: HashMap<PeerId, Vec<RequestContext>>,
+
+    /// Outbound substreams whose requests were canceled before the substream was opened.
+    canceled_substreams: HashSet<SubstreamId>,
 
     /// TX channel for sending events to the user protocol.
     event_tx: Sender<InnerRequestResponseEvent>,
@@ -179,8 +260,38 @@ pub(crate) struct RequestResponseProtocol {
     /// Timeout for outbound requests.
     timeout: Duration,
 
+    /// Maximum size of a request.
+    max_request_size: usize,
+
+    /// Maximum size of a response.
+    max_response_size: usize,
+
     /// Maximum concurrent inbound requests, if specified.
     max_concurrent_inbound_requests: Option<usize>,
+
+    /// Are responses streamed in chunks.
+    streaming_responses: bool,
+
+    /// Per-peer limits for inbound requests.
+    inbound_limits: InboundRequestLimits,
+
+    /// Timers for processing queued inbound substreams of peers limited by the time window.
+    pending_quota_timers: FuturesUnordered<BoxFuture<'static, PeerId>>,
+
+    /// Pending deadlines of queued inbound substreams.
+    pending_queue_deadlines: FuturesUnordered<BoxFuture<'static, PeerId>>,
+
+    /// Requests sent to any of the candidate peers.
+    pending_any_requests: HashMap<RequestId, AnyRequestContext>,
+
+    /// Attempts of requests sent to any of the candidate peers, mapped to the request IDs
+    /// given to the user.
+    any_request_attempts: HashMap<RequestId, RequestId>,
+
+    /// Retries and hedging timers of requests sent to any of the candidate peers.
+    ///
+    /// Hedging timers contain the number of attempts started when the timer was scheduled.
+    pending_any_retries: FuturesUnordered<BoxFuture<'static, (RequestId, Option<usize>)>>,
 }
 
 impl RequestResponseProtocol {
@@ -190,17 +301,27 @@ impl RequestResponseProtocol {
             service,
             peers: HashMap::new(),
             timeout: config.timeout,
+            max_request_size: config.max_request_size,
+            max_response_size: config.max_response_size,
             next_request_id: config.next_request_id,
             event_tx: config.event_tx,
             command_rx: config.command_rx,
             protocol: config.protocol_name,
             pending_dials: HashMap::new(),
+            canceled_substreams: HashSet::new(),
             pending_outbound: HashMap::new(),
             pending_inbound: FuturesUnordered::new(),
             pending_outbound_cancels: HashMap::new(),
             pending_inbound_requests: SubstreamSet::new(),
             pending_outbound_responses: FuturesUnordered::new(),
             max_concurrent_inbound_requests: config.max_concurrent_inbound_request,
+            streaming_responses: config.streaming_responses,
+            inbound_limits: config.inbound_limits,
+            pending_quota_timers: FuturesUnordered::new(),
+            pending_queue_deadlines: FuturesUnordered::new(),
+            pending_any_requests: HashMap::new(),
+            any_request_attempts: HashMap::new(),
+            pending_any_retries: FuturesUnordered::new(),
         }
     }
 
@@ -223,11 +344,12 @@ impl RequestResponseProtocol {
             return Err(Error::PeerAlreadyExists(peer));
         };
 
-        match self.pending_dials.remove(&peer) {
-            None => {
-                entry.insert(PeerContext::new());
-            }
-            Some(context) => match self.service.open_substream(peer) {
+        let contexts = self.pending_dials.remove(&peer).unwrap_or_default();
+        let peer_context = entry.insert(PeerContext::new());
+        let mut failed = Vec::new();
+
+        for context in contexts {
+            match self.service.open_substream(peer) {
                 Ok(substream_id) => {
                     tracing::trace!(
                         target: LOG_TARGET,
@@ -238,19 +360,8 @@ impl RequestResponseProtocol {
                         "dial succeeded, open substream",
                     );
 
-                    entry.insert(PeerContext {
-                        active: HashSet::from_iter([context.request_id]),
-                        active_inbound: HashMap::new(),
-                    });
-                    self.pending_outbound.insert(
-                        substream_id,
-                        RequestContext::new(
-                            peer,
-                            context.request_id,
-                            context.request,
-                            context.fallback,
-                        ),
-                    );
+                    peer_context.active.insert(context.request_id);
+                    self.pending_outbound.insert(substream_id, context);
                 }
                 // only reason the substream would fail to open would be that the connection
                 // would've been reported to the protocol with enough delay that the keep-alive
@@ -266,15 +377,14 @@ impl RequestResponseProtocol {
                         "failed to open substream",
                     );
 
-                    return self
-                        .report_request_failure(
-                            peer,
-                            context.request_id,
-                            RequestResponseError::Rejected,
-                        )
-                        .await;
+                    failed.push(context.request_id);
                 }
-            },
+            }
+        }
+
+        for request_id in failed {
+            self.report_request_failure(peer, request_id, RequestResponseError::Rejected)
+                .await?;
         }
 
         Ok(())
@@ -297,12 +407,7 @@ impl RequestResponseProtocol {
         // sent failure events for all pending outbound requests
         for request_id in context.active {
             let _ = self
-                .event_tx
-                .send(InnerRequestResponseEvent::RequestFailed {
-                    peer,
-                    request_id,
-                    error: RequestResponseError::Rejected,
-                })
+                .report_request_failure(peer, request_id, RequestResponseError::Rejected)
                 .await;
         }
 
@@ -320,10 +425,24 @@ impl RequestResponseProtocol {
         mut substream: Substream,
         fallback_protocol: Option<ProtocolName>,
     ) -> crate::Result<()> {
+        if self.canceled_substreams.remove(&substream_id) {
+            tracing::trace!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                ?substream_id,
+                "substream opened for canceled request, close substream",
+            );
+
+            let _ = substream.close().await;
+            return Ok(());
+        }
+
         let Some(RequestContext {
             request_id,
             request,
             fallback,
+            timeout: request_timeout,
             ..
         }) = self.pending_outbound.remove(&substream_id)
         else {
@@ -355,9 +474,35 @@ impl RequestResponseProtocol {
             _ => request,
         };
 
-        let request_timeout = self.timeout;
+        if request.len() > self.max_request_size {
+            tracing::warn!(
+                target: LOG_TARGET,
+                ?peer,
+                protocol = %self.protocol,
+                ?request_id,
+                size = ?request.len(),
+                max_size = ?self.max_request_size,
+                "tried to send too large request",
+            );
+
+            self.pending_inbound.push(Box::pin(async move {
+                let _ = substream.close().await;
+                (
+                    peer,
+                    request_id,
+                    fallback_protocol,
+                    Err(RequestResponseError::TooLargePayload),
+                )
+            }));
+
+            return Ok(());
+        }
+
+        let max_response_size = self.max_response_size;
         let protocol = self.protocol.clone();
-        let (tx, rx) = oneshot::channel();
+        let streaming = self.streaming_responses;
+        let event_tx = self.event_tx.clone();
+        let (tx, mut rx) = oneshot::channel();
         self.pending_outbound_cancels.insert(request_id, tx);
 
         self.pending_inbound.push(Box::pin(async move {
@@ -390,6 +535,78 @@ impl RequestResponseProtocol {
                     fallback_protocol,
                     Err(RequestResponseError::NotConnected),
                 ),
+                Ok(Ok(_)) if streaming => loop {
+                    tokio::select! {
+                        _ = &mut rx => {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                %protocol,
+                                ?request_id,
+                                "request canceled",
+                            );
+
+                            let _ = substream.close().await;
+                            break (peer, request_id, fallback_protocol, Err(RequestResponseError::Canceled))
+                        }
+                        _ = sleep(request_timeout) => {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                %protocol,
+                                ?request_id,
+                                "request timed out",
+                            );
+
+                            let _ = substream.close().await;
+                            break (peer, request_id, fallback_protocol, Err(RequestResponseError::Timeout))
+                        }
+                        event = substream.next() => match event {
+                            Some(Ok(chunk)) if chunk.is_empty() => {
+                                break (peer, request_id, fallback_protocol, Ok(None))
+                            }
+                            Some(Ok(chunk)) if chunk.len() > max_response_size => {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?peer,
+                                    %protocol,
+                                    ?request_id,
+                                    size = ?chunk.len(),
+                                    "received too large response chunk",
+                                );
+
+                                let _ = substream.close().await;
+                                break (
+                                    peer,
+                                    request_id,
+                                    fallback_protocol,
+                                    Err(RequestResponseError::TooLargeResponse),
+                                )
+                            }
+                            // frame exceeded the maximum size of the substream codec
+                            Some(Err(Error::InvalidData)) => {
+                                let _ = substream.close().await;
+                                break (
+                                    peer,
+                                    request_id,
+                                    fallback_protocol,
+                                    Err(RequestResponseError::TooLargeResponse),
+                                )
+                            }
+                            Some(Ok(chunk)) => {
+                                let _ = event_tx
+                                    .send(InnerRequestResponseEvent::ResponseChunkReceived {
+                                        peer,
+                                        fallback: fallback_protocol.clone(),
+                                        request_id,
+                                        chunk: chunk.freeze().into(),
+                                    })
+                                    .await;
+                            }
+                            _ => break (peer, request_id, fallback_protocol, Err(RequestResponseError::Rejected)),
+                        }
+                    }
+                },
                 Ok(Ok(_)) => {
                     tokio::select! {
                         _ = rx => {
@@ -421,8 +638,36 @@ impl RequestResponseProtocol {
                             (peer, request_id, fallback_protocol, Err(RequestResponseError::Timeout))
                         }
                         event = substream.next() => match event {
+                            Some(Ok(response)) if response.len() > max_response_size => {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    ?peer,
+                                    %protocol,
+                                    ?request_id,
+                                    size = ?response.len(),
+                                    "received too large response",
+                                );
+
+                                let _ = substream.close().await;
+                                (
+                                    peer,
+                                    request_id,
+                                    fallback_protocol,
+                                    Err(RequestResponseError::TooLargeResponse),
+                                )
+                            }
                             Some(Ok(response)) => {
-                                (peer, request_id, fallback_protocol, Ok(response.freeze().into()))
+                                (peer, request_id, fallback_protocol, Ok(Some(response.freeze().into())))
+                            }
+                            // frame exceeded the maximum size of the substream codec
+                            Some(Err(Error::InvalidData)) => {
+                                let _ = substream.close().await;
+                                (
+                                    peer,
+                                    request_id,
+                                    fallback_protocol,
+                                    Err(RequestResponseError::TooLargeResponse),
+                                )
                             }
                             _ => (peer, request_id, fallback_protocol, Err(RequestResponseError::Rejected)),
                         }
@@ -492,17 +737,34 @@ impl RequestResponseProtocol {
             return Err(Error::InvalidData);
         };
 
+        if request.len() > self.max_request_size {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                %protocol,
+                ?request_id,
+                size = ?request.len(),
+                max_size = ?self.max_request_size,
+                "received too large request, rejecting",
+            );
+            let _ = substream.close().await;
+
+            return Err(Error::InvalidData);
+        }
+
         // once the request has been read from the substream, start a future which waits
         // for an input from the user.
         //
         // the input is either a response (succes) or rejection (failure) which is communicated
         // by sending the response over the `oneshot::Sender` or closing it, respectively.
         let timeout = self.timeout;
-        let (response_tx, rx): (
-            oneshot::Sender<(Vec<u8>, Option<channel::oneshot::Sender<()>>)>,
-            _,
-        ) = oneshot::channel();
+        let streaming = self.streaming_responses;
+        let max_response_size = self.max_response_size;
+        let (response_tx, rx): (oneshot::Sender<Response>, _) = oneshot::channel();
 
+        if let Some(context) = self.peers.get_mut(&peer) {
+            context.pending_responses.insert(request_id);
+        }
         self.pending_outbound_responses.push(Box::pin(async move {
             match rx.await {
                 Err(_) => {
@@ -515,7 +777,22 @@ impl RequestResponseProtocol {
                     );
                     let _ = substream.close().await;
                 }
-                Ok((response, mut feedback)) => {
+                Ok(Response::Single { response, .. }) if response.len() > max_response_size => {
+                    tracing::warn!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        %protocol,
+                        ?request_id,
+                        size = ?response.len(),
+                        ?max_response_size,
+                        "tried to send too large response, rejecting request",
+                    );
+                    let _ = substream.close().await;
+                }
+                Ok(Response::Single {
+                    response,
+                    mut feedback,
+                }) => {
                     tracing::trace!(
                         target: LOG_TARGET,
                         ?peer,
@@ -524,9 +801,23 @@ impl RequestResponseProtocol {
                         "send response",
                     );
 
-                    match tokio::time::timeout(timeout, substream.send_framed(response.into()))
-                        .await
-                    {
+                    let result = match streaming {
+                        false =>
+                            tokio::time::timeout(timeout, substream.send_framed(response.into()))
+                                .await,
+                        // send the response as a single chunk, followed by the end-of-stream marker
+                        true =>
+                            tokio::time::timeout(timeout, async {
+                                if !response.is_empty() {
+                                    substream.send_framed(response.into()).await?;
+                                }
+
+                                substream.send_framed(Bytes::new()).await
+                            })
+                            .await,
+                    };
+
+                    match result {
                         Err(_) => tracing::debug!(
                             target: LOG_TARGET,
                             ?peer,
@@ -547,7 +838,87 @@ impl RequestResponseProtocol {
                         ),
                     }
                 }
+                Ok(Response::Stream(_)) if !streaming => {
+                    tracing::warn!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        %protocol,
+                        ?request_id,
+                        "streaming responses not enabled for the protocol, rejecting request",
+                    );
+                    let _ = substream.close().await;
+                }
+                Ok(Response::Stream(mut chunks)) => loop {
+                    // `None` marks the end of the stream and is sent as an empty frame
+                    let Some(chunk) = chunks.recv().await else {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            %protocol,
+                            ?request_id,
+                            "response stream aborted",
+                        );
+                        let _ = substream.close().await;
+                        break;
+                    };
+                    let is_last = chunk.is_none();
+
+                    if chunk.as_ref().is_some_and(|chunk| chunk.len() > max_response_size) {
+                        tracing::warn!(
+                            target: LOG_TARGET,
+                            ?peer,
+                            %protocol,
+                            ?request_id,
+                            ?max_response_size,
+                            "tried to send too large response chunk, aborting response stream",
+                        );
+                        let _ = substream.close().await;
+                        break;
+                    }
+
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        %protocol,
+                        ?request_id,
+                        ?is_last,
+                        "send response chunk",
+                    );
+
+                    match tokio::time::timeout(
+                        timeout,
+                        substream.send_framed(chunk.unwrap_or_default().into()),
+                    )
+                    .await
+                    {
+                        Ok(Ok(_)) if is_last => break,
+                        Ok(Ok(_)) => {}
+                        Err(_) => {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                %protocol,
+                                ?request_id,
+                                "timed out while sending response chunk",
+                            );
+                            break;
+                        }
+                        Ok(Err(error)) => {
+                            tracing::trace!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                %protocol,
+                                ?request_id,
+                                ?error,
+                                "failed to send response chunk to peer",
+                            );
+                            break;
+                        }
+                    }
+                },
             }
+
+            (peer, request_id)
         }));
 
         self.event_tx
@@ -590,29 +961,136 @@ impl RequestResponseProtocol {
             }
         }
 
+        let context = self.peers.get_mut(&peer).ok_or(Error::PeerDoesntExist(peer))?;
+
+        // queued requests are processed first so a new request is only accepted if there are no
+        // queued requests and the peer is within its limits
+        if context.queued_inbound.is_empty()
+            && context.is_within_limits(&self.inbound_limits, Instant::now())
+        {
+            return self.accept_inbound_substream(peer, fallback, substream);
+        }
+
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?peer,
+            protocol = %self.protocol,
+            ?fallback,
+            overflow = ?self.inbound_limits.overflow,
+            "peer exceeded inbound request limits",
+        );
+
+        match self.inbound_limits.overflow {
+            OverflowBehavior::Queue {
+                max_queued,
+                max_wait,
+            } if context.queued_inbound.len() < max_queued => {
+                context
+                    .queued_inbound
+                    .push_back((fallback, substream, Instant::now() + max_wait));
+                self.pending_queue_deadlines.push(Box::pin(async move {
+                    sleep(max_wait).await;
+                    peer
+                }));
+                self.process_queued_inbound(peer);
+            }
+            _ => {
+                let _ = substream.close().await;
+            }
+        }
+
+        self.event_tx
+            .send(InnerRequestResponseEvent::InboundQuotaExceeded { peer })
+            .await
+            .map_err(From::from)
+    }
+
+    /// Accept inbound substream and start reading the request from it.
+    fn accept_inbound_substream(
+        &mut self,
+        peer: PeerId,
+        fallback: Option<ProtocolName>,
+        substream: Substream,
+    ) -> crate::Result<()> {
         // allocate ephemeral id for the inbound request and return it to the user protocol
         //
         // when user responds to the request, this is used to associate the response with the
         // correct substream.
         let request_id = self.next_request_id();
-        self.peers
-            .get_mut(&peer)
-            .ok_or(Error::PeerDoesntExist(peer))?
-            .active_inbound
-            .insert(request_id, fallback);
+        let context = self.peers.get_mut(&peer).ok_or(Error::PeerDoesntExist(peer))?;
+
+        if self.inbound_limits.max_per_window.is_some() {
+            context.inbound_timestamps.push_back(Instant::now());
+        }
+        context.active_inbound.insert(request_id, fallback);
         self.pending_inbound_requests.insert((peer, request_id), substream);
 
         Ok(())
     }
 
+    /// Accept queued inbound substreams of the peer for as long as it's within its limits.
+    ///
+    /// Substreams which have passed their deadline are dropped, rejecting the request. If the
+    /// peer is limited by the time window, a timer is started to process the queue again once the
+    /// oldest request falls out of the window.
+    fn process_queued_inbound(&mut self, peer: PeerId) {
+        loop {
+            let Some(context) = self.peers.get_mut(&peer) else {
+                return;
+            };
+
+            // all substreams wait for the same time so they expire in the order they were queued
+            let now = Instant::now();
+            while context.queued_inbound.front().is_some_and(|(_, _, deadline)| *deadline <= now) {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    protocol = %self.protocol,
+                    "queued inbound request expired, rejecting",
+                );
+
+                context.queued_inbound.pop_front();
+            }
+
+            if context.queued_inbound.is_empty() {
+                return;
+            }
+
+            if !context.is_within_limits(&self.inbound_limits, Instant::now()) {
+                let Some((max_requests, window)) = self.inbound_limits.max_per_window else {
+                    return;
+                };
+
+                if context.quota_timer_pending || context.inbound_timestamps.len() < max_requests {
+                    return;
+                }
+
+                if let Some(oldest) = context.inbound_timestamps.front() {
+                    let delay = (*oldest + window).saturating_duration_since(Instant::now());
+
+                    context.quota_timer_pending = true;
+                    self.pending_quota_timers.push(Box::pin(async move {
+                        sleep(delay).await;
+                        peer
+                    }));
+                }
+
+                return;
+            }
+
+            let (fallback, substream, _) =
+                context.queued_inbound.pop_front().expect("queue to be non-empty");
+            let _ = self.accept_inbound_substream(peer, fallback, substream);
+        }
+    }
+
     async fn on_dial_failure(&mut self, peer: PeerId) {
-        if let Some(context) = self.pending_dials.remove(&peer) {
-            tracing::debug!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "failed to dial peer");
+        let Some(contexts) = self.pending_dials.remove(&peer) else {
+            return;
+        };
+        tracing::debug!(target: LOG_TARGET, ?peer, protocol = %self.protocol, "failed to dial peer");
 
-            let _ = self
-                .peers
-                .get_mut(&peer)
-                .map(|peer_context| peer_context.active.remove(&context.request_id));
+        for context in contexts {
             let _ = self
                 .report_request_failure(peer, context.request_id, RequestResponseError::Rejected)
                 .await;
@@ -625,6 +1103,10 @@ impl RequestResponseProtocol {
         substream: SubstreamId,
         error: Error,
     ) -> crate::Result<()> {
+        if self.canceled_substreams.remove(&substream) {
+            return Ok(());
+        }
+
         let Some(RequestContext {
             request_id, peer, ..
         }) = self.pending_outbound.remove(&substream)
@@ -655,19 +1137,14 @@ impl RequestResponseProtocol {
             .get_mut(&peer)
             .map(|peer_context| peer_context.active.remove(&request_id));
 
-        self.event_tx
-            .send(InnerRequestResponseEvent::RequestFailed {
-                peer,
-                request_id,
-                error: match error {
-                    Error::NegotiationError(NegotiationError::MultistreamSelectError(
-                        MultistreamFailed,
-                    )) => RequestResponseError::UnsupportedProtocol,
-                    _ => RequestResponseError::Rejected,
-                },
-            })
-            .await
-            .map_err(From::from)
+        let error = match error {
+            Error::NegotiationError(NegotiationError::MultistreamSelectError(
+                MultistreamFailed,
+            )) => RequestResponseError::UnsupportedProtocol,
+            _ => RequestResponseError::Rejected,
+        };
+
+        self.report_request_failure(peer, request_id, error).await
     }
 
     /// Report request send failure to user.
@@ -677,6 +1154,10 @@ impl RequestResponseProtocol {
         request_id: RequestId,
         error: RequestResponseError,
     ) -> crate::Result<()> {
+        if let Some(any_request_id) = self.any_request_attempts.remove(&request_id) {
+            return self.on_any_request_failure(peer, any_request_id, request_id, error).await;
+        }
+
         self.event_tx
             .send(InnerRequestResponseEvent::RequestFailed {
                 peer,
@@ -695,6 +1176,7 @@ impl RequestResponseProtocol {
         request: Vec<u8>,
         dial_options: DialOptions,
         fallback: Option<(ProtocolName, Vec<u8>)>,
+        timeout: Option<Duration>,
     ) -> crate::Result<()> {
         tracing::trace!(
             target: LOG_TARGET,
@@ -702,8 +1184,10 @@ impl RequestResponseProtocol {
             protocol = %self.protocol,
             ?request_id,
             ?dial_options,
+            ?timeout,
             "send request to remote peer",
         );
+        let timeout = timeout.unwrap_or(self.timeout);
 
         let Some(context) = self.peers.get_mut(&peer) else {
             match dial_options {
@@ -725,40 +1209,54 @@ impl RequestResponseProtocol {
                         )
                         .await;
                 }
-                DialOptions::Dial => match self.service.dial(&peer) {
-                    Ok(_) => {
+                DialOptions::Dial => {
+                    let context = RequestContext::new(peer, request_id, request, fallback, timeout);
+
+                    if let Some(contexts) = self.pending_dials.get_mut(&peer) {
                         tracing::trace!(
                             target: LOG_TARGET,
                             ?peer,
                             protocol = %self.protocol,
                             ?request_id,
-                            "started dialing peer",
+                            "dial already in progress",
                         );
 
-                        self.pending_dials.insert(
-                            peer,
-                            RequestContext::new(peer, request_id, request, fallback),
-                        );
+                        contexts.push(context);
                         return Ok(());
                     }
-                    Err(error) => {
-                        tracing::debug!(
-                            target: LOG_TARGET,
-                            ?peer,
-                            protocol = %self.protocol,
-                            ?error,
-                            "failed to dial peer"
-                        );
 
-                        return self
-                            .report_request_failure(
-                                peer,
-                                request_id,
-                                RequestResponseError::Rejected,
-                            )
-                            .await;
+                    match self.service.dial(&peer) {
+                        Ok(_) => {
+                            tracing::trace!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                protocol = %self.protocol,
+                                ?request_id,
+                                "started dialing peer",
+                            );
+
+                            self.pending_dials.insert(peer, vec![context]);
+                            return Ok(());
+                        }
+                        Err(error) => {
+                            tracing::debug!(
+                                target: LOG_TARGET,
+                                ?peer,
+                                protocol = %self.protocol,
+                                ?error,
+                                "failed to dial peer"
+                            );
+
+                            return self
+                                .report_request_failure(
+                                    peer,
+                                    request_id,
+                                    RequestResponseError::Rejected,
+                                )
+                                .await;
+                        }
                     }
-                },
+                }
             }
         };
 
@@ -771,7 +1269,7 @@ impl RequestResponseProtocol {
 
                 self.pending_outbound.insert(
                     substream_id,
-                    RequestContext::new(peer, request_id, request, fallback),
+                    RequestContext::new(peer, request_id, request, fallback, timeout),
                 );
 
                 Ok(())
@@ -798,7 +1296,7 @@ impl RequestResponseProtocol {
         peer: PeerId,
         request_id: RequestId,
         fallback: Option<ProtocolName>,
-        message: Result<Vec<u8>, RequestResponseError>,
+        message: Result<Option<Vec<u8>>, RequestResponseError>,
     ) -> crate::Result<()> {
         if !self
             .peers
@@ -817,13 +1315,24 @@ impl RequestResponseProtocol {
             return Err(Error::InvalidState);
         }
 
+        if let Some(any_request_id) = self.any_request_attempts.remove(&request_id) {
+            return match message {
+                Ok(Some(response)) =>
+                    self.on_any_request_response(peer, any_request_id, fallback, response).await,
+                Ok(None) | Err(RequestResponseError::Canceled) => Ok(()),
+                Err(error) =>
+                    self.on_any_request_failure(peer, any_request_id, request_id, error).await,
+            };
+        }
+
         let event = match message {
-            Ok(response) => InnerRequestResponseEvent::ResponseReceived {
+            Ok(Some(response)) => InnerRequestResponseEvent::ResponseReceived {
                 peer,
                 request_id,
                 response,
                 fallback,
             },
+            Ok(None) => InnerRequestResponseEvent::ResponseStreamFinished { peer, request_id },
             Err(error) => match error {
                 RequestResponseError::Canceled => {
                     tracing::debug!(
@@ -833,7 +1342,7 @@ impl RequestResponseProtocol {
                         ?request_id,
                         "request canceled by local node",
                     );
-                    return Ok(());
+                    InnerRequestResponseEvent::RequestCanceled { request_id }
                 }
                 error => InnerRequestResponseEvent::RequestFailed {
                     peer,
@@ -846,10 +1355,234 @@ impl RequestResponseProtocol {
         self.event_tx.send(event).await.map_err(From::from)
     }
 
+    /// Send request to any of the candidate peers.
+    async fn on_send_request_to_any(
+        &mut self,
+        peers: Vec<PeerId>,
+        request_id: RequestId,
+        request: Vec<u8>,
+        policy: RetryPolicy,
+    ) -> crate::Result<()> {
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peers,
+            protocol = %self.protocol,
+            ?request_id,
+            ?policy,
+            "send request to any peer",
+        );
+
+        let Some(peer) = peers.first().copied() else {
+            return Err(Error::InsufficientPeers);
+        };
+
+        if self.streaming_responses {
+            tracing::warn!(
+                target: LOG_TARGET,
+                protocol = %self.protocol,
+                ?request_id,
+                "streaming responses not supported when sending request to any peer",
+            );
+
+            return self
+                .report_request_failure(peer, request_id, RequestResponseError::Rejected)
+                .await;
+        }
+
+        self.pending_any_requests.insert(
+            request_id,
+            AnyRequestContext {
+                peers,
+                request,
+                policy,
+                attempts: 0usize,
+                active: HashSet::new(),
+            },
+        );
+
+        self.on_any_request_attempt(request_id, None).await
+    }
+
+    /// Start a new attempt for a request sent to any of the candidate peers.
+    ///
+    /// `launched` is `Some` if the attempt is started by a hedging timer and contains the number
+    /// of attempts started when the timer was scheduled.
+    async fn on_any_request_attempt(
+        &mut self,
+        request_id: RequestId,
+        launched: Option<usize>,
+    ) -> crate::Result<()> {
+        let attempt_id = self.next_request_id();
+        let Some(context) = self.pending_any_requests.get_mut(&request_id) else {
+            return Ok(());
+        };
+
+        // hedging timer is stale since other attempts have been started since it was scheduled
+        if launched.is_some_and(|launched| launched != context.attempts) {
+            return Ok(());
+        }
+
+        if context.attempts >= context.policy.max_attempts {
+            return Ok(());
+        }
+
+        let peer = context.peers[context.attempts % context.peers.len()];
+        context.attempts += 1;
+        context.active.insert(attempt_id);
+
+        if let RetryStrategy::Hedged { delay } = context.policy.strategy {
+            if context.attempts < context.policy.max_attempts {
+                let attempts = context.attempts;
+
+                self.pending_any_retries.push(Box::pin(async move {
+                    sleep(delay).await;
+                    (request_id, Some(attempts))
+                }));
+            }
+        }
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            protocol = %self.protocol,
+            ?request_id,
+            ?attempt_id,
+            attempt = ?context.attempts,
+            "send request attempt",
+        );
+
+        let request = context.request.clone();
+        let timeout = context.policy.timeout;
+        let dial_options = context.policy.dial_options;
+        self.any_request_attempts.insert(attempt_id, request_id);
+
+        self.on_send_request(peer, attempt_id, request, dial_options, None, timeout)
+            .await
+    }
+
+    /// Attempt of a request sent to any of the candidate peers failed.
+    ///
+    /// The request is retried if the error allows it and the attempt budget isn't exhausted.
+    /// Otherwise the request is failed once all attempts in flight have concluded.
+    async fn on_any_request_failure(
+        &mut self,
+        peer: PeerId,
+        request_id: RequestId,
+        attempt_id: RequestId,
+        error: RequestResponseError,
+    ) -> crate::Result<()> {
+        let Some(context) = self.pending_any_requests.get_mut(&request_id) else {
+            return Ok(());
+        };
+        context.active.remove(&attempt_id);
+
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?peer,
+            protocol = %self.protocol,
+            ?request_id,
+            ?attempt_id,
+            ?error,
+            "request attempt failed",
+        );
+
+        if matches!(
+            error,
+            RequestResponseError::Timeout
+                | RequestResponseError::NotConnected
+                | RequestResponseError::Rejected
+        ) {
+            if context.attempts < context.policy.max_attempts {
+                self.pending_any_retries
+                    .push(Box::pin(futures::future::ready((request_id, None))));
+                return Ok(());
+            }
+
+            if !context.active.is_empty() {
+                return Ok(());
+            }
+        }
+
+        self.cancel_any_request(request_id);
+        self.event_tx
+            .send(InnerRequestResponseEvent::RequestFailed {
+                peer,
+                request_id,
+                error,
+            })
+            .await
+            .map_err(From::from)
+    }
+
+    /// Response received to a request sent to any of the candidate peers.
+    async fn on_any_request_response(
+        &mut self,
+        peer: PeerId,
+        request_id: RequestId,
+        fallback: Option<ProtocolName>,
+        response: Vec<u8>,
+    ) -> crate::Result<()> {
+        if !self.pending_any_requests.contains_key(&request_id) {
+            return Ok(());
+        }
+
+        self.cancel_any_request(request_id);
+        self.event_tx
+            .send(InnerRequestResponseEvent::ResponseReceived {
+                peer,
+                request_id,
+                response,
+                fallback,
+            })
+            .await
+            .map_err(From::from)
+    }
+
+    /// Remove request sent to any of the candidate peers and cancel its attempts in flight.
+    ///
+    /// Attempts waiting for a dial or for their substream to open are dropped immediately.
+    /// Results of attempts which couldn't be canceled are ignored once they conclude.
+    fn cancel_any_request(&mut self, request_id: RequestId) {
+        let Some(context) = self.pending_any_requests.remove(&request_id) else {
+            return;
+        };
+
+        for attempt_id in context.active {
+            if let Some(tx) = self.pending_outbound_cancels.remove(&attempt_id) {
+                let _ = tx.send(());
+                continue;
+            }
+            self.any_request_attempts.remove(&attempt_id);
+
+            self.pending_dials.retain(|_, contexts| {
+                contexts.retain(|context| context.request_id != attempt_id);
+                !contexts.is_empty()
+            });
+
+            let substream_id = self.pending_outbound.iter().find_map(|(substream_id, context)| {
+                (context.request_id == attempt_id).then_some(*substream_id)
+            });
+
+            if let Some(substream_id) = substream_id {
+                if let Some(context) = self.pending_outbound.remove(&substream_id) {
+                    if let Some(peer_context) = self.peers.get_mut(&context.peer) {
+                        peer_context.active.remove(&attempt_id);
+                    }
+                }
+                self.canceled_substreams.insert(substream_id);
+            }
+        }
+    }
+
     /// Cancel outbound request.
     async fn on_cancel_request(&mut self, request_id: RequestId) -> crate::Result<()> {
         tracing::trace!(target: LOG_TARGET, protocol = %self.protocol, ?request_id, "cancel outbound request");
 
+        if self.pending_any_requests.contains_key(&request_id) {
+            self.cancel_any_request(request_id);
+            return Ok(());
+        }
+
         match self.pending_outbound_cancels.remove(&request_id) {
             Some(tx) => tx.send(()).map_err(|_| Error::SubstreamDoesntExist),
             None => {
@@ -933,7 +1666,34 @@ impl RequestResponseProtocol {
 
                     self.pending_outbound_cancels.remove(&request_id);
                 }
-                _ = self.pending_outbound_responses.next(), if !self.pending_outbound_responses.is_empty() => {}
+                (peer, request_id) = self.pending_outbound_responses.select_next_some(), if !self.pending_outbound_responses.is_empty() => {
+                    if let Some(context) = self.peers.get_mut(&peer) {
+                        context.pending_responses.remove(&request_id);
+                    }
+
+                    self.process_queued_inbound(peer);
+                }
+                (request_id, launched) = self.pending_any_retries.select_next_some(), if !self.pending_any_retries.is_empty() => {
+                    if let Err(error) = self.on_any_request_attempt(request_id, launched).await {
+                        tracing::debug!(
+                            target: LOG_TARGET,
+                            protocol = %self.protocol,
+                            ?request_id,
+                            ?error,
+                            "failed to send request attempt",
+                        );
+                    }
+                }
+                peer = self.pending_quota_timers.select_next_some(), if !self.pending_quota_timers.is_empty() => {
+                    if let Some(context) = self.peers.get_mut(&peer) {
+                        context.quota_timer_pending = false;
+                    }
+
+                    self.process_queued_inbound(peer);
+                }
+                peer = self.pending_queue_deadlines.select_next_some(), if !self.pending_queue_deadlines.is_empty() => {
+                    self.process_queued_inbound(peer);
+                }
                 event = self.pending_inbound_requests.next() => match event {
                     Some(((peer, request_id), message)) => {
                         if let Err(error) = self.on_inbound_request(peer, request_id, message).await {
@@ -945,6 +1705,9 @@ impl RequestResponseProtocol {
                                 ?error,
                                 "failed to handle inbound request",
                             );
+
+                            // request was dropped so the peer may be within its limits again
+                            self.process_queued_inbound(peer);
                         }
                     }
                     None => return,
@@ -955,8 +1718,8 @@ impl RequestResponseProtocol {
                         return
                     }
                     Some(command) => match command {
-                        RequestResponseCommand::SendRequest { peer, request_id, request, dial_options } => {
-                            if let Err(error) = self.on_send_request(peer, request_id, request, dial_options, None).await {
+                        RequestResponseCommand::SendRequest { peer, request_id, request, dial_options, timeout } => {
+                            if let Err(error) = self.on_send_request(peer, request_id, request, dial_options, None, timeout).await {
                                 tracing::debug!(
                                     target: LOG_TARGET,
                                     ?peer,
@@ -967,6 +1730,17 @@ impl RequestResponseProtocol {
                                 );
                             }
                         }
+                        RequestResponseCommand::SendRequestToAny { peers, request_id, request, policy } => {
+                            if let Err(error) = self.on_send_request_to_any(peers, request_id, request, policy).await {
+                                tracing::debug!(
+                                    target: LOG_TARGET,
+                                    protocol = %self.protocol,
+                                    ?request_id,
+                                    ?error,
+                                    "failed to send request to any peer",
+                                );
+                            }
+                        }
                         RequestResponseCommand::CancelRequest { request_id } => {
                             if let Err(error) = self.on_cancel_request(request_id).await {
                                 tracing::debug!(
@@ -978,8 +1752,11 @@ impl RequestResponseProtocol {
                                 );
                             }
                         }
-                        RequestResponseCommand::SendRequestWithFallback { peer, request_id, request, fallback, dial_options } => {
-                            if let Err(error) = self.on_send_request(peer, request_id, request, dial_options, Some(fallback)).await {
+                        RequestResponseCommand::ReportPeer { peer, change } => {
+                            self.service.report_peer(peer, change).await;
+                        }
+                        RequestResponseCommand::SendRequestWithFallback { peer, request_id, request, fallback, dial_options, timeout } => {
+                            if let Err(error) = self.on_send_request(peer, request_id, request, dial_options, Some(fallback), timeout).await {
                                 tracing::debug!(
                                     target: LOG_TARGET,
                                     ?peer,
@@ -160,7 +160,12 @@ async fn substream_event_for_unknown_peer() {
     assert!(protocol.peers.contains_key(&peer));
 
     match protocol
-        .on_substream_event(peer, RequestId::from(1337usize), None, Ok(vec![13, 37]))
+        .on_substream_event(
+            peer,
+            RequestId::from(1337usize),
+            None,
+            Ok(Some(vec![13, 37])),
+        )
         .await
     {
         Err(Error::InvalidState) => {}
@@ -277,6 +282,7 @@ async fn request_failure_reported_once() {
             vec![1, 2, 3, 4],
             DialOptions::Reject,
             None,
+            None,
         )
         .await
         .unwrap();
@@ -304,3 +310,18 @@ async fn request_failure_reported_once() {
     })
     .await;
 }
+
+#[test]
+fn single_size_limit_applies_to_requests_and_responses() {
+    let (config, _handle) = ConfigBuilder::new(ProtocolName::from("/req/1"))
+        .with_max_request_size(128)
+        .build();
+    assert_eq!(config.max_request_size, 128);
+    assert_eq!(config.max_response_size, 128);
+
+    let (config, _handle) = ConfigBuilder::new(ProtocolName::from("/req/1"))
+        .with_max_response_size(256)
+        .build();
+    assert_eq!(config.max_request_size, 256);
+    assert_eq!(config.max_response_size, 256);
+}
@@ -0,0 +1,531 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Typed request-response handle.
+//!
+//! [`TypedRequestResponseHandle`] wraps a [`RequestResponseHandle`] and encodes outbound and
+//! decodes inbound messages using a [`MessageCodec`], allowing the protocol implementation to
+//! deal with its own request and response types instead of `Vec<u8>`.
+//!
+//! [`ProtobufCodec`] is always available. `ScaleCodec` requires the `scale-codec` feature and
+//! `JsonCodec` requires the `json-codec` feature.
+
+use crate::{
+    protocol::{
+        request_response::handle::{
+            DialOptions, RequestResponseError, RequestResponseEvent, RequestResponseHandle,
+            RetryPolicy,
+        },
+        ReputationChange,
+    },
+    types::{protocol::ProtocolName, RequestId},
+    PeerId,
+};
+
+use futures::{channel, Stream};
+
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::request-response::typed";
+
+/// Codec for encoding and decoding request-response messages of type `T`.
+pub trait MessageCodec<T> {
+    /// Encode `message` into bytes.
+    fn encode(message: &T) -> crate::Result<Vec<u8>>;
+
+    /// Decode message from `bytes`.
+    fn decode(bytes: &[u8]) -> crate::Result<T>;
+}
+
+/// Protobuf codec for types implementing [`prost::Message`].
+#[derive(Debug)]
+pub struct ProtobufCodec;
+
+impl<T: prost::Message + Default> MessageCodec<T> for ProtobufCodec {
+    fn encode(message: &T) -> crate::Result<Vec<u8>> {
+        Ok(message.encode_to_vec())
+    }
+
+    fn decode(bytes: &[u8]) -> crate::Result<T> {
+        T::decode(bytes).map_err(From::from)
+    }
+}
+
+/// SCALE codec for types implementing [`parity_scale_codec::Encode`] and
+/// [`parity_scale_codec::Decode`].
+///
+/// Decoding fails if the message has trailing bytes.
+#[cfg(feature = "scale-codec")]
+#[derive(Debug)]
+pub struct ScaleCodec;
+
+#[cfg(feature = "scale-codec")]
+impl<T: parity_scale_codec::Encode + parity_scale_codec::Decode> MessageCodec<T> for ScaleCodec {
+    fn encode(message: &T) -> crate::Result<Vec<u8>> {
+        Ok(message.encode())
+    }
+
+    fn decode(bytes: &[u8]) -> crate::Result<T> {
+        <T as parity_scale_codec::DecodeAll>::decode_all(&mut &bytes[..]).map_err(From::from)
+    }
+}
+
+/// JSON codec for types implementing [`serde::Serialize`] and [`serde::Deserialize`].
+#[cfg(feature = "json-codec")]
+#[derive(Debug)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json-codec")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> MessageCodec<T> for JsonCodec {
+    fn encode(message: &T) -> crate::Result<Vec<u8>> {
+        serde_json::to_vec(message).map_err(From::from)
+    }
+
+    fn decode(bytes: &[u8]) -> crate::Result<T> {
+        serde_json::from_slice(bytes).map_err(From::from)
+    }
+}
+
+/// Typed request-response events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedRequestResponseEvent<Req, Resp> {
+    /// Request received from remote
+    RequestReceived {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Fallback protocol, if the substream was negotiated using a fallback.
+        fallback: Option<ProtocolName>,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Received request.
+        request: Req,
+    },
+
+    /// Response received.
+    ResponseReceived {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Fallback protocol, if the substream was negotiated using a fallback.
+        fallback: Option<ProtocolName>,
+
+        /// Received response.
+        response: Resp,
+    },
+
+    /// Response chunk received.
+    ///
+    /// If a chunk fails to decode, the request is reported as failed using
+    /// [`TypedRequestResponseEvent::RequestFailed`] and the rest of the stream, including its end,
+    /// is not reported.
+    ResponseChunkReceived {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Fallback protocol, if the substream was negotiated using a fallback.
+        fallback: Option<ProtocolName>,
+
+        /// Received chunk.
+        chunk: Resp,
+    },
+
+    /// All chunks of the response have been received.
+    ResponseStreamFinished {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+    },
+
+    /// Peer sent an inbound request which exceeded its limits.
+    InboundQuotaExceeded {
+        /// Peer Id.
+        peer: PeerId,
+    },
+
+    /// Request failed.
+    ///
+    /// If an inbound request couldn't be decoded, it's rejected and reported using this event
+    /// with [`RequestResponseError::DecodeFailure`] and the ID of the inbound request.
+    RequestFailed {
+        /// Peer Id.
+        peer: PeerId,
+
+        /// Request ID.
+        request_id: RequestId,
+
+        /// Request-response error.
+        error: RequestResponseError,
+    },
+}
+
+/// Request-response handle which encodes and decodes messages using codec `C`.
+///
+/// Requests are of type `Req` and responses of type `Resp`. Received messages which fail to
+/// decode are reported as [`RequestResponseError::DecodeFailure`].
+pub struct TypedRequestResponseHandle<Req, Resp, C> {
+    /// Inner request-response handle.
+    handle: RequestResponseHandle,
+
+    /// Marker for message types and codec.
+    _marker: PhantomData<fn() -> (Req, Resp, C)>,
+}
+
+impl<Req, Resp, C> TypedRequestResponseHandle<Req, Resp, C>
+where
+    C: MessageCodec<Req> + MessageCodec<Resp>,
+{
+    /// Create new [`TypedRequestResponseHandle`] from [`RequestResponseHandle`].
+    pub fn new(handle: RequestResponseHandle) -> Self {
+        Self {
+            handle,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consume `self` and return the inner [`RequestResponseHandle`].
+    pub fn into_inner(self) -> RequestResponseHandle {
+        self.handle
+    }
+
+    /// Reject an inbound request.
+    pub fn reject_request(&mut self, request_id: RequestId) {
+        self.handle.reject_request(request_id)
+    }
+
+    /// Cancel an outbound request.
+    pub async fn cancel_request(&mut self, request_id: RequestId) {
+        self.handle.cancel_request(request_id).await
+    }
+
+    /// Report reputation change for `peer`.
+    pub async fn report_peer(&self, peer: PeerId, change: ReputationChange) {
+        self.handle.report_peer(peer, change).await
+    }
+
+    /// Encode `request` and send it to remote peer.
+    pub async fn send_request(
+        &mut self,
+        peer: PeerId,
+        request: &Req,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        let request = <C as MessageCodec<Req>>::encode(request)?;
+
+        self.handle.send_request(peer, request, dial_options).await
+    }
+
+    /// Encode `request` and attempt to send it to remote peer and if the channel is clogged,
+    /// return `Error::ChannelClogged`.
+    pub fn try_send_request(
+        &mut self,
+        peer: PeerId,
+        request: &Req,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        let request = <C as MessageCodec<Req>>::encode(request)?;
+
+        self.handle.try_send_request(peer, request, dial_options)
+    }
+
+    /// Encode `request` and send it to remote peer with a custom timeout.
+    pub async fn send_request_with_timeout(
+        &mut self,
+        peer: PeerId,
+        request: &Req,
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        let request = <C as MessageCodec<Req>>::encode(request)?;
+
+        self.handle
+            .send_request_with_timeout(peer, request, timeout, dial_options)
+            .await
+    }
+
+    /// Encode `request` and the fallback request, and send them to remote peer with a custom
+    /// timeout.
+    ///
+    /// See [`RequestResponseHandle::send_request_with_fallback_and_timeout()`] for more details.
+    pub async fn send_request_with_fallback_and_timeout(
+        &mut self,
+        peer: PeerId,
+        request: &Req,
+        fallback: (ProtocolName, &Req),
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        let request = <C as MessageCodec<Req>>::encode(request)?;
+        let fallback = (fallback.0, <C as MessageCodec<Req>>::encode(fallback.1)?);
+
+        self.handle
+            .send_request_with_fallback_and_timeout(peer, request, fallback, timeout, dial_options)
+            .await
+    }
+
+    /// Encode `request` and the fallback request, and attempt to send them to remote peer with a
+    /// custom timeout and if the channel is clogged, return `Error::ChannelClogged`.
+    pub fn try_send_request_with_fallback_and_timeout(
+        &mut self,
+        peer: PeerId,
+        request: &Req,
+        fallback: (ProtocolName, &Req),
+        timeout: Duration,
+        dial_options: DialOptions,
+    ) -> crate::Result<RequestId> {
+        let request = <C as MessageCodec<Req>>::encode(request)?;
+        let fallback = (fallback.0, <C as MessageCodec<Req>>::encode(fallback.1)?);
+
+        self.handle.try_send_request_with_fallback_and_timeout(
+            peer,
+            request,
+            fallback,
+            timeout,
+            dial_options,
+        )
+    }
+
+    /// Encode `request` and send it to any of the candidate `peers`.
+    ///
+    /// See [`RequestResponseHandle::send_request_to_any()`] for more details.
+    pub async fn send_request_to_any(
+        &mut self,
+        peers: Vec<PeerId>,
+        request: &Req,
+        policy: RetryPolicy,
+    ) -> crate::Result<RequestId> {
+        let request = <C as MessageCodec<Req>>::encode(request)?;
+
+        self.handle.send_request_to_any(peers, request, policy).await
+    }
+
+    /// Encode `response` and send it to remote peer.
+    pub fn send_response(&mut self, request_id: RequestId, response: &Resp) -> crate::Result<()> {
+        let response = <C as MessageCodec<Resp>>::encode(response)?;
+
+        self.handle.send_response(request_id, response);
+        Ok(())
+    }
+
+    /// Encode `response` and send it to remote peer with feedback.
+    ///
+    /// See [`RequestResponseHandle::send_response_with_feedback()`] for more details.
+    pub fn send_response_with_feedback(
+        &mut self,
+        request_id: RequestId,
+        response: &Resp,
+        feedback: channel::oneshot::Sender<()>,
+    ) -> crate::Result<()> {
+        let response = <C as MessageCodec<Resp>>::encode(response)?;
+
+        self.handle.send_response_with_feedback(request_id, response, feedback);
+        Ok(())
+    }
+}
+
+impl<Req, Resp, C> Stream for TypedRequestResponseHandle<Req, Resp, C>
+where
+    C: MessageCodec<Req> + MessageCodec<Resp>,
+{
+    type Item = TypedRequestResponseEvent<Req, Resp>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let event = match futures::ready!(Pin::new(&mut self.handle).poll_next(cx)) {
+            None => return Poll::Ready(None),
+            Some(event) => event,
+        };
+
+        Poll::Ready(Some(match event {
+            RequestResponseEvent::RequestReceived {
+                peer,
+                fallback,
+                request_id,
+                request,
+            } => match <C as MessageCodec<Req>>::decode(&request) {
+                Ok(request) => TypedRequestResponseEvent::RequestReceived {
+                    peer,
+                    fallback,
+                    request_id,
+                    request,
+                },
+                Err(error) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?request_id,
+                        ?error,
+                        "failed to decode inbound request",
+                    );
+
+                    self.handle.reject_request(request_id);
+                    TypedRequestResponseEvent::RequestFailed {
+                        peer,
+                        request_id,
+                        error: RequestResponseError::DecodeFailure,
+                    }
+                }
+            },
+            RequestResponseEvent::ResponseReceived {
+                peer,
+                request_id,
+                fallback,
+                response,
+            } => match <C as MessageCodec<Resp>>::decode(&response) {
+                Ok(response) => TypedRequestResponseEvent::ResponseReceived {
+                    peer,
+                    request_id,
+                    fallback,
+                    response,
+                },
+                Err(error) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?request_id,
+                        ?error,
+                        "failed to decode response",
+                    );
+
+                    TypedRequestResponseEvent::RequestFailed {
+                        peer,
+                        request_id,
+                        error: RequestResponseError::DecodeFailure,
+                    }
+                }
+            },
+            RequestResponseEvent::ResponseChunkReceived {
+                peer,
+                request_id,
+                fallback,
+                chunk,
+            } => match <C as MessageCodec<Resp>>::decode(&chunk) {
+                Ok(chunk) => TypedRequestResponseEvent::ResponseChunkReceived {
+                    peer,
+                    request_id,
+                    fallback,
+                    chunk,
+                },
+                Err(error) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?peer,
+                        ?request_id,
+                        ?error,
+                        "failed to decode response chunk",
+                    );
+
+                    // the rest of the stream is of no use so stop the remote peer from sending it
+                    self.handle.discard_request(request_id);
+                    TypedRequestResponseEvent::RequestFailed {
+                        peer,
+                        request_id,
+                        error: RequestResponseError::DecodeFailure,
+                    }
+                }
+            },
+            RequestResponseEvent::ResponseStreamFinished { peer, request_id } =>
+                TypedRequestResponseEvent::ResponseStreamFinished { peer, request_id },
+            RequestResponseEvent::InboundQuotaExceeded { peer } =>
+                TypedRequestResponseEvent::InboundQuotaExceeded { peer },
+            RequestResponseEvent::RequestFailed {
+                peer,
+                request_id,
+                error,
+            } => TypedRequestResponseEvent::RequestFailed {
+                peer,
+                request_id,
+                error,
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn protobuf_codec() {
+        let encoded =
+            <ProtobufCodec as MessageCodec<String>>::encode(&"hello".to_string()).unwrap();
+        let decoded: String = <ProtobufCodec as MessageCodec<String>>::decode(&encoded).unwrap();
+        assert_eq!(decoded, "hello");
+
+        assert!(std::matches!(
+            <ProtobufCodec as MessageCodec<String>>::decode(&[0xff, 0xff]),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[cfg(feature = "scale-codec")]
+    #[test]
+    fn scale_codec() {
+        let message = (1337u32, vec![1u8, 2, 3]);
+        let encoded = <ScaleCodec as MessageCodec<(u32, Vec<u8>)>>::encode(&message).unwrap();
+        let decoded = <ScaleCodec as MessageCodec<(u32, Vec<u8>)>>::decode(&encoded).unwrap();
+        assert_eq!(decoded, message);
+
+        assert!(std::matches!(
+            <ScaleCodec as MessageCodec<(u32, Vec<u8>)>>::decode(&[1, 2]),
+            Err(Error::ParseError(_))
+        ));
+
+        // trailing bytes are rejected
+        let mut encoded = encoded;
+        encoded.push(0u8);
+        assert!(std::matches!(
+            <ScaleCodec as MessageCodec<(u32, Vec<u8>)>>::decode(&encoded),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[cfg(feature = "json-codec")]
+    #[test]
+    fn json_codec() {
+        let message = vec!["hello".to_string(), "world".to_string()];
+        let encoded = <JsonCodec as MessageCodec<Vec<String>>>::encode(&message).unwrap();
+        assert_eq!(encoded, br#"["hello","world"]"#);
+
+        let decoded = <JsonCodec as MessageCodec<Vec<String>>>::decode(&encoded).unwrap();
+        assert_eq!(decoded, message);
+
+        assert!(std::matches!(
+            <JsonCodec as MessageCodec<Vec<String>>>::decode(b"{"),
+            Err(Error::ParseError(_))
+        ));
+    }
+}
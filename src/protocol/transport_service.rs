@@ -21,7 +21,10 @@
 use crate::{
     error::Error,
     protocol::{connection::ConnectionHandle, InnerTransportEvent, TransportEvent},
-    transport::{manager::TransportManagerHandle, Endpoint},
+    transport::{
+        manager::{ReputationChange, TransportManagerHandle},
+        Endpoint,
+    },
     types::{protocol::ProtocolName, ConnectionId, SubstreamId},
     PeerId, DEFAULT_CHANNEL_SIZE,
 };
@@ -293,7 +296,31 @@ impl TransportService {
     ///
     /// The list is filtered for duplicates and unsupported transports.
     pub fn add_known_address(&mut self, peer: &PeerId, addresses: impl Iterator<Item = Multiaddr>) {
-        let addresses: HashSet<Multiaddr> = addresses
+        let addresses = Self::with_peer_id(peer, addresses);
+
+        self.transport_handle.add_known_address(peer, addresses.into_iter());
+    }
+
+    /// Add one or more addresses for `peer` that were certified by a signed peer record.
+    ///
+    /// Certified addresses are preferred over addresses added with
+    /// [`TransportService::add_known_address()`] when dialing `peer`.
+    pub fn add_certified_address(
+        &mut self,
+        peer: &PeerId,
+        addresses: impl Iterator<Item = Multiaddr>,
+    ) {
+        let addresses = Self::with_peer_id(peer, addresses);
+
+        self.transport_handle.add_certified_address(peer, addresses.into_iter());
+    }
+
+    /// Append `peer` to each address that doesn't contain a `PeerId` and remove duplicates.
+    fn with_peer_id(
+        peer: &PeerId,
+        addresses: impl Iterator<Item = Multiaddr>,
+    ) -> HashSet<Multiaddr> {
+        addresses
             .filter_map(|address| {
                 if !std::matches!(address.iter().last(), Some(Protocol::P2p(_))) {
                     Some(address.with(Protocol::P2p(Multihash::from_bytes(&peer.to_bytes()).ok()?)))
@@ -301,9 +328,7 @@ impl TransportService {
                     Some(address)
                 }
             })
-            .collect();
-
-        self.transport_handle.add_known_address(peer, addresses.into_iter());
+            .collect()
     }
 
     /// Open substream to `peer`.
@@ -337,6 +362,27 @@ impl TransportService {
             .map(|_| substream_id)
     }
 
+    /// Report reputation change for `peer`.
+    ///
+    /// The reputation is shared by all installed protocols and if it drops below the ban
+    /// threshold, the peer is disconnected and banned.
+    pub async fn report_peer(&self, peer: PeerId, change: ReputationChange) {
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            protocol = %self.protocol,
+            ?change,
+            "report peer",
+        );
+
+        self.transport_handle.report_peer(peer, change).await;
+    }
+
+    /// Get current reputation of `peer`.
+    pub fn reputation(&self, peer: &PeerId) -> i32 {
+        self.transport_handle.reputation(peer)
+    }
+
     /// Forcibly close the connection, even if other protocols have substreams open over it.
     pub fn force_close(&mut self, peer: PeerId) -> crate::Result<()> {
         let connection =
@@ -431,6 +477,7 @@ mod tests {
             cmd_tx,
             HashSet::new(),
             Default::default(),
+            Default::default(),
         );
 
         let (service, sender) = TransportService::new(
@@ -860,4 +907,25 @@ mod tests {
             Err(_) => {}
         }
     }
+
+    #[tokio::test]
+    async fn peer_banned_after_fatal_report() {
+        let (service, _sender, mut cmd_rx) = transport_service();
+        let peer = PeerId::random();
+
+        service.report_peer(peer, ReputationChange::new(-100, "misbehaved")).await;
+        assert_eq!(service.reputation(&peer), -100);
+        assert!(cmd_rx.try_recv().is_err());
+
+        service.report_peer(peer, ReputationChange::new_fatal("misbehaved")).await;
+        match cmd_rx.try_recv() {
+            Ok(InnerTransportManagerCommand::BanPeer { peer: banned }) => assert_eq!(banned, peer),
+            _ => panic!("expected peer to be banned"),
+        }
+
+        assert!(std::matches!(
+            service.transport_handle.dial(&peer),
+            Err(Error::PeerBanned(banned)) if banned == peer
+        ));
+    }
 }
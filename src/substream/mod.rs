@@ -22,14 +22,14 @@
 //! Substream-related helper code.
 
 use crate::{
-    codec::ProtocolCodec,
+    codec::{Codec, ProtocolCodec},
     error::{Error, SubstreamError},
-    transport::{quic, tcp, websocket},
+    transport::{memory, quic, tcp, websocket},
     types::SubstreamId,
     PeerId,
 };
 
-use bytes::{Buf, Bytes, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use futures::{Sink, Stream};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
 use unsigned_varint::{decode, encode};
@@ -40,6 +40,7 @@ use std::{
     hash::Hash,
     io::ErrorKind,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -52,6 +53,8 @@ macro_rules! poll_flush {
             SubstreamType::Tcp(substream) => Pin::new(substream).poll_flush($cx),
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_flush($cx),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_flush($cx),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_flush($cx),
+            SubstreamType::Custom(substream) => Pin::new(substream).poll_flush($cx),
             #[cfg(test)]
             SubstreamType::Mock(_) => unreachable!(),
         }
@@ -64,6 +67,8 @@ macro_rules! poll_write {
             SubstreamType::Tcp(substream) => Pin::new(substream).poll_write($cx, $frame),
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_write($cx, $frame),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_write($cx, $frame),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_write($cx, $frame),
+            SubstreamType::Custom(substream) => Pin::new(substream).poll_write($cx, $frame),
             #[cfg(test)]
             SubstreamType::Mock(_) => unreachable!(),
         }
@@ -76,6 +81,8 @@ macro_rules! poll_read {
             SubstreamType::Tcp(substream) => Pin::new(substream).poll_read($cx, $buffer),
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_read($cx, $buffer),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_read($cx, $buffer),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_read($cx, $buffer),
+            SubstreamType::Custom(substream) => Pin::new(substream).poll_read($cx, $buffer),
             #[cfg(test)]
             SubstreamType::Mock(_) => unreachable!(),
         }
@@ -88,6 +95,8 @@ macro_rules! poll_shutdown {
             SubstreamType::Tcp(substream) => Pin::new(substream).poll_shutdown($cx),
             SubstreamType::WebSocket(substream) => Pin::new(substream).poll_shutdown($cx),
             SubstreamType::Quic(substream) => Pin::new(substream).poll_shutdown($cx),
+            SubstreamType::Memory(substream) => Pin::new(substream).poll_shutdown($cx),
+            SubstreamType::Custom(substream) => Pin::new(substream).poll_shutdown($cx),
             #[cfg(test)]
             SubstreamType::Mock(substream) => {
                 let _ = Pin::new(substream).poll_close($cx);
@@ -143,11 +152,21 @@ macro_rules! check_size {
     }};
 }
 
+/// Substream opened by a custom transport.
+///
+/// Implemented for all types which implement [`tokio::io::AsyncRead`] and
+/// [`tokio::io::AsyncWrite`].
+pub trait CustomSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> CustomSubstream for T {}
+
 /// Substream type.
 enum SubstreamType {
     Tcp(tcp::Substream),
     WebSocket(websocket::Substream),
     Quic(quic::Substream),
+    Memory(memory::Substream),
+    Custom(Box<dyn CustomSubstream>),
     #[cfg(test)]
     Mock(Box<dyn crate::mock::substream::Substream>),
 }
@@ -158,6 +177,8 @@ impl fmt::Debug for SubstreamType {
             Self::Tcp(_) => write!(f, "Tcp"),
             Self::WebSocket(_) => write!(f, "WebSocket"),
             Self::Quic(_) => write!(f, "Quic"),
+            Self::Memory(_) => write!(f, "Memory"),
+            Self::Custom(_) => write!(f, "Custom"),
             #[cfg(test)]
             Self::Mock(_) => write!(f, "Mock"),
         }
@@ -198,6 +219,9 @@ pub struct Substream {
     current_frame_size: Option<usize>,
 
     size_vec: BytesMut,
+
+    /// Received bytes not yet decoded by a [`ProtocolCodec::Custom`] codec.
+    decode_buffer: BytesMut,
 }
 
 impl fmt::Debug for Substream {
@@ -232,6 +256,7 @@ impl Substream {
             pending_out_frames: VecDeque::new(),
             pending_out_frame: None,
             size_vec: BytesMut::zeroed(10),
+            decode_buffer: BytesMut::new(),
         }
     }
 
@@ -276,6 +301,39 @@ impl Substream {
         Self::new(peer, substream_id, SubstreamType::Quic(substream), codec)
     }
 
+    /// Create new [`Substream`] for the memory transport.
+    pub(crate) fn new_memory(
+        peer: PeerId,
+        substream_id: SubstreamId,
+        substream: memory::Substream,
+        codec: ProtocolCodec,
+    ) -> Self {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for memory");
+
+        Self::new(peer, substream_id, SubstreamType::Memory(substream), codec)
+    }
+
+    /// Create new [`Substream`] for a custom transport.
+    ///
+    /// The transport is responsible for negotiating the protocol of the substream and for
+    /// keeping the connection open while the substream is alive, for example by storing the
+    /// [`Permit`](crate::protocol::Permit) of the substream inside `substream`.
+    pub fn new_custom(
+        peer: PeerId,
+        substream_id: SubstreamId,
+        substream: impl CustomSubstream,
+        codec: ProtocolCodec,
+    ) -> Self {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?codec, "create new substream for custom transport");
+
+        Self::new(
+            peer,
+            substream_id,
+            SubstreamType::Custom(Box::new(substream)),
+            codec,
+        )
+    }
+
     /// Create new [`Substream`] for mocking.
     #[cfg(test)]
     pub(crate) fn new_mock(
@@ -299,6 +357,8 @@ impl Substream {
             SubstreamType::Tcp(mut substream) => substream.shutdown().await,
             SubstreamType::WebSocket(mut substream) => substream.shutdown().await,
             SubstreamType::Quic(mut substream) => substream.shutdown().await,
+            SubstreamType::Memory(mut substream) => substream.shutdown().await,
+            SubstreamType::Custom(mut substream) => substream.shutdown().await,
             #[cfg(test)]
             SubstreamType::Mock(mut substream) => {
                 let _ = futures::SinkExt::close(&mut substream).await;
@@ -322,6 +382,19 @@ impl Substream {
             .map_err(|_| Error::SubstreamError(SubstreamError::ConnectionClosed))
     }
 
+    /// Encode payload using a user-defined codec and send it to remote peer.
+    async fn send_custom_payload<T: AsyncWrite + Unpin>(
+        io: &mut T,
+        codec: &Arc<dyn Codec>,
+        payload: Bytes,
+    ) -> crate::Result<()> {
+        let mut frame = BytesMut::with_capacity(payload.len());
+        codec.encode(payload, &mut frame)?;
+
+        io.write_all(&frame).await?;
+        io.flush().await.map_err(From::from)
+    }
+
     /// Send framed data to remote peer.
     ///
     /// This function may be faster than the provided [`futures::Sink`] implementation for
@@ -353,6 +426,8 @@ impl Substream {
                 ProtocolCodec::Unspecified => panic!("codec is unspecified"),
                 ProtocolCodec::Identity(payload_size) =>
                     Self::send_identity_payload(substream, payload_size, bytes).await,
+                ProtocolCodec::Custom(ref codec) =>
+                    Self::send_custom_payload(substream, codec, bytes).await,
                 ProtocolCodec::UnsignedVarint(max_size) => {
                     check_size!(max_size, bytes.len());
 
@@ -376,6 +451,8 @@ impl Substream {
                 ProtocolCodec::Unspecified => panic!("codec is unspecified"),
                 ProtocolCodec::Identity(payload_size) =>
                     Self::send_identity_payload(substream, payload_size, bytes).await,
+                ProtocolCodec::Custom(ref codec) =>
+                    Self::send_custom_payload(substream, codec, bytes).await,
                 ProtocolCodec::UnsignedVarint(max_size) => {
                     check_size!(max_size, bytes.len());
 
@@ -399,6 +476,8 @@ impl Substream {
                 ProtocolCodec::Unspecified => panic!("codec is unspecified"),
                 ProtocolCodec::Identity(payload_size) =>
                     Self::send_identity_payload(substream, payload_size, bytes).await,
+                ProtocolCodec::Custom(ref codec) =>
+                    Self::send_custom_payload(substream, codec, bytes).await,
                 ProtocolCodec::UnsignedVarint(max_size) => {
                     check_size!(max_size, bytes.len());
 
@@ -409,8 +488,89 @@ impl Substream {
                     substream.write_all_chunks(&mut [len.freeze(), bytes]).await
                 }
             },
+            SubstreamType::Memory(ref mut substream) => match self.codec {
+                ProtocolCodec::Unspecified => panic!("codec is unspecified"),
+                ProtocolCodec::Identity(payload_size) =>
+                    Self::send_identity_payload(substream, payload_size, bytes).await,
+                ProtocolCodec::Custom(ref codec) =>
+                    Self::send_custom_payload(substream, codec, bytes).await,
+                ProtocolCodec::UnsignedVarint(max_size) => {
+                    check_size!(max_size, bytes.len());
+
+                    let mut buffer = [0u8; 10];
+                    let len = unsigned_varint::encode::usize(bytes.len(), &mut buffer);
+                    let mut offset = 0;
+
+                    while offset < len.len() {
+                        offset += substream.write(&len[offset..]).await?;
+                    }
+
+                    while bytes.has_remaining() {
+                        let nwritten = substream.write(&bytes).await?;
+                        bytes.advance(nwritten);
+                    }
+
+                    substream.flush().await.map_err(From::from)
+                }
+            },
+            SubstreamType::Custom(ref mut substream) => match self.codec {
+                ProtocolCodec::Unspecified => panic!("codec is unspecified"),
+                ProtocolCodec::Identity(payload_size) =>
+                    Self::send_identity_payload(substream, payload_size, bytes).await,
+                ProtocolCodec::Custom(ref codec) =>
+                    Self::send_custom_payload(substream, codec, bytes).await,
+                ProtocolCodec::UnsignedVarint(max_size) => {
+                    check_size!(max_size, bytes.len());
+
+                    let mut buffer = [0u8; 10];
+                    let len = unsigned_varint::encode::usize(bytes.len(), &mut buffer);
+                    let mut offset = 0;
+
+                    while offset < len.len() {
+                        offset += substream.write(&len[offset..]).await?;
+                    }
+
+                    while bytes.has_remaining() {
+                        let nwritten = substream.write(&bytes).await?;
+                        bytes.advance(nwritten);
+                    }
+
+                    substream.flush().await.map_err(From::from)
+                }
+            },
         }
     }
+
+    /// Queue `payload` prefixed with `prefix` for sending as a single frame.
+    ///
+    /// Equivalent to calling [`Sink::start_send()`](futures::Sink) with `prefix` and `payload`
+    /// concatenated but with [`ProtocolCodec::UnsignedVarint`], `payload` is queued as-is instead
+    /// of being copied into a new buffer.
+    ///
+    /// [`Sink::poll_ready()`](futures::Sink) must be called before calling this function.
+    pub(crate) fn start_send_prefixed(&mut self, prefix: u8, payload: Bytes) -> crate::Result<()> {
+        let ProtocolCodec::UnsignedVarint(max_size) = self.codec else {
+            let mut frame = BytesMut::with_capacity(payload.len() + 1);
+            frame.put_u8(prefix);
+            frame.extend_from_slice(&payload);
+
+            return Pin::new(self).start_send(frame.freeze());
+        };
+
+        check_size!(max_size, payload.len() + 1);
+
+        let mut buffer = [0u8; 10];
+        let len = unsigned_varint::encode::usize(payload.len() + 1, &mut buffer);
+        let mut header = BytesMut::with_capacity(len.len() + 1);
+        header.extend_from_slice(len);
+        header.put_u8(prefix);
+
+        self.pending_out_bytes += header.len() + payload.len();
+        self.pending_out_frames.push_back(header.freeze());
+        self.pending_out_frames.push_back(payload);
+
+        Ok(())
+    }
 }
 
 impl tokio::io::AsyncRead for Substream {
@@ -590,6 +750,14 @@ impl Stream for Substream {
                                                 }
 
                                                 this.offset = 0;
+
+                                                // an empty frame is a valid zero-length
+                                                // message, reading its payload into an empty
+                                                // buffer would be mistaken for EOF
+                                                if size == 0 {
+                                                    return Poll::Ready(Some(Ok(BytesMut::new())));
+                                                }
+
                                                 this.current_frame_size = Some(size);
                                                 this.read_buffer = BytesMut::zeroed(size);
                                             }
@@ -600,6 +768,29 @@ impl Stream for Substream {
                         }
                     }
                 }
+                ProtocolCodec::Custom(ref codec) => {
+                    match codec.decode(&mut this.decode_buffer) {
+                        Err(error) => return Poll::Ready(Some(Err(error))),
+                        Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
+                        Ok(None) => {}
+                    }
+
+                    let mut read_buf = ReadBuf::new(&mut this.read_buffer[..]);
+
+                    match futures::ready!(poll_read!(&mut this.substream, cx, &mut read_buf)) {
+                        Err(error) => return Poll::Ready(Some(Err(error.into()))),
+                        Ok(_) => match read_buf.filled().len() {
+                            // the substream was closed in the middle of a frame
+                            0 if !this.decode_buffer.is_empty() => {
+                                this.decode_buffer.clear();
+                                return Poll::Ready(Some(Err(Error::InvalidData)));
+                            }
+                            0 => return Poll::Ready(None),
+                            nread =>
+                                this.decode_buffer.extend_from_slice(&this.read_buffer[..nread]),
+                        },
+                    }
+                }
                 ProtocolCodec::Unspecified => panic!("codec is unspecified"),
             }
         }
@@ -647,6 +838,13 @@ impl Sink<Bytes> for Substream {
                 self.pending_out_frames.push_back(len.freeze());
                 self.pending_out_frames.push_back(item);
             }
+            ProtocolCodec::Custom(ref codec) => {
+                let mut frame = BytesMut::with_capacity(item.len());
+                codec.encode(item, &mut frame)?;
+
+                self.pending_out_bytes += frame.len();
+                self.pending_out_frames.push_back(frame.freeze());
+            }
             ProtocolCodec::Unspecified => panic!("codec is unspecified"),
         }
 
@@ -1002,4 +1200,135 @@ mod tests {
             assert!(futures::poll!(set.next()).is_pending());
         }
     }
+
+    /// Codec which prefixes each frame with its length as a big-endian `u16`.
+    #[derive(Debug)]
+    struct LengthPrefixed;
+
+    impl Codec for LengthPrefixed {
+        fn encode(&self, item: Bytes, dst: &mut BytesMut) -> crate::Result<()> {
+            let len = u16::try_from(item.len()).map_err(|_| Error::InvalidData)?;
+
+            dst.extend_from_slice(&len.to_be_bytes());
+            dst.extend_from_slice(&item);
+            Ok(())
+        }
+
+        fn decode(&self, src: &mut BytesMut) -> crate::Result<Option<BytesMut>> {
+            if src.len() < 2 {
+                return Ok(None);
+            }
+
+            let len = u16::from_be_bytes([src[0], src[1]]) as usize;
+            if src.len() < 2 + len {
+                return Ok(None);
+            }
+
+            src.advance(2);
+            Ok(Some(src.split_to(len)))
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_codec() {
+        let (io1, io2) = tokio::io::duplex(1024);
+        let codec = ProtocolCodec::Custom(Arc::new(LengthPrefixed));
+        let mut substream1 =
+            Substream::new_custom(PeerId::random(), SubstreamId::new(), io1, codec.clone());
+        let mut substream2 =
+            Substream::new_custom(PeerId::random(), SubstreamId::new(), io2, codec);
+
+        // frames sent using both `send_framed()` and the `Sink` implementation are decoded
+        substream1.send_framed(Bytes::from_static(b"hello")).await.unwrap();
+        substream1.send(Bytes::from(vec![1u8; 100])).await.unwrap();
+        substream1.send(Bytes::new()).await.unwrap();
+
+        assert_eq!(
+            substream2.next().await.unwrap().unwrap(),
+            BytesMut::from(&b"hello"[..])
+        );
+        assert_eq!(substream2.next().await.unwrap().unwrap(), vec![1u8; 100]);
+        assert!(substream2.next().await.unwrap().unwrap().is_empty());
+
+        // encoding errors are reported to the caller
+        assert!(substream2.send(Bytes::from(vec![0u8; 70_000])).await.is_err());
+
+        drop(substream1);
+        assert!(substream2.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn custom_codec_partial_frame_at_eof() {
+        let (mut io1, io2) = tokio::io::duplex(1024);
+        let mut substream = Substream::new_custom(
+            PeerId::random(),
+            SubstreamId::new(),
+            io2,
+            ProtocolCodec::Custom(Arc::new(LengthPrefixed)),
+        );
+
+        // frame of five bytes is cut short by the remote closing the substream
+        tokio::io::AsyncWriteExt::write_all(&mut io1, &[0, 5, b'h', b'e'])
+            .await
+            .unwrap();
+        drop(io1);
+
+        assert!(std::matches!(
+            substream.next().await,
+            Some(Err(Error::InvalidData))
+        ));
+        assert!(substream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn empty_unsigned_varint_frame() {
+        let (io1, io2) = tokio::io::duplex(1024);
+        let codec = ProtocolCodec::UnsignedVarint(None);
+        let mut substream1 =
+            Substream::new_custom(PeerId::random(), SubstreamId::new(), io1, codec.clone());
+        let mut substream2 =
+            Substream::new_custom(PeerId::random(), SubstreamId::new(), io2, codec);
+
+        substream1.send_framed(Bytes::new()).await.unwrap();
+        substream1.send_framed(Bytes::from_static(b"hello")).await.unwrap();
+
+        assert!(substream2.next().await.unwrap().unwrap().is_empty());
+        assert_eq!(
+            substream2.next().await.unwrap().unwrap(),
+            BytesMut::from(&b"hello"[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn prefixed_frames() {
+        let (io1, io2) = tokio::io::duplex(1024);
+        let mut substream1 = Substream::new_custom(
+            PeerId::random(),
+            SubstreamId::new(),
+            io1,
+            ProtocolCodec::UnsignedVarint(Some(6)),
+        );
+        let mut substream2 = Substream::new_custom(
+            PeerId::random(),
+            SubstreamId::new(),
+            io2,
+            ProtocolCodec::UnsignedVarint(None),
+        );
+
+        substream1.start_send_prefixed(1u8, Bytes::from_static(b"hello")).unwrap();
+        substream1.start_send_prefixed(0u8, Bytes::new()).unwrap();
+        SinkExt::flush(&mut substream1).await.unwrap();
+
+        assert_eq!(
+            substream2.next().await.unwrap().unwrap(),
+            BytesMut::from(&b"\x01hello"[..])
+        );
+        assert_eq!(
+            substream2.next().await.unwrap().unwrap(),
+            BytesMut::from(&b"\x00"[..])
+        );
+
+        // the prefix counts towards the maximum frame size
+        assert!(substream1.start_send_prefixed(1u8, Bytes::from_static(b"hello!")).is_err());
+    }
 }
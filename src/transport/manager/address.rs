@@ -211,6 +211,32 @@ impl AddressStore {
         self.by_score.push(record);
     }
 
+    /// Insert address record into [`AddressStore`] or, if the address already exists,
+    /// raise the score of the stored record to the score of `record` if it's lower.
+    pub fn upsert(&mut self, record: AddressRecord) {
+        if !self.by_address.contains(record.address()) {
+            self.insert(record);
+            return;
+        }
+
+        let mut existing = None;
+        self.by_score.retain(|stored| {
+            if stored.address() == record.address() {
+                existing = Some(stored.clone());
+                return false;
+            }
+            true
+        });
+
+        match existing {
+            Some(mut existing) => {
+                existing.score = std::cmp::max(existing.score, record.score);
+                self.by_score.push(existing);
+            }
+            None => self.by_score.push(record),
+        }
+    }
+
     /// Pop address with the highest score from [`AddressStore`].
     pub fn pop(&mut self) -> Option<AddressRecord> {
         self.by_score.pop().map(|record| {
@@ -349,6 +375,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn upsert_raises_score() {
+        let mut store = AddressStore::new();
+        let peer = PeerId::random();
+        let address1: Multiaddr = "/ip4/127.0.0.1/tcp/8888".parse().unwrap();
+        let address2: Multiaddr = "/ip4/127.0.0.1/tcp/9999".parse().unwrap();
+
+        store.insert(AddressRecord::new(&peer, address1.clone(), 10, None));
+        store.insert(AddressRecord::new(&peer, address2.clone(), 20, None));
+
+        // lower score doesn't downgrade the existing record
+        store.upsert(AddressRecord::new(&peer, address2.clone(), 0, None));
+        // higher score upgrades the existing record
+        store.upsert(AddressRecord::new(&peer, address1.clone(), 30, None));
+
+        assert_eq!(store.by_address.len(), 2);
+        assert_eq!(store.by_score.len(), 2);
+
+        let record = store.pop().unwrap();
+        assert_eq!(record.score(), 30);
+        assert!(record.address().to_string().starts_with(&address1.to_string()));
+
+        let record = store.pop().unwrap();
+        assert_eq!(record.score(), 20);
+        assert!(record.address().to_string().starts_with(&address2.to_string()));
+    }
+
+    #[test]
+    fn upsert_keeps_connection_id() {
+        let mut store = AddressStore::new();
+        let peer = PeerId::random();
+        let address: Multiaddr = "/ip4/127.0.0.1/tcp/8888".parse().unwrap();
+
+        let mut record = AddressRecord::new(&peer, address.clone(), 10, None);
+        record.set_connection_id(ConnectionId::from(1337usize));
+        store.by_address.insert(record.address().clone());
+        store.by_score.push(record);
+
+        store.upsert(AddressRecord::new(&peer, address.clone(), 30, None));
+
+        let record = store.pop().unwrap();
+        assert_eq!(record.score(), 30);
+        assert_eq!(record.connection_id(), &Some(ConnectionId::from(1337usize)));
+    }
+
     #[test]
     fn attempt_to_take_excess_records() {
         let mut store = AddressStore::new();
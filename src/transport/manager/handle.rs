@@ -25,8 +25,9 @@ use crate::{
     protocol::ProtocolSet,
     transport::manager::{
         address::{AddressRecord, AddressStore},
+        reputation::{ReputationChange, ReputationStore},
         types::{PeerContext, PeerState, SupportedTransport},
-        ProtocolContext, TransportManagerEvent, LOG_TARGET,
+        ProtocolContext, TransportManagerEvent, LOG_TARGET, SCORE_CERTIFIED_ADDRESS,
     },
     types::{protocol::ProtocolName, ConnectionId},
     BandwidthSink, PeerId,
@@ -42,6 +43,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::Instant,
 };
 
 /// Inner commands sent from [`TransportManagerHandle`] to
@@ -58,6 +60,12 @@ pub enum InnerTransportManagerCommand {
         /// Remote address.
         address: Multiaddr,
     },
+
+    /// Disconnect and ban peer whose reputation dropped below the ban threshold.
+    BanPeer {
+        /// Remote peer ID.
+        peer: PeerId,
+    },
 }
 
 /// Handle for communicating with [`crate::transport::manager::TransportManager`].
@@ -77,6 +85,9 @@ pub struct TransportManagerHandle {
 
     /// Local listen addresess.
     listen_addresses: Arc<RwLock<HashSet<Multiaddr>>>,
+
+    /// Peer reputations.
+    reputation: Arc<RwLock<ReputationStore>>,
 }
 
 impl TransportManagerHandle {
@@ -87,10 +98,12 @@ impl TransportManagerHandle {
         cmd_tx: Sender<InnerTransportManagerCommand>,
         supported_transport: HashSet<SupportedTransport>,
         listen_addresses: Arc<RwLock<HashSet<Multiaddr>>>,
+        reputation: Arc<RwLock<ReputationStore>>,
     ) -> Self {
         Self {
             peers,
             cmd_tx,
+            reputation,
             local_peer_id,
             listen_addresses,
             supported_transport,
@@ -104,6 +117,15 @@ impl TransportManagerHandle {
 
     /// Check if `address` is supported by one of the enabled transports.
     pub fn supported_transport(&self, address: &Multiaddr) -> bool {
+        if self.supported_transport.iter().any(|transport| {
+            std::matches!(
+                transport,
+                SupportedTransport::Custom(custom) if custom.supports(address)
+            )
+        }) {
+            return true;
+        }
+
         let mut iter = address.iter();
 
         match iter.next() {
@@ -116,6 +138,8 @@ impl TransportManagerHandle {
                     return false;
                 },
             Some(Protocol::Dns(_)) | Some(Protocol::Dns4(_)) | Some(Protocol::Dns6(_)) => {}
+            Some(Protocol::Memory(_)) =>
+                return self.supported_transport.contains(&SupportedTransport::Memory),
             _ => return false,
         }
 
@@ -161,6 +185,30 @@ impl TransportManagerHandle {
         &mut self,
         peer: &PeerId,
         addresses: impl Iterator<Item = Multiaddr>,
+    ) -> usize {
+        self.add_addresses(peer, addresses, false)
+    }
+
+    /// Add one or more addresses for peer which were certified by a signed peer record.
+    ///
+    /// Certified addresses are preferred over addresses learned from other sources, e.g., from
+    /// the DHT, when dialing the peer. If an address is already known, its score is raised.
+    ///
+    /// Returns the number of added addresses after non-supported transports were filtered out.
+    pub fn add_certified_address(
+        &mut self,
+        peer: &PeerId,
+        addresses: impl Iterator<Item = Multiaddr>,
+    ) -> usize {
+        self.add_addresses(peer, addresses, true)
+    }
+
+    /// Add addresses for peer, scoring certified addresses higher than uncertified ones.
+    fn add_addresses(
+        &mut self,
+        peer: &PeerId,
+        addresses: impl Iterator<Item = Multiaddr>,
+        certified: bool,
     ) -> usize {
         let mut peers = self.peers.write();
         let addresses = addresses
@@ -168,6 +216,12 @@ impl TransportManagerHandle {
                 (self.supported_transport(&address) && !self.is_local_address(&address))
                     .then_some(AddressRecord::from_multiaddr(address)?)
             })
+            .map(|mut record| {
+                if certified {
+                    record.update_score(SCORE_CERTIFIED_ADDRESS);
+                }
+                record
+            })
             .collect::<HashSet<_>>();
 
         // if all of the added addresses belonged to unsupported transports, exit early
@@ -186,13 +240,16 @@ impl TransportManagerHandle {
             target: LOG_TARGET,
             ?peer,
             ?addresses,
+            ?certified,
             "add known addresses",
         );
 
         match peers.get_mut(&peer) {
             Some(context) =>
                 for record in addresses {
-                    if !context.addresses.contains(record.address()) {
+                    if certified {
+                        context.addresses.upsert(record);
+                    } else if !context.addresses.contains(record.address()) {
                         context.addresses.insert(record);
                     }
                 },
@@ -211,14 +268,53 @@ impl TransportManagerHandle {
         num_added
     }
 
+    /// Report reputation change for `peer`.
+    ///
+    /// If the reputation of the peer drops below the ban threshold, the peer is disconnected and
+    /// banned for the configured duration.
+    pub async fn report_peer(&self, peer: PeerId, change: ReputationChange) {
+        tracing::trace!(target: LOG_TARGET, ?peer, ?change, "report peer");
+
+        if !self.reputation.write().report(peer, change, Instant::now()) {
+            return;
+        }
+
+        tracing::debug!(
+            target: LOG_TARGET,
+            ?peer,
+            reason = ?change.reason,
+            "reputation dropped below ban threshold, ban peer",
+        );
+
+        // the ban is recorded in the reputation store so the peer can't be dialed even if the
+        // transport manager has exited, but the command must not be lost if the channel is full
+        if let Err(error) = self.cmd_tx.send(InnerTransportManagerCommand::BanPeer { peer }).await {
+            tracing::warn!(target: LOG_TARGET, ?peer, ?error, "failed to ban peer");
+        }
+    }
+
+    /// Get current reputation of `peer`.
+    pub fn reputation(&self, peer: &PeerId) -> i32 {
+        self.reputation.read().reputation(peer, Instant::now())
+    }
+
+    /// Check if `peer` is currently banned.
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.reputation.read().is_banned(peer, Instant::now())
+    }
+
     /// Dial peer using `PeerId`.
     ///
-    /// Returns an error if the peer is unknown or the peer is already connected.
+    /// Returns an error if the peer is unknown, banned or already connected.
     pub fn dial(&self, peer: &PeerId) -> crate::Result<()> {
         if peer == &self.local_peer_id {
             return Err(Error::TriedToDialSelf);
         }
 
+        if self.is_banned(peer) {
+            return Err(Error::PeerBanned(*peer));
+        }
+
         {
             match self.peers.read().get(&peer) {
                 Some(PeerContext {
@@ -263,12 +359,18 @@ impl TransportManagerHandle {
 
     /// Dial peer using `Multiaddr`.
     ///
-    /// Returns an error if address it not valid.
+    /// Returns an error if address it not valid or the peer is banned.
     pub fn dial_address(&self, address: Multiaddr) -> crate::Result<()> {
         if !address.iter().any(|protocol| std::matches!(protocol, Protocol::P2p(_))) {
             return Err(Error::AddressError(AddressError::PeerIdMissing));
         }
 
+        if let Some(peer) = PeerId::try_from_multiaddr(&address) {
+            if self.is_banned(&peer) {
+                return Err(Error::PeerBanned(peer));
+            }
+        }
+
         self.cmd_tx
             .try_send(InnerTransportManagerCommand::DialAddress { address })
             .map_err(|error| match error {
@@ -278,19 +380,41 @@ impl TransportManagerHandle {
     }
 }
 
-// TODO: add getters for these
+/// Handle given to a transport when it's created.
+///
+/// Allows the transport to allocate connection IDs and to report its connections and substreams
+/// to the installed protocols.
 pub struct TransportHandle {
-    pub keypair: Keypair,
-    pub tx: Sender<TransportManagerEvent>,
-    pub protocols: HashMap<ProtocolName, ProtocolContext>,
-    pub next_connection_id: Arc<AtomicUsize>,
-    pub next_substream_id: Arc<AtomicUsize>,
-    pub protocol_names: Vec<ProtocolName>,
-    pub bandwidth_sink: BandwidthSink,
-    pub executor: Arc<dyn Executor>,
+    pub(crate) keypair: Keypair,
+    pub(crate) tx: Sender<TransportManagerEvent>,
+    pub(crate) protocols: HashMap<ProtocolName, ProtocolContext>,
+    pub(crate) next_connection_id: Arc<AtomicUsize>,
+    pub(crate) next_substream_id: Arc<AtomicUsize>,
+    pub(crate) protocol_names: Vec<ProtocolName>,
+    pub(crate) bandwidth_sink: BandwidthSink,
+    pub(crate) executor: Arc<dyn Executor>,
 }
 
 impl TransportHandle {
+    /// Get local keypair.
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    /// Get the executor used to run connection tasks.
+    pub fn executor(&self) -> &Arc<dyn Executor> {
+        &self.executor
+    }
+
+    /// Get bandwidth sink which tracks the bandwidth used by the transport.
+    pub fn bandwidth_sink(&self) -> &BandwidthSink {
+        &self.bandwidth_sink
+    }
+
+    /// Create [`ProtocolSet`] for a new connection.
+    ///
+    /// The connection uses the [`ProtocolSet`] to report its events to the installed protocols
+    /// and to receive requests to open substreams from them.
     pub fn protocol_set(&self, connection_id: ConnectionId) -> ProtocolSet {
         ProtocolSet::new(
             connection_id,
@@ -311,6 +435,7 @@ impl TransportHandle {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transport::manager::CustomTransport;
     use multihash::Multihash;
     use tokio::sync::mpsc::{channel, Receiver};
 
@@ -327,6 +452,7 @@ mod tests {
                 peers: Default::default(),
                 supported_transport: HashSet::new(),
                 listen_addresses: Default::default(),
+                reputation: Default::default(),
             },
             cmd_rx,
         )
@@ -345,6 +471,28 @@ mod tests {
         assert!(handle.supported_transport(&address));
     }
 
+    #[test]
+    fn custom_transport_supported() {
+        let (mut handle, _rx) = make_transport_manager_handle();
+        handle
+            .supported_transport
+            .insert(SupportedTransport::Custom(CustomTransport::new(
+                "sctp",
+                |address| std::matches!(address.iter().nth(1), Some(Protocol::Sctp(_))),
+            )));
+
+        assert!(handle.supported_transport(
+            &Multiaddr::empty()
+                .with(Protocol::Ip4(std::net::Ipv4Addr::new(127, 0, 0, 1)))
+                .with(Protocol::Sctp(8888))
+        ));
+        assert!(!handle.supported_transport(
+            &Multiaddr::empty()
+                .with(Protocol::Ip4(std::net::Ipv4Addr::new(127, 0, 0, 1)))
+                .with(Protocol::Tcp(8888))
+        ));
+    }
+
     #[test]
     fn transport_not_supported() {
         let (handle, _rx) = make_transport_manager_handle();
@@ -401,6 +549,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn certified_addresses_preferred() {
+        let (mut handle, _rx) = make_transport_manager_handle();
+        handle.supported_transport.insert(SupportedTransport::Tcp);
+
+        let peer = PeerId::random();
+        let hearsay = Multiaddr::empty()
+            .with(Protocol::Ip4(std::net::Ipv4Addr::new(192, 168, 0, 1)))
+            .with(Protocol::Tcp(8888))
+            .with(Protocol::P2p(Multihash::from(peer)));
+        let certified = Multiaddr::empty()
+            .with(Protocol::Ip4(std::net::Ipv4Addr::new(192, 168, 0, 2)))
+            .with(Protocol::Tcp(8888))
+            .with(Protocol::P2p(Multihash::from(peer)));
+
+        assert_eq!(
+            handle.add_known_address(&peer, vec![hearsay.clone(), certified.clone()].into_iter()),
+            2usize
+        );
+        assert_eq!(
+            handle.add_certified_address(&peer, vec![certified.clone()].into_iter()),
+            1usize
+        );
+
+        let mut peers = handle.peers.write();
+        let context = peers.get_mut(&peer).unwrap();
+        assert_eq!(context.addresses.by_score.len(), 2);
+
+        let record = context.addresses.pop().unwrap();
+        assert_eq!(record.address(), &certified);
+        assert_eq!(record.score(), SCORE_CERTIFIED_ADDRESS);
+
+        let record = context.addresses.pop().unwrap();
+        assert_eq!(record.address(), &hearsay);
+        assert_eq!(record.score(), 0i32);
+    }
+
     #[tokio::test]
     async fn dial_already_connected_peer() {
         let (mut handle, _rx) = make_transport_manager_handle();
@@ -592,6 +777,7 @@ mod tests {
                     .parse()
                     .expect("valid multiaddress"),
             ]))),
+            reputation: Default::default(),
         };
 
         // local addresses
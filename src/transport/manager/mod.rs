@@ -23,11 +23,12 @@ use crate::{
     crypto::ed25519::Keypair,
     error::{AddressError, Error},
     executor::Executor,
-    protocol::{InnerTransportEvent, TransportService},
+    protocol::{connection::ConnectionHandle, InnerTransportEvent, TransportService},
     transport::{
         manager::{
             address::{AddressRecord, AddressStore},
             handle::InnerTransportManagerCommand,
+            reputation::ReputationStore,
             types::{PeerContext, PeerState},
         },
         Endpoint, Transport, TransportEvent,
@@ -51,12 +52,15 @@ use std::{
         Arc,
     },
     task::{Context, Poll},
+    time::Instant,
 };
 
 pub use handle::{TransportHandle, TransportManagerHandle};
-pub use types::SupportedTransport;
+pub use reputation::{ReputationChange, ReputationConfig};
+pub use types::{CustomTransport, SupportedTransport};
 
 mod address;
+mod reputation;
 mod types;
 
 pub(crate) mod handle;
@@ -75,6 +79,11 @@ const SCORE_DIAL_SUCCESS: i32 = 100i32;
 /// Score for a non-working address.
 const SCORE_DIAL_FAILURE: i32 = -100i32;
 
+/// Score for an address certified by a signed peer record.
+///
+/// Certified addresses are dialed before addresses learned from third parties.
+const SCORE_CERTIFIED_ADDRESS: i32 = 50i32;
+
 /// TODO:
 enum ConnectionEstablishedResult {
     /// Accept connection and inform `Litep2p` about the connection.
@@ -86,6 +95,18 @@ enum ConnectionEstablishedResult {
 
 /// [`crate::transport::manager::TransportManager`] events.
 pub enum TransportManagerEvent {
+    /// Connection established to remote peer.
+    ConnectionEstablished {
+        /// Peer ID.
+        peer: PeerId,
+
+        /// Connection ID.
+        connection: ConnectionId,
+
+        /// Handle to the connection.
+        handle: ConnectionHandle,
+    },
+
     /// Connection closed to remote peer.
     ConnectionClosed {
         /// Peer ID.
@@ -242,6 +263,12 @@ pub struct TransportManager {
 
     /// Pending connections.
     pending_connections: HashMap<ConnectionId, PeerId>,
+
+    /// Handles to open connections, used to disconnect banned peers.
+    connection_handles: HashMap<ConnectionId, (PeerId, ConnectionHandle)>,
+
+    /// Peer reputations.
+    reputation: Arc<RwLock<ReputationStore>>,
 }
 
 impl TransportManager {
@@ -258,12 +285,14 @@ impl TransportManager {
         let (cmd_tx, cmd_rx) = channel(256);
         let (event_tx, event_rx) = channel(256);
         let listen_addresses = Arc::new(RwLock::new(HashSet::new()));
+        let reputation = Arc::new(RwLock::new(ReputationStore::default()));
         let handle = TransportManagerHandle::new(
             local_peer_id,
             peers.clone(),
             cmd_tx,
             supported_transports,
             Arc::clone(&listen_addresses),
+            Arc::clone(&reputation),
         );
 
         (
@@ -273,6 +302,7 @@ impl TransportManager {
                 keypair,
                 event_tx,
                 event_rx,
+                reputation,
                 local_peer_id,
                 bandwidth_sink,
                 listen_addresses,
@@ -282,6 +312,7 @@ impl TransportManager {
                 protocol_names: HashSet::new(),
                 transport_manager_handle: handle.clone(),
                 pending_connections: HashMap::new(),
+                connection_handles: HashMap::new(),
                 next_substream_id: Arc::new(AtomicUsize::new(0usize)),
                 next_connection_id: Arc::new(AtomicUsize::new(0usize)),
             },
@@ -289,6 +320,11 @@ impl TransportManager {
         )
     }
 
+    /// Set reputation configuration.
+    pub(crate) fn set_reputation_config(&mut self, config: ReputationConfig) {
+        self.reputation.write().set_config(config);
+    }
+
     /// Get iterator to installed protocols.
     pub fn protocols(&self) -> impl Iterator<Item = &ProtocolName> {
         self.protocols.keys()
@@ -299,6 +335,19 @@ impl TransportManager {
         self.transports.keys()
     }
 
+    /// Get the custom transport which can dial `address`, if any.
+    fn custom_transport(&self, address: &Multiaddr) -> Option<SupportedTransport> {
+        self.transports
+            .keys()
+            .find(|transport| {
+                std::matches!(
+                    transport,
+                    SupportedTransport::Custom(custom) if custom.supports(address)
+                )
+            })
+            .copied()
+    }
+
     /// Get next connection ID.
     fn next_connection_id(&mut self) -> ConnectionId {
         let connection_id = self.next_connection_id.fetch_add(1usize, Ordering::Relaxed);
@@ -396,6 +445,9 @@ impl TransportManager {
         if peer == self.local_peer_id {
             return Err(Error::TriedToDialSelf);
         }
+        if self.reputation.read().is_banned(&peer, Instant::now()) {
+            return Err(Error::PeerBanned(peer));
+        }
         let mut peers = self.peers.write();
 
         // if the peer is disconnected, return its context
@@ -487,12 +539,26 @@ impl TransportManager {
 
         let mut transports = HashSet::new();
         let mut websocket = Vec::new();
+        let mut memory = Vec::new();
         let mut quic = Vec::new();
         let mut tcp = Vec::new();
+        let mut custom: HashMap<SupportedTransport, Vec<Multiaddr>> = HashMap::new();
 
         for (address, record) in &mut records {
             record.set_connection_id(connection_id);
 
+            if let Some(transport) = self.custom_transport(address) {
+                custom.entry(transport).or_default().push(address.clone());
+                transports.insert(transport);
+                continue;
+            }
+
+            if std::matches!(address.iter().next(), Some(Protocol::Memory(_))) {
+                memory.push(address.clone());
+                transports.insert(SupportedTransport::Memory);
+                continue;
+            }
+
             let mut iter = address.iter();
             match iter.find(|protocol| std::matches!(protocol, Protocol::QuicV1)) {
                 Some(_) => {
@@ -549,6 +615,20 @@ impl TransportManager {
                 .open(connection_id, websocket)?;
         }
 
+        if !memory.is_empty() {
+            self.transports
+                .get_mut(&SupportedTransport::Memory)
+                .expect("transport to be supported")
+                .open(connection_id, memory)?;
+        }
+
+        for (transport, addresses) in custom {
+            self.transports
+                .get_mut(&transport)
+                .expect("transport to be supported")
+                .open(connection_id, addresses)?;
+        }
+
         self.pending_connections.insert(connection_id, peer);
 
         Ok(())
@@ -567,57 +647,67 @@ impl TransportManager {
 
         tracing::debug!(target: LOG_TARGET, address = ?record.address(), "dial remote peer over address");
 
+        let custom_transport = self.custom_transport(record.address());
         let mut protocol_stack = record.as_ref().iter();
-        match protocol_stack
+        let supported_transport = match protocol_stack
             .next()
             .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
         {
-            Protocol::Ip4(_) | Protocol::Ip6(_) => {}
-            Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_) => {}
+            _ if custom_transport.is_some() => custom_transport,
+            Protocol::Memory(_) => Some(SupportedTransport::Memory),
+            Protocol::Ip4(_) | Protocol::Ip6(_) => None,
+            Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_) => None,
             transport => {
                 tracing::error!(
                     target: LOG_TARGET,
                     ?transport,
-                    "invalid transport, expected `ip4`/`ip6`"
+                    "invalid transport, expected `ip4`/`ip6`/`memory`"
                 );
                 return Err(Error::TransportNotSupported(record.address().clone()));
             }
         };
 
-        let supported_transport = match protocol_stack
-            .next()
-            .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
-        {
-            Protocol::Tcp(_) => match protocol_stack.next() {
-                Some(Protocol::Ws(_)) | Some(Protocol::Wss(_)) => SupportedTransport::WebSocket,
-                Some(Protocol::P2p(_)) => SupportedTransport::Tcp,
-                _ => return Err(Error::TransportNotSupported(record.address().clone())),
-            },
-            Protocol::Udp(_) => match protocol_stack
+        let supported_transport = match supported_transport {
+            Some(transport) => transport,
+            None => match protocol_stack
                 .next()
                 .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
             {
-                Protocol::QuicV1 => SupportedTransport::Quic,
-                _ => {
-                    tracing::debug!(target: LOG_TARGET, address = ?record.address(), "expected `quic-v1`");
+                Protocol::Tcp(_) => match protocol_stack.next() {
+                    Some(Protocol::Ws(_)) | Some(Protocol::Wss(_)) => SupportedTransport::WebSocket,
+                    Some(Protocol::P2p(_)) => SupportedTransport::Tcp,
+                    _ => return Err(Error::TransportNotSupported(record.address().clone())),
+                },
+                Protocol::Udp(_) => match protocol_stack
+                    .next()
+                    .ok_or_else(|| Error::TransportNotSupported(record.address().clone()))?
+                {
+                    Protocol::QuicV1 => SupportedTransport::Quic,
+                    _ => {
+                        tracing::debug!(target: LOG_TARGET, address = ?record.address(), "expected `quic-v1`");
+                        return Err(Error::TransportNotSupported(record.address().clone()));
+                    }
+                },
+                protocol => {
+                    tracing::error!(
+                        target: LOG_TARGET,
+                        ?protocol,
+                        "invalid protocol, expected `tcp`"
+                    );
+
                     return Err(Error::TransportNotSupported(record.address().clone()));
                 }
             },
-            protocol => {
-                tracing::error!(
-                    target: LOG_TARGET,
-                    ?protocol,
-                    "invalid protocol, expected `tcp`"
-                );
-
-                return Err(Error::TransportNotSupported(record.address().clone()));
-            }
         };
 
         // when constructing `AddressRecord`, `PeerId` was verified to be part of the address
         let remote_peer_id =
             PeerId::try_from_multiaddr(record.address()).expect("`PeerId` to exist");
 
+        if self.reputation.read().is_banned(&remote_peer_id, Instant::now()) {
+            return Err(Error::PeerBanned(remote_peer_id));
+        }
+
         // set connection id for the address record and put peer into `Dialing` state
         let connection_id = self.next_connection_id();
         record.set_connection_id(connection_id);
@@ -759,6 +849,8 @@ impl TransportManager {
         peer: PeerId,
         connection_id: ConnectionId,
     ) -> crate::Result<Option<TransportEvent>> {
+        self.connection_handles.remove(&connection_id);
+
         let mut peers = self.peers.write();
         let Some(context) = peers.get_mut(&peer) else {
             tracing::warn!(
@@ -912,6 +1004,27 @@ impl TransportManager {
         };
 
         let mut peers = self.peers.write();
+
+        // reject inbound connections from banned peers right away if the local node isn't
+        // dialing them, otherwise the connection is closed once it has been established
+        if endpoint.is_listener() && self.reputation.read().is_banned(&peer, Instant::now()) {
+            if let None
+            | Some(PeerContext {
+                state: PeerState::Disconnected { dial_record: None },
+                ..
+            }) = peers.get(&peer)
+            {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    connection_id = ?endpoint.connection_id(),
+                    "peer is banned, reject connection",
+                );
+
+                return Ok(ConnectionEstablishedResult::Reject);
+            }
+        }
+
         match peers.get_mut(&peer) {
             Some(context) => match context.state {
                 PeerState::Connected { .. } => match context.secondary_connection {
@@ -1338,11 +1451,62 @@ impl TransportManager {
         }
     }
 
+    /// Store handle to an established connection.
+    ///
+    /// If the peer was banned while the connection was being established, the connection is
+    /// closed immediately.
+    fn on_connection_handle(
+        &mut self,
+        peer: PeerId,
+        connection_id: ConnectionId,
+        mut handle: ConnectionHandle,
+    ) {
+        // the handle mustn't keep the connection open
+        handle.close();
+
+        if self.reputation.read().is_banned(&peer, Instant::now()) {
+            tracing::debug!(
+                target: LOG_TARGET,
+                ?peer,
+                ?connection_id,
+                "connection established to banned peer, closing",
+            );
+
+            let _ = handle.force_close();
+        }
+
+        self.connection_handles.insert(connection_id, (peer, handle));
+    }
+
+    /// Disconnect banned peer.
+    fn on_ban_peer(&mut self, peer: PeerId) {
+        tracing::debug!(target: LOG_TARGET, ?peer, "disconnect banned peer");
+
+        for (connection_id, (_, handle)) in
+            self.connection_handles.iter_mut().filter(|(_, (remote, _))| remote == &peer)
+        {
+            if let Err(error) = handle.force_close() {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?peer,
+                    ?connection_id,
+                    ?error,
+                    "failed to close connection to banned peer",
+                );
+            }
+        }
+    }
+
     /// Poll next event from [`crate::transport::manager::TransportManager`].
     pub async fn next(&mut self) -> Option<TransportEvent> {
         loop {
             tokio::select! {
                 event = self.event_rx.recv() => match event? {
+                    TransportManagerEvent::ConnectionEstablished {
+                        peer,
+                        connection,
+                        handle,
+                    } => self.on_connection_handle(peer, connection, handle),
                     TransportManagerEvent::ConnectionClosed {
                         peer,
                         connection: connection_id,
@@ -1367,6 +1531,7 @@ impl TransportManager {
                             tracing::debug!(target: LOG_TARGET, ?error, "failed to dial peer")
                         }
                     }
+                    InnerTransportManagerCommand::BanPeer { peer } => self.on_ban_peer(peer),
                 },
                 event = self.transports.next() => {
                     let (transport, event) = event?;
@@ -0,0 +1,283 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Peer reputation shared by all installed protocols.
+
+use crate::PeerId;
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Default reputation below which a peer is disconnected and banned.
+const DEFAULT_BAN_THRESHOLD: i32 = -1_000_000;
+
+/// Default duration of a ban.
+const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(5 * 60);
+
+/// Default half-life of a reputation value.
+const DEFAULT_DECAY_HALF_LIFE: Duration = Duration::from_secs(60);
+
+/// How often reputations which have fully decayed are pruned.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Reputation change reported by a protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReputationChange {
+    /// Value of the change.
+    ///
+    /// Negative values lower the reputation of the peer and positive values raise it.
+    pub value: i32,
+
+    /// Reason for the change.
+    pub reason: &'static str,
+}
+
+impl ReputationChange {
+    /// Create new [`ReputationChange`].
+    pub const fn new(value: i32, reason: &'static str) -> Self {
+        Self { value, reason }
+    }
+
+    /// Create new [`ReputationChange`] which causes the peer to be banned immediately.
+    pub const fn new_fatal(reason: &'static str) -> Self {
+        Self {
+            value: i32::MIN,
+            reason,
+        }
+    }
+}
+
+/// Reputation configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReputationConfig {
+    /// Reputation below which the peer is disconnected and banned.
+    pub ban_threshold: i32,
+
+    /// How long the peer stays banned after its reputation dropped below the threshold.
+    pub ban_duration: Duration,
+
+    /// Time it takes for a reputation value to decay to half of its value.
+    pub decay_half_life: Duration,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            ban_threshold: DEFAULT_BAN_THRESHOLD,
+            ban_duration: DEFAULT_BAN_DURATION,
+            decay_half_life: DEFAULT_DECAY_HALF_LIFE,
+        }
+    }
+}
+
+/// Reputation of a single peer.
+#[derive(Debug)]
+struct PeerReputation {
+    /// Reputation value at `updated`.
+    value: i32,
+
+    /// When was the value last updated.
+    updated: Instant,
+
+    /// Until when is the peer banned, if at all.
+    banned_until: Option<Instant>,
+}
+
+impl PeerReputation {
+    /// Get the value decayed with `half_life` at `now`.
+    fn value_at(&self, half_life: Duration, now: Instant) -> i32 {
+        let half_life = half_life.as_secs_f64();
+        if half_life == 0f64 {
+            return self.value;
+        }
+
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.value as f64 * 0.5f64.powf(elapsed / half_life)).round() as i32
+    }
+
+    /// Check if the peer is banned at `now`.
+    fn is_banned(&self, now: Instant) -> bool {
+        self.banned_until.is_some_and(|banned_until| banned_until > now)
+    }
+}
+
+/// Reputation store.
+///
+/// Reputation values decay exponentially towards zero so that the values are computed lazily
+/// when they're queried or updated.
+#[derive(Debug, Default)]
+pub(crate) struct ReputationStore {
+    /// Reputation configuration.
+    config: ReputationConfig,
+
+    /// Peer reputations.
+    peers: HashMap<PeerId, PeerReputation>,
+
+    /// When are fully decayed reputations pruned next.
+    next_prune: Option<Instant>,
+}
+
+impl ReputationStore {
+    /// Set reputation configuration.
+    pub(crate) fn set_config(&mut self, config: ReputationConfig) {
+        self.config = config;
+    }
+
+    /// Get the reputation of `peer` at `now`.
+    pub(crate) fn reputation(&self, peer: &PeerId, now: Instant) -> i32 {
+        self.peers.get(peer).map_or(0i32, |reputation| {
+            reputation.value_at(self.config.decay_half_life, now)
+        })
+    }
+
+    /// Check if `peer` is banned at `now`.
+    pub(crate) fn is_banned(&self, peer: &PeerId, now: Instant) -> bool {
+        self.peers.get(peer).is_some_and(|reputation| reputation.is_banned(now))
+    }
+
+    /// Remove reputations which have decayed to zero and whose bans have expired.
+    fn prune(&mut self, now: Instant) {
+        let half_life = self.config.decay_half_life;
+
+        self.peers.retain(|_, reputation| {
+            reputation.is_banned(now) || reputation.value_at(half_life, now) != 0
+        });
+        self.next_prune = Some(now + PRUNE_INTERVAL);
+    }
+
+    /// Apply `change` to the reputation of `peer` at `now`.
+    ///
+    /// Returns `true` if the peer was banned as a result of the change.
+    pub(crate) fn report(&mut self, peer: PeerId, change: ReputationChange, now: Instant) -> bool {
+        if !self.next_prune.is_some_and(|next_prune| next_prune > now) {
+            self.prune(now);
+        }
+
+        let value = self.reputation(&peer, now).saturating_add(change.value);
+
+        let reputation = self.peers.entry(peer).or_insert(PeerReputation {
+            value,
+            updated: now,
+            banned_until: None,
+        });
+        reputation.value = value;
+        reputation.updated = now;
+
+        let banned = reputation.is_banned(now);
+        if banned || value >= self.config.ban_threshold {
+            if value == 0 && !banned {
+                self.peers.remove(&peer);
+            }
+
+            return false;
+        }
+
+        reputation.banned_until = Some(now + self.config.ban_duration);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> ReputationStore {
+        let mut store = ReputationStore::default();
+        store.set_config(ReputationConfig {
+            ban_threshold: -100,
+            ban_duration: Duration::from_secs(30),
+            decay_half_life: Duration::from_secs(10),
+        });
+
+        store
+    }
+
+    #[test]
+    fn reputation_decays() {
+        let mut store = store();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert!(!store.report(peer, ReputationChange::new(-80, "misbehaved"), now));
+        assert_eq!(store.reputation(&peer, now), -80);
+
+        let now = now + Duration::from_secs(10);
+        assert_eq!(store.reputation(&peer, now), -40);
+
+        // the decayed value is used as the base for the next change
+        assert!(!store.report(peer, ReputationChange::new(-50, "misbehaved"), now));
+        assert_eq!(store.reputation(&peer, now), -90);
+        assert!(!store.is_banned(&peer, now));
+    }
+
+    #[test]
+    fn peer_banned_below_threshold() {
+        let mut store = store();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert!(!store.report(peer, ReputationChange::new(-60, "misbehaved"), now));
+        assert!(store.report(peer, ReputationChange::new(-60, "misbehaved"), now));
+        assert!(store.is_banned(&peer, now));
+
+        // peer is already banned so the ban is not reported again
+        assert!(!store.report(peer, ReputationChange::new_fatal("misbehaved"), now));
+
+        assert!(!store.is_banned(&peer, now + Duration::from_secs(31)));
+    }
+
+    #[test]
+    fn positive_changes_offset_negative_ones() {
+        let mut store = store();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        assert!(!store.report(peer, ReputationChange::new(50, "useful"), now));
+        assert!(!store.report(peer, ReputationChange::new(-120, "misbehaved"), now));
+        assert_eq!(store.reputation(&peer, now), -70);
+
+        assert!(!store.report(peer, ReputationChange::new(70, "useful"), now));
+        assert!(store.peers.is_empty());
+    }
+
+    #[test]
+    fn decayed_reputations_pruned() {
+        let mut store = store();
+        store.set_config(ReputationConfig {
+            ban_duration: Duration::from_secs(300),
+            ..store.config
+        });
+        let (peer1, peer2, peer3) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let now = Instant::now();
+
+        assert!(!store.report(peer1, ReputationChange::new(-80, "misbehaved"), now));
+        assert!(store.report(peer2, ReputationChange::new_fatal("misbehaved"), now));
+
+        // reputation of the first peer has decayed to zero by the time the store is pruned but
+        // the second peer is still banned
+        let now = now + 2 * PRUNE_INTERVAL;
+        assert!(!store.report(peer3, ReputationChange::new(10, "useful"), now));
+        assert_eq!(store.peers.len(), 2);
+        assert!(!store.peers.contains_key(&peer1));
+        assert!(store.is_banned(&peer2, now));
+    }
+}
@@ -25,7 +25,10 @@ use crate::{
 
 use multiaddr::Multiaddr;
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
 
 /// Supported protocols.
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
@@ -41,6 +44,56 @@ pub enum SupportedTransport {
 
     /// WebSocket
     WebSocket,
+
+    /// In-process memory transport.
+    Memory,
+
+    /// Custom transport.
+    Custom(CustomTransport),
+}
+
+/// Custom transport registered with
+/// [`ConfigBuilder::with_transport()`](crate::config::ConfigBuilder::with_transport).
+///
+/// Custom transports are identified by their name.
+#[derive(Debug, Copy, Clone)]
+pub struct CustomTransport {
+    /// Name of the transport.
+    name: &'static str,
+
+    /// Check if the transport can dial an address.
+    matcher: fn(&Multiaddr) -> bool,
+}
+
+impl CustomTransport {
+    /// Create new [`CustomTransport`].
+    pub fn new(name: &'static str, matcher: fn(&Multiaddr) -> bool) -> Self {
+        Self { name, matcher }
+    }
+
+    /// Get name of the transport.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Check if the transport can dial `address`.
+    pub fn supports(&self, address: &Multiaddr) -> bool {
+        (self.matcher)(address)
+    }
+}
+
+impl PartialEq for CustomTransport {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for CustomTransport {}
+
+impl Hash for CustomTransport {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
 }
 
 /// Peer state.
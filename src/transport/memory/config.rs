@@ -0,0 +1,93 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Memory transport configuration.
+
+use crate::{
+    crypto::noise::{MAX_READ_AHEAD_FACTOR, MAX_WRITE_BUFFER_SIZE},
+    transport::{memory::network::Network, CONNECTION_OPEN_TIMEOUT, SUBSTREAM_OPEN_TIMEOUT},
+};
+
+/// Memory transport configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Listen addresses for the transport.
+    ///
+    /// Addresses are of the form `/memory/<port>` and port `0` allocates a random unused port.
+    ///
+    /// Default listen address is `/memory/0`.
+    pub listen_addresses: Vec<multiaddr::Multiaddr>,
+
+    /// Yamux configuration.
+    pub yamux_config: crate::yamux::Config,
+
+    /// Should the connections be encrypted using Noise.
+    ///
+    /// If disabled, the Noise handshake is skipped and yamux is negotiated directly over the
+    /// in-process pipe. Peer IDs are then exchanged through the listener registry instead of
+    /// being authenticated by the handshake. Both ends of a connection must use the same setting.
+    ///
+    /// Enabled by default.
+    pub noise: bool,
+
+    /// Noise read-ahead frame count.
+    ///
+    /// See [`tcp::config::Config::noise_read_ahead_frame_count`](crate::transport::tcp::config::Config::noise_read_ahead_frame_count).
+    pub noise_read_ahead_frame_count: usize,
+
+    /// Noise write buffer size.
+    ///
+    /// See [`tcp::config::Config::noise_write_buffer_size`](crate::transport::tcp::config::Config::noise_write_buffer_size).
+    pub noise_write_buffer_size: usize,
+
+    /// Connection open timeout.
+    ///
+    /// How long should litep2p wait for a connection to be negotiated.
+    pub connection_open_timeout: std::time::Duration,
+
+    /// Substream open timeout.
+    ///
+    /// How long should litep2p wait for a substream to be opened before considering
+    /// the substream rejected.
+    pub substream_open_timeout: std::time::Duration,
+
+    /// Simulated network the connections are routed through.
+    ///
+    /// If set, connections dialed by this node are subject to the link characteristics and
+    /// partitions of the [`Network`]. Both nodes should be connected to the same network.
+    ///
+    /// Disabled by default.
+    pub network: Option<Network>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_addresses: vec!["/memory/0".parse().expect("valid address")],
+            yamux_config: Default::default(),
+            noise: true,
+            noise_read_ahead_frame_count: MAX_READ_AHEAD_FACTOR,
+            noise_write_buffer_size: MAX_WRITE_BUFFER_SIZE,
+            connection_open_timeout: CONNECTION_OPEN_TIMEOUT,
+            substream_open_timeout: SUBSTREAM_OPEN_TIMEOUT,
+            network: None,
+        }
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! In-process listener registry for the memory transport.
+
+use crate::{
+    error::{AddressError, Error},
+    transport::memory::network::Network,
+    PeerId,
+};
+
+use futures::Stream;
+use multiaddr::{Multiaddr, Protocol};
+use parking_lot::Mutex;
+use rand::Rng;
+use tokio::{
+    io::DuplexStream,
+    sync::mpsc::{channel, error::TrySendError, Receiver, Sender},
+};
+
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::memory::listener";
+
+/// Size of the buffer of each direction of an in-process pipe.
+const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// How many inbound connections can be waiting for the listener to accept them.
+const PENDING_CONNECTIONS: usize = 256;
+
+/// Listeners of all memory transports of the process, keyed by port.
+static LISTENERS: Mutex<BTreeMap<u64, ListenerContext>> = parking_lot::const_mutex(BTreeMap::new());
+
+/// Registered listener.
+struct ListenerContext {
+    /// Peer ID of the listening node.
+    peer: PeerId,
+
+    /// TX channel for sending inbound connections to the listener.
+    tx: Sender<InboundConnection>,
+}
+
+/// Inbound connection received by [`MemoryListener`].
+#[derive(Debug)]
+pub(super) struct InboundConnection {
+    /// Listener's end of the pipe.
+    pub(super) stream: DuplexStream,
+
+    /// Peer ID of the dialer.
+    pub(super) peer: PeerId,
+
+    /// Address of the dialer.
+    pub(super) address: Multiaddr,
+}
+
+/// Memory listener.
+///
+/// Ports of the listener are released when the listener is dropped.
+pub(super) struct MemoryListener {
+    /// Ports the listener is registered to.
+    ports: Vec<u64>,
+
+    /// RX channel for receiving inbound connections.
+    rx: Receiver<InboundConnection>,
+}
+
+impl MemoryListener {
+    /// Create new [`MemoryListener`] for `peer` and register it to `addresses`.
+    ///
+    /// Port `0` is replaced with a random unused port.
+    pub(super) fn new(
+        peer: PeerId,
+        addresses: Vec<Multiaddr>,
+    ) -> crate::Result<(Self, Vec<Multiaddr>)> {
+        let (tx, rx) = channel(PENDING_CONNECTIONS);
+        let mut listeners = LISTENERS.lock();
+        let mut ports = Vec::new();
+
+        for address in addresses {
+            let (port, _) = Self::get_port(&address)?;
+            let port = match port {
+                0 => loop {
+                    let port = rand::thread_rng().gen_range(1..u64::MAX);
+
+                    if !listeners.contains_key(&port) {
+                        break port;
+                    }
+                },
+                port if listeners.contains_key(&port) => {
+                    // release the ports that were already registered
+                    for port in &ports {
+                        listeners.remove(port);
+                    }
+
+                    return Err(Error::IoError(ErrorKind::AddrInUse));
+                }
+                port => port,
+            };
+
+            listeners.insert(
+                port,
+                ListenerContext {
+                    peer,
+                    tx: tx.clone(),
+                },
+            );
+            ports.push(port);
+        }
+
+        let listen_addresses = ports
+            .iter()
+            .map(|port| Multiaddr::empty().with(Protocol::Memory(*port)))
+            .collect();
+
+        Ok((Self { ports, rx }, listen_addresses))
+    }
+
+    /// Extract port and `PeerId`, if it exists, from `address`.
+    pub(super) fn get_port(address: &Multiaddr) -> crate::Result<(u64, Option<PeerId>)> {
+        let mut iter = address.iter();
+
+        let port = match iter.next() {
+            Some(Protocol::Memory(port)) => port,
+            protocol => {
+                tracing::error!(
+                    target: LOG_TARGET,
+                    ?protocol,
+                    "invalid transport protocol, expected `memory`",
+                );
+                return Err(Error::AddressError(AddressError::InvalidProtocol));
+            }
+        };
+
+        let maybe_peer = match iter.next() {
+            Some(Protocol::P2p(multihash)) => Some(PeerId::from_multihash(multihash)?),
+            None => None,
+            protocol => {
+                tracing::error!(
+                    target: LOG_TARGET,
+                    ?protocol,
+                    "invalid protocol, expected `P2p` or `None`"
+                );
+                return Err(Error::AddressError(AddressError::InvalidProtocol));
+            }
+        };
+
+        Ok((port, maybe_peer))
+    }
+
+    /// Dial `address` as `peer` whose own address is `local_address`.
+    ///
+    /// Returns the dialer's end of the pipe and the `PeerId` of the listener.
+    pub(super) fn dial(
+        peer: PeerId,
+        local_address: Multiaddr,
+        address: &Multiaddr,
+        network: Option<&Network>,
+    ) -> crate::Result<(DuplexStream, PeerId)> {
+        let (port, _) = Self::get_port(address)?;
+        let listeners = LISTENERS.lock();
+        let context = listeners.get(&port).ok_or(Error::IoError(ErrorKind::ConnectionRefused))?;
+
+        let (dialer, listener) = match network {
+            Some(network) => network.connect(peer, context.peer)?,
+            None => tokio::io::duplex(PIPE_BUFFER_SIZE),
+        };
+        let connection = InboundConnection {
+            stream: listener,
+            peer,
+            address: local_address,
+        };
+
+        match context.tx.try_send(connection) {
+            Ok(()) => Ok((dialer, context.peer)),
+            Err(TrySendError::Full(_)) => Err(Error::IoError(ErrorKind::ConnectionRefused)),
+            Err(TrySendError::Closed(_)) => Err(Error::IoError(ErrorKind::ConnectionRefused)),
+        }
+    }
+}
+
+impl Drop for MemoryListener {
+    fn drop(&mut self) {
+        let mut listeners = LISTENERS.lock();
+
+        for port in &self.ports {
+            listeners.remove(port);
+        }
+    }
+}
+
+impl Stream for MemoryListener {
+    type Item = InboundConnection;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn parse_addresses() {
+        let peer = PeerId::random();
+
+        assert_eq!(
+            MemoryListener::get_port(&"/memory/1337".parse().unwrap()).unwrap(),
+            (1337, None)
+        );
+        assert_eq!(
+            MemoryListener::get_port(&format!("/memory/1337/p2p/{peer}").parse().unwrap()).unwrap(),
+            (1337, Some(peer))
+        );
+        assert!(MemoryListener::get_port(&"/ip4/127.0.0.1/tcp/1337".parse().unwrap()).is_err());
+        assert!(MemoryListener::get_port(&"/memory/1337/tcp/1".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn port_in_use() {
+        let (_listener, addresses) =
+            MemoryListener::new(PeerId::random(), vec!["/memory/0".parse().unwrap()]).unwrap();
+
+        assert!(std::matches!(
+            MemoryListener::new(PeerId::random(), addresses),
+            Err(Error::IoError(ErrorKind::AddrInUse))
+        ));
+    }
+
+    #[test]
+    fn port_released_on_drop() {
+        let (listener, addresses) =
+            MemoryListener::new(PeerId::random(), vec!["/memory/0".parse().unwrap()]).unwrap();
+        drop(listener);
+
+        assert!(std::matches!(
+            MemoryListener::dial(PeerId::random(), Multiaddr::empty(), &addresses[0], None),
+            Err(Error::IoError(ErrorKind::ConnectionRefused))
+        ));
+        assert!(MemoryListener::new(PeerId::random(), addresses).is_ok());
+    }
+
+    #[tokio::test]
+    async fn dial_listener() {
+        let listener_peer = PeerId::random();
+        let dialer_peer = PeerId::random();
+        let (mut listener, addresses) =
+            MemoryListener::new(listener_peer, vec!["/memory/0".parse().unwrap()]).unwrap();
+
+        let (_stream, peer) = MemoryListener::dial(
+            dialer_peer,
+            "/memory/1".parse().unwrap(),
+            &addresses[0],
+            None,
+        )
+        .unwrap();
+        assert_eq!(peer, listener_peer);
+
+        let inbound = listener.next().await.unwrap();
+        assert_eq!(inbound.peer, dialer_peer);
+        assert_eq!(inbound.address, "/memory/1".parse().unwrap());
+    }
+}
@@ -0,0 +1,594 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! In-process memory transport.
+//!
+//! Connections are established over in-process pipes between nodes listening on `/memory/<port>`
+//! addresses, which makes the transport useful for tests and simulations that don't need real
+//! sockets. Nodes can only connect to other nodes running in the same process.
+//!
+//! Connections can be routed through a simulated [`Network`](network::Network) to give them
+//! latency, limited bandwidth and loss, or to partition the nodes from each other.
+//! [`Simulation`](simulation::Simulation) spins up several nodes connected to the same network.
+
+use crate::{
+    config::Role,
+    error::Error,
+    transport::{
+        manager::TransportHandle,
+        memory::{
+            config::Config,
+            connection::{MemoryConnection, NegotiatedConnection},
+            listener::{InboundConnection, MemoryListener},
+        },
+        Transport, TransportBuilder, TransportEvent,
+    },
+    types::ConnectionId,
+    PeerId,
+};
+
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, Stream, StreamExt},
+};
+use multiaddr::{Multiaddr, Protocol};
+use tokio::io::DuplexStream;
+
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pub(crate) use substream::Substream;
+
+mod connection;
+mod listener;
+mod substream;
+
+pub mod config;
+pub mod network;
+pub mod simulation;
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::memory";
+
+/// Memory transport.
+pub(crate) struct MemoryTransport {
+    /// Transport context.
+    context: TransportHandle,
+
+    /// Transport configuration.
+    config: Config,
+
+    /// Local peer ID.
+    local_peer_id: PeerId,
+
+    /// Address reported to remote peers as the address of the local node.
+    local_address: Multiaddr,
+
+    /// Memory listener.
+    listener: MemoryListener,
+
+    /// Pending dials.
+    pending_dials: HashMap<ConnectionId, Multiaddr>,
+
+    /// Pending opening connections.
+    pending_connections:
+        FuturesUnordered<BoxFuture<'static, Result<NegotiatedConnection, (ConnectionId, Error)>>>,
+
+    /// Pending raw, unnegotiated connections.
+    pending_raw_connections: FuturesUnordered<
+        BoxFuture<'static, Result<(ConnectionId, Multiaddr, DuplexStream, PeerId), ConnectionId>>,
+    >,
+
+    /// Opened raw connection, waiting for approval/rejection from `TransportManager`.
+    opened_raw: HashMap<ConnectionId, (DuplexStream, Multiaddr, PeerId)>,
+
+    /// Canceled raw connections.
+    canceled: HashSet<ConnectionId>,
+
+    /// Connections which have been opened and negotiated but are being validated by the
+    /// `TransportManager`.
+    pending_open: HashMap<ConnectionId, NegotiatedConnection>,
+}
+
+impl MemoryTransport {
+    /// Handle inbound connection.
+    fn on_inbound_connection(&mut self, connection: InboundConnection) {
+        let connection_id = self.context.next_connection_id();
+        let InboundConnection {
+            stream,
+            peer,
+            address,
+        } = connection;
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?connection_id,
+            ?peer,
+            ?address,
+            "inbound connection",
+        );
+
+        self.negotiate_connection(stream, peer, Role::Listener, address, connection_id);
+    }
+
+    /// Start negotiating the connection in the background.
+    fn negotiate_connection(
+        &mut self,
+        stream: DuplexStream,
+        peer: PeerId,
+        role: Role,
+        address: Multiaddr,
+        connection_id: ConnectionId,
+    ) {
+        let keypair = self.context.keypair.clone();
+        let noise = self.config.noise;
+        let yamux_config = self.config.yamux_config.clone();
+        let max_read_ahead_factor = self.config.noise_read_ahead_frame_count;
+        let max_write_buffer_size = self.config.noise_write_buffer_size;
+        let connection_open_timeout = self.config.connection_open_timeout;
+
+        self.pending_connections.push(Box::pin(async move {
+            match tokio::time::timeout(
+                connection_open_timeout,
+                MemoryConnection::negotiate_connection(
+                    stream,
+                    peer,
+                    role,
+                    address,
+                    connection_id,
+                    keypair,
+                    noise,
+                    yamux_config,
+                    max_read_ahead_factor,
+                    max_write_buffer_size,
+                ),
+            )
+            .await
+            {
+                Err(_) => Err((connection_id, Error::Timeout)),
+                Ok(Err(error)) => Err((connection_id, error)),
+                Ok(Ok(connection)) => Ok(connection),
+            }
+        }));
+    }
+
+    /// Dial `address` and verify that the listener is the expected peer, if specified.
+    fn dial_peer(&self, address: &Multiaddr) -> crate::Result<(DuplexStream, PeerId)> {
+        let (_, expected) = MemoryListener::get_port(address)?;
+        let (stream, peer) = MemoryListener::dial(
+            self.local_peer_id,
+            self.local_address.clone(),
+            address,
+            self.config.network.as_ref(),
+        )?;
+
+        match expected {
+            Some(expected) if expected != peer => Err(Error::PeerIdMismatch(expected, peer)),
+            _ => Ok((stream, peer)),
+        }
+    }
+}
+
+impl TransportBuilder for MemoryTransport {
+    type Config = Config;
+    type Transport = MemoryTransport;
+
+    /// Create new [`MemoryTransport`].
+    fn new(
+        context: TransportHandle,
+        mut config: Self::Config,
+    ) -> crate::Result<(Self, Vec<Multiaddr>)> {
+        tracing::debug!(
+            target: LOG_TARGET,
+            listen_addresses = ?config.listen_addresses,
+            "start memory transport",
+        );
+
+        let local_peer_id = PeerId::from_public_key(&context.keypair.public().into());
+        let (listener, listen_addresses) =
+            MemoryListener::new(local_peer_id, std::mem::take(&mut config.listen_addresses))?;
+        let local_address = listen_addresses
+            .first()
+            .cloned()
+            .unwrap_or_else(|| Multiaddr::empty().with(Protocol::Memory(0)));
+
+        Ok((
+            Self {
+                listener,
+                config,
+                context,
+                local_peer_id,
+                local_address,
+                canceled: HashSet::new(),
+                opened_raw: HashMap::new(),
+                pending_open: HashMap::new(),
+                pending_dials: HashMap::new(),
+                pending_connections: FuturesUnordered::new(),
+                pending_raw_connections: FuturesUnordered::new(),
+            },
+            listen_addresses,
+        ))
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn dial(&mut self, connection_id: ConnectionId, address: Multiaddr) -> crate::Result<()> {
+        tracing::debug!(target: LOG_TARGET, ?connection_id, ?address, "open connection");
+
+        self.pending_dials.insert(connection_id, address.clone());
+
+        match self.dial_peer(&address) {
+            Ok((stream, peer)) =>
+                self.negotiate_connection(stream, peer, Role::Dialer, address, connection_id),
+            Err(error) => self
+                .pending_connections
+                .push(Box::pin(async move { Err((connection_id, error)) })),
+        }
+
+        Ok(())
+    }
+
+    fn accept(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
+        let context = self
+            .pending_open
+            .remove(&connection_id)
+            .ok_or(Error::ConnectionDoesntExist(connection_id))?;
+        let protocol_set = self.context.protocol_set(connection_id);
+        let bandwidth_sink = self.context.bandwidth_sink.clone();
+        let substream_open_timeout = self.config.substream_open_timeout;
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?connection_id,
+            "start connection",
+        );
+
+        self.context.executor.run(Box::pin(async move {
+            if let Err(error) = MemoryConnection::new(
+                context,
+                protocol_set,
+                bandwidth_sink,
+                substream_open_timeout,
+            )
+            .start()
+            .await
+            {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    ?connection_id,
+                    ?error,
+                    "connection exited with error",
+                );
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn reject(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
+        // rejected connection has either been negotiated or it's a raw connection waiting to
+        // be negotiated, in both cases the connection is closed by dropping it
+        match self.pending_open.remove(&connection_id).is_some()
+            || self.opened_raw.remove(&connection_id).is_some()
+        {
+            true => Ok(()),
+            false => Err(Error::ConnectionDoesntExist(connection_id)),
+        }
+    }
+
+    fn open(
+        &mut self,
+        connection_id: ConnectionId,
+        addresses: Vec<Multiaddr>,
+    ) -> crate::Result<()> {
+        // pipes are opened synchronously so the first reachable address is used
+        let result = addresses
+            .into_iter()
+            .find_map(|address| match self.dial_peer(&address) {
+                Ok((stream, peer)) => Some((connection_id, address, stream, peer)),
+                Err(error) => {
+                    tracing::debug!(
+                        target: LOG_TARGET,
+                        ?connection_id,
+                        ?address,
+                        ?error,
+                        "failed to open connection",
+                    );
+                    None
+                }
+            })
+            .ok_or(connection_id);
+
+        self.pending_raw_connections.push(Box::pin(async move { result }));
+
+        Ok(())
+    }
+
+    fn negotiate(&mut self, connection_id: ConnectionId) -> crate::Result<()> {
+        let (stream, address, peer) = self
+            .opened_raw
+            .remove(&connection_id)
+            .ok_or(Error::ConnectionDoesntExist(connection_id))?;
+
+        tracing::trace!(
+            target: LOG_TARGET,
+            ?peer,
+            ?connection_id,
+            ?address,
+            "negotiate connection",
+        );
+
+        self.pending_dials.insert(connection_id, address.clone());
+        self.negotiate_connection(stream, peer, Role::Dialer, address, connection_id);
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, connection_id: ConnectionId) {
+        // raw connection which has already been reported can be dropped right away, otherwise
+        // the connection is dropped when it's polled from `pending_raw_connections`
+        if self.opened_raw.remove(&connection_id).is_none() {
+            self.canceled.insert(connection_id);
+        }
+    }
+}
+
+impl Stream for MemoryTransport {
+    type Item = TransportEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Poll::Ready(event) = self.listener.poll_next_unpin(cx) {
+            match event {
+                None => return Poll::Ready(None),
+                Some(connection) => self.on_inbound_connection(connection),
+            }
+        }
+
+        while let Poll::Ready(Some(result)) = self.pending_raw_connections.poll_next_unpin(cx) {
+            match result {
+                Ok((connection_id, address, stream, peer)) => {
+                    tracing::trace!(
+                        target: LOG_TARGET,
+                        ?connection_id,
+                        ?address,
+                        canceled = self.canceled.contains(&connection_id),
+                        "connection opened",
+                    );
+
+                    if !self.canceled.remove(&connection_id) {
+                        self.opened_raw.insert(connection_id, (stream, address.clone(), peer));
+
+                        return Poll::Ready(Some(TransportEvent::ConnectionOpened {
+                            connection_id,
+                            address,
+                        }));
+                    }
+                }
+                Err(connection_id) =>
+                    if !self.canceled.remove(&connection_id) {
+                        return Poll::Ready(Some(TransportEvent::OpenFailure { connection_id }));
+                    },
+            }
+        }
+
+        while let Poll::Ready(Some(connection)) = self.pending_connections.poll_next_unpin(cx) {
+            match connection {
+                Ok(connection) => {
+                    let peer = connection.peer();
+                    let endpoint = connection.endpoint();
+                    self.pending_dials.remove(&connection.connection_id());
+                    self.pending_open.insert(connection.connection_id(), connection);
+
+                    return Poll::Ready(Some(TransportEvent::ConnectionEstablished {
+                        peer,
+                        endpoint,
+                    }));
+                }
+                Err((connection_id, error)) => {
+                    if let Some(address) = self.pending_dials.remove(&connection_id) {
+                        return Poll::Ready(Some(TransportEvent::DialFailure {
+                            connection_id,
+                            address,
+                            error,
+                        }));
+                    }
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codec::ProtocolCodec,
+        crypto::ed25519::Keypair,
+        executor::DefaultExecutor,
+        transport::manager::{ProtocolContext, TransportHandle},
+        types::protocol::ProtocolName,
+        BandwidthSink,
+    };
+    use multihash::Multihash;
+    use std::sync::Arc;
+    use tokio::sync::mpsc::channel;
+
+    fn make_transport(config: Config) -> (MemoryTransport, Vec<Multiaddr>, PeerId) {
+        let keypair = Keypair::generate();
+        let peer = PeerId::from_public_key(&keypair.public().into());
+        let (tx, _rx) = channel(64);
+        let (event_tx, _event_rx) = channel(64);
+
+        let handle = TransportHandle {
+            executor: Arc::new(DefaultExecutor {}),
+            protocol_names: Vec::new(),
+            next_substream_id: Default::default(),
+            next_connection_id: Default::default(),
+            keypair,
+            tx: event_tx,
+            bandwidth_sink: BandwidthSink::new(),
+
+            protocols: HashMap::from_iter([(
+                ProtocolName::from("/notif/1"),
+                ProtocolContext {
+                    tx,
+                    codec: ProtocolCodec::Identity(32),
+                    fallback_names: Vec::new(),
+                },
+            )]),
+        };
+        let (transport, listen_addresses) = MemoryTransport::new(handle, config).unwrap();
+
+        (transport, listen_addresses, peer)
+    }
+
+    async fn connect_and_accept(noise: bool) {
+        let (mut transport1, listen_addresses, peer1) = make_transport(Config {
+            noise,
+            ..Default::default()
+        });
+        let (mut transport2, _, peer2) = make_transport(Config {
+            noise,
+            ..Default::default()
+        });
+
+        let address = listen_addresses[0].clone().with(Protocol::P2p(
+            Multihash::from_bytes(&peer1.to_bytes()).unwrap(),
+        ));
+        transport2.dial(ConnectionId::new(), address).unwrap();
+
+        let (res1, res2) = tokio::join!(transport1.next(), transport2.next());
+
+        match (res1, res2) {
+            (
+                Some(TransportEvent::ConnectionEstablished { peer: remote1, .. }),
+                Some(TransportEvent::ConnectionEstablished { peer: remote2, .. }),
+            ) => {
+                assert_eq!(remote1, peer2);
+                assert_eq!(remote2, peer1);
+            }
+            event => panic!("invalid event: {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_and_accept_works() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        connect_and_accept(true).await;
+    }
+
+    #[tokio::test]
+    async fn connect_and_accept_without_noise_works() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        connect_and_accept(false).await;
+    }
+
+    #[tokio::test]
+    async fn dial_failure() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (transport1, listen_addresses, _) = make_transport(Default::default());
+        let address = listen_addresses[0].clone();
+        drop(transport1);
+
+        let (mut transport2, _, _) = make_transport(Default::default());
+        let connection_id = ConnectionId::new();
+        transport2.dial(connection_id, address).unwrap();
+
+        match transport2.next().await {
+            Some(TransportEvent::DialFailure {
+                connection_id: failed,
+                ..
+            }) => assert_eq!(failed, connection_id),
+            event => panic!("invalid event: {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn reject_and_cancel_release_connection_state() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (mut transport1, listen_addresses, _) = make_transport(Default::default());
+        let (mut transport2, _, _) = make_transport(Default::default());
+
+        // negotiated connection is rejected
+        let connection_id = ConnectionId::new();
+        transport2.dial(connection_id, listen_addresses[0].clone()).unwrap();
+
+        let (_, res2) = tokio::join!(transport1.next(), transport2.next());
+        assert!(std::matches!(
+            res2,
+            Some(TransportEvent::ConnectionEstablished { .. })
+        ));
+
+        transport2.reject(connection_id).unwrap();
+        assert!(transport2.pending_open.is_empty());
+        assert!(transport2.canceled.is_empty());
+        assert!(transport2.reject(connection_id).is_err());
+
+        // opened raw connection is canceled
+        let connection_id = ConnectionId::from(1usize);
+        transport2.open(connection_id, vec![listen_addresses[0].clone()]).unwrap();
+
+        assert!(std::matches!(
+            transport2.next().await,
+            Some(TransportEvent::ConnectionOpened { .. })
+        ));
+
+        transport2.cancel(connection_id);
+        assert!(transport2.opened_raw.is_empty());
+        assert!(transport2.canceled.is_empty());
+    }
+
+    #[tokio::test]
+    async fn peer_id_mismatch() {
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+            .try_init();
+
+        let (_transport1, listen_addresses, _) = make_transport(Default::default());
+        let (mut transport2, _, _) = make_transport(Default::default());
+
+        let address = listen_addresses[0].clone().with(Protocol::P2p(
+            Multihash::from_bytes(&PeerId::random().to_bytes()).unwrap(),
+        ));
+        transport2.dial(ConnectionId::new(), address).unwrap();
+
+        assert!(std::matches!(
+            transport2.next().await,
+            Some(TransportEvent::DialFailure { .. })
+        ));
+    }
+}
@@ -0,0 +1,1058 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Simulated network for the memory transport.
+//!
+//! [`Network`] sits between the nodes of a [`MemoryTransport`](super::MemoryTransport) and shapes
+//! the traffic of every connection opened through it. Each link between two peers can be given
+//! latency, bandwidth and loss characteristics and peers can be partitioned from each other,
+//! either directly or by scheduling [`NetworkEvent`]s.
+//!
+//! Data sent over a connection and scheduled events are delivered by a single simulator task
+//! which owns the event queue of the network. The queue is ordered by delivery time and, for
+//! entries due at the same time, by the order in which they were queued. All random decisions
+//! are drawn from an RNG seeded with the value given to [`Network::new()`] in that same order, so
+//! running a simulation under paused tokio time (`#[tokio::test(start_paused = true)]`) with the
+//! same seed and the same inputs produces the same [`TraceEvent`]s.
+//!
+//! [`Simulation`](super::simulation::Simulation) can be used to spin up several nodes connected
+//! to the same network.
+
+use crate::{error::Error, transport::memory::config::Config, PeerId};
+
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, DuplexStream, ReadHalf, WriteHalf},
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        OwnedSemaphorePermit, Semaphore,
+    },
+    task::JoinHandle,
+    time::Instant,
+};
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    io::ErrorKind,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+/// Logging target for the file.
+const LOG_TARGET: &str = "litep2p::memory::network";
+
+/// Size of the in-process pipes between a node and the network.
+const PIPE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Maximum size of a chunk forwarded over a link at once.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// How many chunks can be in flight in one direction of a link.
+const MAX_IN_FLIGHT_CHUNKS: usize = 64;
+
+/// Default retransmission timeout for lost chunks.
+const RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Characteristics of a link between two peers.
+///
+/// The configuration applies to both directions of the link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkConfig {
+    /// One-way latency of the link.
+    pub latency: Duration,
+
+    /// Bandwidth of the link in bytes per second.
+    ///
+    /// `None` means the bandwidth is not limited.
+    pub bandwidth: Option<u64>,
+
+    /// Probability, in the range `[0, 1]`, that a chunk of data is lost.
+    ///
+    /// Connections of the memory transport are reliable, so a lost chunk is not dropped but
+    /// delivered again after [`LinkConfig::retransmission_timeout`], which also delays everything
+    /// sent after it.
+    pub loss: f64,
+
+    /// How long it takes for a lost chunk to be retransmitted.
+    pub retransmission_timeout: Duration,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            bandwidth: None,
+            loss: 0f64,
+            retransmission_timeout: RETRANSMISSION_TIMEOUT,
+        }
+    }
+}
+
+/// Event which changes the state of the network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkEvent {
+    /// Set the default link configuration.
+    SetDefaultLink(LinkConfig),
+
+    /// Set the configuration of the link between two peers.
+    SetLink {
+        /// First peer.
+        peer1: PeerId,
+
+        /// Second peer.
+        peer2: PeerId,
+
+        /// Link configuration.
+        config: LinkConfig,
+    },
+
+    /// Partition the network into groups.
+    ///
+    /// See [`Network::partition()`].
+    Partition(Vec<Vec<PeerId>>),
+
+    /// Heal all partitions.
+    Heal,
+}
+
+/// Event recorded by the network while tracing is enabled.
+///
+/// See [`Network::enable_trace()`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    /// Chunk of data was delivered.
+    Delivered {
+        /// Time of the delivery, measured from the creation of the network.
+        time: Duration,
+
+        /// ID of the connection, in the order the connections were opened.
+        connection: usize,
+
+        /// Sending peer.
+        from: PeerId,
+
+        /// Receiving peer.
+        to: PeerId,
+
+        /// Size of the chunk.
+        bytes: usize,
+
+        /// Was the chunk lost and retransmitted.
+        retransmitted: bool,
+    },
+
+    /// Network event was applied.
+    Applied {
+        /// Time when the event was applied, measured from the creation of the network.
+        time: Duration,
+
+        /// Applied event.
+        event: NetworkEvent,
+    },
+}
+
+/// Data carried by a queued delivery.
+enum Payload {
+    /// Chunk of data and the in-flight permit it holds.
+    Data(Vec<u8>, OwnedSemaphorePermit),
+
+    /// Sender closed its side of the connection.
+    Close,
+}
+
+/// Command sent to the simulator task.
+enum Command {
+    /// Payload was sent over a connection.
+    Send {
+        /// Connection ID.
+        connection: usize,
+
+        /// Sending peer.
+        from: PeerId,
+
+        /// Receiving peer.
+        to: PeerId,
+
+        /// When was the payload sent.
+        sent_at: Instant,
+
+        /// Sent payload.
+        payload: Payload,
+    },
+
+    /// Apply `event` at `at`.
+    Schedule {
+        /// When should the event be applied.
+        at: Instant,
+
+        /// Event.
+        event: NetworkEvent,
+    },
+}
+
+/// Action taken by the simulator once it's due.
+enum Action {
+    /// Deliver payload to the receiving peer.
+    Deliver {
+        /// Connection ID.
+        connection: usize,
+
+        /// Sending peer.
+        from: PeerId,
+
+        /// Receiving peer.
+        to: PeerId,
+
+        /// Payload.
+        payload: Payload,
+
+        /// Was the payload lost and retransmitted.
+        retransmitted: bool,
+    },
+
+    /// Apply event to the network.
+    Apply(NetworkEvent),
+}
+
+/// Entry of the simulator's event queue.
+struct QueueEntry {
+    /// When is the entry due.
+    at: Instant,
+
+    /// Sequence number of the entry, breaks ties between entries due at the same time.
+    sequence: u64,
+
+    /// Action to take.
+    action: Action,
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueueEntry {}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.sequence).cmp(&(other.at, other.sequence))
+    }
+}
+
+/// State of one direction of a connection.
+struct Direction {
+    /// When is the link free to transmit the next chunk.
+    link_free_at: Instant,
+
+    /// Delivery time of the latest chunk.
+    last_delivery: Instant,
+}
+
+/// Simulator which owns the event queue of the network.
+struct Simulator {
+    /// Shared state of the network.
+    state: Weak<Mutex<NetworkState>>,
+
+    /// Seeded RNG.
+    rng: StdRng,
+
+    /// RX channel for receiving commands.
+    rx: UnboundedReceiver<Command>,
+
+    /// Event queue, ordered by due time and sequence number.
+    queue: BinaryHeap<Reverse<QueueEntry>>,
+
+    /// Sequence number of the next queue entry.
+    next_sequence: u64,
+
+    /// Directions of active connections, keyed by connection ID and the sending peer.
+    directions: HashMap<(usize, PeerId), Direction>,
+}
+
+impl Simulator {
+    /// Push new entry to the event queue.
+    fn push(&mut self, at: Instant, action: Action) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        self.queue.push(Reverse(QueueEntry {
+            at,
+            sequence,
+            action,
+        }));
+    }
+
+    /// Handle command.
+    fn on_command(&mut self, state: &Arc<Mutex<NetworkState>>, command: Command) {
+        match command {
+            Command::Schedule { at, event } => self.push(at, Action::Apply(event)),
+            Command::Send {
+                connection,
+                from,
+                to,
+                sent_at,
+                payload,
+            } => {
+                let link = state.lock().link(&from, &to);
+                let direction =
+                    self.directions.entry((connection, from)).or_insert_with(|| Direction {
+                        link_free_at: sent_at,
+                        last_delivery: sent_at,
+                    });
+
+                let (delivery, retransmitted) = match &payload {
+                    Payload::Data(chunk, _) => {
+                        // data is serialized onto the link one chunk at a time
+                        if let Some(bandwidth) = link.bandwidth {
+                            let transmission = Duration::from_secs_f64(
+                                chunk.len() as f64 / std::cmp::max(bandwidth, 1) as f64,
+                            );
+                            direction.link_free_at =
+                                std::cmp::max(direction.link_free_at, sent_at) + transmission;
+                        } else {
+                            direction.link_free_at = sent_at;
+                        }
+
+                        let mut delivery = direction.link_free_at + link.latency;
+                        let retransmitted =
+                            link.loss > 0f64 && self.rng.gen_bool(link.loss.min(1f64));
+
+                        if retransmitted {
+                            delivery += link.retransmission_timeout;
+                        }
+
+                        (delivery, retransmitted)
+                    }
+                    Payload::Close => (sent_at + link.latency, false),
+                };
+
+                // the link is reliable so data cannot overtake earlier data
+                direction.last_delivery = std::cmp::max(direction.last_delivery, delivery);
+                let delivery = direction.last_delivery;
+
+                if std::matches!(payload, Payload::Close) {
+                    self.directions.remove(&(connection, from));
+                }
+
+                self.push(
+                    delivery,
+                    Action::Deliver {
+                        connection,
+                        from,
+                        to,
+                        payload,
+                        retransmitted,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Take all actions which are due.
+    fn on_due(&mut self, state: &Arc<Mutex<NetworkState>>) {
+        let now = Instant::now();
+
+        while self.queue.peek().is_some_and(|Reverse(entry)| entry.at <= now) {
+            let Reverse(entry) = self.queue.pop().expect("entry to exist");
+
+            match entry.action {
+                Action::Apply(event) => {
+                    let mut state = state.lock();
+
+                    state.record(|time| TraceEvent::Applied {
+                        time,
+                        event: event.clone(),
+                    });
+                    state.apply(event);
+                }
+                Action::Deliver {
+                    connection,
+                    from,
+                    to,
+                    payload,
+                    retransmitted,
+                } =>
+                    if !state.lock().deliver(connection, from, to, payload, retransmitted) {
+                        self.directions.retain(|(id, _), _| *id != connection);
+                    },
+            }
+        }
+    }
+
+    /// Run the simulator.
+    async fn run(mut self) {
+        loop {
+            let next = self.queue.peek().map(|Reverse(entry)| entry.at);
+            let deadline = next.unwrap_or_else(Instant::now);
+
+            tokio::select! {
+                biased;
+
+                command = self.rx.recv() => match (command, self.state.upgrade()) {
+                    (Some(command), Some(state)) => self.on_command(&state, command),
+                    _ => return,
+                },
+                _ = tokio::time::sleep_until(deadline), if next.is_some() => {
+                    match self.state.upgrade() {
+                        Some(state) => self.on_due(&state),
+                        None => return,
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Connection forwarded by the network.
+struct ForwardedConnection {
+    /// Dialing peer.
+    dialer: PeerId,
+
+    /// Listening peer.
+    listener: PeerId,
+
+    /// TX channel for writing delivered chunks to the dialer.
+    ///
+    /// `None` once the listener has closed its side of the connection.
+    to_dialer: Option<UnboundedSender<(Vec<u8>, OwnedSemaphorePermit)>>,
+
+    /// TX channel for writing delivered chunks to the listener.
+    ///
+    /// `None` once the dialer has closed its side of the connection.
+    to_listener: Option<UnboundedSender<(Vec<u8>, OwnedSemaphorePermit)>>,
+
+    /// Handle of the task forwarding the connection.
+    handle: JoinHandle<()>,
+}
+
+/// Shared state of the network.
+struct NetworkState {
+    /// When was the network created.
+    started: Instant,
+
+    /// Configuration used for links which have not been configured explicitly.
+    default_link: LinkConfig,
+
+    /// Configured links.
+    links: HashMap<(PeerId, PeerId), LinkConfig>,
+
+    /// Partition groups of peers.
+    groups: HashMap<PeerId, usize>,
+
+    /// Active connections.
+    connections: HashMap<usize, ForwardedConnection>,
+
+    /// Next connection ID.
+    next_connection_id: usize,
+
+    /// TX channel for sending commands to the simulator.
+    tx: UnboundedSender<Command>,
+
+    /// Simulator, taken when it's started.
+    simulator: Option<Simulator>,
+
+    /// Recorded trace, `None` if tracing is disabled.
+    trace: Option<Vec<TraceEvent>>,
+}
+
+impl NetworkState {
+    /// Get configuration of the link between `peer1` and `peer2`.
+    fn link(&self, peer1: &PeerId, peer2: &PeerId) -> LinkConfig {
+        self.links.get(&(*peer1, *peer2)).unwrap_or(&self.default_link).clone()
+    }
+
+    /// Check if `peer1` can reach `peer2`.
+    fn is_reachable(&self, peer1: &PeerId, peer2: &PeerId) -> bool {
+        match (self.groups.get(peer1), self.groups.get(peer2)) {
+            (Some(group1), Some(group2)) => group1 == group2,
+            _ => true,
+        }
+    }
+
+    /// Start the simulator if it's not running yet and get the TX channel for sending commands
+    /// to it.
+    fn simulator(&mut self) -> UnboundedSender<Command> {
+        if let Some(simulator) = self.simulator.take() {
+            tokio::spawn(simulator.run());
+        }
+
+        self.tx.clone()
+    }
+
+    /// Record trace event if tracing is enabled.
+    fn record(&mut self, event: impl FnOnce(Duration) -> TraceEvent) {
+        let time = Instant::now().saturating_duration_since(self.started);
+
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(event(time));
+        }
+    }
+
+    /// Apply `event` to the network.
+    fn apply(&mut self, event: NetworkEvent) {
+        match event {
+            NetworkEvent::SetDefaultLink(config) => self.default_link = config,
+            NetworkEvent::SetLink {
+                peer1,
+                peer2,
+                config,
+            } => {
+                self.links.insert((peer1, peer2), config.clone());
+                self.links.insert((peer2, peer1), config);
+            }
+            NetworkEvent::Partition(groups) => {
+                tracing::debug!(target: LOG_TARGET, ?groups, "partition network");
+
+                self.groups = groups
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(index, peers)| peers.into_iter().map(move |peer| (peer, index)))
+                    .collect();
+                self.sever_unreachable();
+            }
+            NetworkEvent::Heal => {
+                tracing::debug!(target: LOG_TARGET, "heal network");
+
+                self.groups.clear();
+            }
+        }
+    }
+
+    /// Deliver `payload` sent by `from` to `to` over `connection`.
+    ///
+    /// Returns `false` if the connection no longer exists.
+    fn deliver(
+        &mut self,
+        connection: usize,
+        from: PeerId,
+        to: PeerId,
+        payload: Payload,
+        retransmitted: bool,
+    ) -> bool {
+        let Some(forwarded) = self.connections.get_mut(&connection) else {
+            return false;
+        };
+        let writer = match forwarded.dialer == from {
+            true => &mut forwarded.to_listener,
+            false => &mut forwarded.to_dialer,
+        };
+
+        match payload {
+            Payload::Close => {
+                writer.take();
+            }
+            Payload::Data(chunk, permit) => {
+                let bytes = chunk.len();
+
+                if let Some(writer) = writer {
+                    let _ = writer.send((chunk, permit));
+                }
+
+                self.record(|time| TraceEvent::Delivered {
+                    time,
+                    connection,
+                    from,
+                    to,
+                    bytes,
+                    retransmitted,
+                });
+            }
+        }
+
+        true
+    }
+
+    /// Close connections between peers that can no longer reach each other.
+    fn sever_unreachable(&mut self) {
+        let unreachable = self
+            .connections
+            .iter()
+            .filter_map(|(id, connection)| {
+                (!self.is_reachable(&connection.dialer, &connection.listener)).then_some(*id)
+            })
+            .collect::<Vec<_>>();
+
+        for id in unreachable {
+            if let Some(connection) = self.connections.remove(&id) {
+                tracing::debug!(
+                    target: LOG_TARGET,
+                    dialer = ?connection.dialer,
+                    listener = ?connection.listener,
+                    "sever connection",
+                );
+
+                connection.handle.abort();
+            }
+        }
+    }
+}
+
+/// Simulated network.
+///
+/// The object is a cheaply cloneable handle and all clones control the same network. The
+/// simulator task is spawned on the tokio runtime when the first connection is opened or the
+/// first event is scheduled.
+#[derive(Clone)]
+pub struct Network {
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Network").finish_non_exhaustive()
+    }
+}
+
+impl Network {
+    /// Create new [`Network`] which draws its random decisions from an RNG seeded with `seed`.
+    pub fn new(seed: u64) -> Self {
+        let (tx, rx) = unbounded_channel();
+
+        Self {
+            state: Arc::new_cyclic(|state| {
+                Mutex::new(NetworkState {
+                    started: Instant::now(),
+                    default_link: LinkConfig::default(),
+                    links: HashMap::new(),
+                    groups: HashMap::new(),
+                    connections: HashMap::new(),
+                    next_connection_id: 0usize,
+                    tx,
+                    simulator: Some(Simulator {
+                        state: state.clone(),
+                        rng: StdRng::seed_from_u64(seed),
+                        rx,
+                        queue: BinaryHeap::new(),
+                        next_sequence: 0u64,
+                        directions: HashMap::new(),
+                    }),
+                    trace: None,
+                })
+            }),
+        }
+    }
+
+    /// Create memory transport configuration which connects the node to this network.
+    pub fn transport_config(&self) -> Config {
+        Config {
+            network: Some(self.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Start recording [`TraceEvent`]s.
+    pub fn enable_trace(&self) {
+        self.state.lock().trace.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the [`TraceEvent`]s recorded so far.
+    pub fn take_trace(&self) -> Vec<TraceEvent> {
+        self.state.lock().trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Set configuration for links which have not been configured explicitly.
+    pub fn set_default_link(&self, config: LinkConfig) {
+        self.apply(NetworkEvent::SetDefaultLink(config));
+    }
+
+    /// Set configuration of the link between `peer1` and `peer2`.
+    ///
+    /// The new configuration also applies to data sent over existing connections.
+    pub fn set_link(&self, peer1: PeerId, peer2: PeerId, config: LinkConfig) {
+        self.apply(NetworkEvent::SetLink {
+            peer1,
+            peer2,
+            config,
+        });
+    }
+
+    /// Partition the network into `groups`.
+    ///
+    /// Peers in different groups cannot reach each other: their connections are closed and new
+    /// dials between them are refused. Peers which are not part of any group can reach every
+    /// peer. Any previous partition is replaced.
+    pub fn partition(&self, groups: Vec<Vec<PeerId>>) {
+        self.apply(NetworkEvent::Partition(groups));
+    }
+
+    /// Heal all partitions.
+    pub fn heal(&self) {
+        self.apply(NetworkEvent::Heal);
+    }
+
+    /// Check if `peer1` can reach `peer2`.
+    pub fn is_reachable(&self, peer1: &PeerId, peer2: &PeerId) -> bool {
+        self.state.lock().is_reachable(peer1, peer2)
+    }
+
+    /// Apply `event` to the network.
+    pub fn apply(&self, event: NetworkEvent) {
+        let mut state = self.state.lock();
+
+        state.record(|time| TraceEvent::Applied {
+            time,
+            event: event.clone(),
+        });
+        state.apply(event);
+    }
+
+    /// Schedule `events` to be applied to the network.
+    ///
+    /// Each event is applied by the simulator once its delay, measured from the time of the call,
+    /// has elapsed. Events due at the same time are applied in the order they were given.
+    pub fn schedule(&self, events: Vec<(Duration, NetworkEvent)>) {
+        let tx = self.state.lock().simulator();
+        let now = Instant::now();
+
+        for (delay, event) in events {
+            let _ = tx.send(Command::Schedule {
+                at: now + delay,
+                event,
+            });
+        }
+    }
+
+    /// Open connection from `dialer` to `listener` through the network.
+    ///
+    /// Returns the streams for the dialer and the listener, respectively.
+    pub(super) fn connect(
+        &self,
+        dialer: PeerId,
+        listener: PeerId,
+    ) -> crate::Result<(DuplexStream, DuplexStream)> {
+        let mut state = self.state.lock();
+
+        if !state.is_reachable(&dialer, &listener) {
+            tracing::debug!(target: LOG_TARGET, ?dialer, ?listener, "peers are partitioned");
+            return Err(Error::IoError(ErrorKind::ConnectionRefused));
+        }
+
+        let (dialer_stream, dialer_link) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+        let (listener_link, listener_stream) = tokio::io::duplex(PIPE_BUFFER_SIZE);
+        let (to_dialer, to_dialer_rx) = unbounded_channel();
+        let (to_listener, to_listener_rx) = unbounded_channel();
+        let connection = state.next_connection_id;
+        state.next_connection_id += 1;
+
+        let tx = state.simulator();
+        let network = Arc::downgrade(&self.state);
+        let handle = tokio::spawn(async move {
+            let (dialer_read, dialer_write) = tokio::io::split(dialer_link);
+            let (listener_read, listener_write) = tokio::io::split(listener_link);
+
+            tokio::join!(
+                Self::read(tx.clone(), connection, dialer, listener, dialer_read),
+                Self::read(tx, connection, listener, dialer, listener_read),
+                Self::write(to_listener_rx, listener_write),
+                Self::write(to_dialer_rx, dialer_write),
+            );
+
+            if let Some(state) = network.upgrade() {
+                state.lock().connections.remove(&connection);
+            }
+        });
+
+        state.connections.insert(
+            connection,
+            ForwardedConnection {
+                dialer,
+                listener,
+                to_dialer: Some(to_dialer),
+                to_listener: Some(to_listener),
+                handle,
+            },
+        );
+
+        Ok((dialer_stream, listener_stream))
+    }
+
+    /// Read data sent by `from` to `to` and pass it to the simulator.
+    async fn read(
+        tx: UnboundedSender<Command>,
+        connection: usize,
+        from: PeerId,
+        to: PeerId,
+        mut reader: ReadHalf<DuplexStream>,
+    ) {
+        let permits = Arc::new(Semaphore::new(MAX_IN_FLIGHT_CHUNKS));
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+
+        loop {
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                return;
+            };
+            let nread = match reader.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(nread) => nread,
+            };
+
+            let command = Command::Send {
+                connection,
+                from,
+                to,
+                sent_at: Instant::now(),
+                payload: Payload::Data(buffer[..nread].to_vec(), permit),
+            };
+
+            if tx.send(command).is_err() {
+                return;
+            }
+        }
+
+        let _ = tx.send(Command::Send {
+            connection,
+            from,
+            to,
+            sent_at: Instant::now(),
+            payload: Payload::Close,
+        });
+    }
+
+    /// Write chunks delivered by the simulator to the receiving peer.
+    async fn write(
+        mut rx: UnboundedReceiver<(Vec<u8>, OwnedSemaphorePermit)>,
+        mut writer: WriteHalf<DuplexStream>,
+    ) {
+        while let Some((chunk, _permit)) = rx.recv().await {
+            if writer.write_all(&chunk).await.is_err() {
+                return;
+            }
+        }
+
+        let _ = writer.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn roundtrip(dialer: &mut DuplexStream, listener: &mut DuplexStream) {
+        let mut buffer = [0u8; 4];
+
+        dialer.write_all(b"ping").await.unwrap();
+        listener.read_exact(&mut buffer).await.unwrap();
+        listener.write_all(b"pong").await.unwrap();
+        dialer.read_exact(&mut buffer).await.unwrap();
+    }
+
+    fn delivery_times(trace: &[TraceEvent]) -> Vec<(Duration, bool)> {
+        trace
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Delivered {
+                    time,
+                    retransmitted,
+                    ..
+                } => Some((*time, *retransmitted)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn latency_is_applied() {
+        let network = Network::new(1337);
+        let (peer1, peer2) = (PeerId::random(), PeerId::random());
+
+        network.set_link(
+            peer1,
+            peer2,
+            LinkConfig {
+                latency: Duration::from_millis(50),
+                ..Default::default()
+            },
+        );
+        network.enable_trace();
+
+        let (mut dialer, mut listener) = network.connect(peer1, peer2).unwrap();
+        roundtrip(&mut dialer, &mut listener).await;
+
+        assert_eq!(
+            delivery_times(&network.take_trace()),
+            vec![
+                (Duration::from_millis(50), false),
+                (Duration::from_millis(100), false)
+            ],
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lost_chunks_are_retransmitted() {
+        let network = Network::new(1337);
+        let (peer1, peer2) = (PeerId::random(), PeerId::random());
+
+        network.set_default_link(LinkConfig {
+            loss: 1f64,
+            retransmission_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        network.enable_trace();
+
+        let (mut dialer, mut listener) = network.connect(peer1, peer2).unwrap();
+        roundtrip(&mut dialer, &mut listener).await;
+
+        assert_eq!(
+            delivery_times(&network.take_trace()),
+            vec![
+                (Duration::from_millis(50), true),
+                (Duration::from_millis(100), true)
+            ],
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bandwidth_is_applied() {
+        let network = Network::new(1337);
+        let (peer1, peer2) = (PeerId::random(), PeerId::random());
+
+        network.set_default_link(LinkConfig {
+            bandwidth: Some(64 * 1024),
+            ..Default::default()
+        });
+        network.enable_trace();
+
+        let (mut dialer, mut listener) = network.connect(peer1, peer2).unwrap();
+        tokio::spawn(async move { dialer.write_all(&vec![0u8; 32 * 1024]).await.unwrap() });
+
+        let mut buffer = vec![0u8; 32 * 1024];
+        listener.read_exact(&mut buffer).await.unwrap();
+
+        // four chunks, each taking 125ms to transmit
+        assert_eq!(
+            delivery_times(&network.take_trace()),
+            (1..=4)
+                .map(|chunk| (Duration::from_millis(125 * chunk), false))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn partition_and_heal() {
+        let network = Network::new(1337);
+        let (peer1, peer2, peer3) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+        let (mut dialer, mut listener) = network.connect(peer1, peer2).unwrap();
+        let _connection = network.connect(peer1, peer3).unwrap();
+        roundtrip(&mut dialer, &mut listener).await;
+
+        network.partition(vec![vec![peer1], vec![peer2]]);
+
+        // the connection is severed and new connections are refused
+        let mut buffer = [0u8; 1];
+        assert!(std::matches!(
+            listener.read(&mut buffer).await,
+            Ok(0) | Err(_)
+        ));
+        assert!(network.connect(peer2, peer1).is_err());
+
+        // `peer3` is not part of any group and can reach everyone
+        assert!(network.is_reachable(&peer1, &peer3));
+        assert!(network.is_reachable(&peer2, &peer3));
+        assert_eq!(network.state.lock().connections.len(), 1);
+
+        network.heal();
+
+        let (mut dialer, mut listener) = network.connect(peer2, peer1).unwrap();
+        roundtrip(&mut dialer, &mut listener).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scheduled_events() {
+        let network = Network::new(1337);
+        let (peer1, peer2) = (PeerId::random(), PeerId::random());
+
+        network.enable_trace();
+        network.schedule(vec![
+            (
+                Duration::from_millis(20),
+                NetworkEvent::Partition(vec![vec![peer1], vec![peer2]]),
+            ),
+            (Duration::from_millis(10), NetworkEvent::Heal),
+        ]);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        assert!(!network.is_reachable(&peer1, &peer2));
+        assert_eq!(
+            network.take_trace(),
+            vec![
+                TraceEvent::Applied {
+                    time: Duration::from_millis(10),
+                    event: NetworkEvent::Heal,
+                },
+                TraceEvent::Applied {
+                    time: Duration::from_millis(20),
+                    event: NetworkEvent::Partition(vec![vec![peer1], vec![peer2]]),
+                },
+            ],
+        );
+    }
+
+    async fn run_lossy_exchange(seed: u64, peers: &[PeerId; 3]) -> Vec<TraceEvent> {
+        let network = Network::new(seed);
+
+        network.enable_trace();
+        network.set_default_link(LinkConfig {
+            latency: Duration::from_millis(10),
+            loss: 0.5f64,
+            ..Default::default()
+        });
+        network.schedule(vec![(
+            Duration::from_secs(10),
+            NetworkEvent::Partition(vec![vec![peers[0]], vec![peers[1]]]),
+        )]);
+
+        let (mut dialer1, mut listener1) = network.connect(peers[0], peers[1]).unwrap();
+        let (mut dialer2, mut listener2) = network.connect(peers[0], peers[2]).unwrap();
+
+        for _ in 0..10 {
+            tokio::join!(
+                roundtrip(&mut dialer1, &mut listener1),
+                roundtrip(&mut dialer2, &mut listener2),
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(20)).await;
+
+        network.take_trace()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn same_seed_produces_same_trace() {
+        let peers = [PeerId::random(), PeerId::random(), PeerId::random()];
+
+        let trace1 = run_lossy_exchange(1337, &peers).await;
+        let trace2 = run_lossy_exchange(1337, &peers).await;
+        let trace3 = run_lossy_exchange(1338, &peers).await;
+
+        // link configuration, 40 chunks and the partition
+        assert_eq!(trace1.len(), 42);
+        assert_eq!(trace1, trace2);
+        assert_ne!(trace1, trace3);
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Harness for running several nodes over a simulated [`Network`].
+
+use crate::{
+    config::ConfigBuilder,
+    crypto::ed25519::{Keypair, SecretKey},
+    error::{AddressError, Error},
+    transport::memory::network::Network,
+    Litep2p, Litep2pEvent, PeerId,
+};
+
+use futures::future::select_all;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::time::Instant;
+
+use std::time::Duration;
+
+/// Salt mixed into the seed of the RNG used to generate node keys, so the keys don't depend on
+/// the random decisions of the network.
+const KEY_SEED_SALT: u64 = 0x6b65_7973;
+
+/// Simulation of several [`Litep2p`] nodes connected to the same [`Network`].
+///
+/// Node keys are derived from the seed of the simulation, so nodes get the same peer IDs in every
+/// run and, when the simulation runs under paused tokio time, two runs with the same seed and the
+/// same inputs record the same trace.
+pub struct Simulation {
+    /// Simulated network.
+    network: Network,
+
+    /// Nodes of the simulation.
+    nodes: Vec<Litep2p>,
+
+    /// When was the simulation started.
+    started: Instant,
+}
+
+impl Simulation {
+    /// Create new [`Simulation`] with `num_nodes` nodes.
+    ///
+    /// The key and the memory transport of each node are configured by the simulation and
+    /// `configure` is called with the index of the node and its [`ConfigBuilder`] to install the
+    /// protocols of the node.
+    pub fn new(
+        seed: u64,
+        num_nodes: usize,
+        mut configure: impl FnMut(usize, ConfigBuilder) -> ConfigBuilder,
+    ) -> crate::Result<Self> {
+        let network = Network::new(seed);
+        let mut rng = StdRng::seed_from_u64(seed ^ KEY_SEED_SALT);
+        let nodes = (0..num_nodes)
+            .map(|index| {
+                let keypair = Keypair::from(SecretKey::from_bytes(rng.gen::<[u8; 32]>())?);
+                let config = ConfigBuilder::new()
+                    .with_keypair(keypair)
+                    .with_memory_transport(network.transport_config());
+
+                Litep2p::new(configure(index, config).build())
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            network,
+            nodes,
+            started: Instant::now(),
+        })
+    }
+
+    /// Get the simulated network.
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    /// Get the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if the simulation has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get peer ID of `node`.
+    pub fn peer_id(&self, node: usize) -> PeerId {
+        *self.nodes[node].local_peer_id()
+    }
+
+    /// Get mutable reference to `node`.
+    pub fn node(&mut self, node: usize) -> &mut Litep2p {
+        &mut self.nodes[node]
+    }
+
+    /// Get time elapsed since the simulation was started.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Poll all nodes and return the index of the first node which emitted an event, and the
+    /// event.
+    ///
+    /// Nodes are polled in the order of their indices. Returns `None` if a node has shut down.
+    pub async fn next_event(&mut self) -> Option<(usize, Litep2pEvent)> {
+        if self.nodes.is_empty() {
+            return futures::future::pending().await;
+        }
+
+        let futures = self.nodes.iter_mut().enumerate().map(|(index, node)| {
+            Box::pin(async move { node.next_event().await.map(|event| (index, event)) })
+        });
+
+        select_all(futures).await.0
+    }
+
+    /// Poll all nodes for `duration` and return the events they emitted, together with the time
+    /// of the event, measured from the start of the simulation.
+    pub async fn run_for(&mut self, duration: Duration) -> Vec<(Duration, usize, Litep2pEvent)> {
+        let deadline = Instant::now() + duration;
+        let mut events = Vec::new();
+
+        loop {
+            tokio::select! {
+                event = self.next_event() => match event {
+                    Some((node, event)) => events.push((self.started.elapsed(), node, event)),
+                    None => return events,
+                },
+                _ = tokio::time::sleep_until(deadline) => return events,
+            }
+        }
+    }
+
+    /// Connect `dialer` to `listener` and wait until both nodes have reported the connection.
+    ///
+    /// Events emitted by the nodes while the connection is being established are discarded.
+    pub async fn connect(&mut self, dialer: usize, listener: usize) -> crate::Result<()> {
+        let (dialer_peer, listener_peer) = (self.peer_id(dialer), self.peer_id(listener));
+        let address = self.nodes[listener]
+            .listen_addresses()
+            .next()
+            .ok_or(Error::AddressError(AddressError::AddressNotAvailable))?
+            .clone();
+
+        self.nodes[dialer].dial_address(address).await?;
+
+        let (mut dialer_connected, mut listener_connected) = (false, false);
+
+        while !dialer_connected || !listener_connected {
+            match self.next_event().await.ok_or(Error::EssentialTaskClosed)? {
+                (node, Litep2pEvent::ConnectionEstablished { peer, .. })
+                    if node == dialer && peer == listener_peer =>
+                    dialer_connected = true,
+                (node, Litep2pEvent::ConnectionEstablished { peer, .. })
+                    if node == listener && peer == dialer_peer =>
+                    listener_connected = true,
+                (node, Litep2pEvent::DialFailure { error, .. }) if node == dialer =>
+                    return Err(error),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -20,13 +20,14 @@
 
 //! Transport protocol implementations provided by [`Litep2p`](`crate::Litep2p`).
 
-use crate::{transport::manager::TransportHandle, types::ConnectionId, Error, PeerId};
+use crate::{types::ConnectionId, Error, PeerId};
 
 use futures::Stream;
 use multiaddr::Multiaddr;
 
 use std::{fmt::Debug, time::Duration};
 
+pub mod memory;
 pub mod quic;
 pub mod tcp;
 pub mod webrtc;
@@ -35,6 +36,8 @@ pub mod websocket;
 pub(crate) mod dummy;
 pub(crate) mod manager;
 
+pub use manager::TransportHandle;
+
 /// Timeout for opening a connection.
 pub(crate) const CONNECTION_OPEN_TIMEOUT: Duration = Duration::from_secs(10);
 
@@ -107,7 +110,7 @@ impl Endpoint {
 
 /// Transport event.
 #[derive(Debug)]
-pub(crate) enum TransportEvent {
+pub enum TransportEvent {
     /// Fully negotiated connection established to remote peer.
     ConnectionEstablished {
         /// Peer ID.
@@ -155,8 +158,15 @@ pub(crate) enum TransportEvent {
     },
 }
 
-pub(crate) trait TransportBuilder {
+/// Builder for a [`Transport`].
+///
+/// Custom transports implement this trait so they can be registered with
+/// [`ConfigBuilder::with_transport()`](crate::config::ConfigBuilder::with_transport).
+pub trait TransportBuilder {
+    /// Transport configuration.
     type Config: Debug;
+
+    /// Transport type.
     type Transport: Transport;
 
     /// Create new [`Transport`] object.
@@ -165,11 +175,23 @@ pub(crate) trait TransportBuilder {
         Self: Sized;
 }
 
-pub(crate) trait Transport: Stream + Unpin + Send {
+/// Transport protocol.
+///
+/// Transports are driven by [`Litep2p`](crate::Litep2p) which polls them for [`TransportEvent`]s
+/// and instructs them to dial, accept or reject connections. Once a connection is accepted, the
+/// transport reports it and its substreams to the installed protocols using the
+/// [`ProtocolSet`](crate::protocol::ProtocolSet) obtained from
+/// [`TransportHandle::protocol_set()`].
+pub trait Transport: Stream + Unpin + Send {
     /// Dial `address` and negotiate connection.
+    ///
+    /// The result is reported with [`TransportEvent::ConnectionEstablished`] or
+    /// [`TransportEvent::DialFailure`].
     fn dial(&mut self, connection_id: ConnectionId, address: Multiaddr) -> crate::Result<()>;
 
     /// Accept negotiated connection.
+    ///
+    /// After the connection has been accepted, it must be reported to the installed protocols.
     fn accept(&mut self, connection_id: ConnectionId) -> crate::Result<()>;
 
     /// Reject negotiated connection.
@@ -177,13 +199,17 @@ pub(crate) trait Transport: Stream + Unpin + Send {
 
     /// Attempt to open connection to remote peer over one or more addresses.
     ///
-    /// TODO: documentation
+    /// Every connection opened successfully is reported with
+    /// [`TransportEvent::ConnectionOpened`] but is not negotiated until
+    /// [`Transport::negotiate()`] is called for it. If none of the addresses could be opened,
+    /// [`TransportEvent::OpenFailure`] is emitted.
     fn open(&mut self, connection_id: ConnectionId, addresses: Vec<Multiaddr>)
         -> crate::Result<()>;
 
     /// Negotiate opened connection.
     ///
-    /// TODO: documentation
+    /// The result is reported with [`TransportEvent::ConnectionEstablished`] or
+    /// [`TransportEvent::DialFailure`].
     fn negotiate(&mut self, connection_id: ConnectionId) -> crate::Result<()>;
 
     /// Cancel opening connections.
@@ -89,6 +89,11 @@ impl Sink<bytes::Bytes> for Substream {
         let item: Vec<u8> = match self.codec.as_ref().expect("codec to exist") {
             ProtocolCodec::Identity(_) => Identity::encode(item)?.into(),
             ProtocolCodec::UnsignedVarint(_) => UnsignedVarint::encode(item)?.into(),
+            ProtocolCodec::Custom(codec) => {
+                let mut frame = BytesMut::with_capacity(item.len());
+                codec.encode(item, &mut frame)?;
+                frame.into()
+            }
             ProtocolCodec::Unspecified => unreachable!(), // TODO: may not be correct
         };
         let id = self.id;
@@ -476,7 +476,7 @@ async fn substrate_close_substream() {
                 NotificationEvent::NotificationReceived { peer, .. } => {
                     assert_eq!(peer.to_bytes(), libp2p_peer.to_bytes());
                 }
-                NotificationEvent::NotificationStreamClosed { peer } => {
+                NotificationEvent::NotificationStreamClosed { peer, .. } => {
                     assert_eq!(peer.to_bytes(), libp2p_peer.to_bytes());
                     break;
                 }
@@ -0,0 +1,475 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Custom transport which passes substreams between nodes of the same process over channels.
+
+use futures::{Stream, StreamExt};
+use litep2p::{
+    config::ConfigBuilder,
+    crypto::ed25519::Keypair,
+    error::AddressError,
+    protocol::{
+        libp2p::ping::{Config as PingConfig, PingEvent},
+        Direction, Permit, ProtocolCommand, ProtocolSet,
+    },
+    substream::Substream,
+    transport::{Endpoint, Transport, TransportBuilder, TransportEvent, TransportHandle},
+    types::{protocol::ProtocolName, ConnectionId},
+    Error, Litep2p, Litep2pEvent, PeerId,
+};
+use multiaddr::{Multiaddr, Protocol};
+use parking_lot::Mutex;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::ErrorKind,
+    net::Ipv4Addr,
+    pin::Pin,
+    sync::atomic::{AtomicU16, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+/// Listeners of the bus.
+static LISTENERS: Mutex<BTreeMap<u16, (PeerId, Sender<BusConnection>)>> =
+    parking_lot::const_mutex(BTreeMap::new());
+
+/// Next listen port.
+static NEXT_PORT: AtomicU16 = AtomicU16::new(1);
+
+/// Check if `address` is a bus address.
+fn is_bus_address(address: &Multiaddr) -> bool {
+    let mut iter = address.iter();
+
+    std::matches!(iter.next(), Some(Protocol::Ip4(_)))
+        && std::matches!(iter.next(), Some(Protocol::Sctp(_)))
+}
+
+/// Connection between two nodes.
+struct BusConnection {
+    /// Remote peer.
+    peer: PeerId,
+
+    /// Send substreams opened by the local node.
+    tx: Sender<(ProtocolName, DuplexStream)>,
+
+    /// Receive substreams opened by the remote node.
+    rx: Receiver<(ProtocolName, DuplexStream)>,
+}
+
+impl BusConnection {
+    /// Create connection between `peer1` and `peer2`.
+    fn pair(peer1: PeerId, peer2: PeerId) -> (Self, Self) {
+        let (tx1, rx1) = channel(64);
+        let (tx2, rx2) = channel(64);
+
+        (
+            Self {
+                peer: peer2,
+                tx: tx1,
+                rx: rx2,
+            },
+            Self {
+                peer: peer1,
+                tx: tx2,
+                rx: rx1,
+            },
+        )
+    }
+
+    /// Run the connection until either node closes it.
+    async fn run(mut self, mut protocol_set: ProtocolSet, endpoint: Endpoint) {
+        let peer = self.peer;
+        let connection_id = endpoint.connection_id();
+
+        if protocol_set.report_connection_established(peer, endpoint).await.is_err() {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                substream = self.rx.recv() => match substream {
+                    Some((protocol, io)) => {
+                        if !protocol_set.protocols().contains(&protocol) {
+                            continue;
+                        }
+
+                        let substream_id = protocol_set.next_substream_id();
+                        let Some(permit) = protocol_set.try_get_permit() else {
+                            break;
+                        };
+                        let codec = protocol_set.protocol_codec(&protocol);
+                        let substream = Substream::new_custom(
+                            peer,
+                            substream_id,
+                            BusSubstream { io, _permit: permit },
+                            codec,
+                        );
+
+                        let _ = protocol_set
+                            .report_substream_open(peer, protocol, Direction::Inbound, substream)
+                            .await;
+                    }
+                    None => break,
+                },
+                command = protocol_set.next() => match command {
+                    Some(ProtocolCommand::OpenSubstream { protocol, substream_id, permit, .. }) => {
+                        let (local, remote) = tokio::io::duplex(64 * 1024);
+
+                        if self.tx.send((protocol.clone(), remote)).await.is_err() {
+                            let _ = protocol_set
+                                .report_substream_open_failure(
+                                    protocol,
+                                    substream_id,
+                                    Error::ConnectionClosed,
+                                )
+                                .await;
+                            continue;
+                        }
+
+                        let codec = protocol_set.protocol_codec(&protocol);
+                        let substream = Substream::new_custom(
+                            peer,
+                            substream_id,
+                            BusSubstream { io: local, _permit: permit },
+                            codec,
+                        );
+
+                        let _ = protocol_set
+                            .report_substream_open(
+                                peer,
+                                protocol,
+                                Direction::Outbound(substream_id),
+                                substream,
+                            )
+                            .await;
+                    }
+                    Some(ProtocolCommand::ForceClose) | None => break,
+                },
+            }
+        }
+
+        let _ = protocol_set.report_connection_closed(peer, connection_id).await;
+    }
+}
+
+/// Substream of a bus connection.
+struct BusSubstream {
+    io: DuplexStream,
+    _permit: Permit,
+}
+
+impl AsyncRead for BusSubstream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for BusSubstream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+/// Transport over the in-process bus.
+struct BusTransport {
+    /// Transport handle.
+    handle: TransportHandle,
+
+    /// Local peer ID.
+    local_peer: PeerId,
+
+    /// Listen address.
+    listen_address: Multiaddr,
+
+    /// Listen port.
+    port: u16,
+
+    /// Inbound connections.
+    inbound: Receiver<BusConnection>,
+
+    /// Connections opened but not yet negotiated.
+    opened: HashMap<ConnectionId, (Multiaddr, BusConnection)>,
+
+    /// Negotiated connections waiting to be accepted.
+    negotiated: HashMap<ConnectionId, (Endpoint, BusConnection)>,
+
+    /// Pending events.
+    events: VecDeque<TransportEvent>,
+
+    /// Waker.
+    waker: Option<Waker>,
+}
+
+impl BusTransport {
+    /// Connect to the node listening on `address`.
+    fn connect(&self, address: &Multiaddr) -> litep2p::Result<BusConnection> {
+        let Some(Protocol::Sctp(port)) = address.iter().nth(1) else {
+            return Err(Error::AddressError(AddressError::InvalidProtocol));
+        };
+        let listeners = LISTENERS.lock();
+        let (peer, tx) =
+            listeners.get(&port).ok_or(Error::IoError(ErrorKind::ConnectionRefused))?;
+        let (local, remote) = BusConnection::pair(self.local_peer, *peer);
+
+        tx.try_send(remote).map_err(|_| Error::IoError(ErrorKind::ConnectionRefused))?;
+        Ok(local)
+    }
+
+    /// Report negotiated outbound connection.
+    fn on_dial_negotiated(
+        &mut self,
+        connection_id: ConnectionId,
+        address: Multiaddr,
+        connection: BusConnection,
+    ) {
+        let peer = connection.peer;
+        let endpoint = Endpoint::Dialer {
+            address,
+            connection_id,
+        };
+
+        self.negotiated.insert(connection_id, (endpoint.clone(), connection));
+        self.push_event(TransportEvent::ConnectionEstablished { peer, endpoint });
+    }
+
+    /// Push `event` and wake up the transport.
+    fn push_event(&mut self, event: TransportEvent) {
+        self.events.push_back(event);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for BusTransport {
+    fn drop(&mut self) {
+        LISTENERS.lock().remove(&self.port);
+    }
+}
+
+impl TransportBuilder for BusTransport {
+    type Config = ();
+    type Transport = BusTransport;
+
+    fn new(
+        handle: TransportHandle,
+        _config: Self::Config,
+    ) -> litep2p::Result<(Self, Vec<Multiaddr>)> {
+        let local_peer = PeerId::from_public_key(&handle.keypair().public().into());
+        let port = NEXT_PORT.fetch_add(1, Ordering::Relaxed);
+        let listen_address = Multiaddr::empty()
+            .with(Protocol::Ip4(Ipv4Addr::new(10, 0, 0, 1)))
+            .with(Protocol::Sctp(port));
+        let (tx, inbound) = channel(64);
+
+        LISTENERS.lock().insert(port, (local_peer, tx));
+
+        Ok((
+            Self {
+                handle,
+                local_peer,
+                listen_address: listen_address.clone(),
+                port,
+                inbound,
+                opened: HashMap::new(),
+                negotiated: HashMap::new(),
+                events: VecDeque::new(),
+                waker: None,
+            },
+            vec![listen_address],
+        ))
+    }
+}
+
+impl Transport for BusTransport {
+    fn dial(&mut self, connection_id: ConnectionId, address: Multiaddr) -> litep2p::Result<()> {
+        match self.connect(&address) {
+            Ok(connection) => self.on_dial_negotiated(connection_id, address, connection),
+            Err(error) => self.push_event(TransportEvent::DialFailure {
+                connection_id,
+                address,
+                error,
+            }),
+        }
+
+        Ok(())
+    }
+
+    fn accept(&mut self, connection_id: ConnectionId) -> litep2p::Result<()> {
+        let (endpoint, connection) = self
+            .negotiated
+            .remove(&connection_id)
+            .ok_or(Error::ConnectionDoesntExist(connection_id))?;
+        let protocol_set = self.handle.protocol_set(connection_id);
+
+        self.handle.executor().run(Box::pin(connection.run(protocol_set, endpoint)));
+        Ok(())
+    }
+
+    fn reject(&mut self, connection_id: ConnectionId) -> litep2p::Result<()> {
+        self.negotiated
+            .remove(&connection_id)
+            .map(|_| ())
+            .ok_or(Error::ConnectionDoesntExist(connection_id))
+    }
+
+    fn open(
+        &mut self,
+        connection_id: ConnectionId,
+        addresses: Vec<Multiaddr>,
+    ) -> litep2p::Result<()> {
+        let event = match addresses
+            .into_iter()
+            .find_map(|address| self.connect(&address).ok().map(|connection| (address, connection)))
+        {
+            Some((address, connection)) => {
+                self.opened.insert(connection_id, (address.clone(), connection));
+                TransportEvent::ConnectionOpened {
+                    connection_id,
+                    address,
+                }
+            }
+            None => TransportEvent::OpenFailure { connection_id },
+        };
+
+        self.push_event(event);
+        Ok(())
+    }
+
+    fn negotiate(&mut self, connection_id: ConnectionId) -> litep2p::Result<()> {
+        let (address, connection) = self
+            .opened
+            .remove(&connection_id)
+            .ok_or(Error::ConnectionDoesntExist(connection_id))?;
+
+        self.on_dial_negotiated(connection_id, address, connection);
+        Ok(())
+    }
+
+    fn cancel(&mut self, _connection_id: ConnectionId) {
+        // connections are opened synchronously so there is nothing to cancel
+    }
+}
+
+impl Stream for BusTransport {
+    type Item = TransportEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Poll::Ready(Some(connection)) = self.inbound.poll_recv(cx) {
+            let connection_id = self.handle.next_connection_id();
+            let peer = connection.peer;
+            let endpoint = Endpoint::Listener {
+                address: self.listen_address.clone(),
+                connection_id,
+            };
+
+            self.negotiated.insert(connection_id, (endpoint.clone(), connection));
+            self.events.push_back(TransportEvent::ConnectionEstablished { peer, endpoint });
+        }
+
+        match self.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn make_node() -> (Litep2p, Box<dyn Stream<Item = PingEvent> + Send + Unpin>) {
+    let (ping_config, ping_event_stream) = PingConfig::default();
+    let config = ConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_transport::<BusTransport>("bus", (), is_bus_address)
+        .with_libp2p_ping(ping_config)
+        .build();
+
+    (Litep2p::new(config).unwrap(), ping_event_stream)
+}
+
+async fn ping_over_custom_transport(dial_known_peer: bool) {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (mut litep2p1, mut ping_event_stream1) = make_node();
+    let (mut litep2p2, mut ping_event_stream2) = make_node();
+    let peer2 = *litep2p2.local_peer_id();
+    let address = litep2p2.listen_addresses().next().unwrap().clone();
+
+    if dial_known_peer {
+        assert_eq!(
+            litep2p1.add_known_address(peer2, vec![address].into_iter()),
+            1
+        );
+        litep2p1.dial(&peer2).await.unwrap();
+    } else {
+        litep2p1.dial_address(address).await.unwrap();
+    }
+
+    let mut litep2p1_connected = false;
+    let mut litep2p1_done = false;
+    let mut litep2p2_done = false;
+
+    while !(litep2p1_connected && litep2p1_done && litep2p2_done) {
+        tokio::select! {
+            event = litep2p1.next_event() => if let Some(Litep2pEvent::ConnectionEstablished { peer, .. }) = event {
+                assert_eq!(peer, peer2);
+                litep2p1_connected = true;
+            },
+            _event = litep2p2.next_event() => {}
+            _event = ping_event_stream1.next() => litep2p1_done = true,
+            _event = ping_event_stream2.next() => litep2p2_done = true,
+            _ = tokio::time::sleep(std::time::Duration::from_secs(10)) => panic!("test timed out"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn dial_address_over_custom_transport() {
+    ping_over_custom_transport(false).await;
+}
+
+#[tokio::test]
+async fn dial_peer_over_custom_transport() {
+    ping_over_custom_transport(true).await;
+}
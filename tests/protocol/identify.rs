@@ -122,12 +122,16 @@ async fn identify_supported(transport1: Transport, transport2: Transport) {
             _event = litep2p1.next_event() => {}
             _event = litep2p2.next_event() => {}
             event = identify_event_stream1.next() => {
-                let IdentifyEvent::PeerIdentified { observed_address, protocol_version, user_agent, .. } = event.unwrap();
+                let IdentifyEvent::PeerIdentified { peer, observed_address, protocol_version, user_agent, signed_peer_record, .. } = event.unwrap();
                 tracing::info!("peer2 observed: {observed_address:?}");
 
                 assert_eq!(protocol_version, Some("/proto/2".to_string()));
                 assert_eq!(user_agent, Some("agent v2".to_string()));
 
+                let signed_peer_record = signed_peer_record.unwrap();
+                assert_eq!(signed_peer_record.record().peer(), &peer);
+                assert!(!signed_peer_record.record().addresses().is_empty());
+
                 litep2p1_done = true;
 
                 if litep2p1_done && litep2p2_done {
@@ -135,12 +139,16 @@ async fn identify_supported(transport1: Transport, transport2: Transport) {
                 }
             }
             event = identify_event_stream2.next() => {
-                let IdentifyEvent::PeerIdentified { observed_address, protocol_version, user_agent, .. } = event.unwrap();
+                let IdentifyEvent::PeerIdentified { peer, observed_address, protocol_version, user_agent, signed_peer_record, .. } = event.unwrap();
                 tracing::info!("peer1 observed: {observed_address:?}");
 
                 assert_eq!(protocol_version, Some("/proto/1".to_string()));
                 assert_eq!(user_agent, Some("agent v1".to_string()));
 
+                let signed_peer_record = signed_peer_record.unwrap();
+                assert_eq!(signed_peer_record.record().peer(), &peer);
+                assert!(!signed_peer_record.record().addresses().is_empty());
+
                 litep2p2_done = true;
 
                 if litep2p1_done && litep2p2_done {
@@ -24,7 +24,9 @@ use futures::StreamExt;
 use litep2p::{
     config::ConfigBuilder,
     crypto::ed25519::Keypair,
-    protocol::libp2p::kademlia::{ConfigBuilder as KademliaConfigBuilder, RecordKey},
+    protocol::libp2p::kademlia::{
+        ConfigBuilder as KademliaConfigBuilder, KademliaEvent, RecordKey,
+    },
     transport::tcp::config::Config as TcpConfig,
     Litep2p, PeerId,
 };
@@ -122,3 +124,76 @@ async fn put_value() {
     //     }
     // }
 }
+
+#[tokio::test]
+async fn get_providers_from_provider() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (kad_config1, mut kad_handle1) = KademliaConfigBuilder::new().build();
+    let (kad_config2, mut kad_handle2) = KademliaConfigBuilder::new().build();
+
+    let mut litep2p1 = Litep2p::new(
+        ConfigBuilder::new()
+            .with_keypair(Keypair::generate())
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_kademlia(kad_config1)
+            .build(),
+    )
+    .unwrap();
+    let mut litep2p2 = Litep2p::new(
+        ConfigBuilder::new()
+            .with_keypair(Keypair::generate())
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_libp2p_kademlia(kad_config2)
+            .build(),
+    )
+    .unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let address1 = litep2p1.listen_addresses().next().unwrap().clone();
+    let key = RecordKey::new(&vec![1, 3, 3, 7]);
+
+    // first peer provides the key and the second peer, who only knows the first peer, finds it
+    kad_handle1.start_providing(key.clone()).await;
+    kad_handle2.add_known_peer(peer1, vec![address1.clone()]).await;
+    let query_id = kad_handle2.get_providers(key.clone()).await;
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = kad_handle1.next() => {},
+            }
+        }
+    });
+
+    loop {
+        match tokio::time::timeout(std::time::Duration::from_secs(10), kad_handle2.next())
+            .await
+            .expect("`GET_PROVIDERS` to finish")
+            .unwrap()
+        {
+            KademliaEvent::GetProvidersSuccess {
+                query_id: id,
+                key: provided_key,
+                providers,
+            } => {
+                assert_eq!(id, query_id);
+                assert_eq!(provided_key, key);
+                assert_eq!(providers, vec![(peer1, vec![address1])]);
+                break;
+            }
+            KademliaEvent::QueryFailed { .. } => panic!("`GET_PROVIDERS` failed"),
+            _ => {}
+        }
+    }
+}
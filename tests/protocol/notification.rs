@@ -23,8 +23,9 @@ use litep2p::{
     crypto::ed25519::Keypair,
     error::Error,
     protocol::notification::{
-        Config as NotificationConfig, ConfigBuilder, Direction, NotificationError,
-        NotificationEvent, NotificationHandle, ValidationResult,
+        BroadcastOutcome, CloseReason, Config as NotificationConfig, ConfigBuilder, Direction,
+        HandshakeValidator, InboundStats, NotificationError, NotificationEvent, NotificationHandle,
+        NotificationPriority, RateLimitAction, RateLimitConfig, SlotsConfig, ValidationResult,
     },
     transport::{
         quic::config::Config as QuicConfig, tcp::config::Config as TcpConfig,
@@ -34,13 +35,15 @@ use litep2p::{
     Litep2p, Litep2pEvent, PeerId,
 };
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use multiaddr::{Multiaddr, Protocol};
 use multihash::Multihash;
 
 use std::{
+    collections::{HashMap, HashSet},
     net::{Ipv4Addr, Ipv6Addr},
+    sync::Arc,
     task::Poll,
     time::Duration,
 };
@@ -568,7 +571,7 @@ async fn notification_stream_closed(transport1: Transport, transport2: Transport
     handle1.close_substream(peer2).await;
 
     match handle2.next().await.unwrap() {
-        NotificationEvent::NotificationStreamClosed { peer } => assert_eq!(peer, peer1),
+        NotificationEvent::NotificationStreamClosed { peer, .. } => assert_eq!(peer, peer1),
         _ => panic!("invalid event received"),
     }
 }
@@ -728,12 +731,12 @@ async fn reconnect_after_disconnect(transport1: Transport, transport2: Transport
     handle2.close_substream(peer1).await;
 
     match handle2.next().await.unwrap() {
-        NotificationEvent::NotificationStreamClosed { peer } => assert_eq!(peer, peer1),
+        NotificationEvent::NotificationStreamClosed { peer, .. } => assert_eq!(peer, peer1),
         _ => panic!("invalid event received"),
     }
 
     match handle1.next().await.unwrap() {
-        NotificationEvent::NotificationStreamClosed { peer } => assert_eq!(peer, peer2),
+        NotificationEvent::NotificationStreamClosed { peer, .. } => assert_eq!(peer, peer2),
         _ => panic!("invalid event received"),
     }
 
@@ -959,18 +962,18 @@ async fn set_new_handshake(transport1: Transport, transport2: Transport) {
     handle2.close_substream(peer1).await;
 
     match handle2.next().await.unwrap() {
-        NotificationEvent::NotificationStreamClosed { peer } => assert_eq!(peer, peer1),
+        NotificationEvent::NotificationStreamClosed { peer, .. } => assert_eq!(peer, peer1),
         _ => panic!("invalid event received"),
     }
 
     match handle1.next().await.unwrap() {
-        NotificationEvent::NotificationStreamClosed { peer } => assert_eq!(peer, peer2),
+        NotificationEvent::NotificationStreamClosed { peer, .. } => assert_eq!(peer, peer2),
         _ => panic!("invalid event received"),
     }
 
     // set new handshakes and open the substream
-    handle1.set_handshake(vec![5, 5, 5, 5]);
-    handle2.set_handshake(vec![6, 6, 6, 6]);
+    handle1.set_handshake(vec![5, 5, 5, 5]).unwrap();
+    handle2.set_handshake(vec![6, 6, 6, 6]).unwrap();
     handle2.open_substream(peer1).await.unwrap();
 
     // accept the substreams
@@ -2953,11 +2956,19 @@ async fn open_and_close_batched(
     // verify the substream is closed for `peer2` and `peer3`
     assert_eq!(
         handle2.next().await.unwrap(),
-        NotificationEvent::NotificationStreamClosed { peer: peer1 }
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer1,
+            reason: CloseReason::RemoteClosed,
+            dropped: 0,
+        }
     );
     assert_eq!(
         handle3.next().await.unwrap(),
-        NotificationEvent::NotificationStreamClosed { peer: peer1 }
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer1,
+            reason: CloseReason::RemoteClosed,
+            dropped: 0,
+        }
     );
 
     // verify `peer1` receives close events for both peers
@@ -2966,7 +2977,7 @@ async fn open_and_close_batched(
 
     while !peer2_closed || !peer3_closed {
         match handle1.next().await.unwrap() {
-            NotificationEvent::NotificationStreamClosed { peer } => {
+            NotificationEvent::NotificationStreamClosed { peer, .. } => {
                 if peer == peer2 && !peer2_closed {
                     peer2_closed = true;
                 } else if peer == peer3 && !peer3_closed {
@@ -3203,11 +3214,19 @@ async fn open_and_close_batched_duplicate_peer(
     // verify the substream is closed for `peer2` and `peer3`
     assert_eq!(
         handle2.next().await.unwrap(),
-        NotificationEvent::NotificationStreamClosed { peer: peer1 }
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer1,
+            reason: CloseReason::RemoteClosed,
+            dropped: 0,
+        }
     );
     assert_eq!(
         handle3.next().await.unwrap(),
-        NotificationEvent::NotificationStreamClosed { peer: peer1 }
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer1,
+            reason: CloseReason::RemoteClosed,
+            dropped: 0,
+        }
     );
 
     // verify `peer1` receives close events for both peers
@@ -3216,7 +3235,7 @@ async fn open_and_close_batched_duplicate_peer(
 
     while !peer2_closed || !peer3_closed {
         match handle1.next().await.unwrap() {
-            NotificationEvent::NotificationStreamClosed { peer } => {
+            NotificationEvent::NotificationStreamClosed { peer, .. } => {
                 if peer == peer2 && !peer2_closed {
                     peer2_closed = true;
                 } else if peer == peer3 && !peer3_closed {
@@ -4127,16 +4146,25 @@ async fn clogged_channel_disconnects_peer(transport1: Transport, transport2: Tra
         }
     }
 
-    // stream closed from `peer1`'s PoV
-    assert_eq!(
-        handle1.next().await.unwrap(),
-        NotificationEvent::NotificationStreamClosed { peer: peer2 },
-    );
+    // stream closed from `peer1`'s PoV because `peer2` was clogged
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamClosed {
+            peer,
+            reason,
+            dropped,
+        } => {
+            assert_eq!(peer, peer2);
+            assert_eq!(reason, CloseReason::Clogged);
+            assert!(dropped > 0);
+        }
+        event => panic!("invalid event: {event:?}"),
+    }
 
     // `peer2` is also reported that the substream is closed
     match tokio::time::timeout(Duration::from_secs(5), async move {
         loop {
-            if let Some(NotificationEvent::NotificationStreamClosed { peer }) = handle2.next().await
+            if let Some(NotificationEvent::NotificationStreamClosed { peer, .. }) =
+                handle2.next().await
             {
                 assert_eq!(peer, peer1);
                 break;
@@ -4149,3 +4177,1358 @@ async fn clogged_channel_disconnects_peer(transport1: Transport, transport2: Tra
         Ok(()) => {}
     }
 }
+
+// accept the substream opened by `peer` and wait until the notification stream is open
+async fn accept_substream(handle: &mut NotificationHandle, peer: PeerId) {
+    match handle.next().await.unwrap() {
+        NotificationEvent::ValidateSubstream {
+            peer: remote_peer, ..
+        } => {
+            assert_eq!(remote_peer, peer);
+            handle.send_validation_result(peer, ValidationResult::Accept);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    match handle.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened {
+            peer: remote_peer,
+            direction,
+            ..
+        } => {
+            assert_eq!(remote_peer, peer);
+            assert_eq!(direction, Direction::Inbound);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn peer_slots_released_on_close() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_peer_slots(SlotsConfig {
+            max_inbound: 0,
+            max_outbound: 1,
+            ..Default::default()
+        })
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let (notif_config3, mut handle3) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config3 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config3)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+    let mut litep2p3 = Litep2p::new(config3).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+    let peer3 = *litep2p3.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    connect_peers(&mut litep2p1, &mut litep2p3).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = litep2p3.next_event() => {},
+            }
+        }
+    });
+
+    accept_substream(&mut handle2, peer1).await;
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpened {
+            protocol: ProtocolName::from("/notif/1"),
+            fallback: None,
+            peer: peer2,
+            handshake: vec![1, 2, 3, 4],
+            direction: Direction::Outbound,
+        }
+    );
+
+    // closing the notification stream releases the slot and the freed slot is used for the
+    // other connected peer
+    handle1.close_substream(peer2).await;
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer2,
+            reason: CloseReason::LocalClosed,
+            dropped: 0,
+        }
+    );
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer1,
+            reason: CloseReason::RemoteClosed,
+            dropped: 0,
+        }
+    );
+
+    accept_substream(&mut handle3, peer1).await;
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpened {
+            protocol: ProtocolName::from("/notif/1"),
+            fallback: None,
+            peer: peer3,
+            handshake: vec![1, 2, 3, 4],
+            direction: Direction::Outbound,
+        }
+    );
+
+    // the closed notification stream is not reopened automatically
+    assert!(tokio::time::timeout(Duration::from_millis(500), handle2.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn peer_slots_reserved_only() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let keypair2 = Keypair::generate();
+    let peer2 = keypair2.public().to_peer_id();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_peer_slots(SlotsConfig {
+            max_inbound: 8,
+            max_outbound: 8,
+            reserved_peers: HashSet::from_iter([peer2]),
+            reserved_only: true,
+        })
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(keypair2)
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let (notif_config3, mut handle3) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config3 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config3)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+    let mut litep2p3 = Litep2p::new(config3).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer3 = *litep2p3.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    connect_peers(&mut litep2p1, &mut litep2p3).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = litep2p3.next_event() => {},
+            }
+        }
+    });
+
+    // notification stream is opened automatically to the reserved peer
+    accept_substream(&mut handle2, peer1).await;
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpened {
+            protocol: ProtocolName::from("/notif/1"),
+            fallback: None,
+            peer: peer2,
+            handshake: vec![1, 2, 3, 4],
+            direction: Direction::Outbound,
+        }
+    );
+
+    // inbound substream from a peer that isn't reserved is rejected
+    handle3.open_substream(peer1).await.unwrap();
+    assert_eq!(
+        handle3.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpenFailure {
+            peer: peer1,
+            error: NotificationError::Rejected,
+        }
+    );
+
+    // and so is opening an outbound substream to them
+    handle1.open_substream(peer3).await.unwrap();
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpenFailure {
+            peer: peer3,
+            error: NotificationError::NoSlotsAvailable,
+        }
+    );
+}
+
+#[tokio::test]
+async fn peer_slots_reject_inbound_when_full() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_peer_slots(SlotsConfig {
+            max_inbound: 1,
+            max_outbound: 0,
+            ..Default::default()
+        })
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let (notif_config3, mut handle3) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config3 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config3)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+    let mut litep2p3 = Litep2p::new(config3).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    connect_peers(&mut litep2p1, &mut litep2p3).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = litep2p3.next_event() => {},
+            }
+        }
+    });
+
+    // first inbound substream occupies the only inbound slot
+    handle2.open_substream(peer1).await.unwrap();
+    accept_substream(&mut handle1, peer2).await;
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpened {
+            protocol: ProtocolName::from("/notif/1"),
+            fallback: None,
+            peer: peer1,
+            handshake: vec![1, 2, 3, 4],
+            direction: Direction::Outbound,
+        }
+    );
+
+    // second inbound substream is rejected
+    handle3.open_substream(peer1).await.unwrap();
+    assert_eq!(
+        handle3.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpenFailure {
+            peer: peer1,
+            error: NotificationError::Rejected,
+        }
+    );
+}
+
+#[tokio::test]
+async fn broadcast_notification() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let (notif_config3, mut handle3) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config3 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config3)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+    let mut litep2p3 = Litep2p::new(config3).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+    let peer3 = *litep2p3.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    connect_peers(&mut litep2p1, &mut litep2p3).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = litep2p3.next_event() => {},
+            }
+        }
+    });
+
+    for (peer, handle) in [(peer2, &mut handle2), (peer3, &mut handle3)] {
+        handle1.open_substream(peer).await.unwrap();
+        accept_substream(handle, peer1).await;
+
+        match handle1.next().await.unwrap() {
+            NotificationEvent::NotificationStreamOpened {
+                peer: remote_peer, ..
+            } => assert_eq!(remote_peer, peer),
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+
+    assert_eq!(
+        handle1.broadcast(vec![1, 3, 3, 7], |_| true),
+        HashMap::from_iter([
+            (peer2, BroadcastOutcome::Sent),
+            (peer3, BroadcastOutcome::Sent)
+        ])
+    );
+
+    for handle in [&mut handle2, &mut handle3] {
+        assert_eq!(
+            handle.next().await.unwrap(),
+            NotificationEvent::NotificationReceived {
+                peer: peer1,
+                notification: BytesMut::from(&[1, 3, 3, 7][..]),
+            }
+        );
+    }
+
+    // only peers accepted by the filter receive the notification
+    assert_eq!(
+        handle1.broadcast(Bytes::from_static(&[1, 3, 3, 8]), |peer| peer == &peer2),
+        HashMap::from_iter([(peer2, BroadcastOutcome::Sent)])
+    );
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        NotificationEvent::NotificationReceived {
+            peer: peer1,
+            notification: BytesMut::from(&[1, 3, 3, 8][..]),
+        }
+    );
+    assert!(tokio::time::timeout(Duration::from_millis(500), handle3.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn send_notifications_with_priority() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+    accept_substream(&mut handle2, peer1).await;
+
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    handle1
+        .send_sync_notification_with_priority(peer2, NotificationPriority::Low, vec![1])
+        .unwrap();
+    handle1
+        .send_async_notification_with_priority(peer2, NotificationPriority::High, vec![2])
+        .await
+        .unwrap();
+    assert_eq!(
+        handle1.broadcast_with_priority(NotificationPriority::High, vec![3], |_| true),
+        HashMap::from_iter([(peer2, BroadcastOutcome::Sent)])
+    );
+
+    let mut received = HashSet::new();
+    while received.len() < 3 {
+        match handle2.next().await.unwrap() {
+            NotificationEvent::NotificationReceived { peer, notification } => {
+                assert_eq!(peer, peer1);
+                received.insert(notification.to_vec());
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+    assert_eq!(received, HashSet::from_iter([vec![1], vec![2], vec![3]]));
+}
+
+#[derive(Debug)]
+struct StaticValidator(ValidationResult);
+
+#[async_trait::async_trait]
+impl HandshakeValidator for StaticValidator {
+    async fn validate(&self, _peer: PeerId, handshake: Vec<u8>) -> ValidationResult {
+        assert_eq!(handshake, vec![1, 2, 3, 4]);
+        self.0
+    }
+}
+
+#[tokio::test]
+async fn handshake_validator_accepts_substream() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_handshake_validator(Arc::new(StaticValidator(ValidationResult::Accept)))
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+
+    // substream is validated without emitting `ValidateSubstream`
+    match handle2.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened {
+            peer, direction, ..
+        } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(direction, Direction::Inbound);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn handshake_validator_rejects_substream() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_handshake_validator(Arc::new(StaticValidator(ValidationResult::Reject)))
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpenFailure {
+            peer: peer2,
+            error: NotificationError::Rejected,
+        }
+    );
+    assert!(tokio::time::timeout(Duration::from_millis(500), handle2.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn substream_rejected_after_validation_timeout() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_validation_timeout(Duration::from_secs(1))
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let started = tokio::time::Instant::now();
+    handle1.open_substream(peer2).await.unwrap();
+
+    // substream is never validated by the user protocol
+    match handle2.next().await.unwrap() {
+        NotificationEvent::ValidateSubstream { peer, .. } => assert_eq!(peer, peer1),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpenFailure {
+            peer: peer2,
+            error: NotificationError::Rejected,
+        }
+    );
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // the user protocol is told that its pending decision was dropped
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        NotificationEvent::NotificationStreamOpenFailure {
+            peer: peer1,
+            error: NotificationError::ValidationTimedOut,
+        }
+    );
+
+    // and a late validation result is ignored
+    handle2.send_validation_result(peer1, ValidationResult::Accept);
+    assert!(tokio::time::timeout(Duration::from_secs(1), handle1.next()).await.is_err());
+    assert!(tokio::time::timeout(Duration::from_secs(1), handle2.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn handshake_update_sent_to_connected_peers() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_handshake_updates(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_handshake_updates(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+    accept_substream(&mut handle2, peer1).await;
+
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    handle1.set_handshake(vec![5, 6, 7, 8]).unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        NotificationEvent::HandshakeUpdated {
+            peer: peer1,
+            handshake: vec![5, 6, 7, 8],
+        }
+    );
+
+    // handshake update that doesn't fit in a notification is rejected locally
+    assert!(matches!(
+        handle1.set_handshake(vec![0u8; 1025]),
+        Err(Error::NotificationError(
+            litep2p::error::NotificationError::HandshakeTooLarge
+        ))
+    ));
+    assert!(tokio::time::timeout(Duration::from_secs(1), handle2.next()).await.is_err());
+
+    // notifications are received without the message type
+    handle2.send_sync_notification(peer1, vec![1, 3, 3, 7]).unwrap();
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationReceived {
+            peer: peer2,
+            notification: BytesMut::from(&[1, 3, 3, 7][..]),
+        }
+    );
+}
+
+#[tokio::test]
+async fn open_substream_with_custom_handshake() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream_with_handshake(peer2, vec![9, 9]).await.unwrap();
+
+    match handle2.next().await.unwrap() {
+        NotificationEvent::ValidateSubstream {
+            peer, handshake, ..
+        } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(handshake, vec![9, 9]);
+            handle2.send_validation_result(peer1, ValidationResult::Accept);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    match handle2.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { handshake, .. } =>
+            assert_eq!(handshake, vec![9, 9]),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    // remote peer receives the handshake of the protocol
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened {
+            peer, handshake, ..
+        } => {
+            assert_eq!(peer, peer2);
+            assert_eq!(handshake, vec![1, 2, 3, 4]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn close_substream_gracefully_delivers_queued_notifications() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+    accept_substream(&mut handle2, peer1).await;
+
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    for i in 0..5u8 {
+        handle1.send_sync_notification(peer2, vec![i; 4]).unwrap();
+    }
+    handle1.close_substream_gracefully(peer2, Duration::from_secs(5)).await;
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        NotificationEvent::NotificationStreamClosed {
+            peer: peer2,
+            reason: CloseReason::LocalClosed,
+            dropped: 0,
+        }
+    );
+
+    // all queued notifications are received even though the substream is closed
+    let mut received = Vec::new();
+    let mut closed = false;
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while received.len() < 5 || !closed {
+            match handle2.next().await.unwrap() {
+                NotificationEvent::NotificationReceived { peer, notification } => {
+                    assert_eq!(peer, peer1);
+                    received.push(notification);
+                }
+                NotificationEvent::NotificationStreamClosed { peer, .. } => {
+                    assert_eq!(peer, peer1);
+                    closed = true;
+                }
+                event => panic!("invalid event received: {event:?}"),
+            }
+        }
+    })
+    .await
+    .expect("all notifications to be received");
+
+    assert_eq!(
+        received,
+        (0..5u8).map(|i| BytesMut::from(&[i; 4][..])).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn notifications_over_inbound_rate_limit_are_dropped() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_inbound_rate_limit(RateLimitConfig {
+            messages_per_second: Some(2),
+            bytes_per_second: None,
+            action: RateLimitAction::Drop,
+        })
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+    accept_substream(&mut handle2, peer1).await;
+
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    for i in 0..5u8 {
+        handle1.send_sync_notification(peer2, vec![i; 4]).unwrap();
+    }
+
+    for i in 0..2u8 {
+        assert_eq!(
+            handle2.next().await.unwrap(),
+            NotificationEvent::NotificationReceived {
+                peer: peer1,
+                notification: BytesMut::from(&[i; 4][..]),
+            }
+        );
+    }
+
+    // wait until the rest of the notifications have been received and dropped
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while handle2.inbound_stats(peer1).unwrap().notifications < 5 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("notifications to be received");
+
+    assert_eq!(
+        handle2.inbound_stats(peer1),
+        Some(InboundStats {
+            notifications: 5,
+            bytes: 20,
+            dropped: 3,
+        })
+    );
+    assert!(tokio::time::timeout(Duration::from_millis(200), handle2.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn notification_stream_closed_when_inbound_rate_limit_exceeded() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .build();
+
+    let (notif_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .with_inbound_rate_limit(RateLimitConfig {
+            messages_per_second: None,
+            bytes_per_second: Some(6),
+            action: RateLimitAction::Close,
+        })
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    handle1.open_substream(peer2).await.unwrap();
+    accept_substream(&mut handle2, peer1).await;
+
+    match handle1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    handle1.send_sync_notification(peer2, vec![1, 3, 3, 7]).unwrap();
+    handle1.send_sync_notification(peer2, vec![1, 3, 3, 8]).unwrap();
+
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        NotificationEvent::NotificationReceived {
+            peer: peer1,
+            notification: BytesMut::from(&[1, 3, 3, 7][..]),
+        }
+    );
+    match handle2.next().await.unwrap() {
+        NotificationEvent::NotificationStreamClosed { peer, reason, .. } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(reason, CloseReason::RateLimited);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn secondary_protocol_follows_main_protocol() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (notif_config1, mut main1) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let (notif_config2, mut secondary1) = ConfigBuilder::new(ProtocolName::from("/notif/2"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![5, 6, 7, 8])
+        .with_main_protocol(ProtocolName::from("/notif/1"))
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config1)
+        .with_notification_protocol(notif_config2)
+        .build();
+
+    let (notif_config3, mut main2) = ConfigBuilder::new(ProtocolName::from("/notif/1"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![1, 2, 3, 4])
+        .with_auto_accept_inbound(true)
+        .build();
+    let (notif_config4, mut secondary2) = ConfigBuilder::new(ProtocolName::from("/notif/2"))
+        .with_max_size(1024usize)
+        .with_handshake(vec![5, 6, 7, 8])
+        .with_main_protocol(ProtocolName::from("/notif/1"))
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_notification_protocol(notif_config3)
+        .with_notification_protocol(notif_config4)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected and spawn the litep2p objects in the background
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    // secondary substream is not opened before the main notification stream is open
+    secondary1.open_substream(peer2).await.unwrap();
+    assert!(tokio::time::timeout(Duration::from_secs(1), secondary2.next()).await.is_err());
+
+    main1.open_substream(peer2).await.unwrap();
+    accept_substream(&mut main2, peer1).await;
+
+    match main1.next().await.unwrap() {
+        NotificationEvent::NotificationStreamOpened { peer, .. } => assert_eq!(peer, peer2),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    // secondary notification streams are opened without validation by the user
+    for (handle, remote) in [(&mut secondary1, peer2), (&mut secondary2, peer1)] {
+        match tokio::time::timeout(Duration::from_secs(5), handle.next())
+            .await
+            .expect("secondary notification stream to open")
+            .unwrap()
+        {
+            NotificationEvent::NotificationStreamOpened {
+                peer, handshake, ..
+            } => {
+                assert_eq!(peer, remote);
+                assert_eq!(handshake, vec![5, 6, 7, 8]);
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+
+    secondary1.send_sync_notification(peer2, vec![1, 3, 3, 7]).unwrap();
+    assert_eq!(
+        secondary2.next().await.unwrap(),
+        NotificationEvent::NotificationReceived {
+            peer: peer1,
+            notification: BytesMut::from(&[1, 3, 3, 7][..]),
+        }
+    );
+
+    // secondary notification streams are closed with the main notification stream
+    main1.close_substream(peer2).await;
+
+    for (handle, remote) in [(&mut secondary1, peer2), (&mut secondary2, peer1)] {
+        match tokio::time::timeout(Duration::from_secs(5), handle.next())
+            .await
+            .expect("secondary notification stream to close")
+            .unwrap()
+        {
+            NotificationEvent::NotificationStreamClosed { peer, .. } => assert_eq!(peer, remote),
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+}
@@ -21,18 +21,26 @@
 use litep2p::{
     config::ConfigBuilder as Litep2pConfigBuilder,
     crypto::ed25519::Keypair,
-    protocol::request_response::{
-        Config as RequestResponseConfig, ConfigBuilder, DialOptions, RequestResponseError,
-        RequestResponseEvent,
+    protocol::{
+        request_response::{
+            Config as RequestResponseConfig, ConfigBuilder, DialOptions, InboundRequestLimits,
+            MessageCodec, OverflowBehavior, ProtobufCodec, RequestResponseError,
+            RequestResponseEvent, RetryPolicy, RetryStrategy, TypedRequestResponseEvent,
+            TypedRequestResponseHandle,
+        },
+        ReputationChange,
     },
     transport::{
         quic::config::Config as QuicConfig, tcp::config::Config as TcpConfig,
         websocket::config::Config as WebSocketConfig,
     },
     types::{protocol::ProtocolName, RequestId},
-    Litep2p, Litep2pEvent, PeerId,
+    Error, Litep2p, Litep2pEvent, PeerId,
 };
 
+#[cfg(feature = "json-codec")]
+use litep2p::protocol::request_response::JsonCodec;
+
 use futures::{channel, StreamExt};
 use multiaddr::{Multiaddr, Protocol};
 use multihash::Multihash;
@@ -3203,3 +3211,1922 @@ async fn binary_incompatible_fallback_compatible_nodes(
         }
     );
 }
+
+#[cfg(feature = "json-codec")]
+#[tokio::test]
+async fn typed_request_response() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, handle1) = RequestResponseConfig::new(
+        ProtocolName::from("/protocol/1"),
+        Vec::new(),
+        1024,
+        Duration::from_secs(5),
+        None,
+    );
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, handle2) = RequestResponseConfig::new(
+        ProtocolName::from("/protocol/1"),
+        Vec::new(),
+        1024,
+        Duration::from_secs(5),
+        None,
+    );
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let mut handle1 = TypedRequestResponseHandle::<Vec<String>, u64, JsonCodec>::new(handle1);
+    let mut handle2 = TypedRequestResponseHandle::<Vec<String>, u64, JsonCodec>::new(handle2);
+
+    // send request to remote peer
+    let request = vec!["hello".to_string(), "world".to_string()];
+    let request_id = handle1.send_request(peer2, &request, DialOptions::Reject).await.unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        TypedRequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id,
+            request,
+        }
+    );
+
+    // send response to the received request
+    handle2.send_response(request_id, &1337u64).unwrap();
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::ResponseReceived {
+            peer: peer2,
+            request_id,
+            response: 1337u64,
+            fallback: None,
+        }
+    );
+}
+
+#[cfg(feature = "json-codec")]
+#[tokio::test]
+async fn typed_request_response_decode_failure() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, handle1) = RequestResponseConfig::new(
+        ProtocolName::from("/protocol/1"),
+        Vec::new(),
+        1024,
+        Duration::from_secs(5),
+        None,
+    );
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = RequestResponseConfig::new(
+        ProtocolName::from("/protocol/1"),
+        Vec::new(),
+        1024,
+        Duration::from_secs(5),
+        None,
+    );
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    // only the first peer uses a typed handle
+    let mut handle1 = TypedRequestResponseHandle::<Vec<String>, u64, JsonCodec>::new(handle1);
+
+    // send invalid request to the first peer and verify it's rejected
+    let request_id = handle2
+        .send_request(peer1, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::DecodeFailure,
+        }
+    );
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer1,
+            request_id,
+            error: RequestResponseError::Rejected,
+        }
+    );
+
+    // send valid request to the second peer and respond with an invalid response
+    let request = vec!["hello".to_string()];
+    let request_id = handle1.send_request(peer2, &request, DialOptions::Reject).await.unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id,
+            request: br#"["hello"]"#.to_vec(),
+        }
+    );
+
+    handle2.send_response(request_id, b"not a number".to_vec());
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::DecodeFailure,
+        }
+    );
+}
+
+#[tokio::test]
+async fn stream_response() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // send response which in total is larger than the maximum message size
+    let mut sender = handle2.send_response_stream(request_id).unwrap();
+    tokio::spawn(async move {
+        for i in 0..8u8 {
+            sender.send_chunk(vec![i; 1000]).await.unwrap();
+        }
+        sender.finish().await.unwrap();
+    });
+
+    for i in 0..8u8 {
+        assert_eq!(
+            handle1.next().await.unwrap(),
+            RequestResponseEvent::ResponseChunkReceived {
+                peer: peer2,
+                request_id,
+                fallback: None,
+                chunk: vec![i; 1000],
+            }
+        );
+    }
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseStreamFinished {
+            peer: peer2,
+            request_id,
+        }
+    );
+}
+
+#[tokio::test]
+async fn stream_single_response() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // response sent with `send_response()` is received as a stream of one chunk
+    handle2.send_response(request_id, vec![1, 3, 3, 8]);
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseChunkReceived {
+            peer: peer2,
+            request_id,
+            fallback: None,
+            chunk: vec![1, 3, 3, 8],
+        }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseStreamFinished {
+            peer: peer2,
+            request_id,
+        }
+    );
+}
+
+#[tokio::test]
+async fn stream_response_aborted() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // send one chunk and drop the sender without finishing the stream
+    let mut sender = handle2.send_response_stream(request_id).unwrap();
+    sender.send_chunk(vec![1, 3, 3, 8]).await.unwrap();
+    assert!(sender.send_chunk(Vec::new()).await.is_err());
+    drop(sender);
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseChunkReceived {
+            peer: peer2,
+            request_id,
+            fallback: None,
+            chunk: vec![1, 3, 3, 8],
+        }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::Rejected,
+        }
+    );
+}
+
+#[tokio::test]
+async fn typed_stream_response_decode_failure() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_streaming_responses()
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+    let mut handle1 = TypedRequestResponseHandle::<String, String, ProtobufCodec>::new(handle1);
+    let encode =
+        |chunk: &str| <ProtobufCodec as MessageCodec<String>>::encode(&chunk.to_string()).unwrap();
+
+    let request_id = handle1
+        .send_request(peer2, &"hello".to_string(), DialOptions::Reject)
+        .await
+        .unwrap();
+    assert!(std::matches!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived { peer, .. } if peer == peer1
+    ));
+
+    // the second chunk cannot be decoded
+    let mut sender = handle2.send_response_stream(request_id).unwrap();
+    sender.send_chunk(encode("first")).await.unwrap();
+    sender.send_chunk(vec![0xff, 0xff]).await.unwrap();
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::ResponseChunkReceived {
+            peer: peer2,
+            request_id,
+            fallback: None,
+            chunk: "first".to_string(),
+        }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::DecodeFailure,
+        }
+    );
+
+    // the request is canceled and the rest of the failed stream is not reported
+    let _ = sender.send_chunk(encode("third")).await;
+    let _ = sender.finish().await;
+
+    let request_id = handle1
+        .send_request(peer2, &"hello".to_string(), DialOptions::Reject)
+        .await
+        .unwrap();
+    assert!(std::matches!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived { peer, .. } if peer == peer1
+    ));
+    handle2.send_response(request_id, encode("response"));
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::ResponseChunkReceived {
+            peer: peer2,
+            request_id,
+            fallback: None,
+            chunk: "response".to_string(),
+        }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        TypedRequestResponseEvent::ResponseStreamFinished {
+            peer: peer2,
+            request_id,
+        }
+    );
+}
+
+// empty frames are valid messages for protocols which don't stream their responses
+#[tokio::test]
+async fn empty_request_and_response() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id = handle1.send_request(peer2, Vec::new(), DialOptions::Reject).await.unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id,
+            request: Vec::new(),
+        }
+    );
+
+    handle2.send_response(request_id, Vec::new());
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseReceived {
+            peer: peer2,
+            request_id,
+            fallback: None,
+            response: Vec::new(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn inbound_limit_exceeded_request_rejected() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let limits = InboundRequestLimits {
+        max_concurrent: Some(1),
+        ..Default::default()
+    };
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_inbound_request_limits(limits)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id1 = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id: request_id1,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // second request exceeds the limit and is rejected
+    let request_id2 = handle1
+        .send_request(peer2, vec![1, 3, 3, 8], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::InboundQuotaExceeded { peer: peer1 }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id: request_id2,
+            error: RequestResponseError::Rejected,
+        }
+    );
+
+    // first request is not affected
+    handle2.send_response(request_id1, vec![1, 3, 3, 9]);
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseReceived {
+            peer: peer2,
+            request_id: request_id1,
+            response: vec![1, 3, 3, 9],
+            fallback: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn inbound_limit_exceeded_request_queued() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let limits = InboundRequestLimits {
+        max_concurrent: Some(1),
+        max_per_window: None,
+        overflow: OverflowBehavior::Queue {
+            max_queued: 1,
+            max_wait: Duration::from_secs(10),
+        },
+    };
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_inbound_request_limits(limits)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id1 = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id: request_id1,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // second request is queued
+    let _request_id2 = handle1
+        .send_request(peer2, vec![1, 3, 3, 8], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::InboundQuotaExceeded { peer: peer1 }
+    );
+
+    // third request is rejected as the queue is full
+    let request_id3 = handle1
+        .send_request(peer2, vec![1, 3, 3, 9], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::InboundQuotaExceeded { peer: peer1 }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id: request_id3,
+            error: RequestResponseError::Rejected,
+        }
+    );
+
+    // respond to the first request and verify the queued request is received
+    handle2.send_response(request_id1, vec![1, 3, 3, 10]);
+
+    match handle2.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { peer, request, .. } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(request, vec![1, 3, 3, 8]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn queued_request_expires() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let limits = InboundRequestLimits {
+        max_concurrent: Some(1),
+        max_per_window: None,
+        overflow: OverflowBehavior::Queue {
+            max_queued: 1,
+            max_wait: Duration::from_millis(500),
+        },
+    };
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_inbound_request_limits(limits)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id1 = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id: request_id1,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // second request is queued and rejected once it has waited for too long
+    let request_id2 = handle1
+        .send_request(peer2, vec![1, 3, 3, 8], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::InboundQuotaExceeded { peer: peer1 }
+    );
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id: request_id2,
+            error: RequestResponseError::Rejected,
+        }
+    );
+
+    // the expired request is not received after the first request has been answered
+    handle2.send_response(request_id1, vec![1, 3, 3, 9]);
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseReceived {
+            peer: peer2,
+            request_id: request_id1,
+            fallback: None,
+            response: vec![1, 3, 3, 9],
+        }
+    );
+
+    let request_id3 = handle1
+        .send_request(peer2, vec![1, 3, 3, 10], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id: request_id3,
+            request: vec![1, 3, 3, 10],
+        }
+    );
+}
+
+#[tokio::test]
+async fn inbound_limit_per_window() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let window = Duration::from_millis(500);
+    let limits = InboundRequestLimits {
+        max_concurrent: None,
+        max_per_window: Some((1, window)),
+        overflow: OverflowBehavior::Queue {
+            max_queued: 4,
+            max_wait: Duration::from_secs(10),
+        },
+    };
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .with_inbound_request_limits(limits)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let started = tokio::time::Instant::now();
+    let request_id1 = handle1
+        .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestReceived {
+            peer: peer1,
+            fallback: None,
+            request_id: request_id1,
+            request: vec![1, 3, 3, 7],
+        }
+    );
+
+    // second request is queued until the first request falls out of the window
+    let _request_id2 = handle1
+        .send_request(peer2, vec![1, 3, 3, 8], DialOptions::Reject)
+        .await
+        .unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::InboundQuotaExceeded { peer: peer1 }
+    );
+
+    match handle2.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { peer, request, .. } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(request, vec![1, 3, 3, 8]);
+            assert!(started.elapsed() >= window);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn request_with_custom_timeout() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let started = tokio::time::Instant::now();
+    let request_id = handle1
+        .send_request_with_timeout(
+            peer2,
+            vec![1, 3, 3, 7],
+            Duration::from_millis(500),
+            DialOptions::Reject,
+        )
+        .await
+        .unwrap();
+
+    match handle2.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { peer, request, .. } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(request, vec![1, 3, 3, 7]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    // the request times out well before the default timeout of the protocol
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::Timeout,
+        }
+    );
+    assert!(started.elapsed() >= Duration::from_millis(500));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn try_request_with_fallback_and_custom_timeout() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let started = tokio::time::Instant::now();
+    let request_id = handle1
+        .try_send_request_with_fallback_and_timeout(
+            peer2,
+            vec![1, 3, 3, 7],
+            (ProtocolName::from("/protocol/1/fallback"), vec![1, 3, 3, 8]),
+            Duration::from_millis(500),
+            DialOptions::Reject,
+        )
+        .unwrap();
+
+    // the main protocol is supported by the remote peer
+    match handle2.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived {
+            peer,
+            fallback,
+            request,
+            ..
+        } => {
+            assert_eq!(peer, peer1);
+            assert_eq!(fallback, None);
+            assert_eq!(request, vec![1, 3, 3, 7]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::Timeout,
+        }
+    );
+    assert!(started.elapsed() >= Duration::from_millis(500));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn request_exceeds_max_request_size() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_request_size(128)
+        .with_max_response_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, _handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    // request is smaller than the maximum response size but larger than the maximum request size
+    let request_id =
+        handle1.send_request(peer2, vec![0u8; 129], DialOptions::Reject).await.unwrap();
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::TooLargePayload,
+        }
+    );
+}
+
+#[tokio::test]
+async fn inbound_request_exceeds_max_request_size() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_request_size(128)
+        .with_max_response_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    // request is within the limits of the sender but too large for the local node
+    let request_id =
+        handle2.send_request(peer1, vec![0u8; 256], DialOptions::Reject).await.unwrap();
+    assert_eq!(
+        handle2.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer1,
+            request_id,
+            error: RequestResponseError::Rejected,
+        }
+    );
+
+    // requests within the limit are still received
+    handle2.send_request(peer1, vec![0u8; 128], DialOptions::Reject).await.unwrap();
+    match handle1.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { peer, request, .. } => {
+            assert_eq!(peer, peer2);
+            assert_eq!(request, vec![0u8; 128]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn response_exceeds_max_response_size() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_request_size(1024)
+        .with_max_response_size(128)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer1 = *litep2p1.local_peer_id();
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    for response_size in [256, 2048] {
+        let request_id = handle1
+            .send_request(peer2, vec![1, 3, 3, 7], DialOptions::Reject)
+            .await
+            .unwrap();
+
+        match handle2.next().await.unwrap() {
+            RequestResponseEvent::RequestReceived {
+                peer,
+                request_id: remote_request_id,
+                ..
+            } => {
+                assert_eq!(peer, peer1);
+                handle2.send_response(remote_request_id, vec![0u8; response_size]);
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+
+        // response exceeds the maximum response size but not the maximum request size
+        let expected = match response_size {
+            256 => RequestResponseError::TooLargeResponse,
+            // too large for the remote peer to send
+            _ => RequestResponseError::Rejected,
+        };
+        assert_eq!(
+            handle1.next().await.unwrap(),
+            RequestResponseEvent::RequestFailed {
+                peer: peer2,
+                request_id,
+                error: expected,
+            }
+        );
+    }
+}
+
+#[tokio::test]
+async fn send_request_to_any_sequential() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let (req_resp_config3, mut handle3) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config3 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config3)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+    let mut litep2p3 = Litep2p::new(config3).unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+    let peer3 = *litep2p3.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    connect_peers(&mut litep2p1, &mut litep2p3).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = litep2p3.next_event() => {},
+            }
+        }
+    });
+
+    // first candidate is not connected, second rejects the request and third responds
+    let request_id = handle1
+        .send_request_to_any(
+            vec![PeerId::random(), peer2, peer3],
+            vec![1, 3, 3, 7],
+            RetryPolicy::default(),
+        )
+        .await
+        .unwrap();
+
+    match handle2.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { request_id, .. } =>
+            handle2.reject_request(request_id),
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    match handle3.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived {
+            request_id,
+            request,
+            ..
+        } => {
+            assert_eq!(request, vec![1, 3, 3, 7]);
+            handle3.send_response(request_id, vec![1, 3, 3, 8]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    // failed attempts are not reported
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseReceived {
+            peer: peer3,
+            fallback: None,
+            request_id,
+            response: vec![1, 3, 3, 8],
+        }
+    );
+}
+
+#[tokio::test]
+async fn send_request_to_any_hedged() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let (req_resp_config3, mut handle3) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config3 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config3)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+    let mut litep2p3 = Litep2p::new(config3).unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+    let peer3 = *litep2p3.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    connect_peers(&mut litep2p1, &mut litep2p3).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+                _ = litep2p3.next_event() => {},
+            }
+        }
+    });
+
+    let started = tokio::time::Instant::now();
+    let request_id = handle1
+        .send_request_to_any(
+            vec![peer2, peer3],
+            vec![1, 3, 3, 7],
+            RetryPolicy {
+                strategy: RetryStrategy::Hedged {
+                    delay: Duration::from_millis(200),
+                },
+                max_attempts: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    // first candidate doesn't respond so the request is sent to the second candidate
+    let slow_request_id = match handle2.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { request_id, .. } => request_id,
+        event => panic!("invalid event received: {event:?}"),
+    };
+
+    match handle3.next().await.unwrap() {
+        RequestResponseEvent::RequestReceived { request_id, .. } => {
+            assert!(started.elapsed() >= Duration::from_millis(200));
+            handle3.send_response(request_id, vec![1, 3, 3, 8]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::ResponseReceived {
+            peer: peer3,
+            fallback: None,
+            request_id,
+            response: vec![1, 3, 3, 8],
+        }
+    );
+
+    // the other attempt was canceled so a late response is not reported
+    handle2.send_response(slow_request_id, vec![1, 3, 3, 9]);
+    assert!(tokio::time::timeout(Duration::from_secs(1), handle1.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn send_request_to_any_budget_exhausted() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, mut handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    let request_id = handle1
+        .send_request_to_any(
+            vec![peer2],
+            vec![1, 3, 3, 7],
+            RetryPolicy {
+                max_attempts: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    // the only candidate is retried until the budget is exhausted
+    for _ in 0..2 {
+        match handle2.next().await.unwrap() {
+            RequestResponseEvent::RequestReceived { request_id, .. } =>
+                handle2.reject_request(request_id),
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+
+    assert_eq!(
+        handle1.next().await.unwrap(),
+        RequestResponseEvent::RequestFailed {
+            peer: peer2,
+            request_id,
+            error: RequestResponseError::Rejected,
+        }
+    );
+    assert!(tokio::time::timeout(Duration::from_millis(500), handle1.next()).await.is_err());
+}
+
+#[tokio::test]
+async fn send_request_to_any_zero_attempts() {
+    let (req_resp_config1, mut handle1) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config1 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config1)
+        .build();
+
+    let (req_resp_config2, _handle2) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+        .with_max_size(1024)
+        .build();
+    let config2 = Litep2pConfigBuilder::new()
+        .with_keypair(Keypair::generate())
+        .with_tcp(TcpConfig {
+            listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+            ..Default::default()
+        })
+        .with_request_response_protocol(req_resp_config2)
+        .build();
+
+    let mut litep2p1 = Litep2p::new(config1).unwrap();
+    let mut litep2p2 = Litep2p::new(config2).unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+
+    // wait until peers have connected
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {},
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    });
+
+    match handle1
+        .send_request_to_any(
+            vec![peer2],
+            vec![1, 3, 3, 7],
+            RetryPolicy {
+                max_attempts: 0,
+                ..Default::default()
+            },
+        )
+        .await
+    {
+        Err(Error::InvalidRetryPolicy) => {}
+        result => panic!("invalid result: {result:?}"),
+    }
+}
+
+#[tokio::test]
+async fn multiple_requests_while_dialing() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let mut litep2ps = Vec::new();
+    let mut handles = Vec::new();
+
+    for _ in 0..2 {
+        let (req_resp_config, handle) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+            .with_max_size(1024)
+            .build();
+        let config = Litep2pConfigBuilder::new()
+            .with_keypair(Keypair::generate())
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_request_response_protocol(req_resp_config)
+            .build();
+
+        litep2ps.push(Litep2p::new(config).unwrap());
+        handles.push(handle);
+    }
+
+    let mut litep2p2 = litep2ps.pop().unwrap();
+    let mut litep2p1 = litep2ps.pop().unwrap();
+    let mut handle2 = handles.pop().unwrap();
+    let mut handle1 = handles.pop().unwrap();
+
+    let peer2 = *litep2p2.local_peer_id();
+    let address = litep2p2.listen_addresses().next().unwrap().clone();
+    litep2p1.add_known_address(peer2, std::iter::once(address));
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = litep2p1.next_event() => {}
+                _ = litep2p2.next_event() => {}
+            }
+        }
+    });
+
+    // the second request is sent while the dial started by the first one is still pending
+    let mut request_ids = HashSet::new();
+    for request in [vec![1], vec![2]] {
+        request_ids.insert(handle1.send_request(peer2, request, DialOptions::Dial).await.unwrap());
+    }
+
+    let mut requests = HashSet::new();
+    for _ in 0..2 {
+        match handle2.next().await.unwrap() {
+            RequestResponseEvent::RequestReceived {
+                request_id,
+                request,
+                ..
+            } => {
+                handle2.send_response(request_id, request.clone());
+                requests.insert(request);
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+    assert_eq!(requests, HashSet::from([vec![1], vec![2]]));
+
+    for _ in 0..2 {
+        match handle1.next().await.unwrap() {
+            RequestResponseEvent::ResponseReceived { request_id, .. } =>
+                assert!(request_ids.remove(&request_id)),
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn reported_peer_disconnected_and_banned() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let mut litep2ps = Vec::new();
+    let mut handles = Vec::new();
+
+    for _ in 0..2 {
+        let (req_resp_config, handle) = ConfigBuilder::new(ProtocolName::from("/protocol/1"))
+            .with_max_size(1024)
+            .build();
+        let config = Litep2pConfigBuilder::new()
+            .with_keypair(Keypair::generate())
+            .with_tcp(TcpConfig {
+                listen_addresses: vec!["/ip6/::1/tcp/0".parse().unwrap()],
+                ..Default::default()
+            })
+            .with_request_response_protocol(req_resp_config)
+            .build();
+
+        litep2ps.push(Litep2p::new(config).unwrap());
+        handles.push(handle);
+    }
+
+    let mut litep2p2 = litep2ps.pop().unwrap();
+    let mut litep2p1 = litep2ps.pop().unwrap();
+    let handle1 = handles.remove(0);
+
+    let peer2 = *litep2p2.local_peer_id();
+    let address2 = litep2p2.listen_addresses().next().unwrap().clone();
+
+    connect_peers(&mut litep2p1, &mut litep2p2).await;
+
+    handle1.report_peer(peer2, ReputationChange::new_fatal("misbehaved")).await;
+
+    let future = async {
+        loop {
+            tokio::select! {
+                event = litep2p1.next_event() => match event {
+                    Some(Litep2pEvent::ConnectionClosed { peer, .. }) => {
+                        assert_eq!(peer, peer2);
+                        break;
+                    }
+                    _ => {}
+                },
+                _ = litep2p2.next_event() => {},
+            }
+        }
+    };
+
+    match tokio::time::timeout(Duration::from_secs(5), future).await {
+        Err(_) => panic!("banned peer wasn't disconnected"),
+        Ok(()) => {}
+    }
+
+    assert!(std::matches!(
+        litep2p1.dial(&peer2).await,
+        Err(litep2p::Error::PeerBanned(peer)) if peer == peer2
+    ));
+    assert!(std::matches!(
+        litep2p1.dial_address(address2).await,
+        Err(litep2p::Error::PeerBanned(peer)) if peer == peer2
+    ));
+}
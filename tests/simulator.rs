@@ -0,0 +1,142 @@
+// Copyright 2023 litep2p developers
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use litep2p::{
+    protocol::libp2p::ping::Config as PingConfig,
+    transport::memory::{
+        network::{LinkConfig, NetworkEvent, TraceEvent},
+        simulation::Simulation,
+    },
+    Litep2pEvent,
+};
+
+use std::time::Duration;
+
+/// Event of a node, with peers replaced by their node indices.
+#[derive(Debug, PartialEq)]
+enum NodeEvent {
+    Connected(usize),
+    Closed(usize),
+    DialFailure,
+}
+
+/// Record the events of the simulation, with peers replaced by their node indices.
+async fn record(
+    simulation: &mut Simulation,
+    duration: Duration,
+    trace: &mut Vec<(Duration, usize, NodeEvent)>,
+) {
+    let peers = (0..simulation.len()).map(|node| simulation.peer_id(node)).collect::<Vec<_>>();
+    let index = |peer| peers.iter().position(|known| known == &peer).unwrap();
+
+    for (time, node, event) in simulation.run_for(duration).await {
+        let event = match event {
+            Litep2pEvent::ConnectionEstablished { peer, .. } => NodeEvent::Connected(index(peer)),
+            Litep2pEvent::ConnectionClosed { peer, .. } => NodeEvent::Closed(index(peer)),
+            Litep2pEvent::DialFailure { .. } => NodeEvent::DialFailure,
+        };
+
+        trace.push((time, node, event));
+    }
+}
+
+/// Dial `listener` from `dialer`.
+async fn dial(simulation: &mut Simulation, dialer: usize, listener: usize) {
+    let address = simulation.node(listener).listen_addresses().next().unwrap().clone();
+    simulation.node(dialer).dial_address(address).await.unwrap();
+}
+
+/// Run a simulation where the first two of three nodes are partitioned from each other for a
+/// second, and return the events of the nodes and the trace of the network.
+async fn run_partition_and_heal(seed: u64) -> (Vec<(Duration, usize, NodeEvent)>, Vec<TraceEvent>) {
+    let mut ping_event_streams = Vec::new();
+    let mut simulation = Simulation::new(seed, 3, |_, config| {
+        let (ping_config, ping_event_stream) = PingConfig::default();
+        ping_event_streams.push(ping_event_stream);
+
+        config.with_libp2p_ping(ping_config)
+    })
+    .unwrap();
+    let (peer0, peer1) = (simulation.peer_id(0), simulation.peer_id(1));
+    let mut events = Vec::new();
+
+    simulation.network().enable_trace();
+    simulation.network().set_default_link(LinkConfig {
+        latency: Duration::from_millis(25),
+        loss: 0.05f64,
+        ..Default::default()
+    });
+    simulation.network().schedule(vec![
+        (
+            Duration::from_secs(1),
+            NetworkEvent::Partition(vec![vec![peer0], vec![peer1]]),
+        ),
+        (Duration::from_secs(2), NetworkEvent::Heal),
+    ]);
+
+    dial(&mut simulation, 0, 1).await;
+    dial(&mut simulation, 0, 2).await;
+    record(&mut simulation, Duration::from_millis(1500), &mut events).await;
+
+    // dialing across the partition fails
+    dial(&mut simulation, 0, 1).await;
+    record(&mut simulation, Duration::from_millis(600), &mut events).await;
+
+    // the partition has healed
+    dial(&mut simulation, 0, 1).await;
+    record(&mut simulation, Duration::from_secs(2), &mut events).await;
+
+    (events, simulation.network().take_trace())
+}
+
+#[tokio::test(start_paused = true)]
+async fn partition_and_heal() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let (events1, trace1) = run_partition_and_heal(1337).await;
+    let (events2, trace2) = run_partition_and_heal(1337).await;
+
+    assert_eq!(events1, events2);
+    assert_eq!(trace1, trace2);
+
+    // the connection between the first two nodes is severed when the partition is applied
+    let second = Duration::from_secs(1);
+    assert!(events1.contains(&(second, 0, NodeEvent::Closed(1))));
+    assert!(events1.contains(&(second, 1, NodeEvent::Closed(0))));
+    assert!(!events1.iter().any(|(_, _, event)| event == &NodeEvent::Closed(2)));
+
+    // the dial across the partition fails and the one after healing succeeds
+    assert_eq!(
+        events1
+            .iter()
+            .filter(|(_, node, event)| *node == 0 && event == &NodeEvent::DialFailure)
+            .count(),
+        1,
+    );
+    assert_eq!(
+        events1
+            .iter()
+            .filter(|(_, node, event)| *node == 1 && event == &NodeEvent::Connected(0))
+            .count(),
+        2,
+    );
+}